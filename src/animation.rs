@@ -0,0 +1,133 @@
+use crate::vehicle::Vehicle;
+use crate::velocities::Velocity;
+use sdl2::rect::Rect;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Size in pixels of one frame of a car sprite sheet, the size of a single
+/// car sprite: a plain 40x70 image is a sheet with one frame
+pub const FRAME_SIZE: (u32, u32) = (40, 70);
+/// Distance a car covers per step of the wheel animation
+const ROLL_PER_FRAME: f32 = 14.0;
+/// Drawn frames the brakes show as on after a car slows down, so a single
+/// frame of braking is long enough to see
+const BRAKE_HOLD: u32 = 20;
+/// How long both vehicles of a close call flash red
+const CLOSE_CALL_FLASH: Duration = Duration::from_secs(1);
+/// Red-and-normal blinks per second while flashing
+const FLASH_RATE: f32 = 5.0;
+
+/// How a car sprite sheet is cut up: one column per step of the wheel
+/// animation, the first row rolling and the optional second row braking
+#[derive(Debug, Clone, Copy)]
+pub struct SheetLayout {
+    columns: u32,
+    rows: u32,
+}
+
+impl SheetLayout {
+    #[cfg(feature = "image")]
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            columns: (width / FRAME_SIZE.0).max(1),
+            rows: (height / FRAME_SIZE.1).max(1),
+        }
+    }
+
+    /// The part of the sheet to draw for `pose`, or None for a plain sprite
+    pub fn frame(&self, pose: Pose) -> Option<Rect> {
+        if self.columns == 1 && self.rows == 1 {
+            return None;
+        }
+        let column = pose.wheel_step % self.columns;
+        let row = if (pose.braking || pose.stopped) && self.rows > 1 {
+            1
+        } else {
+            0
+        };
+        Some(Rect::new(
+            (column * FRAME_SIZE.0) as i32,
+            (row * FRAME_SIZE.1) as i32,
+            FRAME_SIZE.0,
+            FRAME_SIZE.1,
+        ))
+    }
+}
+
+/// Which frame of its sheet a car is drawn with
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Pose {
+    pub wheel_step: u32,
+    pub braking: bool, // slowed down in the last few frames
+    pub stopped: bool,
+    pub flashing: bool, // red for a moment after a close call
+}
+
+struct Animation {
+    rolled: f32, // distance the wheels have turned through
+    position: (f32, f32),
+    brake_frames: u32, // drawn frames left showing the brakes on
+    close_call_at: Option<f32>,
+    flash_started: Option<Instant>,
+}
+
+/// Animation timers for the vehicles on the road, advanced once per drawn
+/// frame. The wheels turn at the pace of `current_speed`, and only while the
+/// car actually moves, so they stop when the simulation is paused. A close
+/// call newer than the last one seen starts the vehicle flashing, so stepping
+/// back through rewound frames doesn't flash it again.
+#[derive(Default)]
+pub struct Animations {
+    vehicles: HashMap<usize, Animation>,
+}
+
+impl Animations {
+    pub fn update(&mut self, vehicles: &[&Vehicle], now: Instant) {
+        self.vehicles
+            .retain(|id, _| vehicles.iter().any(|vehicle| vehicle.id == *id));
+        for vehicle in vehicles {
+            let animation = self.vehicles.entry(vehicle.id).or_insert(Animation {
+                rolled: 0.0,
+                position: vehicle.position,
+                brake_frames: 0,
+                close_call_at: vehicle.close_call_at, // only later ones flash
+                flash_started: None,
+            });
+            if vehicle.position != animation.position {
+                animation.rolled += vehicle.current_speed.pixels_per_frame();
+                animation.position = vehicle.position;
+            }
+            if vehicle.braking {
+                animation.brake_frames = BRAKE_HOLD;
+            } else {
+                animation.brake_frames = animation.brake_frames.saturating_sub(1);
+            }
+            if vehicle.close_call_at > animation.close_call_at {
+                animation.close_call_at = vehicle.close_call_at;
+                animation.flash_started = Some(now);
+            }
+        }
+    }
+
+    /// Vehicles not seen by `update` yet are drawn with the first frame
+    pub fn pose(&self, vehicle: &Vehicle) -> Pose {
+        let stopped = vehicle.current_speed == Velocity::STOPPED;
+        match self.vehicles.get(&vehicle.id) {
+            Some(animation) => Pose {
+                wheel_step: (animation.rolled / ROLL_PER_FRAME) as u32,
+                braking: animation.brake_frames > 0,
+                stopped,
+                flashing: animation.flash_started.is_some_and(|started| {
+                    let flashed = started.elapsed();
+                    flashed < CLOSE_CALL_FLASH
+                        && ((flashed.as_secs_f32() * FLASH_RATE * 2.0) as u32).is_multiple_of(2)
+                }),
+            },
+            None => Pose {
+                braking: vehicle.braking,
+                stopped,
+                ..Pose::default()
+            },
+        }
+    }
+}
@@ -0,0 +1,157 @@
+#[cfg(feature = "image")]
+use sdl2::image::LoadTexture;
+#[cfg(feature = "image")]
+use sdl2::render::{Texture, TextureCreator};
+#[cfg(feature = "image")]
+use sdl2::video::WindowContext;
+use std::path::Path;
+#[cfg(feature = "image")]
+use std::time::{Duration, Instant, SystemTime};
+
+/// A file under `assets/` with a copy built into the binary, so the game
+/// still starts when run from outside the repository. The file on disk wins
+/// when it is there, so assets can be swapped without rebuilding.
+pub struct Asset {
+    pub path: &'static str,
+    pub bytes: &'static [u8],
+}
+
+impl Asset {
+    pub fn on_disk(&self) -> bool {
+        Path::new(self.path).is_file()
+    }
+
+    /// The file's bytes, or the built-in copy if it can't be read. Bytes read
+    /// from disk stay in memory for the rest of the run, for users like the
+    /// chart font registry that keep hold of them.
+    pub fn contents(&self) -> &'static [u8] {
+        if self.on_disk()
+            && let Ok(bytes) = std::fs::read(self.path)
+        {
+            return Box::leak(bytes.into_boxed_slice());
+        }
+        self.bytes
+    }
+}
+
+#[cfg(feature = "image")]
+pub const ROAD_IMAGE: Asset = Asset {
+    path: "assets/road-intersection/road-intersection.png",
+    bytes: include_bytes!("../assets/road-intersection/road-intersection.png"),
+};
+
+/// The first car sprite; it also stands in for any of the others missing on disk
+#[cfg(feature = "image")]
+pub const CAR_SPRITE: Asset = Asset {
+    path: "assets/Cars/car1.png",
+    bytes: include_bytes!("../assets/Cars/car1.png"),
+};
+
+/// Statistics screen font
+#[cfg(feature = "ttf")]
+pub const TITLE_FONT: Asset = Asset {
+    path: "assets/fonts/Orbitron-VariableFont_wght.ttf",
+    bytes: include_bytes!("../assets/fonts/Orbitron-VariableFont_wght.ttf"),
+};
+
+/// Font of the in-game panels and the report charts
+pub const PANEL_FONT: Asset = Asset {
+    path: "assets/fonts/OpenSans-Bold.ttf",
+    bytes: include_bytes!("../assets/fonts/OpenSans-Bold.ttf"),
+};
+
+/// Car sprite `index` (1-5) from disk, or the built-in one
+#[cfg(feature = "image")]
+pub fn load_car_texture(
+    texture_creator: &TextureCreator<WindowContext>,
+    index: u32,
+) -> Result<Texture<'_>, String> {
+    let path = format!("assets/Cars/car{}.png", index);
+    if Path::new(&path).is_file() {
+        texture_creator.load_texture(&path)
+    } else {
+        texture_creator.load_texture_bytes(CAR_SPRITE.bytes)
+    }
+}
+
+/// The road image from disk, or the built-in one
+#[cfg(feature = "image")]
+pub fn load_road_texture(
+    texture_creator: &TextureCreator<WindowContext>,
+) -> Result<Texture<'_>, String> {
+    if ROAD_IMAGE.on_disk() {
+        texture_creator.load_texture(ROAD_IMAGE.path)
+    } else {
+        texture_creator.load_texture_bytes(ROAD_IMAGE.bytes)
+    }
+}
+
+/// How often the image files are checked for changes
+#[cfg(feature = "image")]
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// An image the renderer reloads when its file changes
+#[cfg(feature = "image")]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WatchedImage {
+    Road,
+    Car(u32), // sprite index, 1-5
+}
+
+#[cfg(feature = "image")]
+impl WatchedImage {
+    fn path(self) -> String {
+        match self {
+            WatchedImage::Road => ROAD_IMAGE.path.to_string(),
+            WatchedImage::Car(index) => format!("assets/Cars/car{}.png", index),
+        }
+    }
+}
+
+/// Polls the modification times of the road image and car sprites, so they
+/// can be edited while the simulation runs. A file appearing, changing or
+/// disappearing all count as changes: the last brings back the built-in copy.
+#[cfg(feature = "image")]
+pub struct AssetWatcher {
+    files: Vec<(WatchedImage, Option<SystemTime>)>,
+    next_check: Instant,
+}
+
+#[cfg(feature = "image")]
+impl AssetWatcher {
+    pub fn new() -> Self {
+        let images = std::iter::once(WatchedImage::Road).chain((1..=5).map(WatchedImage::Car));
+        Self {
+            files: images
+                .map(|image| (image, modified(&image.path())))
+                .collect(),
+            next_check: Instant::now() + WATCH_INTERVAL,
+        }
+    }
+
+    /// Images whose file changed since the last call; empty between checks
+    pub fn changed(&mut self) -> Vec<WatchedImage> {
+        let now = Instant::now();
+        if now < self.next_check {
+            return Vec::new();
+        }
+        self.next_check = now + WATCH_INTERVAL;
+
+        let mut changed = Vec::new();
+        for (image, last_modified) in &mut self.files {
+            let modified = modified(&image.path());
+            if modified != *last_modified {
+                *last_modified = modified;
+                changed.push(*image);
+            }
+        }
+        changed
+    }
+}
+
+#[cfg(feature = "image")]
+fn modified(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()
+}
@@ -0,0 +1,76 @@
+use crate::events::{EventKind, SimEvent};
+use sdl2::mixer::{self, Channel, Chunk, DEFAULT_FORMAT};
+
+const ENGINE_CHANNEL: Channel = Channel(0);
+const MAX_VOLUME: i32 = mixer::MAX_VOLUME;
+
+/// Engine, horn and brake sounds driven by simulation events
+pub struct Audio {
+    _subsystem: sdl2::AudioSubsystem,
+    engine: Chunk,
+    horn: Chunk,
+    brake: Chunk,
+    muted: bool,
+}
+
+impl Audio {
+    pub fn init(sdl_context: &sdl2::Sdl) -> Result<Self, String> {
+        let subsystem = sdl_context.audio()?;
+        mixer::open_audio(44_100, DEFAULT_FORMAT, 2, 1_024)?;
+        mixer::allocate_channels(16);
+
+        let engine = Chunk::from_file("assets/sounds/engine.wav")?;
+        let horn = Chunk::from_file("assets/sounds/horn.wav")?;
+        let brake = Chunk::from_file("assets/sounds/brake.wav")?;
+
+        // Ambient engine noise loops forever; its volume follows traffic density
+        ENGINE_CHANNEL.set_volume(0);
+        ENGINE_CHANNEL.play(&engine, -1)?;
+
+        Ok(Self {
+            _subsystem: subsystem,
+            engine,
+            horn,
+            brake,
+            muted: false,
+        })
+    }
+
+    /// Adjust ambience and play one-shot effects for this frame's events
+    pub fn update(&mut self, active_vehicles: usize, events: &[SimEvent]) {
+        if self.muted {
+            return;
+        }
+
+        let engine_volume = (active_vehicles as i32 * 6).min(MAX_VOLUME);
+        ENGINE_CHANNEL.set_volume(engine_volume);
+
+        for event in events {
+            let chunk = match event.kind {
+                EventKind::CloseCall { .. } => &self.horn,
+                EventKind::EmergencyStop => &self.brake,
+                _ => continue,
+            };
+            // Running out of free channels just drops the effect
+            let _ = Channel::all().play(chunk, 0);
+        }
+    }
+
+    pub fn toggle_mute(&mut self) {
+        self.muted = !self.muted;
+        if self.muted {
+            Channel::all().set_volume(0);
+        } else {
+            Channel::all().set_volume(MAX_VOLUME);
+            if !ENGINE_CHANNEL.is_playing() {
+                let _ = ENGINE_CHANNEL.play(&self.engine, -1);
+            }
+        }
+    }
+}
+
+impl Drop for Audio {
+    fn drop(&mut self) {
+        mixer::close_audio();
+    }
+}
@@ -0,0 +1,176 @@
+use crate::compare::{DEFAULT_RATE, DEFAULT_SEED, build_scenario};
+use crate::config::Config;
+use crate::distribution::Spread;
+use rayon::prelude::*;
+use std::path::{Path, PathBuf};
+
+/// File the summary is written to, inside `--output-dir` when given
+pub const BATCH_CSV: &str = "batch.csv";
+
+/// Headline numbers of one episode
+#[derive(Debug, Clone, Copy)]
+pub struct Episode {
+    pub seed: u64,
+    pub controller: &'static str,
+    pub throughput: f32, // vehicles per minute
+    pub delay: f32,      // average seconds stopped before the intersection
+    pub close_calls: u32,
+    pub collisions: u32,
+    pub cost: f32, // weighted cost per minute
+}
+
+/// One measurement of an episode
+#[derive(Debug, Clone, Copy)]
+pub struct Metric {
+    pub name: &'static str,  // CSV column
+    pub label: &'static str, // table row
+    pub value: f32,
+    pub higher_is_better: bool,
+}
+
+impl Episode {
+    /// The measurements the batch aggregates
+    pub fn metrics(&self) -> [Metric; 5] {
+        let metric = |name, label, value, higher_is_better| Metric {
+            name,
+            label,
+            value,
+            higher_is_better,
+        };
+        [
+            metric("throughput", "Throughput (veh/min)", self.throughput, true),
+            metric("delay", "Average delay (s)", self.delay, false),
+            metric("close_calls", "Close calls", self.close_calls as f32, false),
+            metric("collisions", "Collisions", self.collisions as f32, false),
+            metric("cost", "Cost per minute", self.cost, false),
+        ]
+    }
+}
+
+/// Run one seeded episode of Poisson demand headlessly with the configured
+/// intersection
+pub fn run_episode(config: &Config, seed: u64, ticks: u64) -> Result<Episode, String> {
+    let rates = config.arrival_rates.unwrap_or([DEFAULT_RATE; 4]);
+    let scenario = build_scenario(config.layout, rates, seed, ticks);
+    let mut intersection = crate::build_intersection(config)?;
+    intersection.seed(seed);
+
+    let mut next_spawn = 0;
+    for tick in 0..ticks {
+        let current_time = (tick + 1) as f32 / 60.0;
+        while let Some(spawn) = scenario.get(next_spawn).filter(|s| s.tick == tick) {
+            intersection.spawn_vehicle(Some(spawn.direction), Some(spawn.route), current_time);
+            next_spawn += 1;
+        }
+        intersection.update(current_time);
+        intersection.drain_events();
+    }
+
+    let minutes = (intersection.measured_time(ticks as f32 / 60.0) / 60.0).max(1.0 / 60.0);
+    Ok(Episode {
+        seed,
+        controller: intersection.controller.name(),
+        throughput: intersection.total_vehicles_passed as f32 / minutes,
+        delay: intersection.average_wait_time(),
+        close_calls: intersection.close_calls,
+        collisions: intersection.collisions,
+        cost: intersection.cost.total() / minutes,
+    })
+}
+
+/// Run `episodes` consecutive seeds from `--seed` on up to `threads` threads
+/// (0 uses every core). Results come back in seed order whatever the thread
+/// count, so a batch repeats exactly.
+pub fn run_episodes(
+    config: &Config,
+    episodes: usize,
+    ticks: u64,
+    threads: usize,
+) -> Result<Vec<Episode>, String> {
+    let first = config.seed.unwrap_or(DEFAULT_SEED);
+    let seeds: Vec<u64> = (first..).take(episodes).collect();
+    if threads == 1 {
+        return seeds
+            .into_iter()
+            .map(|seed| run_episode(config, seed, ticks))
+            .collect();
+    }
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .map_err(|e| e.to_string())?;
+    pool.install(|| {
+        seeds
+            .into_par_iter()
+            .map(|seed| run_episode(config, seed, ticks))
+            .collect()
+    })
+}
+
+/// Run a batch of episodes, print the mean and standard deviation of each
+/// measurement and write them to `batch.csv`
+pub fn run_batch(config: &Config, episodes: usize) -> Result<(), String> {
+    let ticks = config.batch_ticks;
+    let first = config.seed.unwrap_or(DEFAULT_SEED);
+    println!(
+        "Running {} episodes of {:.0} s, seeds {} to {}",
+        episodes,
+        ticks as f32 / 60.0,
+        first,
+        first + episodes.saturating_sub(1) as u64
+    );
+    let results = run_episodes(config, episodes, ticks, config.batch_threads)?;
+
+    let Some(first_episode) = results.first() else {
+        return Ok(());
+    };
+    let spreads = summarize(&results);
+
+    println!();
+    println!(
+        "{:<30}{:>14}{:>14}",
+        first_episode.controller, "Mean", "Std dev"
+    );
+    for (_, label, spread) in &spreads {
+        println!("{:<30}{:>14.2}{:>14.2}", label, spread.mean, spread.stddev);
+    }
+
+    let path = output_path(config, BATCH_CSV)?;
+    let mut csv = String::from("metric,mean,stddev\n");
+    for (name, _, spread) in &spreads {
+        csv.push_str(&format!("{},{},{}\n", name, spread.mean, spread.stddev));
+    }
+    std::fs::write(&path, csv).map_err(|e| format!("{}: {}", path.display(), e))?;
+    println!();
+    println!("Wrote the summary to {}", path.display());
+    Ok(())
+}
+
+/// Mean and standard deviation of each measurement across episodes: CSV
+/// name, table label and spread
+pub fn summarize(results: &[Episode]) -> Vec<(&'static str, &'static str, Spread)> {
+    let Some(first) = results.first() else {
+        return Vec::new();
+    };
+    first
+        .metrics()
+        .iter()
+        .enumerate()
+        .map(|(index, metric)| {
+            let values: Vec<f32> = results.iter().map(|e| e.metrics()[index].value).collect();
+            (metric.name, metric.label, Spread::of(&values))
+        })
+        .collect()
+}
+
+/// Where a headless summary file goes: inside `--output-dir` when given,
+/// which is created if needed, otherwise the working directory
+pub fn output_path(config: &Config, file: &str) -> Result<PathBuf, String> {
+    match &config.output_dir {
+        Some(dir) => {
+            std::fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir, e))?;
+            Ok(Path::new(dir).join(file))
+        }
+        None => Ok(PathBuf::from(file)),
+    }
+}
@@ -0,0 +1,91 @@
+use crate::intersection::SmartIntersection;
+use std::time::{Duration, Instant};
+
+/// Ticks between synthetic random spawns
+const SPAWN_INTERVAL: u64 = 10;
+
+/// Run `ticks` simulation steps as fast as possible and print a performance report
+pub fn run_bench(ticks: u64) {
+    let mut intersection = SmartIntersection::new();
+    let mut spawn_time = Duration::ZERO;
+    let mut peak_vehicles = 0;
+
+    let started = Instant::now();
+    for tick in 0..ticks {
+        let current_time = tick as f32 / 60.0;
+
+        if tick % SPAWN_INTERVAL == 0 {
+            let spawn_start = Instant::now();
+            intersection.spawn_vehicle(None, None, current_time);
+            spawn_time += spawn_start.elapsed();
+        }
+
+        intersection.update(current_time);
+        intersection.drain_events();
+        peak_vehicles = peak_vehicles.max(intersection.vehicle_count());
+    }
+    let elapsed = started.elapsed();
+
+    let timings = intersection.timings;
+    let percent = |d: Duration| d.as_secs_f64() / elapsed.as_secs_f64() * 100.0;
+
+    println!(
+        "Benchmark: {} ticks in {:.3} s",
+        ticks,
+        elapsed.as_secs_f64()
+    );
+    println!(
+        "Ticks per second:   {:.0}",
+        ticks as f64 / elapsed.as_secs_f64()
+    );
+    println!(
+        "Following:          {:>9.3} ms ({:.1}%)",
+        timings.following.as_secs_f64() * 1000.0,
+        percent(timings.following)
+    );
+    println!(
+        "Reservation checks: {:>9.3} ms ({:.1}%)",
+        timings.reservation.as_secs_f64() * 1000.0,
+        percent(timings.reservation)
+    );
+    println!(
+        "Cell release:       {:>9.3} ms ({:.1}%)",
+        timings.release.as_secs_f64() * 1000.0,
+        percent(timings.release)
+    );
+    println!(
+        "Movement:           {:>9.3} ms ({:.1}%)",
+        timings.movement.as_secs_f64() * 1000.0,
+        percent(timings.movement)
+    );
+    println!(
+        "Spawning:           {:>9.3} ms ({:.1}%)",
+        spawn_time.as_secs_f64() * 1000.0,
+        percent(spawn_time)
+    );
+    println!("Vehicles passed:    {}", intersection.total_vehicles_passed);
+    println!("Peak active:        {}", peak_vehicles);
+    match peak_memory_kb() {
+        Some(kb) => println!("Peak memory:        {:.1} MiB", kb as f64 / 1024.0),
+        None => println!("Peak memory:        n/a"),
+    }
+}
+
+/// Peak resident set size from /proc (Linux only)
+pub fn peak_memory_kb() -> Option<u64> {
+    proc_status_kb("VmHWM:")
+}
+
+/// Current resident set size from /proc (Linux only)
+pub fn resident_memory_kb() -> Option<u64> {
+    proc_status_kb("VmRSS:")
+}
+
+fn proc_status_kb(field: &str) -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    status
+        .lines()
+        .find(|line| line.starts_with(field))
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|kb| kb.parse().ok())
+}
@@ -0,0 +1,117 @@
+use crate::vehicle::Vehicle;
+use sdl2::rect::Rect;
+
+/// Width and height of the simulated world in pixels
+const WORLD_SIZE: f32 = 1000.0;
+/// Magnification while following a vehicle
+const FOLLOW_ZOOM: f32 = 2.0;
+
+/// Which part of the world is shown in the window
+pub struct Camera {
+    pub following: Option<usize>, // id of the vehicle the camera is locked to
+    center: (f32, f32),
+    zoom: f32,
+    screen_size: f32,
+}
+
+impl Camera {
+    pub fn new(screen_size: u32) -> Self {
+        Self {
+            following: None,
+            center: (WORLD_SIZE / 2.0, WORLD_SIZE / 2.0),
+            zoom: 1.0,
+            screen_size: screen_size as f32,
+        }
+    }
+
+    pub fn is_following(&self) -> bool {
+        self.following.is_some()
+    }
+
+    pub fn follow(&mut self, vehicle_id: Option<usize>) {
+        self.following = vehicle_id;
+        if vehicle_id.is_none() {
+            self.center = (WORLD_SIZE / 2.0, WORLD_SIZE / 2.0);
+            self.zoom = 1.0;
+        }
+    }
+
+    /// Lock onto the vehicle after the followed one (by id), wrapping around
+    pub fn follow_next(&mut self, vehicles: &[&Vehicle]) {
+        let current = self.following.unwrap_or(0);
+        let next = vehicles
+            .iter()
+            .map(|v| v.id)
+            .filter(|&id| id > current)
+            .min()
+            .or_else(|| vehicles.iter().map(|v| v.id).min());
+        self.follow(next);
+    }
+
+    /// Keep the followed vehicle centred; return to the full view once it has left
+    pub fn update(&mut self, vehicles: &[&Vehicle]) {
+        let Some(id) = self.following else { return };
+        let Some(vehicle) = vehicles.iter().find(|v| v.id == id) else {
+            self.follow(None);
+            return;
+        };
+
+        let (vx, vy, vw, vh) = vehicle.get_visual_bounds();
+        self.zoom = FOLLOW_ZOOM;
+
+        // Don't scroll past the edge of the road image
+        let half_view = WORLD_SIZE / self.zoom / 2.0;
+        self.center = (
+            (vx + vw / 2.0).clamp(half_view, WORLD_SIZE - half_view),
+            (vy + vh / 2.0).clamp(half_view, WORLD_SIZE - half_view),
+        );
+    }
+
+    /// The world rectangle currently visible
+    pub fn view(&self) -> Rect {
+        let size = WORLD_SIZE / self.zoom;
+        Rect::new(
+            (self.center.0 - size / 2.0) as i32,
+            (self.center.1 - size / 2.0) as i32,
+            size as u32,
+            size as u32,
+        )
+    }
+
+    fn scale(&self) -> f32 {
+        self.screen_size / WORLD_SIZE * self.zoom
+    }
+
+    /// Screen rectangle for a world-space rectangle
+    pub fn to_screen(&self, x: f32, y: f32, w: u32, h: u32) -> Rect {
+        let view = self.view();
+        let scale = self.scale();
+        Rect::new(
+            ((x - view.x() as f32) * scale) as i32,
+            ((y - view.y() as f32) * scale) as i32,
+            (w as f32 * scale) as u32,
+            (h as f32 * scale) as u32,
+        )
+    }
+
+    /// World coordinates under a screen position (e.g. the mouse)
+    pub fn to_world(&self, x: i32, y: i32) -> (f32, f32) {
+        let view = self.view();
+        let scale = self.scale();
+        (
+            view.x() as f32 + x as f32 / scale,
+            view.y() as f32 + y as f32 / scale,
+        )
+    }
+}
+
+/// The vehicle whose body contains a world-space point
+pub fn vehicle_at(vehicles: &[&Vehicle], (x, y): (f32, f32)) -> Option<usize> {
+    vehicles
+        .iter()
+        .find(|v| {
+            let (vx, vy, vw, vh) = v.get_visual_bounds();
+            x >= vx && x <= vx + vw && y >= vy && y <= vy + vh
+        })
+        .map(|v| v.id)
+}
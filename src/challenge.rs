@@ -0,0 +1,173 @@
+use crate::i18n::tr;
+use crate::intersection::SmartIntersection;
+
+/// Objectives of challenge mode; the run ends as soon as it is won or lost
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Challenge {
+    pub vehicles: u32,                // vehicles to pass to win
+    pub max_close_calls: Option<u32>, // close calls allowed along the way
+    pub max_collisions: Option<u32>,
+    pub max_wait: Option<f32>, // seconds any vehicle may stand before the intersection
+    pub time_limit: Option<f32>, // seconds to pass the vehicles in
+}
+
+impl Default for Challenge {
+    /// Pass 100 vehicles with no close calls or collisions and nobody waiting 10 s
+    fn default() -> Self {
+        Self {
+            vehicles: 100,
+            max_close_calls: Some(0),
+            max_collisions: Some(0),
+            max_wait: Some(10.0),
+            time_limit: None,
+        }
+    }
+}
+
+/// Where a challenge stands
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    InProgress,
+    Passed,
+    Failed(String), // which objective was broken
+}
+
+impl Challenge {
+    /// Parse `standard` or a comma-separated list such as
+    /// `vehicles=50,close-calls=2,max-wait=8,time-limit=120`; objectives that
+    /// aren't listed don't apply
+    pub fn parse(text: &str) -> Result<Self, String> {
+        if text == "standard" {
+            return Ok(Self::default());
+        }
+
+        let mut challenge = Self {
+            vehicles: Self::default().vehicles,
+            max_close_calls: None,
+            max_collisions: None,
+            max_wait: None,
+            time_limit: None,
+        };
+        for objective in text.split(',') {
+            let (key, value) = objective
+                .split_once('=')
+                .ok_or_else(|| format!("Challenge objective '{}' needs key=value", objective))?;
+            let invalid = || format!("Invalid value '{}' for challenge objective {}", value, key);
+            match key.trim() {
+                "vehicles" => challenge.vehicles = value.parse().map_err(|_| invalid())?,
+                "close-calls" => {
+                    challenge.max_close_calls = Some(value.parse().map_err(|_| invalid())?)
+                }
+                "collisions" => {
+                    challenge.max_collisions = Some(value.parse().map_err(|_| invalid())?)
+                }
+                "max-wait" => challenge.max_wait = Some(value.parse().map_err(|_| invalid())?),
+                "time-limit" => challenge.time_limit = Some(value.parse().map_err(|_| invalid())?),
+                other => return Err(format!("Unknown challenge objective '{}'", other)),
+            }
+        }
+        if challenge.vehicles == 0 {
+            return Err("A challenge needs at least one vehicle to pass".to_string());
+        }
+        Ok(challenge)
+    }
+
+    /// Check every objective at `current_time`. A broken limit fails the
+    /// challenge at once; passing the vehicles with every limit intact wins it.
+    pub fn check(&self, intersection: &SmartIntersection, current_time: f32) -> Outcome {
+        let passed = intersection.total_vehicles_passed;
+        let longest_wait = intersection.longest_wait();
+
+        if let Some(limit) = self.max_collisions.filter(|&l| intersection.collisions > l) {
+            return Outcome::Failed(tr!(
+                "challenge.too_many_collisions",
+                count = intersection.collisions,
+                limit = limit,
+            ));
+        }
+        if let Some(limit) = self
+            .max_close_calls
+            .filter(|&l| intersection.close_calls > l)
+        {
+            return Outcome::Failed(tr!(
+                "challenge.too_many_close_calls",
+                count = intersection.close_calls,
+                limit = limit,
+            ));
+        }
+        if let Some(limit) = self.max_wait.filter(|&l| longest_wait > l) {
+            return Outcome::Failed(tr!(
+                "challenge.waited_too_long",
+                seconds = format!("{:.1}", longest_wait),
+                limit = format!("{:.0}", limit),
+            ));
+        }
+        if passed >= self.vehicles {
+            return Outcome::Passed;
+        }
+        if let Some(limit) = self.time_limit.filter(|&l| current_time > l) {
+            return Outcome::Failed(tr!(
+                "challenge.out_of_time",
+                passed = passed,
+                target = self.vehicles,
+                limit = format!("{:.0}", limit),
+            ));
+        }
+        Outcome::InProgress
+    }
+
+    /// Live progress for the HUD, one line per objective
+    pub fn progress_lines(
+        &self,
+        intersection: &SmartIntersection,
+        current_time: f32,
+    ) -> Vec<String> {
+        let mut lines = vec![
+            tr!("challenge.title"),
+            tr!(
+                "challenge.vehicles",
+                passed = intersection.total_vehicles_passed,
+                target = self.vehicles,
+            ),
+        ];
+        if let Some(limit) = self.max_close_calls {
+            lines.push(tr!(
+                "challenge.close_calls",
+                count = intersection.close_calls,
+                limit = limit,
+            ));
+        }
+        if let Some(limit) = self.max_collisions {
+            lines.push(tr!(
+                "challenge.collisions",
+                count = intersection.collisions,
+                limit = limit,
+            ));
+        }
+        if let Some(limit) = self.max_wait {
+            lines.push(tr!(
+                "challenge.longest_wait",
+                seconds = format!("{:.1}", intersection.longest_wait()),
+                limit = format!("{:.0}", limit),
+            ));
+        }
+        if let Some(limit) = self.time_limit {
+            lines.push(tr!(
+                "challenge.time_left",
+                seconds = format!("{:.0}", (limit - current_time).max(0.0)),
+            ));
+        }
+        lines
+    }
+}
+
+impl Outcome {
+    /// Line for the statistics screen
+    pub fn describe(&self) -> String {
+        match self {
+            Outcome::InProgress => tr!("challenge.not_finished"),
+            Outcome::Passed => tr!("challenge.passed"),
+            Outcome::Failed(reason) => tr!("challenge.failed", reason = reason),
+        }
+    }
+}
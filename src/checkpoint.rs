@@ -0,0 +1,82 @@
+use crate::controller::ControllerKind;
+use crate::demand::{AutoSpawn, PoissonArrivals};
+use crate::intersection::IntersectionState;
+use crate::stress::StressRamp;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+/// A whole run at one moment, enough to carry on from there with `--resume`
+#[derive(Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub time: f32,
+    pub config_hash: String, // settings the run was started with
+    pub controller: Option<ControllerKind>, // switched to during the run, if it was
+    pub intersection: IntersectionState,
+    pub auto_spawn: AutoSpawn,
+    pub arrivals: Option<PoissonArrivals>,
+    pub stress: Option<StressRamp>,
+}
+
+impl Checkpoint {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path, e))?;
+        serde_json::from_reader(BufReader::new(file))
+            .map_err(|e| format!("Invalid checkpoint {}: {}", path, e))
+    }
+}
+
+/// Writes a checkpoint every `--checkpoint-every` seconds of simulated time,
+/// deleting the oldest once more than `--checkpoints-kept` are on disk
+pub struct Checkpointer {
+    every: f32,
+    dir: PathBuf,
+    kept: usize,
+    next: f32, // simulated time the next checkpoint is due
+    written: VecDeque<PathBuf>,
+}
+
+impl Checkpointer {
+    pub fn new(every: f32, dir: &str, kept: usize, current_time: f32) -> Self {
+        Self {
+            every,
+            dir: PathBuf::from(dir),
+            kept,
+            next: current_time + every,
+            written: VecDeque::new(),
+        }
+    }
+
+    pub fn is_due(&self, current_time: f32) -> bool {
+        current_time >= self.next
+    }
+
+    /// Save `checkpoint` and return where it went
+    pub fn write(&mut self, checkpoint: &Checkpoint) -> Result<String, String> {
+        std::fs::create_dir_all(&self.dir)
+            .map_err(|e| format!("Failed to create {}: {}", self.dir.display(), e))?;
+        // Named in milliseconds so fractional intervals never share a file
+        let millis = (checkpoint.time as f64 * 1000.0).round() as u64;
+        let path = self.dir.join(format!("checkpoint_{}ms.json", millis));
+        save(&path, checkpoint)?;
+        self.next = checkpoint.time + self.every;
+
+        self.written.push_back(path.clone());
+        while self.written.len() > self.kept {
+            if let Some(old) = self.written.pop_front() {
+                std::fs::remove_file(&old)
+                    .map_err(|e| format!("Failed to delete {}: {}", old.display(), e))?;
+            }
+        }
+        Ok(path.display().to_string())
+    }
+}
+
+fn save(path: &Path, checkpoint: &Checkpoint) -> Result<(), String> {
+    let file =
+        File::create(path).map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+    serde_json::to_writer(BufWriter::new(file), checkpoint)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
@@ -0,0 +1,274 @@
+use crate::batch::{Episode, run_episodes};
+use crate::config::Config;
+use crate::controller::ControllerKind;
+use crate::cost::CostTracker;
+use crate::demand::PoissonArrivals;
+use crate::intersection::SmartIntersection;
+use crate::layout::RoadLayout;
+use crate::route::{Direction, Route};
+use crate::significance::{SIGNIFICANCE_LEVEL, wilcoxon_signed_rank};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+/// Arrivals per minute on every approach when `--arrivals` isn't given
+pub const DEFAULT_RATE: f32 = 12.0;
+
+/// Seed used when `--seed` isn't given, so repeated comparisons match
+pub const DEFAULT_SEED: u64 = 1;
+
+/// A spawn at a fixed tick, shared by both runs
+pub struct ScenarioSpawn {
+    pub tick: u64,
+    pub direction: Direction,
+    pub route: Route,
+}
+
+/// Headline numbers of one run
+struct RunResult {
+    controller: &'static str,
+    vehicles_passed: u32,
+    throughput: f32, // vehicles per minute
+    average_wait: f32,
+    average_spawn_delay: f32,
+    max_time_in_intersection: f32,
+    close_calls: u32,
+    collisions: u32,
+    fuel_per_vehicle: f32, // mL
+    co2_per_vehicle: f32,  // g
+    unfinished: usize,     // still on the road or waiting to spawn at the end
+    stops: u32,
+    cost: f32, // weighted cost per minute; lower is better
+}
+
+/// Run the same seeded demand through two controllers headlessly and print
+/// the results side by side; with `--compare-runs` above 1, over that many
+/// paired seeds with a significance test
+pub fn run_comparison(config: &Config, controllers: [ControllerKind; 2]) -> Result<(), String> {
+    if config.compare_runs > 1 {
+        return run_paired(config, controllers, config.compare_runs);
+    }
+    let seed = config.seed.unwrap_or(DEFAULT_SEED);
+    let rates = config.arrival_rates.unwrap_or([DEFAULT_RATE; 4]);
+    let ticks = config.compare_ticks;
+    let scenario = build_scenario(config.layout, rates, seed, ticks);
+
+    println!(
+        "Comparing over {:.0} s of simulated time, {} vehicles, seed {}",
+        ticks as f32 / 60.0,
+        scenario.len(),
+        seed
+    );
+    let results = controllers.map(|kind| run_once(config, kind, &scenario, seed, ticks));
+    print_table(&results);
+    Ok(())
+}
+
+/// Run both controllers on the same `runs` consecutive seeds and test every
+/// measurement pair by pair with the Wilcoxon signed-rank test, so a change
+/// is only called an improvement when it holds up across the seeds rather
+/// than on one lucky run
+fn run_paired(
+    config: &Config,
+    controllers: [ControllerKind; 2],
+    runs: usize,
+) -> Result<(), String> {
+    let first = config.seed.unwrap_or(DEFAULT_SEED);
+    println!(
+        "Comparing over {} paired runs of {:.0} s, seeds {} to {}",
+        runs,
+        config.compare_ticks as f32 / 60.0,
+        first,
+        first + runs as u64 - 1
+    );
+    let [a, b] = controllers.map(|kind| {
+        let mut config = config.clone();
+        config.controller = kind;
+        config.controller_script = None;
+        run_episodes(&config, runs, config.compare_ticks, config.batch_threads)
+    });
+    let (a, b) = (a?, b?);
+    let (Some(name_a), Some(name_b)) = (
+        a.first().map(|e| e.controller),
+        b.first().map(|e| e.controller),
+    ) else {
+        return Ok(());
+    };
+
+    println!();
+    println!(
+        "{:<30}{:>14}{:>14}{:>10}  Verdict",
+        "", name_a, name_b, "p-value"
+    );
+    for (index, metric) in a[0].metrics().iter().enumerate() {
+        let values = |results: &[Episode]| -> Vec<f32> {
+            results.iter().map(|e| e.metrics()[index].value).collect()
+        };
+        let (first, second) = (values(&a), values(&b));
+        let test = wilcoxon_signed_rank(&first, &second);
+        let verdict = if !test.significant() {
+            "no significant difference"
+        } else if test.second_larger() == metric.higher_is_better {
+            name_b
+        } else {
+            name_a
+        };
+        let mean = |values: &[f32]| values.iter().sum::<f32>() / values.len() as f32;
+        println!(
+            "{:<30}{:>14.2}{:>14.2}{:>10.3}  {}",
+            metric.label,
+            mean(&first),
+            mean(&second),
+            test.p_value,
+            verdict
+        );
+    }
+    println!();
+    println!(
+        "The verdict names the better controller where the difference is significant at p < {}",
+        SIGNIFICANCE_LEVEL
+    );
+    Ok(())
+}
+
+/// Poisson arrivals with random routes, all drawn from the seed; arrivals on
+/// approaches the layout has no road for are dropped
+pub fn build_scenario(
+    layout: RoadLayout,
+    rates: [f32; 4],
+    seed: u64,
+    ticks: u64,
+) -> Vec<ScenarioSpawn> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut arrivals = PoissonArrivals::new(rates, &mut rng);
+    let mut scenario = Vec::new();
+    for tick in 0..ticks {
+        let current_time = (tick + 1) as f32 / 60.0;
+        for direction in arrivals.due(current_time, &mut rng) {
+            if let Some(route) = layout.random_route(direction, &mut rng) {
+                scenario.push(ScenarioSpawn {
+                    tick,
+                    direction,
+                    route,
+                });
+            }
+        }
+    }
+    scenario
+}
+
+fn run_once(
+    config: &Config,
+    kind: ControllerKind,
+    scenario: &[ScenarioSpawn],
+    seed: u64,
+    ticks: u64,
+) -> RunResult {
+    let mut intersection = SmartIntersection::new();
+    intersection.weather = config.weather;
+    intersection.layout = config.layout;
+    intersection.profile_mix = config.driver_mix;
+    intersection.fleet_mix = config.fleet_mix;
+    intersection.speed_variance = config.speed_variance;
+    intersection.slot_padding = config.slot_padding;
+    intersection.path_padding = config.path_padding;
+    intersection.breakdown_rate = config.breakdown_rate;
+    intersection.breakdown_duration = config.breakdown_duration;
+    intersection.platooning = config.platooning;
+    intersection.packet_loss = config.packet_loss;
+    intersection.warm_up = config.warm_up;
+    intersection.cost = CostTracker::new(config.cost_weights);
+    intersection.controller = kind.build(config.layout, config.path_padding);
+    intersection.seed(seed);
+
+    let mut next_spawn = 0;
+    for tick in 0..ticks {
+        let current_time = (tick + 1) as f32 / 60.0;
+        while let Some(spawn) = scenario.get(next_spawn).filter(|s| s.tick == tick) {
+            intersection.spawn_vehicle(Some(spawn.direction), Some(spawn.route), current_time);
+            next_spawn += 1;
+        }
+        intersection.update(current_time);
+        intersection.drain_events();
+    }
+
+    let minutes = (intersection.measured_time(ticks as f32 / 60.0) / 60.0).max(1.0 / 60.0);
+    RunResult {
+        controller: intersection.controller.name(),
+        vehicles_passed: intersection.total_vehicles_passed,
+        throughput: intersection.total_vehicles_passed as f32 / minutes,
+        average_wait: intersection.average_wait_time(),
+        average_spawn_delay: intersection.average_spawn_delay(),
+        max_time_in_intersection: intersection.max_time_in_intersection,
+        close_calls: intersection.close_calls,
+        collisions: intersection.collisions,
+        fuel_per_vehicle: intersection.average_fuel(),
+        co2_per_vehicle: intersection.average_co2(),
+        unfinished: intersection.vehicle_count() + intersection.pending_spawn_count(),
+        stops: intersection.cost.totals.stops,
+        cost: intersection.cost.total() / minutes,
+    }
+}
+
+fn print_table(results: &[RunResult; 2]) {
+    let [a, b] = results;
+    let row = |label: &str, a: String, b: String| println!("{:<30}{:>14}{:>14}", label, a, b);
+
+    println!();
+    row("", a.controller.to_string(), b.controller.to_string());
+    row(
+        "Vehicles passed",
+        a.vehicles_passed.to_string(),
+        b.vehicles_passed.to_string(),
+    );
+    row(
+        "Throughput (veh/min)",
+        format!("{:.1}", a.throughput),
+        format!("{:.1}", b.throughput),
+    );
+    row(
+        "Average wait (s)",
+        format!("{:.2}", a.average_wait),
+        format!("{:.2}", b.average_wait),
+    );
+    row(
+        "Average spawn delay (s)",
+        format!("{:.2}", a.average_spawn_delay),
+        format!("{:.2}", b.average_spawn_delay),
+    );
+    row(
+        "Max time in intersection (s)",
+        format!("{:.2}", a.max_time_in_intersection),
+        format!("{:.2}", b.max_time_in_intersection),
+    );
+    row(
+        "Close calls",
+        a.close_calls.to_string(),
+        b.close_calls.to_string(),
+    );
+    row(
+        "Collisions",
+        a.collisions.to_string(),
+        b.collisions.to_string(),
+    );
+    row(
+        "Fuel per vehicle (mL)",
+        format!("{:.1}", a.fuel_per_vehicle),
+        format!("{:.1}", b.fuel_per_vehicle),
+    );
+    row(
+        "CO2 per vehicle (g)",
+        format!("{:.0}", a.co2_per_vehicle),
+        format!("{:.0}", b.co2_per_vehicle),
+    );
+    row(
+        "Unfinished vehicles",
+        a.unfinished.to_string(),
+        b.unfinished.to_string(),
+    );
+    row("Stops", a.stops.to_string(), b.stops.to_string());
+    row(
+        "Cost per minute",
+        format!("{:.1}", a.cost),
+        format!("{:.1}", b.cost),
+    );
+}
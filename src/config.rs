@@ -0,0 +1,487 @@
+use crate::challenge::Challenge;
+use crate::construction::LaneClosure;
+use crate::controller::ControllerKind;
+use crate::cost::CostWeights;
+use crate::demand::PoissonArrivals;
+use crate::driver::ProfileMix;
+use crate::fuel::FleetMix;
+use crate::intersection::DEFAULT_SPEED_VARIANCE;
+use crate::layout::RoadLayout;
+use crate::render::{FrameRate, RendererKind};
+use crate::run_limit::RunLimit;
+use crate::sweep::Sweep;
+use crate::weather::Weather;
+
+/// Run history file used unless `--history-db` or `--no-history` is given
+const DEFAULT_HISTORY_DB: &str = "smart_road_runs.sqlite";
+
+/// Runtime options parsed from the command line
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub sumo_routes: Option<String>, // SUMO .rou.xml file to replay as demand
+    pub spawn_csv: Option<String>,   // CSV of timed spawns to replay as demand
+    pub event_log: Option<String>,   // JSONL file receiving simulation events
+    pub weather: Weather,
+    pub driver_mix: ProfileMix,
+    pub breakdown_rate: f32,             // breakdowns per vehicle per second
+    pub breakdown_duration: Option<f32>, // None = stranded until removed
+    pub stress: bool,                    // ramp spawn rate until saturation
+    pub bench_ticks: Option<u64>,        // run a headless benchmark instead
+    pub soak_minutes: Option<f32>,       // run a headless soak test instead
+    pub controller_script: Option<String>, // Rhai script deciding intersection permissions
+    pub output_dir: Option<String>,      // directory receiving stats.json and charts
+    pub arrival_rates: Option<[f32; 4]>, // Poisson arrivals per minute for N/S/E/W
+    pub controller: ControllerKind,
+    pub renderer: RendererKind,        // drawing backend (sdl or wgpu)
+    pub tui: bool,                     // draw in the terminal instead of an SDL window
+    pub host_port: Option<u16>,        // accept a co-op client on this TCP port
+    pub host_bind: String,             // address the co-op host listens on
+    pub connect: Option<String>,       // join a host at this address as a co-op client
+    pub record_replay: Option<String>, // JSONL file receiving every frame's vehicles
+    pub ghost_replay: Option<String>,  // replay drawn as ghosts and re-used as demand
+    pub seed: Option<u64>,             // fixed random seed for reproducible runs
+    pub compare: Option<[ControllerKind; 2]>, // run both controllers headlessly and compare
+    pub compare_ticks: u64,            // length of each --compare run
+    pub compare_runs: usize,           // paired seeds per --compare controller
+    pub layout: RoadLayout,            // crossroads or T-junction
+    pub merge: Option<[f32; 2]>, // run the highway merge scenario with main/ramp arrival rates
+    pub platooning: bool,        // close followers share their leader's crossing
+    pub packet_loss: f32,        // chance each reservation message is lost
+    pub close_call_dir: Option<String>, // save a replay clip of every close call here
+    pub fleet_mix: FleetMix,     // petrol/diesel/electric weights
+    pub speed_variance: f32,     // drivers cruise up to this share below their top speed
+    pub slot_padding: f32,       // extra seconds booked before and after every reservation
+    pub path_padding: usize,     // extra grid cells booked on every side of each path
+    pub history_db: Option<String>, // SQLite file keeping every run's summary
+    pub challenge: Option<Challenge>, // objectives that end the run when won or lost
+    pub run_limit: Option<RunLimit>, // time or vehicle count that ends the run
+    pub exit_when_done: bool,    // print the stats and exit instead of showing them
+    pub onboarding: bool,        // show the controls overlay on first launch
+    pub language: String,        // locale of all user-facing text
+    pub frame_rate: FrameRate,   // vsync and frame cap
+    pub max_vehicles: Option<usize>, // active vehicles allowed at once; later spawns queue
+    pub starvation_limit: Option<f32>, // seconds at the stop line before a vehicle gets priority
+    pub rerouting: bool,         // vehicles change route around broken-down cars
+    pub lane_closures: Vec<LaneClosure>, // lanes closed for roadworks, with when
+    pub hazard_rate: f32,        // jaywalkers stepping onto the road per second
+    pub warm_up: f32,            // seconds left out of the statistics at the start
+    pub checkpoint_every: Option<f32>, // seconds between saved checkpoints
+    pub checkpoint_dir: String,  // directory receiving the checkpoints
+    pub checkpoints_kept: usize, // older checkpoints are deleted
+    pub resume: Option<String>,  // checkpoint to continue the run from
+    pub verify_ticks: Option<u64>, // run a seeded scenario twice and check it repeats exactly
+    pub gym: bool,               // serve the RL environment on stdin/stdout
+    pub export_dataset: Option<String>, // replay to encode into .npy tensors
+    pub cost_weights: CostWeights, // weights of the cost that scores, compares and rewards runs
+    pub batch: Option<usize>,    // run this many seeded episodes headlessly and summarize them
+    pub batch_ticks: u64,        // length of each --batch episode
+    pub batch_threads: usize,    // threads running --batch episodes; 0 = one per core
+    pub sweep: Option<Sweep>,    // run batches across a range of one setting
+    pub debug_keys: bool,        // keys that freeze vehicles and tamper with bookings
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            sumo_routes: None,
+            spawn_csv: None,
+            event_log: None,
+            weather: Weather::default(),
+            driver_mix: ProfileMix::default(),
+            breakdown_rate: 0.0,
+            breakdown_duration: Some(5.0),
+            stress: false,
+            bench_ticks: None,
+            soak_minutes: None,
+            controller_script: None,
+            output_dir: None,
+            arrival_rates: None,
+            controller: ControllerKind::default(),
+            renderer: RendererKind::default(),
+            tui: false,
+            host_port: None,
+            host_bind: "127.0.0.1".to_string(),
+            connect: None,
+            record_replay: None,
+            ghost_replay: None,
+            seed: None,
+            compare: None,
+            compare_ticks: 18_000,
+            compare_runs: 1,
+            layout: RoadLayout::default(),
+            merge: None,
+            platooning: false,
+            packet_loss: 0.0,
+            close_call_dir: None,
+            fleet_mix: FleetMix::default(),
+            speed_variance: DEFAULT_SPEED_VARIANCE,
+            slot_padding: 0.0,
+            path_padding: 0,
+            history_db: Some(DEFAULT_HISTORY_DB.to_string()),
+            challenge: None,
+            run_limit: None,
+            exit_when_done: false,
+            onboarding: true,
+            language: "en".to_string(),
+            frame_rate: FrameRate::default(),
+            max_vehicles: None,
+            starvation_limit: None,
+            rerouting: true,
+            lane_closures: Vec::new(),
+            hazard_rate: 0.0,
+            warm_up: 0.0,
+            checkpoint_every: None,
+            checkpoint_dir: "checkpoints".to_string(),
+            checkpoints_kept: 5,
+            resume: None,
+            verify_ticks: None,
+            gym: false,
+            export_dataset: None,
+            cost_weights: CostWeights::default(),
+            batch: None,
+            batch_ticks: 18_000,
+            batch_threads: 1,
+            sweep: None,
+            debug_keys: false,
+        }
+    }
+}
+
+impl Config {
+    pub fn from_args() -> Result<Self, String> {
+        Self::parse(std::env::args().skip(1))
+    }
+
+    fn parse(args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut config = Config::default();
+        let mut args = args;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--sumo" => {
+                    config.sumo_routes = Some(next_value(&mut args, &arg)?);
+                }
+                "--schedule" => {
+                    config.spawn_csv = Some(next_value(&mut args, &arg)?);
+                }
+                "--event-log" => {
+                    config.event_log = Some(next_value(&mut args, &arg)?);
+                }
+                "--weather" => {
+                    config.weather = Weather::parse(&next_value(&mut args, &arg)?)?;
+                }
+                "--driver-mix" => {
+                    config.driver_mix = ProfileMix::parse(&next_value(&mut args, &arg)?)?;
+                }
+                "--breakdown-rate" => {
+                    let rate = parse_number(&next_value(&mut args, &arg)?, &arg)?;
+                    if rate < 0.0 {
+                        return Err(format!("--breakdown-rate can't be negative, got {}", rate));
+                    }
+                    config.breakdown_rate = rate;
+                }
+                "--breakdown-duration" => {
+                    // 0 leaves broken vehicles stranded until they are removed
+                    let seconds = parse_number(&next_value(&mut args, &arg)?, &arg)?;
+                    config.breakdown_duration = (seconds > 0.0).then_some(seconds);
+                }
+                "--stress" => config.stress = true,
+                "--bench" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let ticks = value
+                        .parse::<u64>()
+                        .map_err(|_| format!("Invalid tick count '{}' for --bench", value))?;
+                    config.bench_ticks = Some(ticks);
+                }
+                "--soak" => {
+                    let minutes = parse_number(&next_value(&mut args, &arg)?, &arg)?;
+                    if minutes <= 0.0 {
+                        return Err(format!("--soak must be positive, got {}", minutes));
+                    }
+                    config.soak_minutes = Some(minutes);
+                }
+                "--controller-script" => {
+                    config.controller_script = Some(next_value(&mut args, &arg)?);
+                }
+                "--output-dir" => {
+                    config.output_dir = Some(next_value(&mut args, &arg)?);
+                }
+                "--arrivals" => {
+                    let rates = PoissonArrivals::parse_rates(&next_value(&mut args, &arg)?)?;
+                    config.arrival_rates = Some(rates);
+                }
+                "--controller" => {
+                    config.controller = ControllerKind::parse(&next_value(&mut args, &arg)?)?;
+                }
+                "--renderer" => {
+                    config.renderer = RendererKind::parse(&next_value(&mut args, &arg)?)?;
+                }
+                "--tui" => config.tui = true,
+                "--host" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let port = value
+                        .parse::<u16>()
+                        .map_err(|_| format!("Invalid port '{}' for --host", value))?;
+                    config.host_port = Some(port);
+                }
+                "--host-bind" => {
+                    config.host_bind = next_value(&mut args, &arg)?;
+                }
+                "--connect" => {
+                    config.connect = Some(next_value(&mut args, &arg)?);
+                }
+                "--record-replay" => {
+                    config.record_replay = Some(next_value(&mut args, &arg)?);
+                }
+                "--ghost" => {
+                    config.ghost_replay = Some(next_value(&mut args, &arg)?);
+                }
+                "--seed" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let seed = value
+                        .parse::<u64>()
+                        .map_err(|_| format!("Invalid seed '{}' for --seed", value))?;
+                    config.seed = Some(seed);
+                }
+                "--compare" => {
+                    config.compare = Some(parse_controller_pair(&next_value(&mut args, &arg)?)?);
+                }
+                "--compare-ticks" => {
+                    let value = next_value(&mut args, &arg)?;
+                    config.compare_ticks = value.parse::<u64>().map_err(|_| {
+                        format!("Invalid tick count '{}' for --compare-ticks", value)
+                    })?;
+                }
+                "--batch" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let episodes = value
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|&episodes| episodes > 0)
+                        .ok_or_else(|| format!("Invalid episode count '{}' for --batch", value))?;
+                    config.batch = Some(episodes);
+                }
+                "--batch-ticks" => {
+                    let value = next_value(&mut args, &arg)?;
+                    config.batch_ticks = value
+                        .parse::<u64>()
+                        .map_err(|_| format!("Invalid tick count '{}' for --batch-ticks", value))?;
+                }
+                "--batch-threads" => {
+                    let value = next_value(&mut args, &arg)?;
+                    config.batch_threads = value.parse::<usize>().map_err(|_| {
+                        format!("Invalid thread count '{}' for --batch-threads", value)
+                    })?;
+                }
+                "--sweep" => {
+                    config.sweep = Some(Sweep::parse(&next_value(&mut args, &arg)?)?);
+                }
+                "--compare-runs" => {
+                    let value = next_value(&mut args, &arg)?;
+                    config.compare_runs = value
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|&runs| runs > 0)
+                        .ok_or_else(|| {
+                            format!("Invalid run count '{}' for --compare-runs", value)
+                        })?;
+                }
+                "--layout" => {
+                    config.layout = RoadLayout::parse(&next_value(&mut args, &arg)?)?;
+                }
+                "--merge" => {
+                    config.merge = Some(parse_merge_rates(&next_value(&mut args, &arg)?)?);
+                }
+                "--platooning" => config.platooning = true,
+                "--packet-loss" => {
+                    let chance: f32 = parse_number(&next_value(&mut args, &arg)?, &arg)?;
+                    if !(0.0..1.0).contains(&chance) {
+                        return Err(format!("--packet-loss must be in [0, 1), got {}", chance));
+                    }
+                    config.packet_loss = chance;
+                }
+                "--close-call-dir" => {
+                    config.close_call_dir = Some(next_value(&mut args, &arg)?);
+                }
+                "--fleet-mix" => {
+                    config.fleet_mix = FleetMix::parse(&next_value(&mut args, &arg)?)?;
+                }
+                "--speed-variance" => {
+                    let variance: f32 = parse_number(&next_value(&mut args, &arg)?, &arg)?;
+                    if !(0.0..1.0).contains(&variance) {
+                        return Err(format!(
+                            "--speed-variance must be in [0, 1), got {}",
+                            variance
+                        ));
+                    }
+                    config.speed_variance = variance;
+                }
+                "--slot-padding" => {
+                    let padding: f32 = parse_number(&next_value(&mut args, &arg)?, &arg)?;
+                    if padding < 0.0 {
+                        return Err(format!("--slot-padding can't be negative, got {}", padding));
+                    }
+                    config.slot_padding = padding;
+                }
+                "--path-padding" => {
+                    let value = next_value(&mut args, &arg)?;
+                    config.path_padding = value.parse::<usize>().map_err(|_| {
+                        format!("Invalid cell count '{}' for --path-padding", value)
+                    })?;
+                }
+                "--cost-weights" => {
+                    config.cost_weights = CostWeights::parse(&next_value(&mut args, &arg)?)?;
+                }
+                "--history-db" => {
+                    config.history_db = Some(next_value(&mut args, &arg)?);
+                }
+                "--no-history" => {
+                    config.history_db = None;
+                }
+                "--duration" => {
+                    config.run_limit = Some(RunLimit::parse(&next_value(&mut args, &arg)?)?);
+                }
+                "--exit-when-done" => config.exit_when_done = true,
+                "--challenge" => {
+                    config.challenge = Some(Challenge::parse(&next_value(&mut args, &arg)?)?);
+                }
+                "--no-onboarding" => {
+                    config.onboarding = false;
+                }
+                "--lang" => {
+                    config.language = next_value(&mut args, &arg)?;
+                }
+                "--no-vsync" => config.frame_rate.vsync = false,
+                "--debug-keys" => config.debug_keys = true,
+                "--fps" => {
+                    config.frame_rate.fps = FrameRate::parse_fps(&next_value(&mut args, &arg)?)?;
+                }
+                "--max-vehicles" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let cap = value.parse::<usize>().map_err(|_| {
+                        format!("Invalid vehicle count '{}' for --max-vehicles", value)
+                    })?;
+                    if cap == 0 {
+                        return Err("--max-vehicles must be at least 1".to_string());
+                    }
+                    config.max_vehicles = Some(cap);
+                }
+                "--starvation-limit" => {
+                    let limit = parse_number(&next_value(&mut args, &arg)?, &arg)?;
+                    if limit <= 0.0 {
+                        return Err(format!(
+                            "--starvation-limit must be positive, got {}",
+                            limit
+                        ));
+                    }
+                    config.starvation_limit = Some(limit);
+                }
+                "--no-rerouting" => config.rerouting = false,
+                "--closed-lane" => {
+                    config
+                        .lane_closures
+                        .push(LaneClosure::parse(&next_value(&mut args, &arg)?)?);
+                }
+                "--hazard-rate" => {
+                    let rate = parse_number(&next_value(&mut args, &arg)?, &arg)?;
+                    if rate < 0.0 {
+                        return Err(format!("--hazard-rate can't be negative, got {}", rate));
+                    }
+                    config.hazard_rate = rate;
+                }
+                "--warm-up" => {
+                    let seconds = parse_number(&next_value(&mut args, &arg)?, &arg)?;
+                    if seconds < 0.0 {
+                        return Err(format!("--warm-up can't be negative, got {}", seconds));
+                    }
+                    config.warm_up = seconds;
+                }
+                "--checkpoint-every" => {
+                    let every = parse_number(&next_value(&mut args, &arg)?, &arg)?;
+                    if every <= 0.0 {
+                        return Err(format!(
+                            "--checkpoint-every must be positive, got {}",
+                            every
+                        ));
+                    }
+                    config.checkpoint_every = Some(every);
+                }
+                "--checkpoint-dir" => config.checkpoint_dir = next_value(&mut args, &arg)?,
+                "--checkpoints-kept" => {
+                    let value = next_value(&mut args, &arg)?;
+                    config.checkpoints_kept = value
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|kept| *kept > 0)
+                        .ok_or_else(|| {
+                            format!(
+                                "Invalid checkpoint count '{}' for --checkpoints-kept",
+                                value
+                            )
+                        })?;
+                }
+                "--resume" => config.resume = Some(next_value(&mut args, &arg)?),
+                "--verify-determinism" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let ticks = value.parse::<u64>().map_err(|_| {
+                        format!("Invalid tick count '{}' for --verify-determinism", value)
+                    })?;
+                    config.verify_ticks = Some(ticks);
+                }
+                "--gym" => config.gym = true,
+                "--export-dataset" => {
+                    config.export_dataset = Some(next_value(&mut args, &arg)?);
+                }
+                _ => return Err(format!("Unknown argument: {}", arg)),
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+fn next_value(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<String, String> {
+    args.next()
+        .ok_or_else(|| format!("Missing value for {}", flag))
+}
+
+/// A finite number; `NaN` and `inf` parse as floats but fail every range check
+fn parse_number(value: &str, flag: &str) -> Result<f32, String> {
+    value
+        .parse::<f32>()
+        .ok()
+        .filter(|number| number.is_finite())
+        .ok_or_else(|| format!("Invalid number '{}' for {}", value, flag))
+}
+
+/// Parse `--compare`: two controller names separated by a comma
+fn parse_controller_pair(text: &str) -> Result<[ControllerKind; 2], String> {
+    match text.split(',').collect::<Vec<_>>()[..] {
+        [first, second] => Ok([
+            ControllerKind::parse(first.trim())?,
+            ControllerKind::parse(second.trim())?,
+        ]),
+        _ => Err(format!(
+            "--compare needs two controllers separated by a comma, got '{}'",
+            text
+        )),
+    }
+}
+
+/// Parse `--merge`: arrivals per minute on the main road and the ramp
+fn parse_merge_rates(text: &str) -> Result<[f32; 2], String> {
+    let rates: Vec<f32> = text
+        .split(',')
+        .map(|r| r.trim().parse::<f32>())
+        .collect::<Result<_, _>>()
+        .map_err(|_| format!("Invalid merge arrival rates '{}'", text))?;
+    match rates[..] {
+        [main, ramp] if main >= 0.0 && ramp >= 0.0 && main.is_finite() && ramp.is_finite() => {
+            Ok([main, ramp])
+        }
+        _ => Err(format!(
+            "--merge needs two non-negative rates (main,ramp), got '{}'",
+            text
+        )),
+    }
+}
@@ -0,0 +1,88 @@
+use crate::route::{Direction, Route, get_spawn_position};
+use serde::{Deserialize, Serialize};
+
+/// Length (px) of the roadworks on a closed lane, ending at the intersection box
+pub const WORKS_LENGTH: f32 = 120.0;
+/// Gap (px) between the works and each side of the lane
+const WORKS_INSET: f32 = 10.0;
+
+/// A lane closed for roadworks during part of the run (`--closed-lane`)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LaneClosure {
+    pub direction: Direction,
+    pub route: Route,
+    pub start: f32,       // seconds into the run
+    pub end: Option<f32>, // None = closed until the end
+}
+
+impl LaneClosure {
+    /// Parse `north:left`, `north:left@30` or `north:left@30-90`: the approach,
+    /// the lane by its route, and when the works start and end
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let (lane, times) = match text.split_once('@') {
+            Some((lane, times)) => (lane, Some(times)),
+            None => (text, None),
+        };
+        let (direction, route) = lane
+            .split_once(':')
+            .ok_or_else(|| format!("Closed lane '{}' needs direction:route", text))?;
+        let direction = match direction.trim().to_lowercase().as_str() {
+            "north" => Direction::North,
+            "south" => Direction::South,
+            "east" => Direction::East,
+            "west" => Direction::West,
+            other => return Err(format!("Unknown direction '{}' in closed lane", other)),
+        };
+        let route = match route.trim().to_lowercase().as_str() {
+            "left" => Route::Left,
+            "straight" => Route::Straight,
+            "right" => Route::Right,
+            other => return Err(format!("Unknown route '{}' in closed lane", other)),
+        };
+
+        let seconds = |value: &str| {
+            value
+                .trim()
+                .parse::<f32>()
+                .ok()
+                .filter(|s| *s >= 0.0)
+                .ok_or_else(|| format!("Invalid time '{}' in closed lane '{}'", value, text))
+        };
+        let (start, end) = match times {
+            None => (0.0, None),
+            Some(times) => match times.split_once('-') {
+                Some((start, end)) => (seconds(start)?, Some(seconds(end)?)),
+                None => (seconds(times)?, None),
+            },
+        };
+        if end.is_some_and(|end| end <= start) {
+            return Err(format!("Closed lane '{}' must end after it starts", text));
+        }
+
+        Ok(Self {
+            direction,
+            route,
+            start,
+            end,
+        })
+    }
+
+    pub fn is_active(&self, current_time: f32) -> bool {
+        current_time >= self.start && self.end.is_none_or(|end| current_time < end)
+    }
+
+    /// World rectangle (x, y, width, height) of the works: the last stretch
+    /// of the lane before the intersection box. It keeps 10 px inside the lane
+    /// markings, as vehicles in the next lane ride slightly over them.
+    pub fn area(&self) -> (f32, f32, f32, f32) {
+        let (x, y) = get_spawn_position(self.direction, self.route);
+        let (x, y) = (x + WORKS_INSET, y + WORKS_INSET);
+        let width = 50.0 - 2.0 * WORKS_INSET;
+        match self.direction {
+            Direction::North => (x, 650.0, width, WORKS_LENGTH),
+            Direction::South => (x, 350.0 - WORKS_LENGTH, width, WORKS_LENGTH),
+            Direction::East => (350.0 - WORKS_LENGTH, y, WORKS_LENGTH, width),
+            Direction::West => (650.0, y, WORKS_LENGTH, width),
+        }
+    }
+}
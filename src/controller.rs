@@ -0,0 +1,320 @@
+use crate::heatmap::Occupancy;
+use crate::layout::RoadLayout;
+use crate::mpc::MpcController;
+use crate::reservation::{DEFAULT_FOOTPRINT_MARGIN, ReservationController};
+use crate::route::{Direction, Route};
+use crate::stop_sign::StopSignController;
+use crate::traffic_light::TrafficLightController;
+use crate::velocities::Velocity;
+use serde::{Deserialize, Serialize};
+
+/// Everything a controller is told about a vehicle asking to cross
+#[derive(Debug, Clone, Copy)]
+pub struct PermissionRequest {
+    pub vehicle_id: usize,
+    pub direction: Direction,
+    pub route: Route,
+    pub speed: Velocity,     // fastest speed the vehicle would like to cross at
+    pub top_speed: Velocity, // fastest the vehicle can go at all
+    pub acceleration: f32,   // px/frame the vehicle gains each frame pulling away
+    pub distance_to_intersection: f32,
+    pub stopped: bool, // the vehicle is standing still
+    pub margin: f32,   // seconds of slack wanted before and after its crossing (weather + driver)
+}
+
+/// One time window a vehicle holds on a row of a controller's grid
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BookedSlot {
+    pub row: usize,
+    pub vehicle_id: usize,
+    pub start: f32,
+    pub end: f32,
+}
+
+/// A slot another vehicle holds that overlaps the window a request needed
+/// on the same cell
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SlotConflict {
+    pub cell: (usize, usize), // grid column and row
+    pub vehicle_id: usize,    // who holds the slot
+    pub start: f32,
+    pub end: f32,
+}
+
+/// The kinds of reasons a request goes without a crossing, counted in the
+/// final statistics
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DenialCause {
+    Conflict,        // other vehicles' slots overlap the windows needed
+    LaneClosed,      // the lane is closed for roadworks
+    Rule,            // the controller's own rule: a red light, someone else's turn
+    YieldToStarving, // traffic in a long-waiting vehicle's way holds back
+    Handover,        // a controller switched in mid-run waits for the old crossings
+    NoReply,         // the request or its answer was lost, or the backoff isn't over
+}
+
+impl DenialCause {
+    pub const ALL: [DenialCause; 6] = [
+        DenialCause::Conflict,
+        DenialCause::LaneClosed,
+        DenialCause::Rule,
+        DenialCause::YieldToStarving,
+        DenialCause::Handover,
+        DenialCause::NoReply,
+    ];
+
+    /// Locale key of the cause's short description
+    pub fn locale_key(self) -> &'static str {
+        match self {
+            DenialCause::Conflict => "denial.conflict",
+            DenialCause::LaneClosed => "denial.lane_closed",
+            DenialCause::Rule => "denial.rule",
+            DenialCause::YieldToStarving => "denial.yield_to_starving",
+            DenialCause::Handover => "denial.handover",
+            DenialCause::NoReply => "denial.no_reply",
+        }
+    }
+}
+
+/// Why a request was turned down; for slot conflicts, every slot in the way
+/// at the slowest speed tried
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Denial {
+    pub cause: DenialCause,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conflicts: Vec<SlotConflict>,
+}
+
+impl Denial {
+    pub fn new(cause: DenialCause) -> Self {
+        Self {
+            cause,
+            conflicts: Vec::new(),
+        }
+    }
+}
+
+/// Everything a grid-based controller has booked, for debug views
+#[derive(Debug, Clone, Default)]
+pub struct Timeline {
+    pub rows: usize,
+    pub slots: Vec<BookedSlot>,
+}
+
+/// Which vehicles hold each grid cell at one moment, for the live owner overlay
+#[derive(Debug, Clone, Default)]
+pub struct CellOwners {
+    pub cols: usize,
+    pub left: f32, // world position of the grid's top-left corner
+    pub top: f32,
+    pub cell_size: f32,
+    pub owners: Vec<Vec<usize>>, // vehicles booked on each cell right now, row by row
+}
+
+/// Decides which vehicles may enter the intersection and when.
+///
+/// `SmartIntersection` owns the vehicles and physics; a controller only sees
+/// permission requests and lifecycle notifications, so alternative policies
+/// can be plugged in without touching the update loop.
+pub trait IntersectionController {
+    /// Human-readable name shown in the statistics
+    fn name(&self) -> &'static str;
+
+    /// Grant or deny a crossing; `Some(speed)` is the speed the vehicle must hold
+    fn request_permission(
+        &mut self,
+        request: &PermissionRequest,
+        current_time: f32,
+    ) -> Option<Velocity>;
+
+    /// Why the last request or platoon request was turned down, if the
+    /// controller can tell; taking it clears it. None is read as the
+    /// controller's own rule.
+    fn take_denial(&mut self) -> Option<Denial> {
+        None
+    }
+
+    /// Speed a vehicle holding a crossing should drive at right now, for
+    /// controllers that plan a speed profile instead of a single speed. None
+    /// leaves the vehicle at full speed once granted.
+    fn planned_speed(
+        &self,
+        _vehicle_id: usize,
+        _distance_to_intersection: f32,
+        _current_time: f32,
+    ) -> Option<Velocity> {
+        None
+    }
+
+    /// Grant a whole platoon in one go: the leader's request plus its followers
+    /// as (vehicle id, distance to the intersection), all crossing at the
+    /// returned speed. Controllers that can't book platoons return None and the
+    /// vehicles ask one by one.
+    fn request_platoon(
+        &mut self,
+        _leader: &PermissionRequest,
+        _followers: &[(usize, f32)],
+        _current_time: f32,
+    ) -> Option<Velocity> {
+        None
+    }
+
+    /// Whether vehicles on these two paths can need the same space; without
+    /// path geometry a controller treats every pair as conflicting
+    fn paths_conflict(&self, _a: (Direction, Route), _b: (Direction, Route)) -> bool {
+        true
+    }
+
+    /// A lane has closed for roadworks or reopened; while closed nobody may
+    /// book its path
+    fn notify_lane_closed(&mut self, _direction: Direction, _route: Route, _closed: bool) {}
+
+    /// A vehicle has just driven into the intersection box
+    fn notify_enter(&mut self, _vehicle_id: usize, _current_time: f32) {}
+
+    /// A vehicle has driven out of the intersection box
+    fn notify_leave(&mut self, _vehicle_id: usize, _current_time: f32) {}
+
+    /// Called every frame for vehicles near or inside the intersection or
+    /// holding a crossing, with the direction they approached from and the
+    /// bounds they occupied and speed they drove at at the start of the frame
+    fn notify_progress(
+        &mut self,
+        _vehicle_id: usize,
+        _direction: Direction,
+        _route: Route,
+        _bounds: (f32, f32, f32, f32),
+        _speed: Velocity,
+        _current_time: f32,
+    ) {
+    }
+
+    /// Vehicles whose crossing the controller has taken back since it was
+    /// last asked, e.g. for falling too far behind it; they have to ask again
+    fn take_withdrawn(&mut self) -> Vec<usize> {
+        Vec::new()
+    }
+
+    /// A vehicle has left the simulation (exited, towed away or cleared after a crash)
+    fn notify_exit(&mut self, vehicle_id: usize, current_time: f32);
+
+    /// A stranded vehicle blocks `bounds` until `until` (or until it exits)
+    fn notify_blocked(
+        &mut self,
+        _vehicle_id: usize,
+        _bounds: (f32, f32, f32, f32),
+        _current_time: f32,
+        _until: f32,
+    ) {
+    }
+
+    /// A previously stranded vehicle is moving again
+    fn notify_unblocked(&mut self, _vehicle_id: usize) {}
+
+    /// Vehicles holding time between `from` and `until` on the cells under `bounds`
+    fn booked_over(&self, _bounds: (f32, f32, f32, f32), _from: f32, _until: f32) -> Vec<usize> {
+        Vec::new()
+    }
+
+    /// Cancel a vehicle's booking; it has to ask again before crossing
+    fn revoke(&mut self, _vehicle_id: usize) {}
+
+    /// Drop every booked time slot while granted vehicles keep their
+    /// crossing, to provoke conflicts on purpose (`--debug-keys`)
+    fn clear_bookings(&mut self) {}
+
+    /// Earliest start and latest end of the time slots a vehicle holds, if any
+    fn reservation_window(&self, _vehicle_id: usize) -> Option<(f32, f32)> {
+        None
+    }
+
+    /// Booked time slots per grid row, if the controller books any
+    fn timeline(&self) -> Option<Timeline> {
+        None
+    }
+
+    /// Who holds each grid cell at `current_time`, if the controller books any;
+    /// more than one owner on a cell is a conflict
+    fn cell_owners(&self, _current_time: f32) -> Option<CellOwners> {
+        None
+    }
+
+    /// Slots booked on every cell right now, if the controller books any
+    fn booked_slots(&self) -> Option<usize> {
+        None
+    }
+
+    /// How often each grid cell has been booked so far, if the controller books any
+    fn occupancy(&self) -> Option<Occupancy> {
+        None
+    }
+
+    /// Called once per frame before any requests are made
+    fn tick(&mut self, _current_time: f32) {}
+
+    /// Bookings and queues to store in a checkpoint, or None if the
+    /// controller can't save them
+    fn save_state(&self) -> Option<serde_json::Value> {
+        None
+    }
+
+    /// Take back the state `save_state` returned
+    fn restore_state(&mut self, _state: serde_json::Value) -> Result<(), String> {
+        Err(format!(
+            "The {} controller can't resume from a checkpoint",
+            self.name()
+        ))
+    }
+}
+
+/// The built-in controllers selectable with `--controller`
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ControllerKind {
+    #[default]
+    Reservation,
+    TrafficLight,
+    StopSign,
+    Mpc,
+}
+
+impl ControllerKind {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.to_lowercase().as_str() {
+            "reservation" => Ok(ControllerKind::Reservation),
+            "traffic-light" | "traffic_light" | "trafficlight" => Ok(ControllerKind::TrafficLight),
+            "stop-sign" | "stop_sign" | "stopsign" => Ok(ControllerKind::StopSign),
+            "mpc" => Ok(ControllerKind::Mpc),
+            _ => Err(format!(
+                "Unknown controller '{}' (expected reservation, traffic-light, stop-sign or mpc)",
+                name
+            )),
+        }
+    }
+
+    /// The controller for `layout`; grid-based ones widen every path by
+    /// `path_padding` cells
+    pub fn build(self, layout: RoadLayout, path_padding: usize) -> Box<dyn IntersectionController> {
+        match self {
+            ControllerKind::Reservation => Box::new(ReservationController::with_margins(
+                layout,
+                DEFAULT_FOOTPRINT_MARGIN,
+                path_padding,
+            )),
+            ControllerKind::TrafficLight => Box::new(TrafficLightController::new(layout)),
+            ControllerKind::StopSign => Box::new(StopSignController::new()),
+            ControllerKind::Mpc => Box::new(MpcController::new(layout, path_padding)),
+        }
+    }
+
+    /// The controller the K key switches to
+    pub fn next(self) -> Self {
+        match self {
+            ControllerKind::Reservation => ControllerKind::TrafficLight,
+            ControllerKind::TrafficLight => ControllerKind::StopSign,
+            ControllerKind::StopSign => ControllerKind::Mpc,
+            ControllerKind::Mpc => ControllerKind::Reservation,
+        }
+    }
+}
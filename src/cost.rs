@@ -0,0 +1,158 @@
+use crate::vehicle::Vehicle;
+use crate::velocities::Velocity;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// How much each outcome adds to the cost of a run. The same weights score
+/// runs for the leaderboard, compare controllers in `--compare` and reward
+/// `--gym` agents, so every comparison uses one definition of a good run.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CostWeights {
+    pub passed: f32,      // taken off per vehicle through, so throughput lowers the cost
+    pub delay: f32,       // per vehicle-second stopped before the intersection
+    pub stops: f32,       // per time a vehicle comes to a standstill
+    pub close_calls: f32, // per close call
+    pub collisions: f32,  // per collision
+    pub co2: f32,         // per gram of CO2 emitted
+}
+
+impl Default for CostWeights {
+    fn default() -> Self {
+        Self {
+            passed: 10.0,
+            delay: 0.5,
+            stops: 1.0,
+            close_calls: 5.0,
+            collisions: 50.0,
+            co2: 0.01,
+        }
+    }
+}
+
+impl CostWeights {
+    /// Parse `term=weight` pairs separated by commas, such as
+    /// `delay=1,co2=0`; terms left out keep their default weight
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut weights = Self::default();
+        for pair in text.split(',') {
+            let (term, value) = pair
+                .split_once('=')
+                .ok_or_else(|| format!("Cost weight '{}' needs the form term=weight", pair))?;
+            let value: f32 = value
+                .trim()
+                .parse()
+                .map_err(|_| format!("Invalid weight '{}' for {}", value, term))?;
+            let weight = match term.trim() {
+                "passed" => &mut weights.passed,
+                "delay" => &mut weights.delay,
+                "stops" => &mut weights.stops,
+                "close_calls" | "close-calls" => &mut weights.close_calls,
+                "collisions" => &mut weights.collisions,
+                "co2" => &mut weights.co2,
+                _ => {
+                    return Err(format!(
+                        "Unknown cost term '{}' (expected passed, delay, stops, close_calls, collisions or co2)",
+                        term
+                    ));
+                }
+            };
+            *weight = value;
+        }
+        Ok(weights)
+    }
+}
+
+/// Unweighted totals of each cost term
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct CostTerms {
+    pub passed: u32,
+    pub delay: f32, // vehicle-seconds
+    pub stops: u32,
+    pub close_calls: u32,
+    pub collisions: u32,
+    pub co2: f32, // grams
+}
+
+impl CostTerms {
+    pub fn weighted(&self, weights: &CostWeights) -> f32 {
+        weights.delay * self.delay
+            + weights.stops * self.stops as f32
+            + weights.close_calls * self.close_calls as f32
+            + weights.collisions * self.collisions as f32
+            + weights.co2 * self.co2
+            - weights.passed * self.passed as f32
+    }
+}
+
+/// The counters of the simulation the cost is built from, read each tick
+pub struct CostInputs<'a> {
+    pub vehicles: &'a [&'a Vehicle],
+    pub passed: u32,
+    pub close_calls: u32,
+    pub collisions: u32,
+}
+
+/// The cost of a run so far, added up tick by tick
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CostTracker {
+    pub weights: CostWeights,
+    pub totals: CostTerms,
+    last_time: Option<f32>,
+    last_counts: (u32, u32, u32), // passed, close calls and collisions at the last tick
+    vehicles: HashMap<usize, (bool, f32)>, // standing still and mL burned at the last tick
+}
+
+impl CostTracker {
+    pub fn new(weights: CostWeights) -> Self {
+        Self {
+            weights,
+            ..Self::default()
+        }
+    }
+
+    /// Add what happened since the last tick
+    pub fn update(&mut self, inputs: CostInputs, current_time: f32) {
+        let dt = self
+            .last_time
+            .map_or(1.0 / 60.0, |last| current_time - last);
+        self.last_time = Some(current_time);
+
+        let (passed, close_calls, collisions) = self.last_counts;
+        self.totals.passed += inputs.passed.saturating_sub(passed);
+        self.totals.close_calls += inputs.close_calls.saturating_sub(close_calls);
+        self.totals.collisions += inputs.collisions.saturating_sub(collisions);
+        self.last_counts = (inputs.passed, inputs.close_calls, inputs.collisions);
+
+        let mut vehicles = HashMap::with_capacity(inputs.vehicles.len());
+        for vehicle in inputs.vehicles {
+            let stopped = vehicle.current_speed == Velocity::STOPPED;
+            let (was_stopped, burned) = self
+                .vehicles
+                .get(&vehicle.id)
+                .copied()
+                .unwrap_or((false, 0.0));
+            if stopped && !was_stopped {
+                self.totals.stops += 1;
+            }
+            if stopped && !vehicle.entered_intersection {
+                self.totals.delay += dt;
+            }
+            self.totals.co2 += (vehicle.fuel_used - burned).max(0.0) * vehicle.class.co2_per_ml();
+            vehicles.insert(vehicle.id, (stopped, vehicle.fuel_used));
+        }
+        self.vehicles = vehicles;
+    }
+
+    /// Drop the totals so far, as at the end of a warm-up, but keep measuring
+    /// the vehicles on the road from where they are now
+    pub fn restart(&mut self) {
+        self.totals = CostTerms::default();
+        self.last_counts = (0, 0, 0);
+    }
+
+    /// Weighted cost so far
+    pub fn total(&self) -> f32 {
+        self.totals.weighted(&self.weights)
+    }
+}
@@ -0,0 +1,88 @@
+use crate::demand::{ScheduledSpawn, SpawnSchedule};
+use crate::fuel::VehicleClass;
+use crate::route::{Direction, Route};
+
+/// Load vehicle demand from a CSV of `time,direction,route,vehicle_kind` rows.
+///
+/// Times are seconds into the run; directions are the way the vehicle
+/// travels (`north`, `south`, `east`, `west`); routes are `left`, `straight` or
+/// `right`; the kind is `petrol`, `diesel` or `electric`, or empty to draw it
+/// from the fleet mix. A header row, blank lines and lines starting with `#`
+/// are skipped.
+pub fn load_csv_schedule(path: &str) -> Result<SpawnSchedule, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let spawns = parse_csv_schedule(&text).map_err(|e| format!("{}: {}", path, e))?;
+    if spawns.is_empty() {
+        return Err(format!("{}: no spawns in the schedule", path));
+    }
+    Ok(SpawnSchedule::new(spawns))
+}
+
+fn parse_csv_schedule(text: &str) -> Result<Vec<ScheduledSpawn>, String> {
+    let mut spawns = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        // A header names the columns instead of giving a time
+        if spawns.is_empty() && fields[0].eq_ignore_ascii_case("time") {
+            continue;
+        }
+        let spawn = parse_row(&fields).map_err(|e| format!("line {}: {}", index + 1, e))?;
+        spawns.push(spawn);
+    }
+    Ok(spawns)
+}
+
+fn parse_row(fields: &[&str]) -> Result<ScheduledSpawn, String> {
+    let (time, direction, route, kind) = match fields {
+        [time, direction, route] => (time, direction, route, &""),
+        [time, direction, route, kind] => (time, direction, route, kind),
+        _ => {
+            return Err(format!(
+                "expected time,direction,route,vehicle_kind but got {} fields",
+                fields.len()
+            ));
+        }
+    };
+    let time = time
+        .parse::<f32>()
+        .ok()
+        .filter(|t| t.is_finite() && *t >= 0.0)
+        .ok_or_else(|| format!("invalid time '{}'", time))?;
+    let direction = match direction.to_lowercase().as_str() {
+        "north" => Direction::North,
+        "south" => Direction::South,
+        "east" => Direction::East,
+        "west" => Direction::West,
+        other => return Err(format!("unknown direction '{}'", other)),
+    };
+    let route = match route.to_lowercase().as_str() {
+        "left" => Route::Left,
+        "straight" => Route::Straight,
+        "right" => Route::Right,
+        other => return Err(format!("unknown route '{}'", other)),
+    };
+    let class = match kind.to_lowercase().as_str() {
+        "" => None,
+        name => Some(
+            VehicleClass::ALL
+                .into_iter()
+                .find(|class| class.name() == name)
+                .ok_or_else(|| {
+                    format!(
+                        "unknown vehicle kind '{}' (expected petrol, diesel or electric)",
+                        name
+                    )
+                })?,
+        ),
+    };
+    Ok(ScheduledSpawn {
+        time,
+        direction,
+        route,
+        class,
+    })
+}
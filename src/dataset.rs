@@ -0,0 +1,81 @@
+use crate::observation::{Encoding, LANES, VEHICLE_FEATURES, encode};
+use crate::replay::read_frames;
+use crate::vehicle::Vehicle;
+use ndarray::{Array, Array1, Array2, Array3, Axis, Dimension};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+/// Directory the dataset goes to when `--output-dir` isn't given
+pub const DEFAULT_DATASET_DIR: &str = "dataset";
+
+/// Encode every frame of a recorded replay with `observation::encode` and
+/// write the tensors as NumPy `.npy` files into `dir`, for training an
+/// imitation-learning policy offline on what the recorded controller did:
+///
+/// - `times.npy`: frames, the simulation time of each
+/// - `vehicles.npy`: frames × max_vehicles × VEHICLE_FEATURES
+/// - `lanes.npy`: frames × LANES × lane_cells
+/// - `grid.npy`: frames × grid × grid
+/// - `granted.npy`: frames × max_vehicles, 1 where the vehicle in that row
+///   holds a crossing in the next frame: the controller's decision to learn
+///
+/// Returns the number of frames written.
+pub fn export_dataset(replay: &str, dir: &str, encoding: &Encoding) -> Result<usize, String> {
+    let frames = read_frames(replay)?;
+    let count = frames.len();
+    let mut times = Array1::zeros(count);
+    let mut vehicles = Array3::zeros((count, encoding.max_vehicles, VEHICLE_FEATURES));
+    let mut lanes = Array3::zeros((count, LANES, encoding.lane_cells));
+    let mut grid = Array3::zeros((count, encoding.grid, encoding.grid));
+    let mut granted = Array2::zeros((count, encoding.max_vehicles));
+
+    for (index, frame) in frames.iter().enumerate() {
+        let road: Vec<Vehicle> = frame.vehicles.iter().map(|v| v.to_vehicle()).collect();
+        let tensors = encode(&road.iter().collect::<Vec<_>>(), encoding);
+        times[index] = frame.time;
+        vehicles
+            .index_axis_mut(Axis(0), index)
+            .assign(&tensors.vehicles);
+        lanes.index_axis_mut(Axis(0), index).assign(&tensors.lanes);
+        grid.index_axis_mut(Axis(0), index).assign(&tensors.grid);
+
+        let Some(next) = frames.get(index + 1) else {
+            continue;
+        };
+        for (slot, id) in tensors.slots.iter().enumerate() {
+            let holds = next
+                .vehicles
+                .iter()
+                .any(|vehicle| vehicle.id == *id && vehicle.permission);
+            granted[[index, slot]] = if holds { 1.0 } else { 0.0 };
+        }
+    }
+
+    let dir = Path::new(dir);
+    std::fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    write_npy(dir, "times.npy", &times)?;
+    write_npy(dir, "vehicles.npy", &vehicles)?;
+    write_npy(dir, "lanes.npy", &lanes)?;
+    write_npy(dir, "grid.npy", &grid)?;
+    write_npy(dir, "granted.npy", &granted)?;
+    Ok(count)
+}
+
+/// Write an array as a little-endian f32 `.npy` file in C order
+fn write_npy<D: Dimension>(dir: &Path, name: &str, array: &Array<f32, D>) -> Result<(), String> {
+    use npyz::WriterBuilder;
+
+    let path = dir.join(name);
+    let error = |e: std::io::Error| format!("{}: {}", path.display(), e);
+    let file = File::create(&path).map_err(error)?;
+    let shape: Vec<u64> = array.shape().iter().map(|&length| length as u64).collect();
+    let mut writer = npyz::WriteOptions::new()
+        .default_dtype()
+        .shape(&shape)
+        .writer(BufWriter::new(file))
+        .begin_nd()
+        .map_err(error)?;
+    writer.extend(array.iter().copied()).map_err(error)?;
+    writer.finish().map_err(error)
+}
@@ -0,0 +1,176 @@
+use crate::fuel::VehicleClass;
+use crate::route::{Direction, Route};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// A vehicle that should enter the simulation at a given time
+#[derive(Debug, Clone, Copy)]
+pub struct ScheduledSpawn {
+    pub time: f32,
+    pub direction: Direction,
+    pub route: Route,
+    pub class: Option<VehicleClass>, // None = drawn from the fleet mix
+}
+
+/// Time-ordered list of spawns replayed during a run
+#[derive(Debug, Default)]
+pub struct SpawnSchedule {
+    spawns: VecDeque<ScheduledSpawn>,
+}
+
+impl SpawnSchedule {
+    pub fn new(mut spawns: Vec<ScheduledSpawn>) -> Self {
+        spawns.sort_by(|a, b| a.time.total_cmp(&b.time));
+        Self {
+            spawns: spawns.into(),
+        }
+    }
+
+    /// Pop every spawn whose time has come
+    pub fn due(&mut self, current_time: f32) -> Vec<ScheduledSpawn> {
+        let mut due = Vec::new();
+        while let Some(spawn) = self.spawns.front() {
+            if spawn.time > current_time {
+                break;
+            }
+            due.push(*spawn);
+            self.spawns.pop_front();
+        }
+        due
+    }
+
+    /// Both schedules' spawns as one, still in time order
+    pub fn merged(self, other: SpawnSchedule) -> Self {
+        Self::new(self.spawns.into_iter().chain(other.spawns).collect())
+    }
+
+    pub fn len(&self) -> usize {
+        self.spawns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.spawns.is_empty()
+    }
+}
+
+/// Independent Poisson arrival processes, one per approach
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoissonArrivals {
+    rates: [(Direction, f32); 4], // vehicles per minute
+    next_arrival: [f32; 4],
+}
+
+impl PoissonArrivals {
+    /// `rates` are vehicles per minute for north, south, east and west
+    pub fn new(rates: [f32; 4], rng: &mut impl Rng) -> Self {
+        let rates = [
+            (Direction::North, rates[0]),
+            (Direction::South, rates[1]),
+            (Direction::East, rates[2]),
+            (Direction::West, rates[3]),
+        ];
+        let next_arrival = rates.map(|(_, rate)| next_gap(rate, rng));
+        Self {
+            rates,
+            next_arrival,
+        }
+    }
+
+    /// Parse `--arrivals`: one rate for every approach, or `north,south,east,west`
+    pub fn parse_rates(text: &str) -> Result<[f32; 4], String> {
+        let rates: Vec<f32> = text
+            .split(',')
+            .map(|r| r.trim().parse::<f32>())
+            .collect::<Result<_, _>>()
+            .map_err(|_| format!("Invalid arrival rates '{}'", text))?;
+
+        if rates.iter().any(|&r| r < 0.0 || !r.is_finite()) {
+            return Err(format!("Arrival rates must not be negative: '{}'", text));
+        }
+        match rates[..] {
+            [rate] => Ok([rate; 4]),
+            [north, south, east, west] => Ok([north, south, east, west]),
+            _ => Err(format!(
+                "Arrival rates '{}' need one value or four (north,south,east,west)",
+                text
+            )),
+        }
+    }
+
+    /// Directions with a vehicle arriving by `current_time`
+    pub fn due(&mut self, current_time: f32, rng: &mut impl Rng) -> Vec<Direction> {
+        let mut arrivals = Vec::new();
+        for (i, &(direction, rate)) in self.rates.iter().enumerate() {
+            while self.next_arrival[i] <= current_time {
+                arrivals.push(direction);
+                self.next_arrival[i] += next_gap(rate, rng);
+            }
+        }
+        arrivals
+    }
+}
+
+/// Random spawning toggled with R / S, one vehicle every `100 / level` ticks.
+/// Level 5 is the original rate of three vehicles a second; the number keys
+/// pick levels 1-9 while it runs, to sweep demand up to saturation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutoSpawn {
+    pub enabled: bool,
+    level: u32,
+    counter: u32,
+}
+
+impl Default for AutoSpawn {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            level: Self::DEFAULT_LEVEL,
+            counter: 0,
+        }
+    }
+}
+
+impl AutoSpawn {
+    pub const DEFAULT_LEVEL: u32 = 5;
+
+    /// Pick the intensity; levels outside 1-9 are clamped
+    pub fn set_level(&mut self, level: u32) {
+        self.level = level.clamp(1, 9);
+    }
+
+    pub fn level(&self) -> u32 {
+        self.level
+    }
+
+    /// Vehicles per second at the current level with 60 ticks a second
+    pub fn rate(&self) -> f32 {
+        60.0 / self.interval() as f32
+    }
+
+    fn interval(&self) -> u32 {
+        100 / self.level
+    }
+
+    /// Advance one tick; true when a vehicle should be spawned
+    pub fn tick(&mut self) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        self.counter += 1;
+        if self.counter >= self.interval() {
+            self.counter = 0;
+            return true;
+        }
+        false
+    }
+}
+
+/// Exponentially distributed gap (seconds) until the next arrival
+pub(crate) fn next_gap(rate_per_minute: f32, rng: &mut impl Rng) -> f32 {
+    if rate_per_minute <= 0.0 {
+        return f32::INFINITY;
+    }
+    let u: f32 = rng.random_range(f32::EPSILON..1.0);
+    -u.ln() * 60.0 / rate_per_minute
+}
@@ -0,0 +1,58 @@
+use serde::{Deserialize, Serialize};
+
+/// Headline numbers of a set of per-vehicle measurements
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Summary {
+    pub mean: f32,
+    pub median: f32,
+    pub p95: f32, // nearest-rank 95th percentile
+    pub max: f32,
+}
+
+impl Summary {
+    /// All zero for an empty set
+    pub fn of(values: &[f32]) -> Self {
+        if values.is_empty() {
+            return Self::default();
+        }
+        let mut sorted = values.to_vec();
+        sorted.sort_by(f32::total_cmp);
+        let middle = sorted.len() / 2;
+        let median = if sorted.len().is_multiple_of(2) {
+            (sorted[middle - 1] + sorted[middle]) / 2.0
+        } else {
+            sorted[middle]
+        };
+        Self {
+            mean: sorted.iter().sum::<f32>() / sorted.len() as f32,
+            median,
+            p95: sorted[(sorted.len() * 95).div_ceil(100) - 1],
+            max: sorted[sorted.len() - 1],
+        }
+    }
+}
+
+/// Mean and sample standard deviation of a set of per-run measurements
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Spread {
+    pub mean: f32,
+    pub stddev: f32, // zero for fewer than two values
+}
+
+impl Spread {
+    /// All zero for an empty set
+    pub fn of(values: &[f32]) -> Self {
+        if values.is_empty() {
+            return Self::default();
+        }
+        let count = values.len() as f32;
+        let mean = values.iter().sum::<f32>() / count;
+        let stddev = if values.len() < 2 {
+            0.0
+        } else {
+            let squares: f32 = values.iter().map(|value| (value - mean).powi(2)).sum();
+            (squares / (count - 1.0)).sqrt()
+        };
+        Self { mean, stddev }
+    }
+}
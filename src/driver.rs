@@ -0,0 +1,106 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// How a simulated driver trades safety for speed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DriverProfile {
+    Cautious,
+    Normal,
+    Aggressive,
+}
+
+impl DriverProfile {
+    /// Gap kept to the vehicle ahead at a standstill, on top of the vehicle
+    /// length
+    pub fn safety_distance(self) -> f32 {
+        match self {
+            DriverProfile::Cautious => 75.0,
+            DriverProfile::Normal => 50.0,
+            DriverProfile::Aggressive => 30.0,
+        }
+    }
+
+    /// Seconds of travel at the current speed kept to the vehicle ahead on
+    /// top of the standstill gap
+    pub fn time_headway(self) -> f32 {
+        match self {
+            DriverProfile::Cautious => 0.1,
+            DriverProfile::Normal => 0.06,
+            DriverProfile::Aggressive => 0.03,
+        }
+    }
+
+    /// Seconds added to both ends of a reservation slot.
+    /// Negative values mean the driver accepts tighter windows than the
+    /// weather calls for; the total margin never drops below zero.
+    pub fn reservation_padding(self) -> f32 {
+        match self {
+            DriverProfile::Cautious => 0.2,
+            DriverProfile::Normal => 0.0,
+            DriverProfile::Aggressive => -0.1,
+        }
+    }
+
+    /// Speed gained per frame when pulling away, in px/frame; the vehicle's
+    /// class may allow less
+    pub fn acceleration(self) -> f32 {
+        match self {
+            DriverProfile::Cautious => 0.3,
+            DriverProfile::Normal => 0.8,
+            DriverProfile::Aggressive => f32::INFINITY, // as hard as the vehicle can
+        }
+    }
+}
+
+/// Relative weights used when assigning profiles to new vehicles
+#[derive(Debug, Clone, Copy)]
+pub struct ProfileMix {
+    pub cautious: u32,
+    pub normal: u32,
+    pub aggressive: u32,
+}
+
+impl Default for ProfileMix {
+    fn default() -> Self {
+        Self {
+            cautious: 0,
+            normal: 1,
+            aggressive: 0,
+        }
+    }
+}
+
+impl ProfileMix {
+    /// Parse a `cautious,normal,aggressive` weight triple such as `20,60,20`
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let weights: Vec<u32> = text
+            .split(',')
+            .map(|w| w.trim().parse::<u32>())
+            .collect::<Result<_, _>>()
+            .map_err(|_| format!("Invalid driver mix '{}'", text))?;
+
+        match weights[..] {
+            [cautious, normal, aggressive] if cautious + normal + aggressive > 0 => Ok(Self {
+                cautious,
+                normal,
+                aggressive,
+            }),
+            _ => Err(format!(
+                "Driver mix '{}' needs three weights (cautious,normal,aggressive) with a positive sum",
+                text
+            )),
+        }
+    }
+
+    pub fn sample(&self, rng: &mut impl Rng) -> DriverProfile {
+        let total = self.cautious + self.normal + self.aggressive;
+        let pick = rng.random_range(0..total);
+        if pick < self.cautious {
+            DriverProfile::Cautious
+        } else if pick < self.cautious + self.normal {
+            DriverProfile::Normal
+        } else {
+            DriverProfile::Aggressive
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use crate::controller::{DenialCause, SlotConflict};
+use crate::route::{Direction, Route};
+use crate::velocities::Velocity;
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+/// Something that happened to a vehicle during the simulation
+#[derive(Debug, Clone, Serialize)]
+pub struct SimEvent {
+    pub time: f32,
+    pub vehicle_id: usize,
+    #[serde(flatten)]
+    pub kind: EventKind,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum EventKind {
+    Spawn {
+        direction: Direction,
+        route: Route,
+    },
+    PermissionGranted {
+        speed: Velocity,
+    },
+    PermissionDenied {
+        cause: DenialCause,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        conflicts: Vec<SlotConflict>,
+    },
+    JoinedPlatoon {
+        leader_id: usize,
+        speed: Velocity,
+    },
+    MessageLost {
+        reply: bool,
+    },
+    EnteredIntersection,
+    Turned {
+        direction: Direction,
+    },
+    Rerouted {
+        route: Route,
+    },
+    EmergencyStop,
+    Exited,
+    CloseCall {
+        other_vehicle_id: usize,
+    },
+    Collision {
+        other_vehicle_id: usize,
+    },
+    BrokeDown,
+    Repaired,
+    Removed,
+}
+
+impl SimEvent {
+    pub fn new(time: f32, vehicle_id: usize, kind: EventKind) -> Self {
+        Self {
+            time,
+            vehicle_id,
+            kind,
+        }
+    }
+}
+
+/// Writes simulation events as one JSON object per line
+pub struct EventLog {
+    writer: BufWriter<File>,
+}
+
+impl EventLog {
+    pub fn create(path: &str) -> Result<Self, String> {
+        let file = File::create(path).map_err(|e| format!("{}: {}", path, e))?;
+        Ok(Self {
+            writer: BufWriter::new(file),
+        })
+    }
+
+    pub fn write(&mut self, events: &[SimEvent]) -> Result<(), String> {
+        for event in events {
+            serde_json::to_writer(&mut self.writer, event).map_err(|e| e.to_string())?;
+            self.writer.write_all(b"\n").map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<(), String> {
+        self.writer.flush().map_err(|e| e.to_string())
+    }
+}
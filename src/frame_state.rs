@@ -0,0 +1,95 @@
+use crate::driver::DriverProfile;
+use crate::events::SimEvent;
+use crate::intersection::SmartIntersection;
+use crate::route::{Direction, Route};
+use crate::vehicle::Vehicle;
+use crate::velocities::Velocity;
+use serde::Serialize;
+
+/// Where a vehicle is in its dealings with the intersection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CrossingState {
+    Approaching, // hasn't asked to cross yet
+    Waiting,     // asked and has no crossing booked
+    Granted,     // booked, not in the box yet
+    Crossing,    // inside the intersection box
+    Crossed,     // through the box and heading off the road
+}
+
+/// What an outside observer sees of one vehicle
+#[derive(Debug, Clone, Serialize)]
+pub struct VehicleState {
+    pub id: usize,
+    pub direction: Direction, // current heading, changed by turning
+    pub route: Route,
+    pub centre: (f32, f32),
+    pub rotation: f64, // degrees
+    pub speed: Velocity,
+    pub braking: bool, // slowed down during the step
+    pub distance_to_intersection: f32,
+    pub crossing: CrossingState,
+    pub waiting_time: f32,
+    pub profile: DriverProfile,
+    pub broken_down: bool,
+    pub crashed: bool,
+}
+
+impl VehicleState {
+    pub fn new(vehicle: &Vehicle) -> Self {
+        let crossing = if vehicle.is_in_intersection() {
+            CrossingState::Crossing
+        } else if vehicle.entered_intersection {
+            CrossingState::Crossed
+        } else if vehicle.intersection_permission {
+            CrossingState::Granted
+        } else if vehicle.requested_intersection {
+            CrossingState::Waiting
+        } else {
+            CrossingState::Approaching
+        };
+        Self {
+            id: vehicle.id,
+            direction: vehicle.direction,
+            route: vehicle.route,
+            centre: vehicle.get_visual_center(),
+            rotation: vehicle.rotation,
+            speed: vehicle.current_speed,
+            braking: vehicle.braking,
+            distance_to_intersection: vehicle.distance_to_intersection(),
+            crossing,
+            waiting_time: vehicle.waiting_time,
+            profile: vehicle.profile,
+            broken_down: vehicle.breakdown.is_some(),
+            crashed: vehicle.crashed_at.is_some(),
+        }
+    }
+}
+
+/// The simulation after one `SmartIntersection::step`: every vehicle on the
+/// road and what happened during the step, for programs driving the
+/// simulation without a window
+#[derive(Debug, Clone, Serialize)]
+pub struct FrameState {
+    pub time: f32,
+    pub vehicles: Vec<VehicleState>,
+    pub events: Vec<SimEvent>,
+    pub vehicles_passed: u32,
+    pub pending_spawns: usize, // vehicles waiting for room to spawn
+}
+
+impl FrameState {
+    pub fn new(time: f32, intersection: &SmartIntersection, events: Vec<SimEvent>) -> Self {
+        Self {
+            time,
+            vehicles: intersection
+                .vehicles()
+                .into_iter()
+                .map(VehicleState::new)
+                .collect(),
+            events,
+            vehicles_passed: intersection.total_vehicles_passed,
+            pending_spawns: intersection.pending_spawn_count(),
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use crate::velocities::Velocity;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// Metres per world pixel: a 70 px car is about 4.5 m long
+const METRES_PER_PX: f32 = 0.064;
+/// Kilograms of a typical passenger car
+const VEHICLE_MASS: f32 = 1300.0;
+/// Millilitres burned per second with the engine idling (about 0.9 L/h)
+const IDLE_RATE: f32 = 0.25;
+/// Millilitres burned per metre at a steady speed (about 7 L/100 km)
+const CRUISE_RATE: f32 = 0.07;
+/// Millilitres per joule of kinetic energy gained: petrol holds about 34 MJ/L
+/// and a quarter of it reaches the wheels
+const ACCELERATION_RATE: f32 = 1.0 / (34.2e6 * 0.25 / 1000.0);
+
+/// Fuel burned during one frame, split into what moving and idling cost
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FrameFuel {
+    pub moving: f32, // mL spent driving and speeding up
+    pub idle: f32,   // mL spent standing with the engine running
+}
+
+/// Speed in metres per second
+fn metres_per_second(speed: Velocity) -> f32 {
+    speed.pixels_per_second() * METRES_PER_PX
+}
+
+/// Fuel for one frame at `current` after the previous frame at `previous`.
+/// Braking is free; each stop costs the energy of getting back up to speed,
+/// so stop-and-go traffic burns noticeably more than a steady flow.
+pub fn frame_fuel(previous: Velocity, current: Velocity) -> FrameFuel {
+    if current == Velocity::STOPPED {
+        return FrameFuel {
+            moving: 0.0,
+            idle: IDLE_RATE / 60.0,
+        };
+    }
+
+    let (before, after) = (metres_per_second(previous), metres_per_second(current));
+    let gained = (0.5 * VEHICLE_MASS * (after * after - before * before)).max(0.0);
+    FrameFuel {
+        moving: after / 60.0 * CRUISE_RATE + gained * ACCELERATION_RATE,
+        idle: 0.0,
+    }
+}
+
+/// What a vehicle runs on, which decides its tailpipe emissions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VehicleClass {
+    #[default]
+    Petrol,
+    Diesel,
+    Electric,
+}
+
+impl VehicleClass {
+    pub const ALL: [VehicleClass; 3] = [
+        VehicleClass::Petrol,
+        VehicleClass::Diesel,
+        VehicleClass::Electric,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            VehicleClass::Petrol => "petrol",
+            VehicleClass::Diesel => "diesel",
+            VehicleClass::Electric => "electric",
+        }
+    }
+
+    /// Grams of CO2 per mL of fuel burned. Electric vehicles use the same
+    /// energy model (as petrol-equivalent mL) but emit nothing at the tailpipe.
+    pub fn co2_per_ml(self) -> f32 {
+        match self {
+            VehicleClass::Petrol => 2.31,
+            VehicleClass::Diesel => 2.64,
+            VehicleClass::Electric => 0.0,
+        }
+    }
+
+    /// Grams of NOx per mL of fuel, from typical real-world Euro 6 figures
+    pub fn nox_per_ml(self) -> f32 {
+        match self {
+            VehicleClass::Petrol => 0.0004,
+            VehicleClass::Diesel => 0.005,
+            VehicleClass::Electric => 0.0,
+        }
+    }
+
+    /// Top speed. Every class can reach the fast preset for now: crossings
+    /// are booked from the vehicle's centre, and a slower class leaves too
+    /// little slack behind it for the next booking.
+    pub fn max_speed(self) -> Velocity {
+        match self {
+            VehicleClass::Petrol | VehicleClass::Diesel | VehicleClass::Electric => Velocity::FAST,
+        }
+    }
+
+    /// Most speed gained per frame at full throttle, in px/frame
+    pub fn acceleration(self) -> f32 {
+        match self {
+            VehicleClass::Petrol => 1.0,
+            VehicleClass::Diesel => 0.6,
+            VehicleClass::Electric => 1.5, // full torque from a standstill
+        }
+    }
+
+    /// Most speed shed per frame braking hard, in px/frame
+    pub fn deceleration(self) -> f32 {
+        match self {
+            VehicleClass::Petrol | VehicleClass::Electric => 3.5,
+            VehicleClass::Diesel => 2.5,
+        }
+    }
+}
+
+/// Relative weights used when assigning classes to new vehicles
+#[derive(Debug, Clone, Copy)]
+pub struct FleetMix {
+    pub petrol: u32,
+    pub diesel: u32,
+    pub electric: u32,
+}
+
+impl Default for FleetMix {
+    fn default() -> Self {
+        Self {
+            petrol: 1,
+            diesel: 0,
+            electric: 0,
+        }
+    }
+}
+
+impl FleetMix {
+    /// Parse a `petrol,diesel,electric` weight triple such as `60,30,10`
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let weights: Vec<u32> = text
+            .split(',')
+            .map(|w| w.trim().parse::<u32>())
+            .collect::<Result<_, _>>()
+            .map_err(|_| format!("Invalid fleet mix '{}'", text))?;
+
+        match weights[..] {
+            [petrol, diesel, electric] if petrol + diesel + electric > 0 => Ok(Self {
+                petrol,
+                diesel,
+                electric,
+            }),
+            _ => Err(format!(
+                "Fleet mix '{}' needs three weights (petrol,diesel,electric) with a positive sum",
+                text
+            )),
+        }
+    }
+
+    pub fn sample(&self, rng: &mut impl Rng) -> VehicleClass {
+        let total = self.petrol + self.diesel + self.electric;
+        let pick = rng.random_range(0..total);
+        if pick < self.petrol {
+            VehicleClass::Petrol
+        } else if pick < self.petrol + self.diesel {
+            VehicleClass::Diesel
+        } else {
+            VehicleClass::Electric
+        }
+    }
+}
+
+/// Vehicles of one class that left the road and what they emitted
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct ClassEmissions {
+    pub vehicles: u32,
+    pub co2_g: f32,
+    pub nox_g: f32,
+}
@@ -0,0 +1,371 @@
+use crate::compare::{DEFAULT_RATE, DEFAULT_SEED, ScenarioSpawn, build_scenario};
+use crate::config::Config;
+use crate::controller::{CellOwners, Denial, IntersectionController, PermissionRequest, Timeline};
+use crate::heatmap::Occupancy;
+use crate::intersection::SmartIntersection;
+use crate::observation::{Encoding, encode};
+use crate::route::{Direction, Route};
+use crate::stop_sign::StopSignController;
+use crate::vehicle::set_next_id;
+use crate::velocities::Velocity;
+use ndarray::Array2;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::io::{BufRead, Write};
+use std::rc::Rc;
+
+/// Episode settings sent with each reset; anything left out keeps its default
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct ResetOptions {
+    pub seed: Option<u64>,    // demand and driver seed; `--seed` or 1 when unset
+    pub ticks: u64,           // episode length in frames
+    pub frames_per_step: u32, // frames simulated per action
+    #[serde(flatten)]
+    pub encoding: Encoding,
+}
+
+impl Default for ResetOptions {
+    fn default() -> Self {
+        Self {
+            seed: None,
+            ticks: 18_000,
+            frames_per_step: 6,
+            encoding: Encoding::default(),
+        }
+    }
+}
+
+/// What the agent sees after each reset and step: the tensors of
+/// `observation::encode`, row by row
+#[derive(Debug, Clone, Serialize)]
+pub struct Observation {
+    pub time: f32,
+    pub vehicles: Vec<Vec<f32>>,
+    pub lanes: Vec<Vec<f32>>,
+    pub grid: Vec<Vec<f32>>,
+}
+
+/// Counters of the episode so far, for logging rather than learning
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct StepInfo {
+    pub vehicles_passed: u32,
+    pub collisions: u32,
+    pub close_calls: u32,
+    pub waiting: usize, // vehicles stopped short of the box right now
+    pub cost: f32,      // weighted cost of the episode so far
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Transition {
+    pub observation: Observation,
+    pub reward: f32,
+    pub done: bool,
+    pub info: StepInfo,
+}
+
+/// One line of the `--gym` protocol
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Command {
+    Reset(ResetOptions),
+    Step(Action),
+}
+
+/// The approaches whose vehicles may be let into the box until the next
+/// step; None lets every approach in
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Action {
+    pub admit: Option<Vec<Direction>>,
+}
+
+/// Passes requests from admitted approaches on to the configured controller
+/// and turns the others away, so an agent decides who goes while the
+/// controller still keeps those it lets in apart
+struct Gate {
+    inner: Box<dyn IntersectionController>,
+    admitted: Rc<RefCell<Option<Vec<Direction>>>>,
+}
+
+impl IntersectionController for Gate {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn request_permission(
+        &mut self,
+        request: &PermissionRequest,
+        current_time: f32,
+    ) -> Option<Velocity> {
+        let admitted = self
+            .admitted
+            .borrow()
+            .as_ref()
+            .is_none_or(|approaches| approaches.contains(&request.direction));
+        if !admitted {
+            self.inner.take_denial(); // not the reason this time
+            return None;
+        }
+        self.inner.request_permission(request, current_time)
+    }
+
+    fn take_denial(&mut self) -> Option<Denial> {
+        self.inner.take_denial()
+    }
+
+    fn planned_speed(
+        &self,
+        vehicle_id: usize,
+        distance_to_intersection: f32,
+        current_time: f32,
+    ) -> Option<Velocity> {
+        self.inner
+            .planned_speed(vehicle_id, distance_to_intersection, current_time)
+    }
+
+    fn paths_conflict(&self, a: (Direction, Route), b: (Direction, Route)) -> bool {
+        self.inner.paths_conflict(a, b)
+    }
+
+    fn notify_lane_closed(&mut self, direction: Direction, route: Route, closed: bool) {
+        self.inner.notify_lane_closed(direction, route, closed);
+    }
+
+    fn notify_enter(&mut self, vehicle_id: usize, current_time: f32) {
+        self.inner.notify_enter(vehicle_id, current_time);
+    }
+
+    fn notify_leave(&mut self, vehicle_id: usize, current_time: f32) {
+        self.inner.notify_leave(vehicle_id, current_time);
+    }
+
+    fn notify_progress(
+        &mut self,
+        vehicle_id: usize,
+        direction: Direction,
+        route: Route,
+        bounds: (f32, f32, f32, f32),
+        speed: Velocity,
+        current_time: f32,
+    ) {
+        self.inner
+            .notify_progress(vehicle_id, direction, route, bounds, speed, current_time);
+    }
+
+    fn take_withdrawn(&mut self) -> Vec<usize> {
+        self.inner.take_withdrawn()
+    }
+
+    fn notify_exit(&mut self, vehicle_id: usize, current_time: f32) {
+        self.inner.notify_exit(vehicle_id, current_time);
+    }
+
+    fn notify_blocked(
+        &mut self,
+        vehicle_id: usize,
+        bounds: (f32, f32, f32, f32),
+        current_time: f32,
+        until: f32,
+    ) {
+        self.inner
+            .notify_blocked(vehicle_id, bounds, current_time, until);
+    }
+
+    fn notify_unblocked(&mut self, vehicle_id: usize) {
+        self.inner.notify_unblocked(vehicle_id);
+    }
+
+    fn booked_over(&self, bounds: (f32, f32, f32, f32), from: f32, until: f32) -> Vec<usize> {
+        self.inner.booked_over(bounds, from, until)
+    }
+
+    fn revoke(&mut self, vehicle_id: usize) {
+        self.inner.revoke(vehicle_id);
+    }
+
+    fn clear_bookings(&mut self) {
+        self.inner.clear_bookings();
+    }
+
+    fn reservation_window(&self, vehicle_id: usize) -> Option<(f32, f32)> {
+        self.inner.reservation_window(vehicle_id)
+    }
+
+    fn timeline(&self) -> Option<Timeline> {
+        self.inner.timeline()
+    }
+
+    fn cell_owners(&self, current_time: f32) -> Option<CellOwners> {
+        self.inner.cell_owners(current_time)
+    }
+
+    fn booked_slots(&self) -> Option<usize> {
+        self.inner.booked_slots()
+    }
+
+    fn occupancy(&self) -> Option<Occupancy> {
+        self.inner.occupancy()
+    }
+
+    fn tick(&mut self, current_time: f32) {
+        self.inner.tick(current_time);
+    }
+}
+
+/// The headless simulation as a reinforcement-learning environment: `reset`
+/// starts a seeded episode of the `--compare` style Poisson demand, `step`
+/// applies an action for a few frames and returns the observation, reward
+/// and whether the episode is over. The reward is the run's cost over the
+/// step, negated, so the agent is judged like the leaderboard and
+/// `--compare` judge controllers.
+pub struct Environment {
+    config: Config,
+    options: ResetOptions,
+    intersection: SmartIntersection,
+    scenario: Vec<ScenarioSpawn>,
+    next_spawn: usize,
+    tick: u64,
+    admitted: Rc<RefCell<Option<Vec<Direction>>>>,
+}
+
+impl Environment {
+    pub fn new(config: &Config) -> Result<Self, String> {
+        let mut environment = Self {
+            config: config.clone(),
+            options: ResetOptions::default(),
+            intersection: SmartIntersection::new(),
+            scenario: Vec::new(),
+            next_spawn: 0,
+            tick: 0,
+            admitted: Rc::new(RefCell::new(None)),
+        };
+        environment.reset(ResetOptions::default())?;
+        Ok(environment)
+    }
+
+    pub fn reset(&mut self, options: ResetOptions) -> Result<Observation, String> {
+        if options.frames_per_step == 0 {
+            return Err("frames_per_step must be at least 1".to_string());
+        }
+        let seed = options.seed.or(self.config.seed).unwrap_or(DEFAULT_SEED);
+        let rates = self.config.arrival_rates.unwrap_or([DEFAULT_RATE; 4]);
+        let mut intersection = crate::build_intersection(&self.config)?;
+        intersection.seed(seed);
+        // Rewards are the cost added between steps, which restarting the
+        // statistics after a warm-up would turn into one large bonus
+        intersection.warm_up = 0.0;
+        set_next_id(1);
+        // Wrap whatever controller the config asked for
+        let inner = std::mem::replace(
+            &mut intersection.controller,
+            Box::new(StopSignController::new()),
+        );
+        *self.admitted.borrow_mut() = None;
+        intersection.switch_controller(
+            Box::new(Gate {
+                inner,
+                admitted: Rc::clone(&self.admitted),
+            }),
+            0.0,
+        );
+
+        self.scenario = build_scenario(self.config.layout, rates, seed, options.ticks);
+        self.intersection = intersection;
+        self.options = options;
+        self.next_spawn = 0;
+        self.tick = 0;
+        Ok(self.observation())
+    }
+
+    pub fn step(&mut self, action: Action) -> Transition {
+        *self.admitted.borrow_mut() = action.admit;
+        let cost = self.intersection.cost.total();
+        for _ in 0..self.options.frames_per_step {
+            if self.done() {
+                break;
+            }
+            let current_time = (self.tick + 1) as f32 / 60.0;
+            while let Some(spawn) = self
+                .scenario
+                .get(self.next_spawn)
+                .filter(|s| s.tick == self.tick)
+            {
+                self.intersection.spawn_vehicle(
+                    Some(spawn.direction),
+                    Some(spawn.route),
+                    current_time,
+                );
+                self.next_spawn += 1;
+            }
+            self.intersection.step();
+            self.tick += 1;
+        }
+
+        Transition {
+            observation: self.observation(),
+            reward: cost - self.intersection.cost.total(),
+            done: self.done(),
+            info: StepInfo {
+                vehicles_passed: self.intersection.total_vehicles_passed,
+                collisions: self.intersection.collisions,
+                close_calls: self.intersection.close_calls,
+                waiting: self.waiting(),
+                cost: self.intersection.cost.total(),
+            },
+        }
+    }
+
+    pub fn done(&self) -> bool {
+        self.tick >= self.options.ticks
+    }
+
+    pub fn observation(&self) -> Observation {
+        let rows = |array: &Array2<f32>| array.rows().into_iter().map(|row| row.to_vec()).collect();
+        let tensors = encode(&self.intersection.vehicles(), &self.options.encoding);
+        Observation {
+            time: self.tick as f32 / 60.0,
+            vehicles: rows(&tensors.vehicles),
+            lanes: rows(&tensors.lanes),
+            grid: rows(&tensors.grid),
+        }
+    }
+
+    /// Vehicles standing still short of the box
+    fn waiting(&self) -> usize {
+        self.intersection
+            .vehicles()
+            .iter()
+            .filter(|v| v.current_speed == Velocity::STOPPED && !v.entered_intersection)
+            .count()
+    }
+}
+
+/// Serve the environment over stdin and stdout, one JSON object per line, so
+/// agents in other languages can train against it. `{"reset": {...}}` starts
+/// an episode and answers with its first observation; `{"step": {"admit":
+/// ["North", "South"]}}` answers with a transition. A line that can't be
+/// handled gets `{"error": "..."}` and the episode carries on.
+pub fn serve(config: &Config) -> Result<(), String> {
+    let mut environment = Environment::new(config)?;
+    let mut out = std::io::stdout().lock();
+    for line in std::io::stdin().lock().lines() {
+        let line = line.map_err(|e| e.to_string())?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let reply = match serde_json::from_str::<Command>(&line) {
+            Ok(Command::Reset(options)) => environment.reset(options).and_then(|observation| {
+                serde_json::to_value(observation).map_err(|e| e.to_string())
+            }),
+            Ok(Command::Step(action)) => {
+                serde_json::to_value(environment.step(action)).map_err(|e| e.to_string())
+            }
+            Err(e) => Err(format!("Invalid command: {}", e)),
+        }
+        .unwrap_or_else(|error| serde_json::json!({ "error": error }));
+        writeln!(out, "{}", reply).map_err(|e| e.to_string())?;
+        out.flush().map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
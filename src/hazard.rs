@@ -0,0 +1,143 @@
+use crate::layout::RoadLayout;
+use crate::vehicle::allocate_id;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// Who wandered onto the road
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum HazardKind {
+    Pedestrian,
+    Animal,
+}
+
+impl HazardKind {
+    /// Width and height, px
+    fn size(self) -> (f32, f32) {
+        match self {
+            HazardKind::Pedestrian => (16.0, 16.0),
+            HazardKind::Animal => (26.0, 16.0),
+        }
+    }
+
+    /// Walking speed, px/s
+    fn speed(self) -> f32 {
+        match self {
+            HazardKind::Pedestrian => 60.0,
+            HazardKind::Animal => 110.0,
+        }
+    }
+
+    pub fn color(self) -> (u8, u8, u8) {
+        match self {
+            HazardKind::Pedestrian => (80, 200, 255),
+            HazardKind::Animal => (150, 90, 40),
+        }
+    }
+}
+
+/// A pedestrian or animal crossing the road straight across the traffic,
+/// mid-block or through the intersection box. It takes an id from the
+/// vehicle sequence so the controller can hold the cells it walks over.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Hazard {
+    pub id: usize,
+    pub kind: HazardKind,
+    pub centre: (f32, f32),
+    velocity: (f32, f32),    // px/s
+    target: f32,             // coordinate along the walk where it reaches the far kerb
+    pub stopped: Vec<usize>, // vehicles it already made emergency-stop
+}
+
+impl Hazard {
+    /// A random crossing on an open stretch of road: a quarter of them inside
+    /// the intersection box, the rest 80-250 px up one of the arms
+    pub fn random(rng: &mut impl Rng, layout: RoadLayout) -> Option<Self> {
+        let kind = if rng.random_bool(0.7) {
+            HazardKind::Pedestrian
+        } else {
+            HazardKind::Animal
+        };
+        // Where it crosses along the road, and whether it walks across a
+        // vertical road (sideways) or a horizontal one (up or down)
+        let (along, across_vertical_road) = if rng.random_bool(0.25) {
+            (rng.random_range(370.0..630.0), rng.random_bool(0.5))
+        } else {
+            let offset = rng.random_range(80.0..250.0);
+            match rng.random_range(0..4) {
+                0 => (350.0 - offset, true),
+                1 => (650.0 + offset, true),
+                2 => (350.0 - offset, false),
+                _ => (650.0 + offset, false),
+            }
+        };
+
+        let forwards = rng.random_bool(0.5);
+        // From the verge on one side to the verge on the other
+        let (kerb, target) = if forwards {
+            (330.0, 670.0)
+        } else {
+            (670.0, 330.0)
+        };
+        let speed = kind.speed() * if forwards { 1.0 } else { -1.0 };
+        let (centre, velocity) = if across_vertical_road {
+            ((kerb, along), (speed, 0.0))
+        } else {
+            ((along, kerb), (0.0, speed))
+        };
+
+        // Nobody crosses the grass over a closed arm
+        let middle = if across_vertical_road {
+            (500.0, along)
+        } else {
+            (along, 500.0)
+        };
+        if let Some((cx, cy, cw, ch)) = layout.closed_area()
+            && middle.0 >= cx
+            && middle.0 < cx + cw
+            && middle.1 >= cy
+            && middle.1 < cy + ch
+        {
+            return None;
+        }
+        Some(Self {
+            id: allocate_id(),
+            kind,
+            centre,
+            velocity,
+            target,
+            stopped: Vec::new(),
+        })
+    }
+
+    pub fn step(&mut self, dt: f32) {
+        self.centre.0 += self.velocity.0 * dt;
+        self.centre.1 += self.velocity.1 * dt;
+    }
+
+    /// Whether it has reached the far side of the road
+    pub fn is_across(&self) -> bool {
+        let (position, speed) = if self.velocity.0 != 0.0 {
+            (self.centre.0, self.velocity.0)
+        } else {
+            (self.centre.1, self.velocity.1)
+        };
+        if speed > 0.0 {
+            position >= self.target
+        } else {
+            position <= self.target
+        }
+    }
+
+    /// World rectangle (x, y, width, height) it covers now
+    pub fn area(&self) -> (f32, f32, f32, f32) {
+        let (w, h) = self.kind.size();
+        (self.centre.0 - w / 2.0, self.centre.1 - h / 2.0, w, h)
+    }
+
+    /// Rectangle it sweeps over during the next `seconds`
+    pub fn swept_area(&self, seconds: f32) -> (f32, f32, f32, f32) {
+        let (x, y, w, h) = self.area();
+        let (dx, dy) = (self.velocity.0 * seconds, self.velocity.1 * seconds);
+        (x + dx.min(0.0), y + dy.min(0.0), w + dx.abs(), h + dy.abs())
+    }
+}
@@ -0,0 +1,66 @@
+use serde::{Deserialize, Serialize};
+
+/// How often each cell of a controller's grid has been booked over a run
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Occupancy {
+    pub cols: usize,
+    pub rows: usize,
+    pub left: f32, // world position of the grid's top-left corner
+    pub top: f32,
+    pub cell_size: f32,
+    pub counts: Vec<u32>, // frames each cell was booked, row by row
+    pub frames: u32,      // frames counted so far
+}
+
+impl Occupancy {
+    pub fn new(cols: usize, rows: usize, (left, top): (f32, f32), cell_size: f32) -> Self {
+        Self {
+            cols,
+            rows,
+            left,
+            top,
+            cell_size,
+            counts: vec![0; cols * rows],
+            frames: 0,
+        }
+    }
+
+    /// Share of the counted frames the cell was booked, 0..=1
+    pub fn share(&self, col: usize, row: usize) -> f32 {
+        if self.frames == 0 {
+            0.0
+        } else {
+            self.counts[row * self.cols + col] as f32 / self.frames as f32
+        }
+    }
+
+    /// Share of the busiest cell; colours are scaled to it so hot spots stand out
+    pub fn max_share(&self) -> f32 {
+        (0..self.rows)
+            .flat_map(|row| (0..self.cols).map(move |col| (col, row)))
+            .map(|(col, row)| self.share(col, row))
+            .fold(0.0, f32::max)
+    }
+}
+
+/// Colour ramp from dark blue (never booked) through yellow to red (as busy as
+/// the busiest cell), as RGB
+pub fn heat_color(share: f32, max_share: f32) -> (u8, u8, u8) {
+    let t = if max_share > 0.0 {
+        (share / max_share).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    let lerp = |a: f32, b: f32, t: f32| (a + (b - a) * t) as u8;
+    if t < 0.5 {
+        let t = t * 2.0;
+        (
+            lerp(20.0, 255.0, t),
+            lerp(30.0, 220.0, t),
+            lerp(120.0, 40.0, t),
+        )
+    } else {
+        let t = (t - 0.5) * 2.0;
+        (255, lerp(220.0, 30.0, t), lerp(40.0, 20.0, t))
+    }
+}
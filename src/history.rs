@@ -0,0 +1,89 @@
+use crate::heatmap::Occupancy;
+use crate::intersection::SmartIntersection;
+use serde::Serialize;
+
+/// Seconds of simulation time between samples
+const SAMPLE_INTERVAL: f32 = 1.0;
+/// Side (px) of the world grid squares vehicle positions are counted on
+const DENSITY_CELL: f32 = 20.0;
+/// Side (px) of the world
+const WORLD_SIZE: f32 = 1000.0;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct HistorySample {
+    pub time: f32,
+    pub vehicles_passed: u32, // cumulative
+    pub queued: usize,        // vehicles stopped before the intersection
+    pub active: usize,
+}
+
+/// Time series of a run, used for the post-run charts
+#[derive(Debug)]
+pub struct RunHistory {
+    pub samples: Vec<HistorySample>,
+    next_sample: f32,
+    // Frames each square of a coarse world grid held a vehicle's centre
+    pub density: Occupancy,
+}
+
+impl Default for RunHistory {
+    fn default() -> Self {
+        let cells = (WORLD_SIZE / DENSITY_CELL) as usize;
+        Self {
+            samples: Vec::new(),
+            next_sample: 0.0,
+            density: Occupancy::new(cells, cells, (0.0, 0.0), DENSITY_CELL),
+        }
+    }
+}
+
+impl RunHistory {
+    /// Count every vehicle's position on the density grid, and take a sample
+    /// if a full interval has passed since the previous one
+    pub fn record(&mut self, current_time: f32, intersection: &SmartIntersection) {
+        self.record_density(intersection);
+        if current_time < self.next_sample {
+            return;
+        }
+        self.next_sample = current_time + SAMPLE_INTERVAL;
+
+        self.samples.push(HistorySample {
+            time: current_time,
+            vehicles_passed: intersection.total_vehicles_passed,
+            queued: intersection.queued_vehicles(),
+            active: intersection.vehicle_count(),
+        });
+    }
+
+    fn record_density(&mut self, intersection: &SmartIntersection) {
+        let density = &mut self.density;
+        density.frames += 1;
+        for vehicle in intersection.vehicles() {
+            let (x, y) = vehicle.get_visual_center();
+            let col = (x - density.left) / density.cell_size;
+            let row = (y - density.top) / density.cell_size;
+            if col >= 0.0
+                && row >= 0.0
+                && (col as usize) < density.cols
+                && (row as usize) < density.rows
+            {
+                density.counts[row as usize * density.cols + col as usize] += 1;
+            }
+        }
+    }
+
+    /// Vehicles per minute over a sliding window of `window` samples
+    pub fn throughput(&self, window: usize) -> Vec<(f32, f32)> {
+        self.samples
+            .iter()
+            .enumerate()
+            .skip(window)
+            .map(|(i, sample)| {
+                let earlier = &self.samples[i - window];
+                let passed = (sample.vehicles_passed - earlier.vehicles_passed) as f32;
+                let minutes = (sample.time - earlier.time) / 60.0;
+                (sample.time, passed / minutes)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// Built in, so English text is there even without the assets directory and
+/// fills in any key another locale leaves out
+const ENGLISH: &str = include_str!("../assets/locales/en.json");
+
+static LOCALE: OnceLock<Locale> = OnceLock::new();
+
+/// User-facing text of one language, keyed like `stats.collisions`; values
+/// may contain `{name}` placeholders
+struct Locale {
+    strings: HashMap<String, String>,
+    english: HashMap<String, String>,
+}
+
+impl Locale {
+    fn english() -> Self {
+        let english: HashMap<String, String> =
+            serde_json::from_str(ENGLISH).expect("built-in English locale is valid JSON");
+        Self {
+            strings: english.clone(),
+            english,
+        }
+    }
+
+    fn load(language: &str) -> Result<Self, String> {
+        let path = format!("assets/locales/{}.json", language);
+        let text = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read locale {}: {}", path, e))?;
+        let strings = serde_json::from_str(&text)
+            .map_err(|e| format!("Invalid locale file {}: {}", path, e))?;
+        Ok(Self {
+            strings,
+            ..Self::english()
+        })
+    }
+}
+
+/// Pick the language of all user-facing text (`--lang`); English when never called
+pub fn init(language: &str) -> Result<(), String> {
+    let locale = match language {
+        "en" => Locale::english(),
+        other => Locale::load(other)?,
+    };
+    LOCALE
+        .set(locale)
+        .map_err(|_| "The language was already chosen".to_string())
+}
+
+/// Text for `key`, falling back to English and then to the key itself
+pub fn text(key: &str) -> String {
+    let locale = LOCALE.get_or_init(Locale::english);
+    match locale.strings.get(key).or_else(|| locale.english.get(key)) {
+        Some(text) => text.clone(),
+        None => key.to_string(),
+    }
+}
+
+/// Replace each `{name}` placeholder in `template` with its value
+pub fn fill(template: &str, values: &[(&str, String)]) -> String {
+    values
+        .iter()
+        .fold(template.to_string(), |text, (name, value)| {
+            text.replace(&format!("{{{}}}", name), value)
+        })
+}
+
+/// Localized text: `tr!("stats.collisions", count = self.collisions)`. Values
+/// are inserted with `Display`, so format numbers first when precision matters.
+macro_rules! tr {
+    ($key:expr) => {
+        $crate::i18n::text($key)
+    };
+    ($key:expr, $($name:ident = $value:expr),+ $(,)?) => {
+        $crate::i18n::fill(
+            &$crate::i18n::text($key),
+            &[$((stringify!($name), $value.to_string())),+],
+        )
+    };
+}
+pub(crate) use tr;
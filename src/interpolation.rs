@@ -0,0 +1,44 @@
+use crate::intersection::SmartIntersection;
+use crate::vehicle::Vehicle;
+use std::collections::HashMap;
+
+/// Where each vehicle was before the latest simulation tick, so frames drawn
+/// between ticks can place it part of the way to where it is now
+#[derive(Default)]
+pub struct PreviousPoses {
+    poses: HashMap<usize, ((f32, f32), f64)>, // id -> (position, rotation)
+}
+
+impl PreviousPoses {
+    /// Remember the current poses; call just before a tick
+    pub fn capture(&mut self, intersection: &SmartIntersection) {
+        self.poses.clear();
+        self.poses.extend(
+            intersection
+                .vehicles()
+                .into_iter()
+                .map(|v| (v.id, (v.position, v.rotation))),
+        );
+    }
+
+    /// Copies of `vehicles` placed `alpha` (0..1) of the way from their
+    /// previous pose to their current one. Vehicles that just spawned or
+    /// turned during the tick are drawn where they are: a turn moves the car
+    /// onto its new lane at once, and easing across it would cut the corner.
+    pub fn interpolate(&self, vehicles: &[&Vehicle], alpha: f32) -> Vec<Vehicle> {
+        let alpha = alpha.clamp(0.0, 1.0);
+        vehicles
+            .iter()
+            .map(|&vehicle| {
+                let mut drawn = vehicle.clone();
+                if let Some(&((x, y), rotation)) = self.poses.get(&vehicle.id)
+                    && rotation == vehicle.rotation
+                {
+                    let (cx, cy) = vehicle.position;
+                    drawn.position = (x + (cx - x) * alpha, y + (cy - y) * alpha);
+                }
+                drawn
+            })
+            .collect()
+    }
+}
@@ -1,522 +1,1335 @@
+use crate::construction::LaneClosure;
+use crate::controller::{
+    ControllerKind, Denial, DenialCause, IntersectionController, PermissionRequest, Timeline,
+};
+use crate::cost::{CostInputs, CostTracker};
+use crate::distribution::Summary;
+use crate::driver::{DriverProfile, ProfileMix};
+use crate::events::{EventKind, SimEvent};
+use crate::frame_state::FrameState;
+use crate::fuel::{ClassEmissions, FleetMix, VehicleClass};
+use crate::hazard::Hazard;
+use crate::i18n::tr;
+use crate::layout::RoadLayout;
+use crate::obstacle::{Obstacle, ObstacleKind};
+use crate::queues::{APPROACHES, QueueTracker};
+use crate::report::RunSummary;
+use crate::reservation::ReservationController;
 use crate::route::{
-    Direction, Route, get_random_direction, get_random_route, get_spawn_position, get_turn_position,
+    Direction, Route, exit_direction, get_spawn_position, get_turn_position, lane_corridor,
+};
+use crate::systems::{
+    Following, Obstacles, Plan, RoadConditions, Transitions, following_system, movement_system,
 };
-use crate::vehicle::Vehicle;
+use crate::vehicle::{Breakdown, VEHICLE_LENGTH, VEHICLE_WIDTH, Vehicle, next_id, set_next_id};
 use crate::velocities::Velocity;
-use sdl2::render::TextureCreator;
-use sdl2::video::WindowContext;
-use std::collections::HashMap;
-
-/// Intersection geometry
-const IX_MIN: f32 = 350.0;
-const IY_MIN: f32 = 350.0;
-const IX_MAX: f32 = 650.0;
-const IY_MAX: f32 = 650.0;
-
-#[derive(Clone)]
-struct TimeSlot {
-    start: f32,
-    end: f32,
-    vehicle_id: usize,
-}
-
-#[derive(Clone)]
-struct Cell {
-    slots: Vec<TimeSlot>,
+use crate::weather::Weather;
+use bevy_ecs::prelude::*;
+use bevy_ecs::system::RunSystemOnce;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, trace_span, warn};
+
+/// Simulated seconds per update; vehicle speeds are in pixels per tick
+pub const SIM_TICK: f32 = 1.0 / 60.0;
+
+/// Seconds a crashed vehicle stays on the road before it is cleared away
+const CRASH_CLEAR_DELAY: f32 = 3.0;
+
+/// Free road (px) a new vehicle needs ahead of and behind its body along its lane
+const SPAWN_CLEARANCE: f32 = 100.0;
+
+/// Largest gap (centre to centre, px) between platoon members
+const PLATOON_GAP: f32 = 160.0;
+/// Most vehicles, leader included, crossing on one platoon booking
+const MAX_PLATOON_SIZE: usize = 4;
+
+/// Seconds a vehicle waits for an answer before asking again; doubles with
+/// every lost message up to RETRY_BACKOFF_MAX
+const RETRY_BACKOFF: f32 = 0.1;
+const RETRY_BACKOFF_MAX: f32 = 1.6;
+
+/// Seconds of a jaywalker's walk through the box whose cells are held ahead of it
+const HAZARD_HOLD_AHEAD: f32 = 1.0;
+
+/// Largest share below its top speed a driver may cruise at unless
+/// `--speed-variance` says otherwise
+pub const DEFAULT_SPEED_VARIANCE: f32 = 0.0;
+
+/// Wall-clock time spent in each phase of the vehicle update
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PhaseTimings {
+    pub following: Duration,   // car-following target speeds
+    pub reservation: Duration, // intersection requests and grid checks
+    pub release: Duration,     // progress and exit notices freeing grid cells
+    pub movement: Duration,    // applying speeds, moving, safety checks
 }
 
-/// Memoized path data for each direction+route combination
-#[derive(Clone, Debug)]
-struct PathSegment {
-    cells: Vec<(usize, usize)>,
-    distance: f32,
+/// A spawn request waiting for its lane's spawn area to clear
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct PendingSpawn {
+    requested_at: f32,
+    class: Option<VehicleClass>, // None = drawn from the fleet mix
 }
 
-#[derive(Clone, Debug)]
-struct VehiclePath {
-    segment1: PathSegment, // Entry to turn position (or full path for straight)
-    segment2: Option<PathSegment>, // Turn position to exit (None for straight)
+/// Everything about a run in progress that doesn't come from the settings,
+/// as stored in a checkpoint. Resuming takes the settings from the command
+/// line again.
+#[derive(Serialize, Deserialize)]
+pub struct IntersectionState {
+    vehicles: Vec<Vehicle>,
+    next_vehicle_id: usize,
+    seed: u64, // the random stream restarts from this at the checkpoint
+    controller: Option<serde_json::Value>,
+    handover: Vec<usize>,
+    weather: Weather,
+    profiles_spawned: HashMap<DriverProfile, u32>,
+    emissions: HashMap<VehicleClass, ClassEmissions>,
+    breakdowns: u32,
+    breakdown_downtime: f32,
+    breakdown_delay: f32,
+    reroutes: u32,
+    obstacles: Vec<Obstacle>,
+    closed_lanes: Vec<LaneClosure>,
+    redirected_spawns: u32,
+    hazards: Vec<Hazard>,
+    hazards_seen: u32,
+    emergency_stops: u32,
+    revoked_bookings: u32,
+    platoons: u32,
+    platooned_vehicles: u32,
+    requests_lost: u32,
+    replies_lost: u32,
+    permissions_lost: u32,
+    retries: u32,
+    duplicate_reservations: u32,
+    withdrawn_crossings: u32,
+    denials: HashMap<DenialCause, u32>,
+    priority_grants: u32,
+    total_velocities: f32,
+    total_vehicles_passed: u32,
+    passed_by_approach: HashMap<Direction, u32>,
+    max_velocity_recorded: f32,
+    min_velocity_recorded: f32,
+    max_time_in_intersection: f32,
+    min_time_in_intersection: f32,
+    total_wait_time: f32,
+    total_fuel: f32,
+    idle_fuel: f32,
+    close_calls: u32,
+    close_call_spots: Vec<(f32, f32)>,
+    close_calls_by_approach: HashMap<Direction, u32>,
+    collisions: u32,
+    vehicles_crashed: u32,
+    exit_velocities: Vec<f32>,
+    travel_times: Vec<f32>,
+    wait_times: Vec<f32>,
+    stop_counts: Vec<u32>,
+    vehicle_intersection_times: HashMap<usize, f32>,
+    pending_spawns: Vec<((Direction, Route), VecDeque<PendingSpawn>)>,
+    total_spawn_delay: f32,
+    spawns_held: u32,
+    cost: CostTracker,
+    queues: QueueTracker,
+    warmed_up: bool,
 }
 
-type PathCache = HashMap<(Direction, Route), VehiclePath>;
-
-pub struct SmartIntersection<'a> {
-    pub active_vehicles: Vec<Vehicle<'a>>,
-
-    // --- reservation grid ---
-    zone_px: u32, // e.g., 30 => 10x10 grid
-    cols: usize,  // 300/zone_px
-    rows: usize,
-    grid: Vec<Cell>, // flattened rows*cols
-
-    // Memoized path calculations
-    path_cache: PathCache,
+pub struct SmartIntersection {
+    // Vehicles live as entities in an ECS world; `order` keeps their ids and
+    // entities in spawn order so every pass visits them deterministically
+    world: World,
+    order: Vec<(usize, Entity)>,
+
+    // Decides who may enter the intersection; the reservation grid by default
+    pub controller: Box<dyn IntersectionController>,
+    // Vehicles still crossing on a grant from the controller before the last
+    // switch; the new one grants nothing until they are through
+    handover: Vec<usize>,
+
+    // Road conditions widen following gaps and reservation margins
+    pub weather: Weather,
+
+    // Which approaches and routes exist; the controller must be built for the same layout
+    pub layout: RoadLayout,
+
+    // Share of cautious/normal/aggressive drivers among new vehicles
+    pub profile_mix: ProfileMix,
+    profiles_spawned: HashMap<DriverProfile, u32>,
+
+    // Each driver cruises at up to this share below their vehicle's top speed
+    pub speed_variance: f32,
+
+    // Safety margins on every reservation: seconds added before and after
+    // each slot, and cells added around each path (the controller is built
+    // with the latter)
+    pub slot_padding: f32,
+    pub path_padding: usize,
+
+    // Share of petrol/diesel/electric vehicles, and what each class emitted on its way through
+    pub fleet_mix: FleetMix,
+    pub emissions: HashMap<VehicleClass, ClassEmissions>,
+
+    // Breakdowns: chance per vehicle per second, and how long they last (None = until removed)
+    pub breakdown_rate: f32,
+    pub breakdown_duration: Option<f32>,
+    pub breakdowns: u32,
+    pub breakdown_downtime: f32, // seconds broken-down vehicles spent stranded
+    pub breakdown_delay: f32,    // seconds other vehicles spent queued behind them
+
+    // Rerouting: vehicles whose path runs into a broken-down car switch to a
+    // free route's lane before booking a crossing
+    pub rerouting: bool,
+    pub reroutes: u32,
+
+    // Stalled cars and debris dropped by the user, held like breakdowns until cleared
+    pub obstacles: Vec<Obstacle>,
+
+    // Roadworks: lanes closed for part of the run; their spawns go to another
+    // lane of the same approach
+    pub lane_closures: Vec<LaneClosure>,
+    closed_lanes: Vec<LaneClosure>, // the closures in force right now
+    pub redirected_spawns: u32,
+
+    // Jaywalking: pedestrians and animals crossing the road at random
+    pub hazard_rate: f32, // chance per second of one stepping onto the road
+    pub hazards: Vec<Hazard>,
+    pub hazards_seen: u32,
+    pub emergency_stops: u32,  // vehicles that stopped dead for one
+    pub revoked_bookings: u32, // crossings cancelled because one got in the way
+
+    // Platooning: close followers in the same lane cross on their leader's booking
+    pub platooning: bool,
+    pub platoons: u32,
+    pub platooned_vehicles: u32, // followers that crossed as part of a platoon
+
+    // Radio link: chance that each request or reply between a vehicle and the controller is lost
+    pub packet_loss: f32,
+    pub requests_lost: u32,
+    pub replies_lost: u32,
+    pub permissions_lost: u32,       // lost replies that carried a grant
+    pub retries: u32,                // requests sent again after getting no answer
+    pub duplicate_reservations: u32, // grants replacing one whose reply was lost
+
+    // Crossings the controller took back because the vehicle fell behind its
+    // booking or was in the way of one that did
+    pub withdrawn_crossings: u32,
+
+    // Requests turned down, counted once per wait by why the first refusal was
+    pub denials: HashMap<DenialCause, u32>,
+
+    // Aging: once a vehicle near the intersection has waited this long, no
+    // vehicle whose path crosses its path gets a new crossing until it has one
+    pub starvation_limit: Option<f32>,
+    pub priority_grants: u32, // crossings granted to vehicles past the limit
+    starving: Vec<(usize, Direction, Route)>, // vehicles past the limit this tick
+
+    // Warm-up: what happens in the first this many seconds is left out of the
+    // statistics, which restart once it is over
+    pub warm_up: f32,
+    warmed_up: bool,
 
     // Stats
     pub total_velocities: f32,
     pub total_vehicles_passed: u32,
+    pub passed_by_approach: HashMap<Direction, u32>, // vehicles through, by the side they came from
     pub max_velocity_recorded: f32,
     pub min_velocity_recorded: f32,
     pub max_time_in_intersection: f32,
     pub min_time_in_intersection: f32,
+    pub total_wait_time: f32, // seconds exited vehicles spent stopped before the intersection
+    pub total_fuel: f32,      // mL burned by exited vehicles
+    pub idle_fuel: f32,       // the part of it burned standing still
     pub close_calls: u32,
+    pub close_call_spots: Vec<(f32, f32)>, // midpoint between the vehicles of each close call
+    pub close_calls_by_approach: HashMap<Direction, u32>, // close calls involving a vehicle from that side
+    pub collisions: u32,
+    pub vehicles_crashed: u32,
+    pub exit_velocities: Vec<f32>, // px/s of every vehicle that left the canvas
+    pub travel_times: Vec<f32>,    // seconds from spawn to leaving the canvas, per vehicle
+    pub wait_times: Vec<f32>, // seconds each exited vehicle spent stopped before the intersection
+    pub stop_counts: Vec<u32>, // complete stops each exited vehicle made on its journey
 
     pub close_call_pairs_this_frame: std::collections::HashSet<(usize, usize)>,
 
+    // Weighted cost of the run, added up every tick
+    pub cost: CostTracker,
+
+    // Vehicles queued on each approach, longest and averaged over the run
+    pub queues: QueueTracker,
+
     vehicle_intersection_times: HashMap<usize, f32>,
+
+    // Spawn requests per lane that arrived while the spawn area was occupied,
+    // kept in lane order so requests made together spawn the same way every run
+    pending_spawns: BTreeMap<(Direction, Route), VecDeque<PendingSpawn>>,
+    pub total_spawn_delay: f32, // seconds requested vehicles waited to appear
+
+    // Admission control: with this many vehicles on the road, new spawns wait
+    // in their lane's queue instead of being created
+    pub max_active: Option<usize>,
+    pub spawns_held: u32, // requests queued because the cap was reached
+
+    // Events emitted since the last drain
+    events: Vec<SimEvent>,
+    // Seconds simulated through `step`, summed in f64 so fixed steps don't drift
+    clock: f64,
+
+    pub timings: PhaseTimings,
+
+    // Spawned routes, driver profiles and breakdowns draw from this so a
+    // seeded run is reproducible
+    rng: StdRng,
 }
 
-impl<'a> SmartIntersection<'a> {
+impl SmartIntersection {
     pub fn new() -> Self {
-        let zone_px = 10;
-        let cols = 300 / zone_px;
-        let rows = cols;
-
-        let mut intersection = Self {
-            active_vehicles: Vec::new(),
-            zone_px: zone_px as u32,
-            cols,
-            rows,
-            grid: vec![Cell { slots: Vec::new() }; cols * rows],
-            path_cache: HashMap::new(),
+        Self {
+            world: World::new(),
+            order: Vec::new(),
+            controller: Box::new(ReservationController::new()),
+            handover: Vec::new(),
+            weather: Weather::default(),
+            layout: RoadLayout::default(),
+            profile_mix: ProfileMix::default(),
+            speed_variance: DEFAULT_SPEED_VARIANCE,
+            slot_padding: 0.0,
+            path_padding: 0,
+            profiles_spawned: HashMap::new(),
+            fleet_mix: FleetMix::default(),
+            emissions: HashMap::new(),
+            breakdown_rate: 0.0,
+            breakdown_duration: Some(5.0),
+            breakdowns: 0,
+            breakdown_downtime: 0.0,
+            breakdown_delay: 0.0,
+            rerouting: true,
+            reroutes: 0,
+            obstacles: Vec::new(),
+            lane_closures: Vec::new(),
+            closed_lanes: Vec::new(),
+            redirected_spawns: 0,
+            hazard_rate: 0.0,
+            hazards: Vec::new(),
+            hazards_seen: 0,
+            emergency_stops: 0,
+            revoked_bookings: 0,
+            platooning: false,
+            platoons: 0,
+            platooned_vehicles: 0,
+            packet_loss: 0.0,
+            requests_lost: 0,
+            replies_lost: 0,
+            permissions_lost: 0,
+            retries: 0,
+            duplicate_reservations: 0,
+            withdrawn_crossings: 0,
+            denials: HashMap::new(),
+            starvation_limit: None,
+            priority_grants: 0,
+            starving: Vec::new(),
+            warm_up: 0.0,
+            warmed_up: false,
             total_velocities: 0.0,
             total_vehicles_passed: 0,
+            passed_by_approach: HashMap::new(),
             max_velocity_recorded: 0.0,
             min_velocity_recorded: f32::MAX,
             max_time_in_intersection: 0.0,
             min_time_in_intersection: f32::MAX,
+            total_wait_time: 0.0,
+            total_fuel: 0.0,
+            idle_fuel: 0.0,
             close_calls: 0,
+            close_call_spots: Vec::new(),
+            close_calls_by_approach: HashMap::new(),
+            collisions: 0,
+            vehicles_crashed: 0,
+            exit_velocities: Vec::new(),
+            travel_times: Vec::new(),
+            wait_times: Vec::new(),
+            stop_counts: Vec::new(),
 
             close_call_pairs_this_frame: std::collections::HashSet::new(),
+            cost: CostTracker::default(),
+            queues: QueueTracker::default(),
             vehicle_intersection_times: HashMap::new(),
-        };
+            pending_spawns: BTreeMap::new(),
+            total_spawn_delay: 0.0,
+            max_active: None,
+            spawns_held: 0,
+            events: Vec::new(),
+            clock: 0.0,
+            timings: PhaseTimings::default(),
+            rng: StdRng::from_rng(&mut rand::rng()),
+        }
+    }
 
-        // Pre-calculate all possible paths
-        intersection.initialize_path_cache();
-        intersection
+    /// Make driver profiles and breakdowns repeat exactly for the same seed
+    pub fn seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
     }
 
-    /// Pre-calculate all possible vehicle paths for memoization
-    fn initialize_path_cache(&mut self) {
-        let directions = [
-            Direction::North,
-            Direction::South,
-            Direction::East,
-            Direction::West,
-        ];
-        let routes = [Route::Straight, Route::Left, Route::Right];
+    /// Random stream for demand drawn outside the intersection (Poisson
+    /// arrival gaps), so a seeded or resumed run repeats its traffic too
+    pub fn demand_rng(&mut self) -> &mut StdRng {
+        &mut self.rng
+    }
 
-        for &direction in &directions {
-            for &route in &routes {
-                let path = self.calculate_vehicle_path(direction, route);
-                self.path_cache.insert((direction, route), path); // This now works because path implements Clone
-            }
+    /// Capture the run for a checkpoint. The random stream is reseeded from
+    /// itself so that a resumed run draws the same numbers as this one.
+    pub fn save_state(&mut self) -> IntersectionState {
+        let seed = self.rng.random::<u64>();
+        self.seed(seed);
+        IntersectionState {
+            vehicles: self.vehicles().into_iter().cloned().collect(),
+            next_vehicle_id: next_id(),
+            seed,
+            controller: self.controller.save_state(),
+            handover: self.handover.clone(),
+            weather: self.weather,
+            profiles_spawned: self.profiles_spawned.clone(),
+            emissions: self.emissions.clone(),
+            breakdowns: self.breakdowns,
+            breakdown_downtime: self.breakdown_downtime,
+            breakdown_delay: self.breakdown_delay,
+            reroutes: self.reroutes,
+            obstacles: self.obstacles.clone(),
+            closed_lanes: self.closed_lanes.clone(),
+            redirected_spawns: self.redirected_spawns,
+            hazards: self.hazards.clone(),
+            hazards_seen: self.hazards_seen,
+            emergency_stops: self.emergency_stops,
+            revoked_bookings: self.revoked_bookings,
+            platoons: self.platoons,
+            platooned_vehicles: self.platooned_vehicles,
+            requests_lost: self.requests_lost,
+            replies_lost: self.replies_lost,
+            permissions_lost: self.permissions_lost,
+            retries: self.retries,
+            duplicate_reservations: self.duplicate_reservations,
+            withdrawn_crossings: self.withdrawn_crossings,
+            denials: self.denials.clone(),
+            priority_grants: self.priority_grants,
+            total_velocities: self.total_velocities,
+            total_vehicles_passed: self.total_vehicles_passed,
+            passed_by_approach: self.passed_by_approach.clone(),
+            max_velocity_recorded: self.max_velocity_recorded,
+            min_velocity_recorded: self.min_velocity_recorded,
+            max_time_in_intersection: self.max_time_in_intersection,
+            min_time_in_intersection: self.min_time_in_intersection,
+            total_wait_time: self.total_wait_time,
+            total_fuel: self.total_fuel,
+            idle_fuel: self.idle_fuel,
+            close_calls: self.close_calls,
+            close_call_spots: self.close_call_spots.clone(),
+            close_calls_by_approach: self.close_calls_by_approach.clone(),
+            collisions: self.collisions,
+            vehicles_crashed: self.vehicles_crashed,
+            exit_velocities: self.exit_velocities.clone(),
+            travel_times: self.travel_times.clone(),
+            wait_times: self.wait_times.clone(),
+            stop_counts: self.stop_counts.clone(),
+            vehicle_intersection_times: self.vehicle_intersection_times.clone(),
+            pending_spawns: self
+                .pending_spawns
+                .iter()
+                .map(|(&lane, queue)| (lane, queue.clone()))
+                .collect(),
+            total_spawn_delay: self.total_spawn_delay,
+            spawns_held: self.spawns_held,
+            cost: self.cost.clone(),
+            queues: self.queues.clone(),
+            warmed_up: self.warmed_up,
         }
     }
 
-    /// Calculate the complete path for a vehicle (called during initialization)
-    fn calculate_vehicle_path(&self, direction: Direction, route: Route) -> VehiclePath {
-        match route {
-            Route::Straight => {
-                let cells = self.calculate_straight_path_cells(direction);
-                let distance = self.calculate_straight_path_distance(direction);
+    /// Put back a run saved by `save_state` on an intersection built from the
+    /// same settings, replacing whatever is on the road
+    pub fn restore_state(&mut self, state: IntersectionState) -> Result<(), String> {
+        match state.controller {
+            Some(controller) => self.controller.restore_state(controller)?,
+            None => warn!(
+                "The checkpoint has no {} controller state; it starts empty",
+                self.controller.name()
+            ),
+        }
+        self.handover = state.handover;
 
-                VehiclePath {
-                    segment1: PathSegment { cells, distance },
-                    segment2: None,
-                }
-            }
-            Route::Right | Route::Left => {
-                let turn_pos = get_turn_position(direction, route);
-                let (segment1_cells, segment1_distance) =
-                    self.calculate_path_to_turn(direction, route, turn_pos);
-                let (segment2_cells, segment2_distance) =
-                    self.calculate_path_from_turn(direction, route, turn_pos);
-
-                VehiclePath {
-                    segment1: PathSegment {
-                        cells: segment1_cells,
-                        distance: segment1_distance,
-                    },
-                    segment2: Some(PathSegment {
-                        cells: segment2_cells,
-                        distance: segment2_distance,
-                    }),
-                }
-            }
+        for (_, entity) in std::mem::take(&mut self.order) {
+            self.world.despawn(entity);
         }
+        for vehicle in state.vehicles {
+            let vehicle_id = vehicle.id;
+            let entity = self
+                .world
+                .spawn((
+                    vehicle,
+                    Following::default(),
+                    Plan::default(),
+                    Transitions::default(),
+                ))
+                .id();
+            self.order.push((vehicle_id, entity));
+        }
+        set_next_id(state.next_vehicle_id);
+        self.seed(state.seed);
+
+        self.weather = state.weather;
+        self.profiles_spawned = state.profiles_spawned;
+        self.emissions = state.emissions;
+        self.breakdowns = state.breakdowns;
+        self.breakdown_downtime = state.breakdown_downtime;
+        self.breakdown_delay = state.breakdown_delay;
+        self.reroutes = state.reroutes;
+        self.obstacles = state.obstacles;
+        self.closed_lanes = state.closed_lanes;
+        self.redirected_spawns = state.redirected_spawns;
+        self.hazards = state.hazards;
+        self.hazards_seen = state.hazards_seen;
+        self.emergency_stops = state.emergency_stops;
+        self.revoked_bookings = state.revoked_bookings;
+        self.platoons = state.platoons;
+        self.platooned_vehicles = state.platooned_vehicles;
+        self.requests_lost = state.requests_lost;
+        self.replies_lost = state.replies_lost;
+        self.permissions_lost = state.permissions_lost;
+        self.retries = state.retries;
+        self.duplicate_reservations = state.duplicate_reservations;
+        self.withdrawn_crossings = state.withdrawn_crossings;
+        self.denials = state.denials;
+        self.priority_grants = state.priority_grants;
+        self.total_velocities = state.total_velocities;
+        self.total_vehicles_passed = state.total_vehicles_passed;
+        self.passed_by_approach = state.passed_by_approach;
+        self.max_velocity_recorded = state.max_velocity_recorded;
+        self.min_velocity_recorded = state.min_velocity_recorded;
+        self.max_time_in_intersection = state.max_time_in_intersection;
+        self.min_time_in_intersection = state.min_time_in_intersection;
+        self.total_wait_time = state.total_wait_time;
+        self.total_fuel = state.total_fuel;
+        self.idle_fuel = state.idle_fuel;
+        self.close_calls = state.close_calls;
+        self.close_call_spots = state.close_call_spots;
+        self.close_calls_by_approach = state.close_calls_by_approach;
+        self.collisions = state.collisions;
+        self.vehicles_crashed = state.vehicles_crashed;
+        self.exit_velocities = state.exit_velocities;
+        self.travel_times = state.travel_times;
+        self.wait_times = state.wait_times;
+        self.stop_counts = state.stop_counts;
+        self.vehicle_intersection_times = state.vehicle_intersection_times;
+        self.pending_spawns = state.pending_spawns.into_iter().collect();
+        self.total_spawn_delay = state.total_spawn_delay;
+        self.spawns_held = state.spawns_held;
+        self.cost = state.cost;
+        self.queues = state.queues;
+        self.warmed_up = state.warmed_up;
+        Ok(())
     }
 
-    /// Calculate straight path cells
-    fn calculate_straight_path_cells(&self, direction: Direction) -> Vec<(usize, usize)> {
-        let mut cells = Vec::new();
+    /// Every vehicle on the road, in spawn order
+    pub fn vehicles(&self) -> Vec<&Vehicle> {
+        self.order
+            .iter()
+            .filter_map(|&(_, entity)| self.world.get::<Vehicle>(entity))
+            .collect()
+    }
 
-        match direction {
-            Direction::North => {
-                // Northbound straight: lanes around x=550 (cols 20-24)
-                for row in 0..self.rows {
-                    for col in 20..25 {
-                        if col < self.cols {
-                            cells.push((col, row));
-                        }
-                    }
-                }
-            }
-            Direction::South => {
-                // Southbound straight: lanes around x=400 (cols 5-9)
-                for row in 0..self.rows {
-                    for col in 5..10 {
-                        if col < self.cols {
-                            cells.push((col, row));
-                        }
-                    }
-                }
-            }
-            Direction::East => {
-                // Eastbound straight: lanes around y=550 (rows 20-24)
-                for col in 0..self.cols {
-                    for row in 20..25 {
-                        if row < self.rows {
-                            cells.push((col, row));
-                        }
-                    }
-                }
-            }
-            Direction::West => {
-                // Westbound straight: lanes around y=400 (rows 5-9)
-                for col in 0..self.cols {
-                    for row in 5..10 {
-                        if row < self.rows {
-                            cells.push((col, row));
-                        }
-                    }
-                }
-            }
-        }
+    pub fn vehicle_count(&self) -> usize {
+        self.order.len()
+    }
 
-        cells
+    pub fn vehicle(&self, vehicle_id: usize) -> Option<&Vehicle> {
+        let &(_, entity) = self.order.iter().find(|&&(id, _)| id == vehicle_id)?;
+        self.world.get::<Vehicle>(entity)
     }
 
-    /// Calculate distance for straight path through intersection
-    fn calculate_straight_path_distance(&self, _direction: Direction) -> f32 {
-        300.0 // Intersection is 300px across
+    fn vehicle_mut(&mut self, vehicle_id: usize) -> Option<Mut<'_, Vehicle>> {
+        let &(_, entity) = self.order.iter().find(|&&(id, _)| id == vehicle_id)?;
+        self.world.get_mut::<Vehicle>(entity)
     }
 
-    /// Calculate path from entry to turn position
-    fn calculate_path_to_turn(
-        &self,
-        direction: Direction,
-        route: Route,
-        turn_pos: (f32, f32),
-    ) -> (Vec<(usize, usize)>, f32) {
-        let mut cells = Vec::new();
-
-        match direction {
-            Direction::North => {
-                let cols = if route == Route::Left { 15..20 } else { 25..30 }; // Left or right lane
-                let entry_y = 650.0;
-                let turn_y = turn_pos.1;
-                let distance = entry_y - turn_y;
-
-                let start_row = ((turn_y - IY_MIN) / self.zone_px as f32) as usize;
-                let end_row = ((entry_y - IY_MIN) / self.zone_px as f32) as usize;
-
-                for row in start_row..=end_row.min(self.rows - 1) {
-                    for col in cols.clone() {
-                        if col < self.cols {
-                            cells.push((col, row));
-                        }
-                    }
-                }
+    fn despawn(&mut self, vehicle_id: usize) {
+        if let Some(index) = self.order.iter().position(|&(id, _)| id == vehicle_id) {
+            let (_, entity) = self.order.remove(index);
+            self.world.despawn(entity);
+        }
+    }
+
+    /// Advance the simulation by one SIM_TICK and report the road afterwards
+    /// with the events of the step. Time is kept here, so this replaces
+    /// calling `update` and `drain_events` with an explicit time.
+    pub fn step(&mut self) -> FrameState {
+        self.clock += SIM_TICK as f64;
+        let current_time = self.clock as f32;
+        self.update(current_time);
+        let events = self.drain_events();
+        FrameState::new(current_time, self, events)
+    }
 
-                (cells, distance)
+    /// Main update function
+    pub fn update(&mut self, current_time: f32) {
+        if !self.warmed_up && current_time >= self.warm_up {
+            self.warmed_up = true;
+            if self.warm_up > 0.0 {
+                self.reset_stats();
+                info!(
+                    "Warm-up over after {:.0} s; statistics start now",
+                    self.warm_up
+                );
             }
-            Direction::South => {
-                let cols = if route == Route::Left { 10..15 } else { 0..5 }; // Left or right lane
-                let entry_y = 350.0;
-                let turn_y = turn_pos.1;
-                let distance = turn_y - entry_y;
+        }
+        self.controller.tick(current_time);
+        self.prune_handover();
+        self.update_lane_closures(current_time);
+        self.spawn_pending(current_time);
+        self.update_breakdowns(current_time);
+        self.update_hazards(current_time);
+        self.update_vehicles_with_two_path_system(current_time);
 
-                let start_row = ((entry_y - IY_MIN) / self.zone_px as f32) as usize;
-                let end_row = ((turn_y - IY_MIN) / self.zone_px as f32) as usize;
+        // Exiting vehicles free whatever cells they still hold
+        let phase_start = Instant::now();
+        let span = trace_span!("release").entered();
+        let exiting: Vec<usize> = self
+            .vehicles()
+            .into_iter()
+            .filter(|v| v.is_outside_canvas())
+            .map(|v| v.id)
+            .collect();
+
+        for vehicle_id in exiting {
+            // Copy out what the stats need before borrowing self mutably
+            let Some(vehicle) = self.vehicle(vehicle_id) else {
+                continue;
+            };
+            let vehicle_velocity = vehicle.get_velocity(current_time);
+            let waiting_time = vehicle.waiting_time;
+            let stops = vehicle.stops;
+            let approach = vehicle.approach_direction();
+            let travel_time = current_time - vehicle.spawned_at;
+            let (fuel, idle_fuel, class) = (vehicle.fuel_used, vehicle.idle_fuel, vehicle.class);
+
+            self.total_wait_time += waiting_time;
+            self.wait_times.push(waiting_time);
+            self.stop_counts.push(stops);
+            *self.passed_by_approach.entry(approach).or_default() += 1;
+            self.travel_times.push(travel_time);
+            self.total_fuel += fuel;
+            self.idle_fuel += idle_fuel;
+            let emissions = self.emissions.entry(class).or_default();
+            emissions.vehicles += 1;
+            emissions.co2_g += fuel * class.co2_per_ml();
+            emissions.nox_g += fuel * class.nox_per_ml();
+            self.update_stats_for_exiting_vehicle_by_data(
+                vehicle_id,
+                vehicle_velocity,
+                current_time,
+            );
+            self.controller.notify_exit(vehicle_id, current_time);
+            self.emit(current_time, vehicle_id, EventKind::Exited);
+            self.despawn(vehicle_id);
+        }
+        drop(span);
+        self.timings.release += phase_start.elapsed();
+
+        self.clear_crashed_vehicles(current_time);
+        self.update_cost(current_time);
+        let mut queues = std::mem::take(&mut self.queues);
+        queues.update(&self.vehicles(), current_time);
+        self.queues = queues;
+    }
 
-                for row in start_row..=end_row.min(self.rows - 1) {
-                    for col in cols.clone() {
-                        if col < self.cols {
-                            cells.push((col, row));
-                        }
-                    }
-                }
+    /// Seconds of warm-up still to go; zero once the statistics are running
+    pub fn warm_up_left(&self, current_time: f32) -> f32 {
+        if self.warmed_up {
+            0.0
+        } else {
+            (self.warm_up - current_time).max(0.0)
+        }
+    }
 
-                (cells, distance)
-            }
-            Direction::East => {
-                let rows = if route == Route::Left { 15..20 } else { 25..30 }; // Left or right lane
-                let entry_x = 350.0;
-                let turn_x = turn_pos.0;
-                let distance = turn_x - entry_x;
+    /// Seconds of the run the statistics cover: all of it but the warm-up
+    pub fn measured_time(&self, current_time: f32) -> f32 {
+        (current_time - self.warm_up).max(0.0)
+    }
 
-                let start_col = ((entry_x - IX_MIN) / self.zone_px as f32) as usize;
-                let end_col = ((turn_x - IX_MIN) / self.zone_px as f32) as usize;
+    /// Forget every statistic gathered so far. Vehicles on the road stay and
+    /// count in full when they leave.
+    fn reset_stats(&mut self) {
+        self.profiles_spawned.clear();
+        self.emissions.clear();
+        self.breakdowns = 0;
+        self.breakdown_downtime = 0.0;
+        self.breakdown_delay = 0.0;
+        self.reroutes = 0;
+        self.redirected_spawns = 0;
+        self.hazards_seen = 0;
+        self.emergency_stops = 0;
+        self.revoked_bookings = 0;
+        self.platoons = 0;
+        self.platooned_vehicles = 0;
+        self.requests_lost = 0;
+        self.replies_lost = 0;
+        self.permissions_lost = 0;
+        self.retries = 0;
+        self.duplicate_reservations = 0;
+        self.withdrawn_crossings = 0;
+        self.denials.clear();
+        self.priority_grants = 0;
+        self.total_velocities = 0.0;
+        self.total_vehicles_passed = 0;
+        self.passed_by_approach.clear();
+        self.max_velocity_recorded = 0.0;
+        self.min_velocity_recorded = f32::MAX;
+        self.max_time_in_intersection = 0.0;
+        self.min_time_in_intersection = f32::MAX;
+        self.total_wait_time = 0.0;
+        self.total_fuel = 0.0;
+        self.idle_fuel = 0.0;
+        self.close_calls = 0;
+        self.close_call_spots.clear();
+        self.close_calls_by_approach.clear();
+        self.collisions = 0;
+        self.vehicles_crashed = 0;
+        self.exit_velocities.clear();
+        self.travel_times.clear();
+        self.wait_times.clear();
+        self.stop_counts.clear();
+        self.cost.restart();
+        self.queues = QueueTracker::default();
+        self.total_spawn_delay = 0.0;
+        self.spawns_held = 0;
+    }
 
-                for col in start_col..=end_col.min(self.cols - 1) {
-                    for row in rows.clone() {
-                        if row < self.rows {
-                            cells.push((col, row));
-                        }
-                    }
-                }
+    fn update_cost(&mut self, current_time: f32) {
+        let mut cost = std::mem::take(&mut self.cost);
+        cost.update(
+            CostInputs {
+                vehicles: &self.vehicles(),
+                passed: self.total_vehicles_passed,
+                close_calls: self.close_calls,
+                collisions: self.collisions,
+            },
+            current_time,
+        );
+        self.cost = cost;
+    }
 
-                (cells, distance)
-            }
-            Direction::West => {
-                let rows = if route == Route::Left { 10..15 } else { 0..5 }; // Left or right lane
-                let entry_x = 650.0;
-                let turn_x = turn_pos.0;
-                let distance = entry_x - turn_x;
+    /// Break down random vehicles and get repaired ones moving again
+    fn update_breakdowns(&mut self, current_time: f32) {
+        let chance_per_frame = self.breakdown_rate / 60.0;
+        let mut broke_down = Vec::new();
+        let mut repaired = Vec::new();
 
-                let start_col = ((turn_x - IX_MIN) / self.zone_px as f32) as usize;
-                let end_col = ((entry_x - IX_MIN) / self.zone_px as f32) as usize;
+        for &(_, entity) in &self.order {
+            let Some(mut vehicle) = self.world.get_mut::<Vehicle>(entity) else {
+                continue;
+            };
+            if vehicle.crashed_at.is_some() || vehicle.is_outside_canvas() {
+                continue;
+            }
 
-                for col in start_col..=end_col.min(self.cols - 1) {
-                    for row in rows.clone() {
-                        if row < self.rows {
-                            cells.push((col, row));
-                        }
+            match vehicle.breakdown {
+                Some(breakdown) => {
+                    self.breakdown_downtime += 1.0 / 60.0;
+                    if breakdown.until.is_some_and(|until| current_time >= until) {
+                        vehicle.breakdown = None;
+                        repaired.push(vehicle.id);
+                    }
+                }
+                None => {
+                    if chance_per_frame > 0.0 && self.rng.random::<f32>() < chance_per_frame {
+                        vehicle.breakdown = Some(Breakdown {
+                            until: self.breakdown_duration.map(|d| current_time + d),
+                        });
+                        vehicle.current_speed = Velocity::STOPPED;
+                        broke_down.push((vehicle.id, vehicle.get_visual_bounds()));
                     }
                 }
-
-                (cells, distance)
             }
         }
-    }
 
-    /// Calculate path from turn position to exit
-    fn calculate_path_from_turn(
-        &self,
-        direction: Direction,
-        route: Route,
-        turn_pos: (f32, f32),
-    ) -> (Vec<(usize, usize)>, f32) {
-        let mut cells = Vec::new();
-
-        // After turning, vehicle changes direction
-        let new_direction = match (direction, route) {
-            (Direction::North, Route::Right) => Direction::East,
-            (Direction::North, Route::Left) => Direction::West,
-            (Direction::South, Route::Right) => Direction::West,
-            (Direction::South, Route::Left) => Direction::East,
-            (Direction::East, Route::Right) => Direction::South,
-            (Direction::East, Route::Left) => Direction::North,
-            (Direction::West, Route::Right) => Direction::North,
-            (Direction::West, Route::Left) => Direction::South,
-            _ => direction, // Should not happen for turns
-        };
+        for (vehicle_id, bounds) in broke_down {
+            self.breakdowns += 1;
+            let until = self
+                .vehicle(vehicle_id)
+                .and_then(|v| v.breakdown)
+                .and_then(|b| b.until)
+                .unwrap_or(f32::MAX);
+            self.controller
+                .notify_blocked(vehicle_id, bounds, current_time, until);
+            self.emit(current_time, vehicle_id, EventKind::BrokeDown);
+        }
+        for vehicle_id in repaired {
+            self.controller.notify_unblocked(vehicle_id);
+            self.emit(current_time, vehicle_id, EventKind::Repaired);
+        }
+    }
 
-        match new_direction {
-            Direction::North => {
-                let exit_y = 350.0;
-                let turn_y = turn_pos.1;
-                let distance = turn_y - exit_y;
+    /// Bring on, walk and retire jaywalkers. A vehicle with one just ahead in
+    /// its lane stops dead. Inside the box the controller holds the cells one is
+    /// about to walk over and cancels the crossings booked through them; a
+    /// vehicle held up by one on its approach loses its booking too.
+    fn update_hazards(&mut self, current_time: f32) {
+        let chance_per_frame = self.hazard_rate / 60.0;
+        if chance_per_frame > 0.0
+            && self.rng.random::<f32>() < chance_per_frame
+            && let Some(hazard) = Hazard::random(&mut self.rng, self.layout)
+            && !self.vehicles().iter().any(|v| {
+                let (x, y, w, h) = hazard.area();
+                let (vx, vy, vw, vh) = v.get_visual_bounds();
+                x < vx + vw && vx < x + w && y < vy + vh && vy < y + h
+            })
+        {
+            debug!(id = hazard.id, kind = ?hazard.kind, at = ?hazard.centre, "Jaywalker on the road");
+            self.hazards_seen += 1;
+            self.hazards.push(hazard);
+        }
 
-                let cols = 15..20; // After-turn lane width
-                let start_row = ((exit_y - IY_MIN) / self.zone_px as f32) as usize;
-                let end_row = ((turn_y - IY_MIN) / self.zone_px as f32) as usize;
+        // They wait rather than walk into the side of a passing vehicle
+        let bodies: Vec<_> = self
+            .vehicles()
+            .iter()
+            .filter(|v| v.current_speed != Velocity::STOPPED)
+            .map(|v| v.get_visual_bounds())
+            .collect();
+        for hazard in &mut self.hazards {
+            let (x, y, w, h) = hazard.swept_area(0.2);
+            let blocked = bodies
+                .iter()
+                .any(|&(bx, by, bw, bh)| x < bx + bw && bx < x + w && y < by + bh && by < y + h);
+            if !blocked {
+                hazard.step(1.0 / 60.0);
+            }
+        }
+        let (across, walking): (Vec<Hazard>, Vec<Hazard>) = std::mem::take(&mut self.hazards)
+            .into_iter()
+            .partition(Hazard::is_across);
+        self.hazards = walking;
+        for hazard in across {
+            self.controller.notify_unblocked(hazard.id);
+        }
 
-                for row in start_row..=end_row.min(self.rows - 1) {
-                    for col in cols.clone() {
-                        if col < self.cols {
-                            cells.push((col, row));
-                        }
-                    }
+        let until = current_time + HAZARD_HOLD_AHEAD;
+        let mut stops = Vec::new();
+        let mut revoked = Vec::new();
+        for (index, hazard) in self.hazards.iter().enumerate() {
+            let area = hazard.area();
+            for vehicle in self.vehicles() {
+                if vehicle.crashed_at.is_some() || vehicle.breakdown.is_some() {
+                    continue;
+                }
+                let Some(distance) = vehicle.distance_to_obstacle(&[area]) else {
+                    continue;
+                };
+                if vehicle.current_speed != Velocity::STOPPED
+                    && distance < vehicle.get_safe_obstacle_distance(self.weather)
+                    && !hazard.stopped.contains(&vehicle.id)
+                {
+                    stops.push((index, vehicle.id));
                 }
+                if vehicle.intersection_permission
+                    && !vehicle.is_in_intersection()
+                    && !vehicle.is_past_intersection()
+                    && distance < vehicle.distance_to_intersection()
+                {
+                    revoked.push(vehicle.id);
+                }
+            }
+
+            let ahead = hazard.swept_area(HAZARD_HOLD_AHEAD);
+            self.controller.notify_unblocked(hazard.id);
+            self.controller
+                .notify_blocked(hazard.id, ahead, current_time, until);
+            revoked.extend(
+                self.controller
+                    .booked_over(ahead, current_time, until)
+                    .into_iter()
+                    .filter(|&id| {
+                        self.vehicle(id)
+                            .is_some_and(|v| !v.is_in_intersection() && !v.is_past_intersection())
+                    }),
+            );
+        }
 
-                (cells, distance)
+        for (index, vehicle_id) in stops {
+            if let Some(mut vehicle) = self.vehicle_mut(vehicle_id) {
+                vehicle.current_speed = Velocity::STOPPED;
             }
-            Direction::South => {
-                let exit_y = 650.0;
-                let turn_y = turn_pos.1;
-                let distance = exit_y - turn_y;
+            self.hazards[index].stopped.push(vehicle_id);
+            self.emergency_stops += 1;
+            self.emit(current_time, vehicle_id, EventKind::EmergencyStop);
+        }
+        revoked.sort_unstable();
+        revoked.dedup();
+        for vehicle_id in revoked {
+            self.cancel_crossing(vehicle_id);
+            self.revoked_bookings += 1;
+            debug!(vehicle_id, "Crossing cancelled for a jaywalker");
+        }
+    }
 
-                let cols = 10..15; // After-turn lane width
-                let start_row = ((turn_y - IY_MIN) / self.zone_px as f32) as usize;
-                let end_row = ((exit_y - IY_MIN) / self.zone_px as f32) as usize;
+    /// Switch to the built-in controller after `current` (the one in use,
+    /// None while a script decides) for the K key, and return it
+    pub fn cycle_controller(
+        &mut self,
+        current: Option<ControllerKind>,
+        current_time: f32,
+    ) -> ControllerKind {
+        // A scripted controller is left for the built-in ones
+        let kind = current.map_or(ControllerKind::Reservation, ControllerKind::next);
+        self.switch_controller(kind.build(self.layout, self.path_padding), current_time);
+        kind
+    }
 
-                for row in start_row..=end_row.min(self.rows - 1) {
-                    for col in cols.clone() {
-                        if col < self.cols {
-                            cells.push((col, row));
-                        }
-                    }
-                }
+    /// Replace the controller mid-run. The new one starts empty: it is told
+    /// about closed lanes and whatever blocks the road, and vehicles already
+    /// granted a crossing keep it, with nobody else let in until they are
+    /// through. Vehicles still waiting simply ask the new controller.
+    pub fn switch_controller(
+        &mut self,
+        controller: Box<dyn IntersectionController>,
+        current_time: f32,
+    ) {
+        self.controller = controller;
+        for closure in &self.closed_lanes {
+            self.controller
+                .notify_lane_closed(closure.direction, closure.route, true);
+        }
+        let stranded: Vec<_> = self
+            .vehicles()
+            .iter()
+            .filter_map(|v| {
+                let until = v.breakdown?.until.unwrap_or(f32::MAX);
+                Some((v.id, v.get_visual_bounds(), until))
+            })
+            .collect();
+        for (vehicle_id, bounds, until) in stranded {
+            self.controller
+                .notify_blocked(vehicle_id, bounds, current_time, until);
+        }
+        for obstacle in &self.obstacles {
+            self.controller
+                .notify_blocked(obstacle.id, obstacle.area, current_time, f32::MAX);
+        }
+        self.handover = self
+            .vehicles()
+            .iter()
+            .filter(|v| v.intersection_permission && !v.is_past_intersection())
+            .map(|v| v.id)
+            .collect();
+        info!(
+            controller = self.controller.name(),
+            crossing = self.handover.len(),
+            "Controller switched"
+        );
+    }
 
-                (cells, distance)
-            }
-            Direction::East => {
-                let exit_x = 650.0;
-                let turn_x = turn_pos.0;
-                let distance = exit_x - turn_x;
+    /// Drop vehicles from the handover once they are through the box or have
+    /// lost their grant
+    fn prune_handover(&mut self) {
+        let handover = std::mem::take(&mut self.handover);
+        self.handover = handover
+            .into_iter()
+            .filter(|&id| {
+                self.vehicle(id)
+                    .is_some_and(|v| v.intersection_permission && !v.is_past_intersection())
+            })
+            .collect();
+    }
 
-                let rows = 10..15; // After-turn lane width
-                let start_col = ((turn_x - IX_MIN) / self.zone_px as f32) as usize;
-                let end_col = ((exit_x - IX_MIN) / self.zone_px as f32) as usize;
+    /// Tow away every vehicle that is currently broken down
+    pub fn remove_broken_down_vehicles(&mut self, current_time: f32) {
+        let stranded: Vec<usize> = self
+            .vehicles()
+            .into_iter()
+            .filter(|v| v.breakdown.is_some())
+            .map(|v| v.id)
+            .collect();
+
+        for vehicle_id in stranded {
+            self.despawn(vehicle_id);
+            self.vehicle_intersection_times.remove(&vehicle_id);
+            self.controller.notify_exit(vehicle_id, current_time);
+            self.emit(current_time, vehicle_id, EventKind::Removed);
+        }
+    }
 
-                for col in start_col..=end_col.min(self.cols - 1) {
-                    for row in rows.clone() {
-                        if row < self.rows {
-                            cells.push((col, row));
-                        }
-                    }
-                }
+    /// Drop an obstacle on the lane under the world point `at`. Refused off
+    /// the road and on top of a vehicle or another obstacle.
+    pub fn place_obstacle(
+        &mut self,
+        kind: ObstacleKind,
+        at: (f32, f32),
+        current_time: f32,
+    ) -> bool {
+        let Some(obstacle) = Obstacle::place(kind, at, self.layout) else {
+            return false;
+        };
+        let (x, y, w, h) = obstacle.area;
+        let overlaps = |(ox, oy, ow, oh): (f32, f32, f32, f32)| {
+            x < ox + ow && ox < x + w && y < oy + oh && oy < y + h
+        };
+        if self
+            .vehicles()
+            .iter()
+            .any(|v| overlaps(v.get_visual_bounds()))
+            || self.obstacles.iter().any(|o| overlaps(o.area))
+        {
+            return false;
+        }
+        self.controller
+            .notify_blocked(obstacle.id, obstacle.area, current_time, f32::MAX);
+        info!(?kind, area = ?obstacle.area, "Obstacle placed");
+        self.obstacles.push(obstacle);
+        true
+    }
 
-                (cells, distance)
-            }
-            Direction::West => {
-                let exit_x = 350.0;
-                let turn_x = turn_pos.0;
-                let distance = turn_x - exit_x;
+    /// Clear every obstacle off the road
+    pub fn clear_obstacles(&mut self, current_time: f32) {
+        for obstacle in std::mem::take(&mut self.obstacles) {
+            self.controller.notify_exit(obstacle.id, current_time);
+        }
+    }
 
-                let rows = 15..20; // After-turn lane width
-                let start_col = ((exit_x - IX_MIN) / self.zone_px as f32) as usize;
-                let end_col = ((turn_x - IX_MIN) / self.zone_px as f32) as usize;
+    /// Take a vehicle's crossing away; it has to ask again before entering
+    fn cancel_crossing(&mut self, vehicle_id: usize) {
+        self.controller.revoke(vehicle_id);
+        if let Some(mut vehicle) = self.vehicle_mut(vehicle_id) {
+            vehicle.intersection_permission = false;
+            vehicle.requested_intersection = false;
+            vehicle.unacknowledged_grant = false;
+            vehicle.platoon_ahead = None;
+        }
+    }
 
-                for col in start_col..=end_col.min(self.cols - 1) {
-                    for row in rows.clone() {
-                        if row < self.rows {
-                            cells.push((col, row));
-                        }
-                    }
-                }
+    /// Hold a vehicle in place, or let a held one go again; returns whether
+    /// it is now frozen, or None when there is no such vehicle. A debug aid:
+    /// the vehicle keeps whatever it booked.
+    pub fn toggle_frozen(&mut self, vehicle_id: usize) -> Option<bool> {
+        let mut vehicle = self.vehicle_mut(vehicle_id)?;
+        vehicle.frozen = !vehicle.frozen;
+        if vehicle.frozen {
+            vehicle.current_speed = Velocity::STOPPED;
+        }
+        Some(vehicle.frozen)
+    }
 
-                (cells, distance)
-            }
+    /// Let a vehicle cross without the controller booking anything for it,
+    /// so it drives into whatever others have booked. A debug aid; refused
+    /// for vehicles already through or too far out to keep a permission.
+    pub fn force_grant(&mut self, vehicle_id: usize) -> bool {
+        let Some(mut vehicle) = self.vehicle_mut(vehicle_id) else {
+            return false;
+        };
+        if vehicle.is_past_intersection() || vehicle.distance_to_intersection() > 150.0 {
+            return false;
         }
+        vehicle.requested_intersection = true;
+        vehicle.intersection_permission = true;
+        vehicle.unacknowledged_grant = false;
+        vehicle.denial = None;
+        true
     }
 
-    /// Main update function
-    pub fn update(&mut self, current_time: f32) {
-        self.update_vehicles_with_two_path_system(current_time);
+    /// Take a vehicle's crossing away even once it is committed to it. A
+    /// debug aid; false when it had no crossing to lose.
+    pub fn revoke_crossing(&mut self, vehicle_id: usize) -> bool {
+        let granted = self
+            .vehicle(vehicle_id)
+            .is_some_and(|v| v.intersection_permission || v.unacknowledged_grant);
+        if granted {
+            self.cancel_crossing(vehicle_id);
+        }
+        granted
+    }
+
+    /// Wipe the controller's bookings while granted vehicles keep their
+    /// crossing. A debug aid for provoking conflicts.
+    pub fn clear_bookings(&mut self) {
+        self.controller.clear_bookings();
+    }
 
-        // Collect vehicle data first (without borrowing self mutably)
-        let mut vehicles_to_remove = Vec::new();
-        for (i, vehicle) in self.active_vehicles.iter().enumerate() {
-            if vehicle.is_outside_canvas() {
-                let vehicle_velocity = vehicle.get_velocity(current_time);
-                vehicles_to_remove.push((i, vehicle.id, vehicle_velocity));
+    /// Count time lost by vehicles stuck in a queue that ends at a broken-down vehicle
+    fn accumulate_breakdown_delay(&mut self) {
+        for &(_, entity) in &self.order {
+            let (Some(following), Some(vehicle)) = (
+                self.world.get::<Following>(entity),
+                self.world.get::<Vehicle>(entity),
+            ) else {
+                continue;
+            };
+            if following.speed != Velocity::STOPPED || vehicle.breakdown.is_some() {
+                continue;
             }
-        }
 
-        // Process the collected data (now we can borrow self mutably)
-        for &(_, vehicle_id, vehicle_velocity) in &vehicles_to_remove {
-            self.update_stats_for_exiting_vehicle_by_data(
-                vehicle_id,
-                vehicle_velocity,
-                current_time,
-            );
+            // Walk up the queue; the length bound guards against cycles
+            let mut next = following.leader;
+            for _ in 0..self.order.len() {
+                let Some(leader) = next else { break };
+                if self
+                    .world
+                    .get::<Vehicle>(leader)
+                    .is_some_and(|v| v.breakdown.is_some())
+                {
+                    self.breakdown_delay += 1.0 / 60.0;
+                    break;
+                }
+                next = self.world.get::<Following>(leader).and_then(|f| f.leader);
+            }
         }
+    }
 
-        // Remove vehicles (in reverse order to maintain correct indices)
-        for &(i, _vehicle_id, _) in vehicles_to_remove.iter().rev() {
-            self.active_vehicles.remove(i);
+    /// Remove wrecks that have been sitting on the road long enough
+    fn clear_crashed_vehicles(&mut self, current_time: f32) {
+        let cleared: Vec<usize> = self
+            .vehicles()
+            .into_iter()
+            .filter(|v| {
+                v.crashed_at
+                    .is_some_and(|t| current_time - t >= CRASH_CLEAR_DELAY)
+            })
+            .map(|v| v.id)
+            .collect();
+
+        for vehicle_id in cleared {
+            self.despawn(vehicle_id);
+            self.vehicle_intersection_times.remove(&vehicle_id);
+            self.controller.notify_exit(vehicle_id, current_time);
+            self.emit(current_time, vehicle_id, EventKind::Removed);
         }
     }
 
-    /// Updated vehicle management with two-path system
+    /// Updated vehicle management with two-path system: the following and
+    /// movement/turning systems, with the reservation requests, cell release
+    /// and safety checks, which need the controller, run in between as methods
     fn update_vehicles_with_two_path_system(&mut self, current_time: f32) {
         // Calculate traffic speeds
-        let mut target_speeds = Vec::with_capacity(self.active_vehicles.len());
+        let phase_start = Instant::now();
+        let span = trace_span!("following").entered();
+        if self.rerouting {
+            self.reroute_around_breakdowns(current_time);
+        }
+        self.world.insert_resource(RoadConditions(self.weather));
+        self.world.insert_resource(Obstacles(
+            self.obstacles
+                .iter()
+                .map(|obstacle| obstacle.area)
+                .chain(self.roadworks())
+                .chain(self.hazards.iter().map(Hazard::area))
+                .collect(),
+        ));
+        self.run_system(following_system);
+        self.accumulate_breakdown_delay();
+        drop(span);
+        self.timings.following += phase_start.elapsed();
+
+        let phase_start = Instant::now();
+        trace_span!("reservation").in_scope(|| self.plan_intersection_requests(current_time));
+        self.timings.reservation += phase_start.elapsed();
+
+        let phase_start = Instant::now();
+        trace_span!("movement").in_scope(|| self.run_system(movement_system));
+        let movement = phase_start.elapsed();
+
+        let phase_start = Instant::now();
+        trace_span!("release").in_scope(|| self.publish_transitions(current_time));
+        self.timings.release += phase_start.elapsed();
+
+        let phase_start = Instant::now();
+        let span = trace_span!("safety").entered();
+        self.detect_collisions(current_time);
+        self.detect_close_calls(current_time);
+        drop(span);
+        self.timings.movement += movement + phase_start.elapsed();
+    }
 
-        for i in 0..self.active_vehicles.len() {
-            let current_vehicle = &self.active_vehicles[i];
+    /// Move vehicles that haven't booked a crossing yet off routes blocked by a
+    /// broken-down car or an obstacle: onto the lane of the first free route (straight, then
+    /// right, then left) when that lane is clear beside them
+    fn reroute_around_breakdowns(&mut self, current_time: f32) {
+        let stranded: Vec<(usize, (f32, f32))> = self
+            .vehicles()
+            .iter()
+            .filter(|v| v.breakdown.is_some())
+            .map(|v| (v.id, v.get_visual_center()))
+            .chain(self.obstacles.iter().map(|o| (o.id, o.centre())))
+            .chain(self.roadworks().into_iter().map(|(x, y, w, h)| {
+                (usize::MAX, (x + w / 2.0, y + h / 2.0)) // no vehicle has this id
+            }))
+            .collect();
+        if stranded.is_empty() {
+            return;
+        }
 
-            if current_vehicle.is_past_intersection() {
-                target_speeds.push(Velocity::Fast);
+        let mut reroutes = Vec::new();
+        for vehicle in self.vehicles() {
+            let committed = vehicle.has_turned
+                || vehicle.intersection_permission
+                || vehicle.unacknowledged_grant
+                || vehicle.crashed_at.is_some()
+                || vehicle.breakdown.is_some()
+                || vehicle.is_in_intersection()
+                || vehicle.distance_to_intersection() <= 0.0;
+            if committed || !self.route_blocked(vehicle, vehicle.route, &stranded) {
                 continue;
             }
+            let alternative = [Route::Straight, Route::Right, Route::Left]
+                .into_iter()
+                .filter(|&route| {
+                    route != vehicle.route
+                        && self.layout.allows(vehicle.direction, route)
+                        && !self.lane_closed(vehicle.direction, route)
+                })
+                .find(|&route| {
+                    !self.route_blocked(vehicle, route, &stranded)
+                        && self.lane_clear_beside(vehicle, route)
+                });
+            if let Some(route) = alternative {
+                reroutes.push((vehicle.id, route));
+            }
+        }
 
-            let mut target_speed = Velocity::Fast;
-            let mut closest_distance = f32::MAX;
-            let mut required_distance = 0.0;
-
-            for (j, other_vehicle) in self.active_vehicles.iter().enumerate() {
-                if i == j {
-                    continue;
-                }
-
-                if current_vehicle.is_ahead_of_me(other_vehicle) {
-                    let distance = current_vehicle.distance_to_vehicle(other_vehicle);
-                    if distance < closest_distance {
-                        closest_distance = distance;
-                        required_distance =
-                            current_vehicle.get_safe_following_distance(other_vehicle);
-                    }
-                }
+        for (vehicle_id, route) in reroutes {
+            if let Some(mut vehicle) = self.vehicle_mut(vehicle_id) {
+                vehicle.position = relaned_position(&vehicle, route);
+                vehicle.route = route;
+                vehicle.turn_position = get_turn_position(vehicle.direction, route);
+                vehicle.requested_intersection = false;
+                vehicle.platoon_ahead = None;
             }
+            self.reroutes += 1;
+            self.emit(current_time, vehicle_id, EventKind::Rerouted { route });
+        }
+    }
 
-            if closest_distance != f32::MAX && closest_distance < required_distance {
-                if closest_distance < required_distance * 0.7 {
-                    target_speed = Velocity::Stopped
-                } else if closest_distance < required_distance * 0.8 {
-                    target_speed = Velocity::Medium;
-                }
+    /// Whether a stranded vehicle or obstacle sits on what `vehicle` would drive over
+    /// following `route` from its lane: up to the turn, then out along the exit
+    fn route_blocked(
+        &self,
+        vehicle: &Vehicle,
+        route: Route,
+        stranded: &[(usize, (f32, f32))],
+    ) -> bool {
+        let (x, y) = relaned_position(vehicle, route);
+        let start = (
+            x + vehicle.width as f32 / 2.0,
+            y + vehicle.height as f32 / 2.0,
+        );
+        let corridors = match route {
+            Route::Straight => vec![lane_corridor(start, vehicle.direction, 1000.0)],
+            Route::Left | Route::Right => {
+                let (tx, ty) = get_turn_position(vehicle.direction, route);
+                let turn = match vehicle.direction {
+                    Direction::North | Direction::South => (start.0, ty),
+                    Direction::East | Direction::West => (tx, start.1),
+                };
+                let approach = (turn.0 - start.0).abs() + (turn.1 - start.1).abs();
+                vec![
+                    lane_corridor(start, vehicle.direction, approach),
+                    lane_corridor(turn, exit_direction(vehicle.direction, route), 1000.0),
+                ]
             }
+        };
+        stranded.iter().any(|&(id, (cx, cy))| {
+            id != vehicle.id
+                && corridors
+                    .iter()
+                    .any(|&(x, y, w, h)| cx >= x && cx <= x + w && cy >= y && cy <= y + h)
+        })
+    }
 
-            target_speeds.push(target_speed);
-        }
+    /// Whether `vehicle` can move sideways into `route`'s lane: nobody there
+    /// within its safety distance ahead or behind
+    fn lane_clear_beside(&self, vehicle: &Vehicle, route: Route) -> bool {
+        let mut moved = vehicle.clone();
+        moved.position = relaned_position(vehicle, route);
+        let (x, y, w, h) = moved.get_visual_bounds();
+        let gap = vehicle.safety_distance;
+        let (x, y, w, h) = match vehicle.direction {
+            Direction::North | Direction::South => (x, y - gap, w, h + 2.0 * gap),
+            Direction::East | Direction::West => (x - gap, y, w + 2.0 * gap, h),
+        };
+        self.vehicles().iter().all(|other| {
+            let (ox, oy, ow, oh) = other.get_visual_bounds();
+            other.id == vehicle.id || ox >= x + w || x >= ox + ow || oy >= y + h || y >= oy + oh
+        })
+    }
 
+    /// Decide every moving vehicle's speed and ask the controller for crossings
+    fn plan_intersection_requests(&mut self, current_time: f32) {
+        self.starving = self.starving_vehicles();
         // Process intersection requests with two-path system
-        let mut vehicle_updates = Vec::new();
-
-        for i in 0..self.active_vehicles.len() {
-            let vehicle = &self.active_vehicles[i];
-            let vehicle_id = vehicle.id;
+        for (vehicle_id, entity) in self.order.clone() {
+            let Some(vehicle) = self.world.get::<Vehicle>(entity) else {
+                continue;
+            };
+            let lane_speed = self
+                .world
+                .get::<Following>(entity)
+                .map_or(Velocity::FAST, |f| f.speed);
+            let platoon_speed = self.platoon_speed(vehicle);
+            let traffic_speed = platoon_speed.min(lane_speed);
+            if vehicle.crashed_at.is_some() || vehicle.breakdown.is_some() || vehicle.frozen {
+                // Wrecks, stranded and frozen vehicles don't move or request anything
+                self.world.entity_mut(entity).insert(Plan::default());
+                continue;
+            }
             let distance_to_intersection = vehicle.distance_to_intersection();
             let is_past_intersection = vehicle.is_past_intersection();
             let is_in_intersection = vehicle.is_in_intersection();
             let mut requested_intersection = vehicle.requested_intersection;
             let mut intersection_permission = vehicle.intersection_permission;
+            let was_denied = requested_intersection && !intersection_permission;
             let vehicle_route = vehicle.route;
             let vehicle_direction = vehicle.direction;
             let vehicle_speed = vehicle.current_speed;
-            let (vx, vy, vw, vh) = vehicle.get_visual_bounds();
-            let traffic_speed = target_speeds[i];
+            let top_speed = vehicle.top_speed();
+            let bounds = vehicle.get_visual_bounds();
+            let approach_direction = vehicle.approach_direction();
+            let in_platoon = vehicle.platoon_ahead.is_some();
 
-            // Reset intersection status if far away
-            if distance_to_intersection > 150.0 {
+            // Reset intersection status if far away (platoon followers booked from further back)
+            if distance_to_intersection > 150.0 && !in_platoon {
                 requested_intersection = false;
                 intersection_permission = false;
             }
 
-            let intersection_speed = if is_past_intersection {
-                Velocity::Fast
-            } else if distance_to_intersection > 60.0 {
-                Velocity::Fast
-            } else if is_in_intersection {
-                Velocity::Fast
+            let intersection_speed = if is_past_intersection
+                || distance_to_intersection > 60.0
+                || is_in_intersection
+            {
+                Velocity::FAST
             } else if !requested_intersection || !intersection_permission {
                 // Check if vehicle should stop at intersection entrance
                 if distance_to_intersection <= 10.0 && !intersection_permission {
                     // Vehicle is at intersection entrance and was previously denied
-                    // Keep trying with fast speed while stopped
-                    let (permission, _recommended_speed) = self.try_two_path_intersection_request(
+                    // Keep trying at top speed while stopped
+                    let (permission, _recommended_speed) = self.request_permission(
                         vehicle_id,
                         vehicle_route,
                         vehicle_direction,
-                        Velocity::Fast, // Always try with fast speed when stopped
+                        top_speed, // Always try at top speed when stopped
                         current_time,
                         distance_to_intersection,
                     );
                     requested_intersection = true;
                     intersection_permission = permission;
 
+                    self.log_permission_result(current_time, vehicle_id, permission, was_denied);
+
                     if permission {
-                        Velocity::Fast
+                        Velocity::FAST
                     } else {
-                        Velocity::Stopped
+                        Velocity::STOPPED
                     }
                 } else {
                     // Normal intersection request with adaptive speed
-                    let (permission, recommended_speed) = self.try_two_path_intersection_request(
+                    let (permission, recommended_speed) = self.request_permission(
                         vehicle_id,
                         vehicle_route,
                         vehicle_direction,
@@ -526,330 +1339,661 @@ impl<'a> SmartIntersection<'a> {
                     );
                     requested_intersection = true;
                     intersection_permission = permission;
+                    self.log_permission_result(current_time, vehicle_id, permission, was_denied);
 
                     if !permission && distance_to_intersection <= 15.0 {
                         // Close to intersection but denied - stop the vehicle
-                        Velocity::Stopped
+                        Velocity::STOPPED
                     } else {
                         recommended_speed
                     }
                 }
             } else {
-                Velocity::Fast
+                Velocity::FAST
+            };
+            // Controllers that plan a speed profile steer the whole crossing
+            let intersection_speed = if intersection_permission && !is_past_intersection {
+                self.controller
+                    .planned_speed(vehicle_id, distance_to_intersection, current_time)
+                    .unwrap_or(intersection_speed)
+            } else {
+                intersection_speed
             };
 
             // Determine final speed
             let final_speed = if is_past_intersection {
-                Velocity::Fast
+                lane_speed // only obstacles slow vehicles down once they are through
             } else {
-                match (traffic_speed, intersection_speed) {
-                    (Velocity::Stopped, _) | (_, Velocity::Stopped) => Velocity::Stopped, // NEW: Stop overrides everything
-                    (Velocity::Slow, _) | (_, Velocity::Slow) => Velocity::Slow,
-                    (Velocity::Medium, _) | (_, Velocity::Medium) => Velocity::Medium,
-                    (Velocity::Fast, Velocity::Fast) => Velocity::Fast,
-                }
+                traffic_speed.min(intersection_speed) // the stricter limit wins
             };
 
-            // Calculate cells to release
-            let cells_to_release = if is_in_intersection || distance_to_intersection < 50.0 {
-                self.calculate_cells_to_release_two_path(
-                    vehicle_direction,
-                    vehicle_route,
-                    vx,
-                    vy,
-                    vw,
-                    vh,
-                )
-            } else {
-                Vec::new()
-            };
+            // Told to stop dead while still at full speed
+            if vehicle_speed >= top_speed && final_speed == Velocity::STOPPED {
+                self.emit(current_time, vehicle_id, EventKind::EmergencyStop);
+            }
 
-            vehicle_updates.push((
-                i,
-                final_speed,
+            // Let the controller free whatever the vehicle has already driven
+            // over and check it is keeping to its crossing
+            let progress =
+                (is_in_intersection || distance_to_intersection < 50.0 || intersection_permission)
+                    .then_some((approach_direction, vehicle_route, bounds, vehicle_speed));
+
+            self.world.entity_mut(entity).insert(Plan {
+                speed: Some(final_speed),
                 requested_intersection,
                 intersection_permission,
-                cells_to_release,
-                vehicle_id,
-            ));
+                progress,
+            });
         }
+    }
 
-        // Apply updates
-        for (
-            i,
-            final_speed,
-            requested_intersection,
-            intersection_permission,
-            cells_to_release,
-            vehicle_id,
-        ) in vehicle_updates
-        {
-            let vehicle = &mut self.active_vehicles[i];
-
-            vehicle.current_speed = final_speed;
-            vehicle.requested_intersection = requested_intersection;
-            vehicle.intersection_permission = intersection_permission;
+    /// Report what the movement pass did: turns, entering and leaving the
+    /// intersection, and the area each vehicle has driven over
+    fn publish_transitions(&mut self, current_time: f32) {
+        for (vehicle_id, entity) in self.order.clone() {
+            let (Some(transitions), Some(plan), Some(vehicle)) = (
+                self.world.get::<Transitions>(entity).copied(),
+                self.world.get::<Plan>(entity).copied(),
+                self.world.get::<Vehicle>(entity),
+            ) else {
+                continue;
+            };
+            let new_direction = vehicle.direction;
 
-            vehicle.update();
+            if transitions.turned {
+                self.emit(
+                    current_time,
+                    vehicle_id,
+                    EventKind::Turned {
+                        direction: new_direction,
+                    },
+                );
+            }
+            if transitions.entered {
+                self.controller.notify_enter(vehicle_id, current_time);
+                self.emit(current_time, vehicle_id, EventKind::EnteredIntersection);
+            }
+            if transitions.left {
+                self.controller.notify_leave(vehicle_id, current_time);
+            }
 
-            if !cells_to_release.is_empty() {
-                self.release_specific_cells(&cells_to_release, vehicle_id);
+            if let Some((direction, route, bounds, speed)) = plan.progress {
+                self.controller.notify_progress(
+                    vehicle_id,
+                    direction,
+                    route,
+                    bounds,
+                    speed,
+                    current_time,
+                );
             }
+        }
+        self.drop_withdrawn_crossings();
+    }
 
-            self.detect_close_calls(i);
+    /// Cancel the crossings the controller took back from vehicles that fell
+    /// behind their booking or were in the way of one that did; they ask
+    /// again from where they are
+    fn drop_withdrawn_crossings(&mut self) {
+        for vehicle_id in self.controller.take_withdrawn() {
+            if let Some(mut vehicle) = self.vehicle_mut(vehicle_id) {
+                vehicle.intersection_permission = false;
+                vehicle.requested_intersection = false;
+                vehicle.unacknowledged_grant = false;
+                vehicle.platoon_ahead = None;
+            }
+            self.withdrawn_crossings += 1;
+            debug!(vehicle_id, "Crossing withdrawn by the controller");
         }
     }
 
-    /// Try intersection request with two-path system and adaptive speed
-    fn try_two_path_intersection_request(
+    /// Run a system against the vehicle world
+    fn run_system<M>(&mut self, system: impl IntoSystem<(), (), M>) {
+        self.world
+            .run_system_once(system)
+            .expect("vehicle systems have no failing parameters");
+    }
+
+    /// Ask the controller for permission, padding the request for road
+    /// conditions and the driver's profile
+    fn request_permission(
         &mut self,
         vehicle_id: usize,
         route: Route,
         direction: Direction,
-        current_speed: Velocity,
+        speed: Velocity,
         current_time: f32,
         distance_to_intersection: f32,
     ) -> (bool, Velocity) {
-        // Get cached path for this direction+route combination
-        let path = match self.path_cache.get(&(direction, route)) {
-            Some(p) => p.clone(),
-            None => {
-                return (false, Velocity::Slow);
-            }
-        };
-
-        // Try different speeds until we get permission
-        let speeds_to_try = match current_speed {
-            Velocity::Fast => vec![Velocity::Fast, Velocity::Medium, Velocity::Slow],
-            Velocity::Medium => vec![Velocity::Medium, Velocity::Slow],
-            Velocity::Slow => vec![Velocity::Slow],
-            Velocity::Stopped => vec![Velocity::Fast],
+        // Slippery roads need extra time before and after each slot, and
+        // the driver's profile decides how tight a window they will accept,
+        // on top of the configured slot padding
+        let (padding, stopped, top_speed, acceleration) =
+            self.vehicle(vehicle_id)
+                .map_or((0.0, false, Velocity::FAST, f32::INFINITY), |v| {
+                    (
+                        v.profile.reservation_padding(),
+                        v.current_speed == Velocity::STOPPED,
+                        v.top_speed(),
+                        v.acceleration(),
+                    )
+                });
+
+        let request = PermissionRequest {
+            vehicle_id,
+            direction,
+            route,
+            speed: speed.min(top_speed),
+            top_speed,
+            acceleration,
+            distance_to_intersection,
+            stopped,
+            // Aggressive drivers shave the margin, but a negative one would
+            // turn a short window inside out and book nothing
+            margin: (self.weather.reservation_margin() + padding + self.slot_padding).max(0.0),
         };
 
-        for attempt_speed in speeds_to_try {
-            if let Some(vehicle) = self.active_vehicles.iter_mut().find(|v| v.id == vehicle_id) {
-                vehicle.current_speed = attempt_speed;
-            }
-            // Calculate timing for segment 1
-            let time_to_intersection =
-                self.calculate_time_with_speed(distance_to_intersection, attempt_speed);
-            let segment1_time =
-                self.calculate_time_with_speed(path.segment1.distance, attempt_speed);
+        if !self.handover.is_empty() {
+            return self.deny(vehicle_id, Denial::new(DenialCause::Handover));
+        }
 
-            let segment1_entry = current_time + time_to_intersection;
-            let segment1_exit = segment1_entry + segment1_time;
+        // Someone has waited too long; traffic in their way holds back until they cross
+        let starving = self.starving.iter().any(|&(id, _, _)| id == vehicle_id);
+        if !starving && self.yields_to_starving(vehicle_id, direction, route) {
+            return self.deny(vehicle_id, Denial::new(DenialCause::YieldToStarving));
+        }
 
-            // Try to reserve segment 1
-            if !self.can_reserve_cells(&path.segment1.cells, segment1_entry, segment1_exit) {
-                continue; // Try slower speed
-            }
+        // Still waiting out the backoff after a lost message
+        let Some(retry) = self.vehicle(vehicle_id).map(|v| v.unanswered > 0) else {
+            return (false, Velocity::STOPPED);
+        };
+        if self
+            .vehicle(vehicle_id)
+            .and_then(|v| v.retry_at)
+            .is_some_and(|t| current_time < t)
+        {
+            return self.deny(vehicle_id, Denial::new(DenialCause::NoReply));
+        }
+        if retry {
+            self.retries += 1;
+        }
 
-            // If there's a second segment (turning vehicles), check that too
-            let mut segment2_exit = segment1_exit;
-            if let Some(ref segment2) = path.segment2 {
-                let segment2_time =
-                    self.calculate_time_with_speed(segment2.distance, attempt_speed);
-                segment2_exit = segment1_exit + segment2_time;
+        if self.message_lost() {
+            self.requests_lost += 1;
+            self.await_retry(vehicle_id, false, current_time);
+            return self.deny(vehicle_id, Denial::new(DenialCause::NoReply));
+        }
 
-                if !self.can_reserve_cells(&segment2.cells, segment1_exit, segment2_exit) {
-                    continue; // Try slower speed
+        let followers = if self.platooning {
+            self.platoon_followers(vehicle_id)
+        } else {
+            Vec::new()
+        };
+        let platoon_speed = if followers.is_empty() {
+            None
+        } else {
+            self.controller
+                .request_platoon(&request, &followers, current_time)
+        };
+        let granted =
+            platoon_speed.or_else(|| self.controller.request_permission(&request, current_time));
+        let denial = self
+            .controller
+            .take_denial()
+            .unwrap_or(Denial::new(DenialCause::Rule));
+
+        if self.message_lost() {
+            // The controller may have booked a crossing the vehicle never hears about
+            self.replies_lost += 1;
+            if granted.is_some() {
+                self.permissions_lost += 1;
+                if let Some(mut vehicle) = self.vehicle_mut(vehicle_id) {
+                    vehicle.unacknowledged_grant = true;
                 }
             }
+            self.await_retry(vehicle_id, true, current_time);
+            return self.deny(vehicle_id, Denial::new(DenialCause::NoReply));
+        }
 
-            // Both segments can be reserved - make the reservations
-            self.reserve_cells_for_vehicle(
-                vehicle_id,
-                &path.segment1.cells,
-                segment1_entry,
-                segment1_exit,
-            );
+        if let Some(mut vehicle) = self.vehicle_mut(vehicle_id) {
+            vehicle.retry_at = None;
+            vehicle.unanswered = 0;
+        }
+        if let Some(speed) = platoon_speed {
+            self.join_platoon(vehicle_id, &followers, speed, current_time);
+        }
 
-            if let Some(ref segment2) = path.segment2 {
-                self.reserve_cells_for_vehicle(
+        match granted {
+            Some(granted_speed) => {
+                let duplicate = self.vehicle_mut(vehicle_id).is_some_and(|mut v| {
+                    v.denial = None;
+                    std::mem::replace(&mut v.unacknowledged_grant, false)
+                });
+                if duplicate {
+                    self.duplicate_reservations += 1;
+                }
+                if starving {
+                    self.priority_grants += 1;
+                    self.starving.retain(|&(id, _, _)| id != vehicle_id);
+                    debug!(vehicle_id, "Priority crossing after waiting past the limit");
+                }
+                self.emit(
+                    current_time,
                     vehicle_id,
-                    &segment2.cells,
-                    segment1_exit,
-                    segment2_exit,
+                    EventKind::PermissionGranted {
+                        speed: granted_speed,
+                    },
                 );
+                (true, granted_speed)
             }
-            return (true, attempt_speed);
+            None => self.deny(vehicle_id, denial),
         }
+    }
 
-        if let Some(vehicle) = self.active_vehicles.iter_mut().find(|v| v.id == vehicle_id) {
-            vehicle.current_speed = Velocity::Stopped;
+    /// Turn a request down, noting why on the vehicle
+    fn deny(&mut self, vehicle_id: usize, denial: Denial) -> (bool, Velocity) {
+        if let Some(mut vehicle) = self.vehicle_mut(vehicle_id) {
+            vehicle.denial = Some(denial);
         }
-        (false, Velocity::Stopped)
+        (false, Velocity::STOPPED)
     }
 
-    /// Check if cells can be reserved (without actually reserving them)
-    fn can_reserve_cells(&self, cells: &[(usize, usize)], start_time: f32, end_time: f32) -> bool {
-        for &(col, row) in cells {
-            if col >= self.cols || row >= self.rows {
-                continue;
-            }
-            let idx = self.cell_index(col, row);
-            if self.conflict(&self.grid[idx], start_time, end_time) {
-                return false;
+    /// Vehicles close enough to ask for a crossing that have waited longer
+    /// than the starvation limit without getting one
+    fn starving_vehicles(&self) -> Vec<(usize, Direction, Route)> {
+        let Some(limit) = self.starvation_limit else {
+            return Vec::new();
+        };
+        self.vehicles()
+            .into_iter()
+            .filter(|v| {
+                v.waiting_time > limit
+                    && !v.intersection_permission
+                    && v.distance_to_intersection() <= 60.0
+                    && !v.is_in_intersection()
+                    && !v.is_past_intersection()
+                    && v.crashed_at.is_none()
+                    && v.breakdown.is_none()
+            })
+            .map(|v| (v.id, v.direction, v.route))
+            .collect()
+    }
+
+    /// Whether a request must wait for a starving vehicle: true when it comes
+    /// from someone else whose path crosses a starving vehicle's path
+    fn yields_to_starving(&self, vehicle_id: usize, direction: Direction, route: Route) -> bool {
+        self.starving.iter().any(|&(id, d, r)| {
+            id != vehicle_id && self.controller.paths_conflict((direction, route), (d, r))
+        })
+    }
+
+    /// Whether the next message on the radio link gets lost
+    fn message_lost(&mut self) -> bool {
+        self.packet_loss > 0.0 && self.rng.random::<f32>() < self.packet_loss
+    }
+
+    /// A vehicle got no answer: back off exponentially, with jitter so vehicles
+    /// that lost messages together don't all ask again on the same frame
+    fn await_retry(&mut self, vehicle_id: usize, reply: bool, current_time: f32) {
+        let jitter = self.rng.random_range(0.5..1.0);
+        if let Some(mut vehicle) = self.vehicle_mut(vehicle_id) {
+            let backoff =
+                (RETRY_BACKOFF * 2f32.powi(vehicle.unanswered as i32)).min(RETRY_BACKOFF_MAX);
+            vehicle.unanswered += 1;
+            vehicle.retry_at = Some(current_time + backoff * jitter);
+        }
+        self.emit(current_time, vehicle_id, EventKind::MessageLost { reply });
+    }
+
+    /// Vehicles queued close behind `vehicle_id` in its lane that haven't got a
+    /// crossing yet, as (id, distance to the intersection), nearest first
+    fn platoon_followers(&self, vehicle_id: usize) -> Vec<(usize, f32)> {
+        let mut followers = Vec::new();
+        let Some(&(_, mut ahead)) = self.order.iter().find(|&&(id, _)| id == vehicle_id) else {
+            return followers;
+        };
+
+        while followers.len() + 1 < MAX_PLATOON_SIZE {
+            let Some(&(id, entity)) = self.order.iter().find(|&&(_, entity)| {
+                self.world
+                    .get::<Following>(entity)
+                    .is_some_and(|f| f.leader == Some(ahead))
+            }) else {
+                break;
+            };
+            let (Some(vehicle), Some(leader)) = (
+                self.world.get::<Vehicle>(entity),
+                self.world.get::<Vehicle>(ahead),
+            ) else {
+                break;
+            };
+            // Following also tracks vehicles cutting across, so check the lane
+            if vehicle.direction != leader.direction
+                || vehicle.route != leader.route
+                || vehicle.has_turned
+                || vehicle.intersection_permission
+                || vehicle.crashed_at.is_some()
+                || vehicle.breakdown.is_some()
+                || vehicle.distance_to_vehicle(leader) > PLATOON_GAP
+            {
+                break;
             }
+            followers.push((id, vehicle.distance_to_intersection()));
+            ahead = entity;
         }
-        true
+        followers
     }
 
-    /// Reserve cells for a vehicle
-    fn reserve_cells_for_vehicle(
+    /// Give every follower the crossing its platoon leader just booked for it
+    fn join_platoon(
         &mut self,
-        vehicle_id: usize,
-        cells: &[(usize, usize)],
-        start_time: f32,
-        end_time: f32,
+        leader_id: usize,
+        followers: &[(usize, f32)],
+        speed: Velocity,
+        current_time: f32,
     ) {
-        for &(col, row) in cells {
-            if col >= self.cols || row >= self.rows {
+        self.platoons += 1;
+        self.platooned_vehicles += followers.len() as u32;
+        let mut ahead = leader_id;
+        for &(vehicle_id, _) in followers {
+            let Some(&(_, entity)) = self.order.iter().find(|&&(id, _)| id == vehicle_id) else {
                 continue;
+            };
+            // Set both so the grant holds whether or not this frame's plan is already made
+            if let Some(mut vehicle) = self.world.get_mut::<Vehicle>(entity) {
+                vehicle.requested_intersection = true;
+                vehicle.intersection_permission = true;
+                vehicle.platoon_ahead = Some(ahead);
+                vehicle.denial = None;
+            }
+            if let Some(mut plan) = self.world.get_mut::<Plan>(entity) {
+                plan.requested_intersection = true;
+                plan.intersection_permission = true;
             }
-            let idx = self.cell_index(col, row);
-            self.grid[idx].slots.push(TimeSlot {
-                start: start_time,
-                end: end_time,
+            self.emit(
+                current_time,
                 vehicle_id,
-            });
+                EventKind::JoinedPlatoon { leader_id, speed },
+            );
+            ahead = vehicle_id;
         }
     }
 
-    /// Calculate time with specific speed
-    fn calculate_time_with_speed(&self, distance: f32, speed: Velocity) -> f32 {
-        let speed_pixels_per_frame = match speed {
-            Velocity::Slow => 3.0,
-            Velocity::Medium => 5.0,
-            Velocity::Fast => 7.0,
-            Velocity::Stopped => return 0.0,
+    /// Speed a platoon follower may drive behind the member ahead. Lane-based
+    /// following loses sight of it once it turns, and the shared booking no
+    /// longer keeps the two apart, so this goes by straight-line distance.
+    fn platoon_speed(&self, vehicle: &Vehicle) -> Velocity {
+        let Some(ahead) = vehicle
+            .platoon_ahead
+            .and_then(|id| self.vehicle(id))
+            .filter(|ahead| !ahead.is_past_intersection())
+        else {
+            return Velocity::FAST;
         };
+        let (x, y) = vehicle.get_visual_center();
+        let (ax, ay) = ahead.get_visual_center();
+        let distance = (x - ax).hypot(y - ay);
+        let required = vehicle.get_safe_following_distance(self.weather);
+        if distance < required * 0.7 {
+            Velocity::STOPPED
+        } else if distance < required * 0.8 {
+            Velocity::MEDIUM
+        } else {
+            Velocity::FAST
+        }
+    }
+
+    // === UTILITY METHODS ===
 
-        distance / speed_pixels_per_frame / 60.0 // Convert to seconds
+    pub fn spawn_vehicle(
+        &mut self,
+        direction: Option<Direction>,
+        route: Option<Route>,
+        current_time: f32,
+    ) {
+        self.spawn_vehicle_of_class(direction, route, None, current_time);
     }
 
-    /// Calculate cells to release for two-path system
-    fn calculate_cells_to_release_two_path(
-        &self,
-        direction: Direction,
-        route: Route,
-        vx: f32,
-        vy: f32,
-        vw: f32,
-        vh: f32,
-    ) -> Vec<(usize, usize)> {
-        // Use the cached path to determine which cells to release
-        let path = match self.path_cache.get(&(direction, route)) {
-            Some(p) => p,
-            None => return Vec::new(),
+    /// Like `spawn_vehicle`, but the vehicle runs on `class` instead of one
+    /// drawn from the fleet mix when it is given
+    pub fn spawn_vehicle_of_class(
+        &mut self,
+        direction: Option<Direction>,
+        route: Option<Route>,
+        class: Option<VehicleClass>,
+        current_time: f32,
+    ) {
+        let dir = match direction {
+            Some(d) => d,
+            None => self.layout.random_direction(&mut self.rng),
         };
 
-        let mut cells_to_release = Vec::new();
-
-        // Release cells behind the vehicle based on its current position
-        match direction {
-            Direction::North => {
-                let behind_y = vy + vh;
-                if behind_y >= IY_MIN && behind_y <= IY_MAX {
-                    let row = ((behind_y - IY_MIN) / self.zone_px as f32) as usize;
+        let route = match route.or_else(|| self.layout.random_route(dir, &mut self.rng)) {
+            Some(r) if self.layout.allows(dir, r) => r,
+            _ => {
+                debug!(
+                    ?dir,
+                    ?route,
+                    "No road for this spawn on a {}",
+                    self.layout.name()
+                );
+                return;
+            }
+        };
 
-                    // Release cells from segment 1 that are behind the vehicle
-                    for &(col, cell_row) in &path.segment1.cells {
-                        if cell_row == row {
-                            cells_to_release.push((col, cell_row));
-                        }
-                    }
+        let route = if self.lane_closed(dir, route) {
+            match self.open_route(dir) {
+                Some(open) => {
+                    self.redirected_spawns += 1;
+                    open
+                }
+                None => {
+                    debug!(?dir, "Every lane of this approach is closed; spawn dropped");
+                    return;
                 }
             }
-            Direction::South => {
-                let behind_y = vy;
-                if behind_y >= IY_MIN && behind_y <= IY_MAX {
-                    let row = ((behind_y - IY_MIN) / self.zone_px as f32) as usize;
+        } else {
+            route
+        };
 
-                    for &(col, cell_row) in &path.segment1.cells {
-                        if cell_row == row {
-                            cells_to_release.push((col, cell_row));
-                        }
-                    }
-                }
+        // Wait behind earlier requests for the same lane, or until the spawn area clears
+        let lane_is_waiting = self
+            .pending_spawns
+            .get(&(dir, route))
+            .is_some_and(|queue| !queue.is_empty());
+        let at_capacity = self.at_capacity();
+        if at_capacity {
+            self.spawns_held += 1;
+        }
+        if lane_is_waiting || at_capacity || !self.is_safe_to_spawn(dir, route) {
+            self.pending_spawns
+                .entry((dir, route))
+                .or_default()
+                .push_back(PendingSpawn {
+                    requested_at: current_time,
+                    class,
+                });
+            return;
+        }
+
+        self.create_vehicle(dir, route, class, current_time);
+    }
+
+    /// One random-route vehicle from every open approach at once, for a quick
+    /// symmetric conflict; any whose spawn area is taken waits in its lane's queue
+    pub fn spawn_from_all_directions(&mut self, current_time: f32) {
+        for direction in [
+            Direction::North,
+            Direction::South,
+            Direction::East,
+            Direction::West,
+        ] {
+            if self.layout.has_approach(direction) {
+                self.spawn_vehicle(Some(direction), None, current_time);
             }
-            Direction::East => {
-                let behind_x = vx;
-                if behind_x >= IX_MIN && behind_x <= IX_MAX {
-                    let col = ((behind_x - IX_MIN) / self.zone_px as f32) as usize;
+        }
+    }
 
-                    for &(cell_col, row) in &path.segment1.cells {
-                        if cell_col == col {
-                            cells_to_release.push((cell_col, row));
-                        }
-                    }
-                }
+    /// Open and close lanes whose roadworks start or end now. Requests already
+    /// queued for a lane that closes move to the lane its spawns now go to.
+    fn update_lane_closures(&mut self, current_time: f32) {
+        let active: Vec<LaneClosure> = self
+            .lane_closures
+            .iter()
+            .copied()
+            .filter(|closure| closure.is_active(current_time))
+            .collect();
+        if active == self.closed_lanes {
+            return;
+        }
+
+        let previous = std::mem::replace(&mut self.closed_lanes, active);
+        for closure in &previous {
+            if !self.lane_closed(closure.direction, closure.route) {
+                self.controller
+                    .notify_lane_closed(closure.direction, closure.route, false);
+                info!(direction = ?closure.direction, route = ?closure.route, "Lane reopened");
+            }
+        }
+        for closure in self.closed_lanes.clone() {
+            let lane = (closure.direction, closure.route);
+            if previous.iter().any(|c| (c.direction, c.route) == lane) {
+                continue;
             }
-            Direction::West => {
-                let behind_x = vx + vw;
-                if behind_x >= IX_MIN && behind_x <= IX_MAX {
-                    let col = ((behind_x - IX_MIN) / self.zone_px as f32) as usize;
+            self.controller.notify_lane_closed(lane.0, lane.1, true);
+            info!(direction = ?lane.0, route = ?lane.1, "Lane closed for roadworks");
+
+            let queued = self.pending_spawns.remove(&lane).unwrap_or_default();
+            if let Some(open) = self.open_route(lane.0) {
+                self.redirected_spawns += queued.len() as u32;
+                let queue = self.pending_spawns.entry((lane.0, open)).or_default();
+                queue.extend(queued);
+                queue
+                    .make_contiguous()
+                    .sort_by(|a, b| a.requested_at.total_cmp(&b.requested_at));
+            }
+        }
+    }
 
-                    for &(cell_col, row) in &path.segment1.cells {
-                        if cell_col == col {
-                            cells_to_release.push((cell_col, row));
-                        }
-                    }
-                }
+    /// Whether roadworks close the lane of `route` on the `direction` approach
+    pub fn lane_closed(&self, direction: Direction, route: Route) -> bool {
+        self.closed_lanes
+            .iter()
+            .any(|c| c.direction == direction && c.route == route)
+    }
+
+    /// First lane of the approach, straight then right then left, that has a
+    /// road and is open
+    fn open_route(&self, direction: Direction) -> Option<Route> {
+        [Route::Straight, Route::Right, Route::Left]
+            .into_iter()
+            .find(|&route| {
+                self.layout.allows(direction, route) && !self.lane_closed(direction, route)
+            })
+    }
+
+    /// World rectangles of the roadworks in force
+    pub fn roadworks(&self) -> Vec<(f32, f32, f32, f32)> {
+        self.closed_lanes.iter().map(LaneClosure::area).collect()
+    }
+
+    /// Spawn the oldest pending vehicle of every lane whose spawn area is clear,
+    /// longest-waiting first while the vehicle cap leaves room
+    fn spawn_pending(&mut self, current_time: f32) {
+        let mut ready = Vec::new();
+        for (&(dir, route), queue) in &self.pending_spawns {
+            if let Some(&pending) = queue.front()
+                && self.is_safe_to_spawn(dir, route)
+            {
+                ready.push((dir, route, pending));
             }
         }
+        // Stable, so requests made at the same time keep the lane order
+        ready.sort_by(|a, b| a.2.requested_at.total_cmp(&b.2.requested_at));
 
-        cells_to_release
+        for (dir, route, pending) in ready {
+            if self.at_capacity() {
+                break;
+            }
+            if let Some(queue) = self.pending_spawns.get_mut(&(dir, route)) {
+                queue.pop_front();
+            }
+            self.total_spawn_delay += current_time - pending.requested_at;
+            self.create_vehicle(dir, route, pending.class, current_time);
+        }
     }
 
-    // === UTILITY METHODS ===
+    /// Whether the vehicle cap leaves no room for another vehicle
+    fn at_capacity(&self) -> bool {
+        self.max_active
+            .is_some_and(|cap| self.vehicle_count() >= cap)
+    }
 
-    pub fn spawn_vehicle(
+    /// Vehicles requested but still waiting for their spawn area to clear
+    pub fn pending_spawn_count(&self) -> usize {
+        self.pending_spawns.values().map(|queue| queue.len()).sum()
+    }
+
+    /// Average seconds between a spawn request and the vehicle appearing
+    pub fn average_spawn_delay(&self) -> f32 {
+        let spawned: u32 = self.profiles_spawned.values().sum();
+        if spawned == 0 {
+            0.0
+        } else {
+            self.total_spawn_delay / spawned as f32
+        }
+    }
+
+    fn create_vehicle(
         &mut self,
-        texture_creator: &'a TextureCreator<WindowContext>,
-        direction: Option<Direction>,
+        dir: Direction,
+        route: Route,
+        class: Option<VehicleClass>,
         current_time: f32,
     ) {
-        let dir = match direction {
-            Some(d) => d,
-            None => get_random_direction(),
-        };
-
-        let route = get_random_route();
         let spawn_pos = get_spawn_position(dir, route);
         let turn_pos = get_turn_position(dir, route);
-
-        if self.is_safe_to_spawn(dir, route, spawn_pos) {
-            match Vehicle::new(texture_creator, route, dir, spawn_pos, turn_pos) {
-                Ok(vehicle) => {
-                    let vehicle_id = vehicle.id;
-                    self.active_vehicles.push(vehicle);
-                    if !self.vehicle_intersection_times.contains_key(&vehicle_id) {
-                        self.vehicle_intersection_times
-                            .insert(vehicle_id, current_time);
-                    }
-                }
-                Err(e) => println!("Failed to create vehicle: {}", e),
-            }
+        let profile = self.profile_mix.sample(&mut self.rng);
+        let mut vehicle = Vehicle::new(route, dir, spawn_pos, turn_pos, profile);
+        vehicle.class = class.unwrap_or_else(|| self.fleet_mix.sample(&mut self.rng));
+        if self.speed_variance > 0.0 {
+            vehicle.cruise_factor = 1.0 - self.rng.random_range(0.0..self.speed_variance);
         }
+        vehicle.current_speed = vehicle.top_speed();
+        vehicle.spawned_at = current_time;
+        *self.profiles_spawned.entry(profile).or_insert(0) += 1;
+        let vehicle_id = vehicle.id;
+        let entity = self
+            .world
+            .spawn((
+                vehicle,
+                Following::default(),
+                Plan::default(),
+                Transitions::default(),
+            ))
+            .id();
+        self.order.push((vehicle_id, entity));
+        self.vehicle_intersection_times
+            .entry(vehicle_id)
+            .or_insert(current_time);
+        self.emit(
+            current_time,
+            vehicle_id,
+            EventKind::Spawn {
+                direction: dir,
+                route,
+            },
+        );
     }
 
-    fn is_safe_to_spawn(&self, direction: Direction, route: Route, spawn_pos: (f32, f32)) -> bool {
-        // Simplified spawn safety check
-        for vehicle in &self.active_vehicles {
-            if vehicle.direction == direction && vehicle.route == route {
-                let distance = match direction {
-                    Direction::North | Direction::South => (vehicle.position.1 - spawn_pos.1).abs(),
-                    Direction::East | Direction::West => (vehicle.position.0 - spawn_pos.0).abs(),
-                };
-
-                if distance < 100.0 {
-                    // Minimum spawn distance
-                    return false;
-                }
-            }
-        }
-        true
+    /// Whether the spawn area of the lane, clearance included, is free of
+    /// every vehicle on the road, whatever its lane or heading
+    fn is_safe_to_spawn(&self, direction: Direction, route: Route) -> bool {
+        let (x, y, w, h) = spawn_area(direction, route);
+        self.vehicles().iter().all(|vehicle| {
+            let (vx, vy, vw, vh) = vehicle.get_visual_bounds();
+            !(vx < x + w && x < vx + vw && vy < y + h && y < vy + vh)
+        })
     }
 
     fn update_stats_for_exiting_vehicle_by_data(
@@ -859,6 +2003,7 @@ impl<'a> SmartIntersection<'a> {
         current_time: f32,
     ) {
         self.total_velocities += vehicle_velocity;
+        self.exit_velocities.push(vehicle_velocity);
         self.total_vehicles_passed += 1;
 
         let entry_time = self.vehicle_intersection_times[&vehicle_id];
@@ -881,82 +2026,693 @@ impl<'a> SmartIntersection<'a> {
         self.vehicle_intersection_times.remove(&vehicle_id);
     }
 
-    fn detect_close_calls(&mut self, vehicle_index: usize) {
-        let current_vehicle = &self.active_vehicles[vehicle_index];
-        if !current_vehicle.is_in_intersection() {
-            return;
+    /// Count pairs of vehicles passing dangerously close inside the intersection.
+    /// Pairs are found in parallel and each pair is only counted once per run.
+    fn detect_close_calls(&mut self, current_time: f32) {
+        let min_safe_distance = 5.0;
+        let vehicles = self.vehicles();
+
+        let candidates: Vec<(usize, usize)> = (0..vehicles.len())
+            .into_par_iter()
+            .filter(|&i| vehicles[i].is_in_intersection())
+            .flat_map_iter(|i| {
+                let current_vehicle = &vehicles[i];
+                vehicles
+                    .iter()
+                    .enumerate()
+                    .filter(move |&(j, other_vehicle)| {
+                        i != j
+                            && other_vehicle.is_in_intersection()
+                            && current_vehicle.distance_to_vehicle(other_vehicle)
+                                < min_safe_distance
+                    })
+                    .map(move |(_, other_vehicle)| (current_vehicle.id, other_vehicle.id))
+            })
+            .collect();
+
+        for (vehicle_id, other_vehicle_id) in candidates {
+            // Normalize the pair (smaller ID first) so (2,3) and (3,2) count once
+            let pair = (
+                vehicle_id.min(other_vehicle_id),
+                vehicle_id.max(other_vehicle_id),
+            );
+            if self.close_call_pairs_this_frame.insert(pair) {
+                self.close_calls += 1;
+                let mut centres = Vec::new();
+                let mut approaches = Vec::new();
+                for id in [vehicle_id, other_vehicle_id] {
+                    if let Some(mut vehicle) = self.vehicle_mut(id) {
+                        vehicle.close_call_at = Some(current_time);
+                        centres.push(vehicle.get_visual_center());
+                        approaches.push(vehicle.approach_direction());
+                    }
+                }
+                // Once per side, so two vehicles from one approach count once
+                approaches.dedup();
+                for approach in approaches {
+                    *self.close_calls_by_approach.entry(approach).or_default() += 1;
+                }
+                if let [(x1, y1), (x2, y2)] = centres[..] {
+                    self.close_call_spots
+                        .push(((x1 + x2) / 2.0, (y1 + y2) / 2.0));
+                }
+                self.emit(
+                    current_time,
+                    vehicle_id,
+                    EventKind::CloseCall { other_vehicle_id },
+                );
+            }
         }
+    }
 
-        for (j, other_vehicle) in self.active_vehicles.iter().enumerate() {
-            if vehicle_index == j {
-                continue;
+    /// Crash every pair of vehicles whose bodies overlap
+    fn detect_collisions(&mut self, current_time: f32) {
+        let vehicles = self.vehicles();
+
+        let mut hits = Vec::new();
+        for (i, current_vehicle) in vehicles.iter().enumerate() {
+            for other_vehicle in &vehicles[i + 1..] {
+                if !(current_vehicle.crashed_at.is_some() && other_vehicle.crashed_at.is_some())
+                    && current_vehicle.overlaps(other_vehicle)
+                {
+                    hits.push((current_vehicle.id, other_vehicle.id));
+                }
             }
+        }
 
-            // Create a normalized pair (smaller ID first) to avoid counting (2,3) and (3,2) as different
-            let pair = if current_vehicle.id < other_vehicle.id {
-                (current_vehicle.id, other_vehicle.id)
-            } else {
-                (other_vehicle.id, current_vehicle.id)
-            };
-
-            // Skip if we already processed this pair this frame
-            if self.close_call_pairs_this_frame.contains(&pair) {
-                continue;
+        for (vehicle_id, other_vehicle_id) in hits {
+            for id in [vehicle_id, other_vehicle_id] {
+                if let Some(mut vehicle) = self.vehicle_mut(id)
+                    && vehicle.crashed_at.is_none()
+                {
+                    vehicle.crashed_at = Some(current_time);
+                    vehicle.current_speed = Velocity::STOPPED;
+                    self.vehicles_crashed += 1;
+                }
             }
 
-            let distance = current_vehicle.distance_to_vehicle(other_vehicle);
-            let min_safe_distance = 5.0;
+            self.collisions += 1;
+            self.emit(
+                current_time,
+                vehicle_id,
+                EventKind::Collision { other_vehicle_id },
+            );
+        }
+    }
+
+    /// Average time exited vehicles spent waiting before the intersection
+    pub fn average_wait_time(&self) -> f32 {
+        if self.total_vehicles_passed == 0 {
+            0.0
+        } else {
+            self.total_wait_time / self.total_vehicles_passed as f32
+        }
+    }
+
+    /// Longest stop so far, counting vehicles still waiting as well as those that left
+    pub fn longest_wait(&self) -> f32 {
+        self.vehicles()
+            .iter()
+            .map(|v| v.waiting_time)
+            .chain(self.wait_times.iter().copied())
+            .fold(0.0, f32::max)
+    }
+
+    /// Vehicles holding bookings nobody will use: gone from the road, or never
+    /// told about their grant because the reply was lost. Cells blocked under
+    /// obstacles and jaywalkers are held on purpose.
+    pub fn orphaned_bookings(&self, timeline: &Timeline) -> Vec<usize> {
+        let mut orphaned: Vec<usize> = timeline
+            .slots
+            .iter()
+            .map(|slot| slot.vehicle_id)
+            .filter(|&id| self.obstacles.iter().all(|o| o.id != id))
+            .filter(|&id| self.hazards.iter().all(|h| h.id != id))
+            .filter(|&id| self.vehicle(id).is_none_or(|v| v.unacknowledged_grant))
+            .collect();
+        orphaned.sort_unstable();
+        orphaned.dedup();
+        orphaned
+    }
+
+    /// Exited vehicles that crossed without ever standing still on the way in
+    pub fn vehicles_never_stopped(&self) -> usize {
+        self.wait_times.iter().filter(|&&wait| wait == 0.0).count()
+    }
+
+    /// Complete stops per exited vehicle, over the whole journey
+    pub fn average_stops(&self) -> f32 {
+        if self.stop_counts.is_empty() {
+            0.0
+        } else {
+            self.stop_counts.iter().sum::<u32>() as f32 / self.stop_counts.len() as f32
+        }
+    }
+
+    /// Share of the exited vehicles that never came to a complete stop
+    pub fn zero_stop_share(&self) -> f32 {
+        if self.stop_counts.is_empty() {
+            0.0
+        } else {
+            let smooth = self.stop_counts.iter().filter(|&&stops| stops == 0).count();
+            smooth as f32 / self.stop_counts.len() as f32
+        }
+    }
+
+    /// Human-readable state of one vehicle for the inspect panel
+    pub fn describe_vehicle(&self, vehicle_id: usize) -> Option<Vec<String>> {
+        let vehicle = self.vehicle(vehicle_id)?;
+
+        let permission = if vehicle.intersection_permission {
+            tr!("inspect.granted")
+        } else if vehicle.requested_intersection {
+            tr!("inspect.denied")
+        } else {
+            tr!("inspect.not_requested")
+        };
+        let state = if vehicle.crashed_at.is_some() {
+            tr!("inspect.crashed")
+        } else if vehicle.breakdown.is_some() {
+            tr!("inspect.broken_down")
+        } else if vehicle.frozen {
+            tr!("inspect.frozen")
+        } else if vehicle.is_past_intersection() {
+            tr!("inspect.past_intersection")
+        } else if vehicle.is_in_intersection() {
+            tr!("inspect.in_intersection")
+        } else {
+            tr!("inspect.approaching")
+        };
+        let reservation = match self.controller.reservation_window(vehicle_id) {
+            Some((start, end)) => tr!(
+                "inspect.reservation_window",
+                start = format!("{:.2}", start),
+                end = format!("{:.2}", end),
+            ),
+            None => tr!("inspect.none"),
+        };
+
+        Some(vec![
+            tr!("inspect.vehicle", id = vehicle.id),
+            tr!(
+                "inspect.direction",
+                direction = format!("{:?}", vehicle.direction)
+            ),
+            tr!("inspect.route", route = format!("{:?}", vehicle.route)),
+            tr!(
+                "inspect.speed",
+                speed = format!(
+                    "{:.1} px/frame ({})",
+                    vehicle.current_speed.pixels_per_frame(),
+                    vehicle.current_speed.name()
+                )
+            ),
+            tr!(
+                "inspect.distance",
+                distance = format!("{:.0}", vehicle.distance_to_intersection())
+            ),
+            tr!("inspect.permission", permission = permission),
+            tr!("inspect.reservation", window = reservation),
+            tr!(
+                "inspect.denial",
+                reason = match &vehicle.denial {
+                    Some(denial) if !vehicle.intersection_permission => describe_denial(denial),
+                    _ => tr!("inspect.none"),
+                }
+            ),
+            tr!("inspect.state", state = state),
+            tr!("inspect.driver", profile = format!("{:?}", vehicle.profile)),
+            tr!(
+                "inspect.fuel",
+                ml = format!("{:.1}", vehicle.fuel_used),
+                class = vehicle.class.name()
+            ),
+        ])
+    }
 
-            if distance < min_safe_distance
-                && (current_vehicle.is_in_intersection() && other_vehicle.is_in_intersection())
+    /// For each approach with vehicles waiting to enter, one line per vehicle
+    /// that has asked for a crossing: those with a booked slot first, in the
+    /// order they are booked to enter, then the rest nearest the line first
+    pub fn entry_queue_lines(&self) -> Vec<(Direction, Vec<String>)> {
+        let mut waiting = Vec::new();
+        for vehicle in self.vehicles() {
+            if !vehicle.requested_intersection
+                || vehicle.entered_intersection
+                || vehicle.crashed_at.is_some()
             {
-                self.close_calls += 1;
-                self.close_call_pairs_this_frame.insert(pair);
+                continue;
+            }
+            let window = self.controller.reservation_window(vehicle.id);
+            let line = match window {
+                Some((start, end)) => tr!(
+                    "queue.booked",
+                    id = vehicle.id,
+                    start = format!("{:.2}", start),
+                    end = format!("{:.2}", end),
+                ),
+                None if vehicle.intersection_permission => {
+                    tr!("queue.granted", id = vehicle.id)
+                }
+                None => tr!(
+                    "queue.denied",
+                    id = vehicle.id,
+                    reason = vehicle.denial.as_ref().map_or_else(
+                        || tr!(DenialCause::Rule.locale_key()),
+                        |denial| match denial.conflicts.first() {
+                            Some(conflict) => tr!("denial.held_by", id = conflict.vehicle_id),
+                            None => tr!(denial.cause.locale_key()),
+                        }
+                    ),
+                ),
+            };
+            // Booked vehicles by when they enter, then the rest by distance
+            let order = match window {
+                Some((start, _)) => (false, start),
+                None => (true, vehicle.distance_to_intersection()),
+            };
+            waiting.push((vehicle.approach_direction(), order, line));
+        }
+        waiting.sort_by(|a, b| a.1.0.cmp(&b.1.0).then(a.1.1.total_cmp(&b.1.1)));
+
+        let mut queues: Vec<(Direction, Vec<String>)> = Vec::new();
+        for (direction, _, line) in waiting {
+            match queues.iter_mut().find(|(d, _)| *d == direction) {
+                Some((_, lines)) => lines.push(line),
+                None => queues.push((
+                    direction,
+                    vec![
+                        tr!("queue.title", direction = format!("{:?}", direction)),
+                        line,
+                    ],
+                )),
             }
         }
+        queues
     }
 
-    pub fn get_final_stats(&self) -> String {
-        format!(
-            "SMART ROAD STATS\n\nTotal vehicles passed: {}\nAverage Velocity: {:.1} px/s\nMax velocity recorded: {:.1} px/s\nMin velocity recorded: {:.1} px/s\nMax time in intersection: {:.2} s\nMin time in intersection: {:.2} s\nClose calls detected: {}\nActive vehicles remaining: {}\n\n\nPress esc button to quit",
-            self.total_vehicles_passed,
-            self.total_velocities / self.total_vehicles_passed as f32,
-            self.max_velocity_recorded,
-            if self.min_velocity_recorded == f32::MAX {
-                0.0
-            } else {
-                self.min_velocity_recorded
-            },
-            self.max_time_in_intersection,
-            if self.min_time_in_intersection == f32::MAX {
-                0.0
-            } else {
-                self.min_time_in_intersection
-            },
-            self.close_calls,
-            self.active_vehicles.len()
-        )
+    /// Vehicles standing still in a queue before the intersection
+    pub fn queued_vehicles(&self) -> usize {
+        self.vehicles()
+            .into_iter()
+            .filter(|v| {
+                v.current_speed == Velocity::STOPPED
+                    && !v.entered_intersection
+                    && v.crashed_at.is_none()
+            })
+            .count()
     }
 
-    fn release_specific_cells(&mut self, cells: &[(usize, usize)], vehicle_id: usize) {
-        for &(col, row) in cells {
-            if col >= self.cols || row >= self.rows {
-                continue;
+    /// Final statistics text; `extra_lines` are appended after the built-in stats.
+    /// Given the `previous` run, the headline stats end in a tab and their
+    /// change since that run.
+    pub fn get_final_stats(&self, extra_lines: &[String], previous: Option<&RunSummary>) -> String {
+        let travel = Summary::of(&self.travel_times);
+        let wait = Summary::of(&self.wait_times);
+        let or_zero = |value: f32| if value == f32::MAX { 0.0 } else { value };
+        let one = |value: f32| format!("{:.1}", value);
+        let two = |value: f32| format!("{:.2}", value);
+        let average_velocity = self.total_velocities / self.total_vehicles_passed as f32;
+        let versus =
+            |line: String, key: &str, now: f32, then: fn(&RunSummary) -> f32, decimals: usize| {
+                match previous {
+                    Some(previous) => {
+                        let change = format!("{:+.*}", decimals, now - then(previous));
+                        format!("{}\t{}", line, tr!(key, change = change))
+                    }
+                    None => line,
+                }
+            };
+
+        let mut lines = vec![
+            tr!("stats.title"),
+            String::new(),
+            versus(
+                tr!("stats.vehicles_passed", count = self.total_vehicles_passed),
+                "delta.vehicles",
+                self.total_vehicles_passed as f32,
+                |run| run.vehicles_passed as f32,
+                0,
+            ),
+            versus(
+                tr!("stats.average_velocity", speed = one(average_velocity)),
+                "delta.speed",
+                if self.total_vehicles_passed > 0 {
+                    average_velocity
+                } else {
+                    0.0
+                },
+                |run| run.average_velocity,
+                1,
+            ),
+            tr!(
+                "stats.max_velocity",
+                speed = one(self.max_velocity_recorded)
+            ),
+            tr!(
+                "stats.min_velocity",
+                speed = one(or_zero(self.min_velocity_recorded))
+            ),
+            tr!(
+                "stats.max_time_in_intersection",
+                seconds = two(self.max_time_in_intersection)
+            ),
+            tr!(
+                "stats.min_time_in_intersection",
+                seconds = two(or_zero(self.min_time_in_intersection))
+            ),
+            tr!("stats.controller", name = self.controller.name()),
+            tr!("stats.layout", name = self.layout.name()),
+            tr!("stats.weather", name = self.weather.name()),
+            tr!(
+                "stats.drivers",
+                cautious = self.profile_count(DriverProfile::Cautious),
+                normal = self.profile_count(DriverProfile::Normal),
+                aggressive = self.profile_count(DriverProfile::Aggressive),
+            ),
+            versus(
+                tr!("stats.close_calls", count = self.close_calls),
+                "delta.close_calls",
+                self.close_calls as f32,
+                |run| run.close_calls as f32,
+                0,
+            ),
+            versus(
+                tr!("stats.collisions", count = self.collisions),
+                "delta.collisions",
+                self.collisions as f32,
+                |run| run.collisions as f32,
+                0,
+            ),
+            tr!("stats.vehicles_crashed", count = self.vehicles_crashed),
+            tr!("stats.breakdowns", count = self.breakdowns),
+            tr!(
+                "stats.breakdown_downtime",
+                seconds = one(self.breakdown_downtime)
+            ),
+            tr!("stats.breakdown_delay", seconds = one(self.breakdown_delay)),
+            tr!("stats.reroutes", count = self.reroutes),
+            tr!(
+                "stats.hazards",
+                count = self.hazards_seen,
+                stops = self.emergency_stops,
+                revoked = self.revoked_bookings
+            ),
+            tr!(
+                "stats.platoons",
+                count = self.platoons,
+                size = one(self.average_platoon_size()),
+                followers = self.platooned_vehicles,
+            ),
+            tr!(
+                "stats.messages_lost",
+                requests = self.requests_lost,
+                replies = self.replies_lost,
+            ),
+            tr!("stats.permissions_lost", count = self.permissions_lost),
+            tr!("stats.retries", count = self.retries),
+            tr!(
+                "stats.duplicate_reservations",
+                count = self.duplicate_reservations
+            ),
+            tr!(
+                "stats.withdrawn_crossings",
+                count = self.withdrawn_crossings
+            ),
+            tr!(
+                "stats.denials",
+                conflict = self.denial_count(DenialCause::Conflict),
+                lane_closed = self.denial_count(DenialCause::LaneClosed),
+                rule = self.denial_count(DenialCause::Rule),
+                starving = self.denial_count(DenialCause::YieldToStarving),
+                handover = self.denial_count(DenialCause::Handover),
+                no_reply = self.denial_count(DenialCause::NoReply),
+            ),
+            versus(
+                tr!(
+                    "stats.average_wait",
+                    seconds = two(self.average_wait_time())
+                ),
+                "delta.seconds",
+                self.average_wait_time(),
+                |run| run.average_wait_time,
+                2,
+            ),
+            tr!(
+                "stats.wait_distribution",
+                p95 = two(wait.p95),
+                max = two(wait.max)
+            ),
+            tr!(
+                "stats.never_stopped",
+                count = self.vehicles_never_stopped(),
+                total = self.wait_times.len(),
+            ),
+            versus(
+                tr!(
+                    "stats.stops",
+                    average = two(self.average_stops()),
+                    share = format!("{:.0}", self.zero_stop_share() * 100.0),
+                ),
+                "delta.stops",
+                self.average_stops(),
+                |run| run.average_stops,
+                2,
+            ),
+            versus(
+                tr!(
+                    "stats.travel_time",
+                    mean = one(travel.mean),
+                    median = one(travel.median),
+                    max = one(travel.max),
+                ),
+                "delta.seconds",
+                travel.mean,
+                |run| run.travel_time.mean,
+                1,
+            ),
+            tr!(
+                "stats.spawn_delay",
+                seconds = two(self.average_spawn_delay())
+            ),
+            tr!("stats.pending_spawns", count = self.pending_spawn_count()),
+            tr!("stats.spawns_held", count = self.spawns_held),
+            tr!("stats.redirected_spawns", count = self.redirected_spawns),
+            tr!("stats.active_remaining", count = self.vehicle_count()),
+            versus(
+                tr!(
+                    "stats.fuel",
+                    litres = two(self.total_fuel / 1000.0),
+                    per_vehicle = one(self.average_fuel()),
+                    idle = one(self.idle_share() * 100.0),
+                ),
+                "delta.fuel",
+                self.average_fuel(),
+                |run| run.average_fuel_ml,
+                1,
+            ),
+            versus(
+                tr!(
+                    "stats.co2",
+                    kg = two(self.total_co2() / 1000.0),
+                    per_vehicle = format!("{:.0}", self.average_co2()),
+                ),
+                "delta.kg",
+                self.total_co2() / 1000.0,
+                |run| run.co2_kg,
+                2,
+            ),
+            tr!("stats.nox", grams = one(self.total_nox())),
+            tr!(
+                "stats.fleet",
+                petrol = self.class_emissions(VehicleClass::Petrol).vehicles,
+                diesel = self.class_emissions(VehicleClass::Diesel).vehicles,
+                electric = self.class_emissions(VehicleClass::Electric).vehicles,
+            ),
+        ];
+        lines.extend(APPROACHES.map(|direction| {
+            let queue = self.queues.approach(direction);
+            tr!(
+                "stats.queue",
+                direction = format!("{:?}", direction),
+                max = queue.max,
+                average = two(queue.average),
+            )
+        }));
+        if let Some(previous) = previous {
+            lines.insert(
+                2,
+                tr!(
+                    "stats.versus_previous",
+                    controller = previous.controller,
+                    seconds = format!("{:.0}", previous.duration),
+                ),
+            );
+        }
+        if let Some(limit) = self.starvation_limit {
+            lines.push(tr!(
+                "stats.starvation",
+                limit = one(limit),
+                longest = two(self.longest_wait()),
+                grants = self.priority_grants,
+            ));
+        }
+        lines.extend(extra_lines.iter().cloned());
+        lines.extend([String::new(), String::new(), tr!("stats.quit")]);
+        lines.join("\n")
+    }
+
+    /// mL burned per vehicle that left the road
+    pub fn average_fuel(&self) -> f32 {
+        if self.total_vehicles_passed == 0 {
+            0.0
+        } else {
+            self.total_fuel / self.total_vehicles_passed as f32
+        }
+    }
+
+    /// Share of the fuel burned standing still
+    pub fn idle_share(&self) -> f32 {
+        if self.total_fuel == 0.0 {
+            0.0
+        } else {
+            self.idle_fuel / self.total_fuel
+        }
+    }
+
+    /// What the vehicles of `class` that left the road emitted
+    pub fn class_emissions(&self, class: VehicleClass) -> ClassEmissions {
+        self.emissions.get(&class).copied().unwrap_or_default()
+    }
+
+    /// Grams of CO2 emitted by the vehicles that left the road
+    pub fn total_co2(&self) -> f32 {
+        self.emissions.values().map(|e| e.co2_g).sum()
+    }
+
+    /// Grams of NOx emitted by the vehicles that left the road
+    pub fn total_nox(&self) -> f32 {
+        self.emissions.values().map(|e| e.nox_g).sum()
+    }
+
+    /// Grams of CO2 per vehicle that left the road
+    pub fn average_co2(&self) -> f32 {
+        if self.total_vehicles_passed == 0 {
+            0.0
+        } else {
+            self.total_co2() / self.total_vehicles_passed as f32
+        }
+    }
+
+    /// Vehicles per platoon, leader included
+    pub fn average_platoon_size(&self) -> f32 {
+        if self.platoons == 0 {
+            0.0
+        } else {
+            (self.platoons + self.platooned_vehicles) as f32 / self.platoons as f32
+        }
+    }
+
+    fn profile_count(&self, profile: DriverProfile) -> u32 {
+        self.profiles_spawned.get(&profile).copied().unwrap_or(0)
+    }
+
+    /// Requests first turned down for `cause`
+    pub fn denial_count(&self, cause: DenialCause) -> u32 {
+        self.denials.get(&cause).copied().unwrap_or(0)
+    }
+
+    /// Take all events emitted since the previous call
+    pub fn drain_events(&mut self) -> Vec<SimEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    fn emit(&mut self, time: f32, vehicle_id: usize, kind: EventKind) {
+        match kind {
+            EventKind::Collision { .. } => warn!(vehicle = vehicle_id, time, "{:?}", kind),
+            EventKind::CloseCall { .. } | EventKind::BrokeDown => {
+                info!(vehicle = vehicle_id, time, "{:?}", kind)
             }
-            let idx = self.cell_index(col, row);
-            self.grid[idx]
-                .slots
-                .retain(|slot| slot.vehicle_id != vehicle_id);
+            _ => debug!(vehicle = vehicle_id, time, "{:?}", kind),
         }
+        self.events.push(SimEvent::new(time, vehicle_id, kind));
     }
 
-    fn conflict(&self, cell: &Cell, start: f32, end: f32) -> bool {
-        cell.slots
-            .iter()
-            .any(|slot| start < slot.end && slot.start < end)
+    /// Log a denial, with why, and count its cause only on the first failed
+    /// attempt so retries don't flood the log
+    fn log_permission_result(
+        &mut self,
+        time: f32,
+        vehicle_id: usize,
+        permission: bool,
+        was_denied: bool,
+    ) {
+        if permission || was_denied {
+            return;
+        }
+        let denial = self
+            .vehicle(vehicle_id)
+            .and_then(|v| v.denial.clone())
+            .unwrap_or(Denial::new(DenialCause::Rule));
+        *self.denials.entry(denial.cause).or_insert(0) += 1;
+        self.emit(
+            time,
+            vehicle_id,
+            EventKind::PermissionDenied {
+                cause: denial.cause,
+                conflicts: denial.conflicts,
+            },
+        );
+    }
+}
+
+/// Why a request was turned down, naming the first slot in the way when
+/// other vehicles' bookings were
+fn describe_denial(denial: &Denial) -> String {
+    let cause = tr!(denial.cause.locale_key());
+    let Some(first) = denial.conflicts.first() else {
+        return cause;
+    };
+    let reason = tr!(
+        "denial.conflict_with",
+        cause = cause,
+        id = first.vehicle_id,
+        col = first.cell.0,
+        row = first.cell.1,
+        start = format!("{:.2}", first.start),
+        end = format!("{:.2}", first.end),
+    );
+    match denial.conflicts.len() - 1 {
+        0 => reason,
+        more => tr!("denial.more", reason = reason, count = more),
     }
+}
+
+/// Where `vehicle` would be in `route`'s approach lane, level with where it is now
+fn relaned_position(vehicle: &Vehicle, route: Route) -> (f32, f32) {
+    let (lane_x, lane_y) = get_spawn_position(vehicle.direction, route);
+    match vehicle.direction {
+        Direction::North | Direction::South => (lane_x, vehicle.position.1),
+        Direction::East | Direction::West => (vehicle.position.0, lane_y),
+    }
+}
 
-    fn cell_index(&self, col: usize, row: usize) -> usize {
-        row * self.cols + col
+/// Body of a vehicle spawned in the lane, stretched by SPAWN_CLEARANCE along it
+fn spawn_area(direction: Direction, route: Route) -> (f32, f32, f32, f32) {
+    let (x, y) = get_spawn_position(direction, route);
+    let (width, length) = (VEHICLE_WIDTH as f32, VEHICLE_LENGTH as f32);
+    match direction {
+        Direction::North | Direction::South => (
+            x,
+            y - SPAWN_CLEARANCE,
+            width,
+            length + 2.0 * SPAWN_CLEARANCE,
+        ),
+        // Turned a quarter about the centre of the upright body
+        Direction::East | Direction::West => (
+            x + width / 2.0 - length / 2.0 - SPAWN_CLEARANCE,
+            y + length / 2.0 - width / 2.0,
+            length + 2.0 * SPAWN_CLEARANCE,
+            width,
+        ),
     }
 }
@@ -0,0 +1,117 @@
+use crate::route::{Direction, Route, exit_direction, get_random_direction, random_route};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// Shape of the road network around the intersection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoadLayout {
+    #[default]
+    Crossroads,
+    TJunction, // the northern arm is closed
+}
+
+impl RoadLayout {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.to_lowercase().as_str() {
+            "cross" | "crossroads" => Ok(RoadLayout::Crossroads),
+            "t" | "t-junction" | "t_junction" => Ok(RoadLayout::TJunction),
+            _ => Err(format!(
+                "Unknown layout '{}' (expected crossroads or t-junction)",
+                name
+            )),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RoadLayout::Crossroads => "Crossroads",
+            RoadLayout::TJunction => "T-junction",
+        }
+    }
+
+    /// Heading that would lead into the closed arm, if any. No vehicle leaves
+    /// that way, and none enter from it (those would head the opposite way).
+    fn closed_heading(self) -> Option<Direction> {
+        match self {
+            RoadLayout::Crossroads => None,
+            RoadLayout::TJunction => Some(Direction::North),
+        }
+    }
+
+    /// Whether vehicles entering with `direction` can follow `route`
+    pub fn allows(self, direction: Direction, route: Route) -> bool {
+        match self.closed_heading() {
+            None => true,
+            Some(closed) => {
+                direction != opposite(closed) && exit_direction(direction, route) != closed
+            }
+        }
+    }
+
+    /// Every (direction, route) pair with a road to drive on
+    pub fn paths(self) -> Vec<(Direction, Route)> {
+        let mut paths = Vec::new();
+        for direction in [
+            Direction::North,
+            Direction::South,
+            Direction::East,
+            Direction::West,
+        ] {
+            for route in [Route::Straight, Route::Left, Route::Right] {
+                if self.allows(direction, route) {
+                    paths.push((direction, route));
+                }
+            }
+        }
+        paths
+    }
+
+    /// Whether any route starts from `direction`
+    pub fn has_approach(self, direction: Direction) -> bool {
+        self.paths().iter().any(|&(d, _)| d == direction)
+    }
+
+    /// Random direction among the open approaches
+    pub fn random_direction(self, rng: &mut impl Rng) -> Direction {
+        loop {
+            let direction = get_random_direction(rng);
+            if self.has_approach(direction) {
+                return direction;
+            }
+        }
+    }
+
+    /// Random route that `direction` can follow, or None if the approach is closed
+    pub fn random_route(self, direction: Direction, rng: &mut impl Rng) -> Option<Route> {
+        if !self.has_approach(direction) {
+            return None;
+        }
+        loop {
+            let route = random_route(rng);
+            if self.allows(direction, route) {
+                return Some(route);
+            }
+        }
+    }
+
+    /// World rectangle (x, y, width, height) of the closed arm, drawn as grass
+    pub fn closed_area(self) -> Option<(f32, f32, f32, f32)> {
+        match self.closed_heading() {
+            None => None,
+            Some(Direction::North) => Some((350.0, 0.0, 300.0, 350.0)),
+            Some(Direction::South) => Some((350.0, 650.0, 300.0, 350.0)),
+            Some(Direction::East) => Some((650.0, 350.0, 350.0, 300.0)),
+            Some(Direction::West) => Some((0.0, 350.0, 350.0, 300.0)),
+        }
+    }
+}
+
+fn opposite(direction: Direction) -> Direction {
+    match direction {
+        Direction::North => Direction::South,
+        Direction::South => Direction::North,
+        Direction::East => Direction::West,
+        Direction::West => Direction::East,
+    }
+}
@@ -0,0 +1,103 @@
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::EnvFilter;
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+
+/// Lines kept for the on-screen log
+const LOG_CAPACITY: usize = 100;
+
+/// Filter used when `RUST_LOG` isn't set
+const DEFAULT_FILTER: &str = "info";
+
+/// The most recent log lines, shared with the logging layer that fills it
+#[derive(Clone, Default)]
+pub struct LogBuffer {
+    lines: Arc<Mutex<VecDeque<String>>>,
+}
+
+impl LogBuffer {
+    /// The last `count` lines, oldest first
+    pub fn recent(&self, count: usize) -> Vec<String> {
+        let lines = self.lines.lock().unwrap_or_else(|e| e.into_inner());
+        lines
+            .iter()
+            .skip(lines.len().saturating_sub(count))
+            .cloned()
+            .collect()
+    }
+
+    fn push(&self, line: String) {
+        let mut lines = self.lines.lock().unwrap_or_else(|e| e.into_inner());
+        if lines.len() == LOG_CAPACITY {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+}
+
+/// Install the global subscriber. Levels and targets come from `RUST_LOG`
+/// (e.g. `RUST_LOG=smart_road::intersection=debug`). With `to_stderr` off,
+/// nothing is printed and lines only reach the returned buffer, which keeps
+/// terminal front ends intact. At `trace`, the update phase and render spans
+/// print their busy time as they close.
+pub fn init(to_stderr: bool) -> LogBuffer {
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER));
+    let buffer = LogBuffer::default();
+    let stderr = to_stderr.then(|| {
+        tracing_subscriber::fmt::layer()
+            .with_writer(std::io::stderr)
+            .with_span_events(FmtSpan::CLOSE)
+            .without_time()
+    });
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(stderr)
+        .with(BufferLayer(buffer.clone()))
+        .init();
+    buffer
+}
+
+/// Formats every enabled event as one short line into a `LogBuffer`
+struct BufferLayer(LogBuffer);
+
+impl<S: Subscriber> Layer<S> for BufferLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        let target = metadata
+            .target()
+            .strip_prefix("smart_road::")
+            .unwrap_or(metadata.target());
+        let mut visitor = LineVisitor::default();
+        event.record(&mut visitor);
+        self.0.push(format!(
+            "{:<5} {}: {}{}",
+            metadata.level(),
+            target,
+            visitor.message,
+            visitor.fields
+        ));
+    }
+}
+
+/// Collects the message and the other fields as ` name=value` pairs
+#[derive(Default)]
+struct LineVisitor {
+    message: String,
+    fields: String,
+}
+
+impl Visit for LineVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{:?}", value);
+        } else {
+            let _ = write!(self.fields, " {}={:?}", field.name(), value);
+        }
+    }
+}
@@ -1,98 +1,420 @@
 use sdl2::event::Event;
-use sdl2::image::{InitFlag, LoadTexture};
-use sdl2::keyboard::Keycode;
-use std::time::Duration;
+use sdl2::keyboard::{Keycode, Mod};
+use sdl2::mouse::MouseButton;
+use std::time::Instant;
+use tracing::info;
+mod animation;
+mod assets;
+#[cfg(feature = "audio")]
+mod audio;
+mod batch;
+mod bench;
+mod camera;
+mod challenge;
+mod checkpoint;
+mod compare;
+mod config;
+mod construction;
+mod controller;
+mod cost;
+mod csv_demand;
+mod dataset;
+mod demand;
+mod determinism;
+mod distribution;
+mod driver;
+mod events;
+mod frame_state;
+mod fuel;
+mod gym;
+mod hazard;
+mod heatmap;
+mod history;
+mod i18n;
+mod interpolation;
 mod intersection;
+mod layout;
+mod logging;
+mod merge;
+mod mpc;
+mod net;
+mod observation;
+mod obstacle;
+mod onboarding;
+mod profiler;
+mod queues;
+mod render;
+#[cfg(feature = "wgpu")]
+mod render_wgpu;
+mod replay;
+mod report;
+mod reservation;
 mod route;
+mod run_db;
+mod run_limit;
+#[cfg(feature = "scripting")]
+mod script;
+mod significance;
+mod skid_marks;
+mod soak;
+#[cfg(feature = "ttf")]
 mod stats;
+mod stop_sign;
+mod stress;
+mod sumo;
+mod sweep;
+mod systems;
+mod text;
+mod traffic_light;
+mod tui;
 mod vehicle;
 mod velocities;
+mod weather;
 
+use camera::*;
+use challenge::*;
+use checkpoint::*;
+use config::*;
+use controller::*;
+use cost::CostTracker;
+use csv_demand::load_csv_schedule;
+use demand::*;
+use events::*;
+use history::*;
+use i18n::tr;
+use interpolation::PreviousPoses;
 use intersection::*;
+use logging::LogBuffer;
+use obstacle::ObstacleKind;
+use profiler::FrameProfiler;
+use render::*;
+use replay::*;
+use report::*;
 use route::*;
+use run_db::*;
+#[cfg(feature = "ttf")]
 use stats::*;
+use stress::*;
+use sumo::*;
 
 // Constants for the game design
 const WINDOW_WIDTH: u32 = 1000;
 const WINDOW_HEIGHT: u32 = 1000;
-const FRAME_DELAY: Duration = Duration::from_millis(16);
+/// Real time simulated at most per frame, so a stall isn't caught up all at once
+const MAX_LAG: f32 = 0.25;
+/// Log lines shown by the on-screen log
+const LOG_PANEL_LINES: usize = 12;
 
 fn run_game(
     sdl_context: &sdl2::Sdl,
-    video_subsystem: &sdl2::VideoSubsystem,
-) -> Result<Option<String>, String> {
-    let window = video_subsystem
-        .window("SMART ROAD", WINDOW_WIDTH, WINDOW_HEIGHT)
-        .position_centered()
-        .build()
-        .map_err(|e| e.to_string())?;
+    renderer: &mut dyn Renderer,
+    config: &Config,
+    logs: &LogBuffer,
+) -> Result<Option<FinalStats>, String> {
+    let mut intersection = build_intersection(config)?;
+    let mut current_time = 0.0f32;
+    let mut auto_spawn = AutoSpawn::default();
+    let mut show_minimap = false;
+    let mut show_log = false;
+    let mut show_timeline = false;
+    let mut show_heatmap = false;
+    let mut show_owners = false;
+    let mut show_queues = false;
+    let mut show_labels = false;
+    let mut profiler: Option<FrameProfiler> = None;
+    let mut close_calls = CloseCallBuffer::default();
+    let mut rewind = RewindBuffer::default();
+    let mut paused = false;
+    let mut slow_motion: Option<SlowMotionReplay> = None;
+    let mut camera = Camera::new(WINDOW_WIDTH);
 
-    let mut canvas = window
-        .into_canvas()
-        .present_vsync()
-        .build()
-        .map_err(|e| e.to_string())?;
+    let mut schedule = SpawnSchedule::default();
+    if let Some(path) = &config.sumo_routes {
+        let loaded = load_sumo_routes(path)?;
+        info!("Loaded {} vehicles from {}", loaded.len(), path);
+        schedule = schedule.merged(loaded);
+    }
+    if let Some(path) = &config.spawn_csv {
+        let loaded = load_csv_schedule(path)?;
+        info!("Loaded {} vehicles from {}", loaded.len(), path);
+        schedule = schedule.merged(loaded);
+    }
 
-    let texture_creator = canvas.texture_creator();
-    let road_texture =
-        texture_creator.load_texture("assets/road-intersection/road-intersection.png")?;
+    let mut arrivals = config
+        .arrival_rates
+        .map(|rates| PoissonArrivals::new(rates, intersection.demand_rng()));
 
-    let mut intersection = SmartIntersection::new();
-    let mut current_time = 0.0f32;
-    let mut continuous_spawning = false;
-    let mut spawn_counter = 0;
+    let mut event_log = match &config.event_log {
+        Some(path) => Some(EventLog::create(path)?),
+        None => None,
+    };
 
-    let mut event_pump = sdl_context.event_pump()?;
-    loop {
-        current_time += 1.0 / 60.0;
+    let mut stress = config.stress.then(StressRamp::new);
+    let mut challenge_outcome = Outcome::InProgress;
+    let mut limit_reached = false;
+    let mut show_onboarding = config.onboarding && onboarding::first_launch();
+
+    #[cfg(feature = "audio")]
+    let mut audio = match audio::Audio::init(sdl_context) {
+        Ok(audio) => Some(audio),
+        Err(e) => {
+            tracing::warn!("Audio disabled: {}", e);
+            None
+        }
+    };
+
+    let mut history = RunHistory::default();
+
+    let mut recorder = match &config.record_replay {
+        Some(path) => Some(ReplayRecorder::create(path)?),
+        None => None,
+    };
+    let mut ghost = match &config.ghost_replay {
+        Some(path) => {
+            let ghost = GhostReplay::load(path)?;
+            info!("Loaded {} replay frames from {}", ghost.len(), path);
+            Some(ghost)
+        }
+        None => None,
+    };
 
+    let mut host = match config.host_port {
+        Some(port) => Some(net::NetHost::listen(&config.host_bind, port)?),
+        None => None,
+    };
+
+    // Set once K picks a controller other than the configured one
+    let mut switched_controller = None;
+    if let Some(path) = &config.resume {
+        let checkpoint = Checkpoint::load(path)?;
+        if checkpoint.config_hash != config_hash(config) {
+            tracing::warn!("{} was saved with different settings", path);
+        }
+        if let Some(kind) = checkpoint.controller {
+            intersection.controller = kind.build(intersection.layout, intersection.path_padding);
+            switched_controller = Some(kind);
+        }
+        intersection.restore_state(checkpoint.intersection)?;
+        current_time = checkpoint.time;
+        auto_spawn = checkpoint.auto_spawn;
+        arrivals = checkpoint.arrivals;
+        stress = checkpoint.stress;
+        // Fixed demand already spawned before the checkpoint
+        schedule.due(current_time);
+        if let Some(ghost) = &mut ghost {
+            ghost.advance(current_time);
+        }
+        info!("Resumed from {} at {:.1}s", path, current_time);
+    }
+    let mut checkpoints = config.checkpoint_every.map(|every| {
+        Checkpointer::new(
+            every,
+            &config.checkpoint_dir,
+            config.checkpoints_kept,
+            current_time,
+        )
+    });
+
+    let mut limiter = FrameLimiter::new(config.frame_rate);
+    let mut poses = PreviousPoses::default();
+    let mut lag = 0.0f32; // real time not yet simulated
+    let mut last_frame = Instant::now();
+
+    let mut event_pump = sdl_context.event_pump()?;
+    'running: loop {
         for event in event_pump.poll_iter() {
             match event {
-                Event::Quit { .. } => {
-                    return Ok(Some(intersection.get_final_stats())); // Quit the whole application
-                }
-                Event::KeyDown {
+                Event::Quit { .. }
+                | Event::KeyDown {
                     keycode: Some(Keycode::Escape),
                     ..
+                } => break 'running,
+                Event::MouseButtonDown {
+                    mouse_btn: MouseButton::Left,
+                    x,
+                    y,
+                    ..
                 } => {
-                    return Ok(Some(intersection.get_final_stats()));
+                    let at = camera.to_world(x, y);
+                    let mods = sdl_context.keyboard().mod_state();
+                    let obstacle = if mods.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) {
+                        Some(ObstacleKind::StalledCar)
+                    } else if mods.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) {
+                        Some(ObstacleKind::Debris)
+                    } else {
+                        None
+                    };
+                    match obstacle {
+                        Some(kind) => {
+                            if !intersection.place_obstacle(kind, at, current_time) {
+                                info!("No room for an obstacle there");
+                            }
+                        }
+                        None => {
+                            // Clicking empty road releases the camera
+                            let clicked = vehicle_at(&intersection.vehicles(), at);
+                            camera.follow(clicked);
+                        }
+                    }
                 }
                 Event::KeyDown {
                     keycode: Some(key), ..
                 } => match key {
-                    Keycode::Up => {
-                        intersection.spawn_vehicle(
-                            &texture_creator,
-                            Some(Direction::North),
-                            current_time,
+                    Keycode::Up | Keycode::Down | Keycode::Right | Keycode::Left => {
+                        let direction = match key {
+                            Keycode::Up => Direction::North,
+                            Keycode::Down => Direction::South,
+                            Keycode::Right => Direction::East,
+                            _ => Direction::West,
+                        };
+                        let mods = sdl_context.keyboard().mod_state();
+                        let route = route_for_modifiers(
+                            mods.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD),
+                            mods.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD),
                         );
+                        intersection.spawn_vehicle(Some(direction), route, current_time);
                     }
-                    Keycode::Down => {
-                        intersection.spawn_vehicle(
-                            &texture_creator,
-                            Some(Direction::South),
-                            current_time,
-                        );
+                    Keycode::A => {
+                        intersection.spawn_from_all_directions(current_time);
                     }
-                    Keycode::Right => {
-                        intersection.spawn_vehicle(
-                            &texture_creator,
-                            Some(Direction::East),
-                            current_time,
-                        );
+                    Keycode::Return | Keycode::F1 if show_onboarding => {
+                        show_onboarding = false;
+                        onboarding::mark_seen();
                     }
-                    Keycode::Left => {
-                        intersection.spawn_vehicle(
-                            &texture_creator,
-                            Some(Direction::West),
-                            current_time,
-                        );
+                    Keycode::F1 => {
+                        show_onboarding = true;
+                    }
+                    Keycode::F3 => {
+                        profiler = match profiler {
+                            Some(_) => None,
+                            None => Some(FrameProfiler::new(intersection.timings)),
+                        };
+                    }
+                    Keycode::N => {
+                        camera.follow_next(&intersection.vehicles());
+                    }
+                    Keycode::Tab => {
+                        show_minimap = !show_minimap;
+                    }
+                    Keycode::L => {
+                        show_log = !show_log;
+                    }
+                    Keycode::T => {
+                        show_timeline = !show_timeline;
+                    }
+                    Keycode::H => {
+                        show_heatmap = !show_heatmap;
+                    }
+                    Keycode::O => {
+                        show_owners = !show_owners;
+                    }
+                    Keycode::Q => {
+                        show_queues = !show_queues;
+                    }
+                    Keycode::I => {
+                        show_labels = !show_labels;
+                    }
+                    Keycode::C => {
+                        slow_motion = match slow_motion {
+                            Some(_) => None,
+                            None => {
+                                let replay = close_calls.latest().map(SlowMotionReplay::new);
+                                if replay.is_none() {
+                                    info!("No close call to replay yet");
+                                }
+                                replay
+                            }
+                        };
                     }
                     Keycode::R => {
-                        continuous_spawning = !continuous_spawning;
+                        auto_spawn.enabled = !auto_spawn.enabled;
+                    }
+                    Keycode::P => {
+                        paused = !paused;
+                        if !paused {
+                            rewind.resume();
+                        }
+                    }
+                    Keycode::LeftBracket | Keycode::RightBracket if paused => {
+                        // A frame at a time, or a second with Shift held
+                        let mods = sdl_context.keyboard().mod_state();
+                        let frames = if mods.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) {
+                            60
+                        } else {
+                            1
+                        };
+                        rewind.step(if key == Keycode::LeftBracket {
+                            -frames
+                        } else {
+                            frames
+                        });
+                    }
+                    Keycode::X => {
+                        intersection.remove_broken_down_vehicles(current_time);
+                        intersection.clear_obstacles(current_time);
+                    }
+                    Keycode::W => {
+                        intersection.weather = intersection.weather.next();
+                        info!("Weather: {}", intersection.weather.name());
+                    }
+                    Keycode::K => {
+                        let current = switched_controller.or(config
+                            .controller_script
+                            .is_none()
+                            .then_some(config.controller));
+                        switched_controller =
+                            Some(intersection.cycle_controller(current, current_time));
                     }
                     Keycode::S => {
-                        continuous_spawning = false; // Stop spawning 
+                        auto_spawn.enabled = false; // Stop spawning
+                    }
+                    Keycode::Num1
+                    | Keycode::Num2
+                    | Keycode::Num3
+                    | Keycode::Num4
+                    | Keycode::Num5
+                    | Keycode::Num6
+                    | Keycode::Num7
+                    | Keycode::Num8
+                    | Keycode::Num9 => {
+                        auto_spawn.set_level((key.into_i32() - Keycode::Num0.into_i32()) as u32);
+                        info!("Auto-spawn level {}", auto_spawn.level());
+                    }
+                    Keycode::F | Keycode::G | Keycode::V if config.debug_keys => {
+                        let Some(vehicle_id) = camera.following else {
+                            info!("Follow a vehicle first (click it or press N)");
+                            continue;
+                        };
+                        match key {
+                            Keycode::F => match intersection.toggle_frozen(vehicle_id) {
+                                Some(true) => info!("Vehicle {} frozen", vehicle_id),
+                                Some(false) => info!("Vehicle {} released", vehicle_id),
+                                None => {}
+                            },
+                            Keycode::G if intersection.force_grant(vehicle_id) => {
+                                info!(
+                                    "Vehicle {} granted a crossing without a booking",
+                                    vehicle_id
+                                );
+                            }
+                            Keycode::G => {
+                                info!("Vehicle {} can't be granted a crossing", vehicle_id)
+                            }
+                            _ if intersection.revoke_crossing(vehicle_id) => {
+                                info!("Vehicle {} lost its crossing", vehicle_id);
+                            }
+                            _ => info!("Vehicle {} has no crossing to revoke", vehicle_id),
+                        }
+                    }
+                    Keycode::Delete if config.debug_keys => {
+                        intersection.clear_bookings();
+                        info!("Cleared every booking");
+                    }
+                    #[cfg(feature = "audio")]
+                    Keycode::M => {
+                        if let Some(audio) = &mut audio {
+                            audio.toggle_mute();
+                        }
                     }
                     _ => {}
                 },
@@ -100,50 +422,493 @@ fn run_game(
             }
         }
 
-        intersection.update(current_time);
-        if continuous_spawning {
-            spawn_counter += 1;
-            if spawn_counter >= 20 {
-                intersection.spawn_vehicle(&texture_creator, None, current_time);
-                spawn_counter = 0;
+        let now = Instant::now();
+        let elapsed = now.duration_since(last_frame).as_secs_f32();
+        last_frame = now;
+
+        // The road waits while a close call is replayed
+        if let Some(replay) = &mut slow_motion {
+            if replay.advance(elapsed) {
+                let (a, b) = replay.vehicles;
+                renderer.draw_frame(&FrameView {
+                    vehicles: &replay.vehicles(),
+                    camera: &camera,
+                    show_minimap,
+                    info_lines: Some(vec![
+                        tr!("replay.title", speed = SLOW_MOTION),
+                        tr!("replay.vehicles", a = a, b = b),
+                        tr!(
+                            "replay.time_to_close_call",
+                            seconds = format!("{:.1}", replay.time_to_close_call())
+                        ),
+                        tr!("replay.back"),
+                    ]),
+                    ghosts: &[],
+                    log_lines: show_log.then(|| logs.recent(LOG_PANEL_LINES)),
+                    layout: intersection.layout,
+                    timeline: None,
+                    heatmap: None,
+                    cell_owners: None,
+                    hud_lines: None,
+                    onboarding: false,
+                    obstacles: &[],
+                    roadworks: Vec::new(),
+                    hazards: &[],
+                    close_call_spots: &intersection.close_call_spots,
+                    entry_queues: Vec::new(),
+                    vehicle_labels: show_labels,
+                    profile: None,
+                })?;
+                limiter.wait();
+                continue;
             }
+            slow_motion = None;
         }
 
-        canvas.clear();
-        canvas.copy(&road_texture, None, None)?;
+        // Paused on an earlier frame: show it instead of the live road
+        if paused && let Some(vehicles) = rewind.vehicles() {
+            renderer.draw_frame(&FrameView {
+                vehicles: &vehicles,
+                camera: &camera,
+                show_minimap,
+                info_lines: Some(vec![
+                    tr!("rewind.title"),
+                    tr!(
+                        "rewind.position",
+                        seconds = format!("{:.2}", rewind.seconds_back()),
+                        span = format!("{:.1}", rewind.span())
+                    ),
+                    tr!("rewind.keys"),
+                ]),
+                ghosts: &[],
+                log_lines: show_log.then(|| logs.recent(LOG_PANEL_LINES)),
+                layout: intersection.layout,
+                timeline: None,
+                heatmap: None,
+                cell_owners: None,
+                hud_lines: None,
+                onboarding: false,
+                obstacles: &[],
+                roadworks: Vec::new(),
+                hazards: &[],
+                close_call_spots: &intersection.close_call_spots,
+                entry_queues: Vec::new(),
+                vehicle_labels: show_labels,
+                profile: None,
+            })?;
+            limiter.wait();
+            continue;
+        }
 
-        for vehicle in &intersection.active_vehicles {
-            let dest_rect = sdl2::rect::Rect::new(
-                vehicle.position.0 as i32,
-                vehicle.position.1 as i32,
-                vehicle.width,
-                vehicle.height,
-            );
+        // The simulation ticks at a fixed 60 Hz however often frames are drawn,
+        // and not at all while paused
+        lag = if paused {
+            0.0
+        } else {
+            (lag + elapsed).min(MAX_LAG)
+        };
+        while lag >= SIM_TICK {
+            lag -= SIM_TICK;
+            poses.capture(&intersection);
+            current_time += SIM_TICK;
 
-            canvas.copy_ex(
-                &vehicle.texture,
-                None,
-                dest_rect,
-                vehicle.rotation,
-                None,
-                false,
-                false,
+            if !schedule.is_empty() {
+                for spawn in schedule.due(current_time) {
+                    intersection.spawn_vehicle_of_class(
+                        Some(spawn.direction),
+                        Some(spawn.route),
+                        spawn.class,
+                        current_time,
+                    );
+                }
+            }
+
+            if let Some(arrivals) = &mut arrivals {
+                for direction in arrivals.due(current_time, intersection.demand_rng()) {
+                    intersection.spawn_vehicle(Some(direction), None, current_time);
+                }
+            }
+
+            if let Some(ghost) = &mut ghost {
+                for (direction, route) in ghost.advance(current_time) {
+                    intersection.spawn_vehicle(Some(direction), Some(route), current_time);
+                }
+            }
+
+            if let Some(host) = &mut host {
+                for direction in host.poll_spawns() {
+                    intersection.spawn_vehicle(direction, None, current_time);
+                }
+            }
+
+            intersection.update(current_time);
+            history.record(current_time, &intersection);
+            rewind.record(current_time, &intersection);
+            if let Some(host) = &mut host {
+                host.send_state(current_time, &intersection);
+            }
+            if let Some(recorder) = &mut recorder {
+                recorder.record(current_time, &intersection)?;
+            }
+
+            let events = intersection.drain_events();
+            if let Some(clip) = close_calls.record(current_time, &intersection, &events) {
+                let (a, b) = clip.vehicles;
+                info!("Close call between {} and {}; press C to replay it", a, b);
+                if let Some(dir) = &config.close_call_dir {
+                    info!("Close-call clip saved to {}", clip.save(dir)?);
+                }
+            }
+            if let Some(log) = &mut event_log {
+                log.write(&events)?;
+            }
+            #[cfg(feature = "audio")]
+            if let Some(audio) = &mut audio {
+                audio.update(intersection.vehicle_count(), &events);
+            }
+            if let Some(ramp) = &mut stress {
+                if ramp.tick(current_time, &intersection) {
+                    intersection.spawn_vehicle(None, None, current_time);
+                }
+                if ramp.is_finished() {
+                    break 'running;
+                }
+            }
+            if let Some(challenge) = &config.challenge {
+                challenge_outcome = challenge.check(&intersection, current_time);
+                if challenge_outcome != Outcome::InProgress {
+                    info!("{}", challenge_outcome.describe());
+                    break 'running;
+                }
+            }
+            if let Some(limit) = &config.run_limit
+                && limit.reached(&intersection, current_time)
+            {
+                limit_reached = true;
+                info!("{}", limit.describe());
+                break 'running;
+            }
+
+            if auto_spawn.tick() {
+                intersection.spawn_vehicle(None, None, current_time);
+            }
+
+            if let Some(checkpoints) = &mut checkpoints
+                && checkpoints.is_due(current_time)
+            {
+                let path = checkpoints.write(&Checkpoint {
+                    time: current_time,
+                    config_hash: config_hash(config),
+                    controller: switched_controller,
+                    intersection: intersection.save_state(),
+                    auto_spawn: auto_spawn.clone(),
+                    arrivals: arrivals.clone(),
+                    stress: stress.clone(),
+                })?;
+                info!("Checkpoint saved to {}", path);
+            }
+        }
+
+        let vehicles = intersection.vehicles();
+        camera.update(&vehicles);
+        let drawn = poses.interpolate(&vehicles, lag / SIM_TICK);
+        let render_start = Instant::now();
+        renderer.draw_frame(&FrameView {
+            vehicles: &drawn.iter().collect::<Vec<_>>(),
+            camera: &camera,
+            show_minimap: show_minimap || camera.is_following(),
+            info_lines: camera
+                .following
+                .and_then(|id| intersection.describe_vehicle(id)),
+            ghosts: &ghost
+                .as_ref()
+                .map(GhostReplay::vehicles)
+                .unwrap_or_default(),
+            log_lines: show_log.then(|| logs.recent(LOG_PANEL_LINES)),
+            layout: intersection.layout,
+            timeline: show_timeline
+                .then(|| intersection.controller.timeline())
+                .flatten()
+                .map(|timeline| TimelineView {
+                    now: current_time,
+                    orphaned: intersection.orphaned_bookings(&timeline),
+                    timeline,
+                }),
+            heatmap: show_heatmap
+                .then(|| intersection.controller.occupancy())
+                .flatten(),
+            cell_owners: show_owners
+                .then(|| intersection.controller.cell_owners(current_time))
+                .flatten(),
+            hud_lines: hud_lines(
+                paused,
+                &auto_spawn,
+                config.challenge.as_ref(),
+                &intersection,
+                current_time,
+            ),
+            onboarding: show_onboarding,
+            obstacles: &intersection.obstacles,
+            roadworks: intersection.roadworks(),
+            hazards: &intersection.hazards,
+            close_call_spots: &intersection.close_call_spots,
+            entry_queues: if show_queues {
+                intersection.entry_queue_lines()
+            } else {
+                Vec::new()
+            },
+            vehicle_labels: show_labels,
+            profile: profiler.as_ref(),
+        })?;
+        if let Some(profiler) = &mut profiler {
+            profiler.record(intersection.timings, render_start.elapsed());
+        }
+
+        limiter.wait();
+    }
+
+    if let Some(log) = &mut event_log {
+        log.flush()?;
+    }
+    if let Some(recorder) = &mut recorder {
+        recorder.flush()?;
+    }
+    if let Some(dir) = &config.output_dir {
+        write_run_report(dir, &intersection, &history, current_time)?;
+        info!("Run report written to {}", dir);
+    }
+    let summary = RunSummary::new(&intersection, current_time);
+    let previous = previous_run(config);
+    let mut extra = record_run(config, &summary, previous.as_ref());
+    if let Some(stress) = &stress {
+        extra.extend(stress.report());
+    }
+    if config.challenge.is_some() {
+        extra.push(challenge_outcome.describe());
+    }
+    if let Some(limit) = config.run_limit.filter(|_| limit_reached) {
+        extra.push(limit.describe());
+    }
+    Ok(Some(FinalStats {
+        text: intersection.get_final_stats(&extra, previous.as_ref()),
+        #[cfg(feature = "ttf")]
+        json: serde_json::to_string_pretty(&summary).map_err(|e| e.to_string())?,
+        #[cfg(feature = "ttf")]
+        charts: direction_charts(&summary),
+    }))
+}
+
+/// Bottom-right panel: the auto-spawn rate while it runs, then challenge progress
+fn hud_lines(
+    paused: bool,
+    auto_spawn: &AutoSpawn,
+    challenge: Option<&Challenge>,
+    intersection: &SmartIntersection,
+    current_time: f32,
+) -> Option<Vec<String>> {
+    let mut lines = Vec::new();
+    if paused {
+        lines.push(tr!("hud.paused"));
+    }
+    let warm_up_left = intersection.warm_up_left(current_time);
+    if warm_up_left > 0.0 {
+        lines.push(tr!(
+            "hud.warm_up",
+            seconds = format!("{:.0}", warm_up_left.ceil())
+        ));
+    }
+    if auto_spawn.enabled {
+        lines.push(tr!(
+            "hud.auto_spawn",
+            rate = format!("{:.1}", auto_spawn.rate()),
+            level = auto_spawn.level(),
+        ));
+    }
+    if let Some(challenge) = challenge {
+        lines.extend(challenge.progress_lines(intersection, current_time));
+    }
+    (!lines.is_empty()).then_some(lines)
+}
+
+/// Either play locally (optionally hosting a co-op client) or join a host
+fn run_front_end(
+    sdl_context: &sdl2::Sdl,
+    renderer: &mut dyn Renderer,
+    config: &Config,
+    logs: &LogBuffer,
+) -> Result<Option<FinalStats>, String> {
+    match &config.connect {
+        Some(address) => {
+            net::run_client(
+                sdl_context,
+                renderer,
+                address,
+                WINDOW_WIDTH,
+                config.frame_rate,
             )?;
+            Ok(None)
         }
+        None => run_game(sdl_context, renderer, config, logs),
+    }
+}
 
-        canvas.present();
-        std::thread::sleep(FRAME_DELAY);
+/// Simulation configured from the command line, shared by every front end
+fn build_intersection(config: &Config) -> Result<SmartIntersection, String> {
+    let mut intersection = SmartIntersection::new();
+    intersection.weather = config.weather;
+    intersection.layout = config.layout;
+    intersection.profile_mix = config.driver_mix;
+    intersection.fleet_mix = config.fleet_mix;
+    intersection.speed_variance = config.speed_variance;
+    intersection.slot_padding = config.slot_padding;
+    intersection.path_padding = config.path_padding;
+    intersection.breakdown_rate = config.breakdown_rate;
+    intersection.breakdown_duration = config.breakdown_duration;
+    intersection.platooning = config.platooning;
+    intersection.packet_loss = config.packet_loss;
+    intersection.max_active = config.max_vehicles;
+    intersection.starvation_limit = config.starvation_limit;
+    intersection.rerouting = config.rerouting;
+    intersection.lane_closures = config.lane_closures.clone();
+    intersection.hazard_rate = config.hazard_rate;
+    intersection.warm_up = config.warm_up;
+    intersection.cost = CostTracker::new(config.cost_weights);
+    intersection.controller = config.controller.build(config.layout, config.path_padding);
+    if let Some(seed) = config.seed {
+        intersection.seed(seed);
+    }
+    if let Some(path) = &config.controller_script {
+        intersection.controller = load_script_controller(path)?;
+        info!("Using controller script {}", path);
     }
+    Ok(intersection)
+}
+
+#[cfg(feature = "scripting")]
+fn load_script_controller(path: &str) -> Result<Box<dyn IntersectionController>, String> {
+    Ok(Box::new(script::ScriptController::load(path)?))
+}
+
+#[cfg(not(feature = "scripting"))]
+fn load_script_controller(_path: &str) -> Result<Box<dyn IntersectionController>, String> {
+    Err("--controller-script needs a build with the `scripting` feature".to_string())
+}
+
+#[cfg(feature = "wgpu")]
+fn open_wgpu_renderer(
+    window: sdl2::video::Window,
+    vsync: bool,
+) -> Result<Box<dyn Renderer>, String> {
+    Ok(Box::new(render_wgpu::WgpuRenderer::new(window, vsync)?))
+}
+
+#[cfg(not(feature = "wgpu"))]
+fn open_wgpu_renderer(
+    _window: sdl2::video::Window,
+    _vsync: bool,
+) -> Result<Box<dyn Renderer>, String> {
+    Err("--renderer wgpu needs a build with the `wgpu` feature".to_string())
 }
 
 fn main() -> Result<(), String> {
+    let config = Config::from_args()?;
+    i18n::init(&config.language)?;
+    // The terminal front end owns the screen, so its logs only go to the buffer
+    let logs = logging::init(!config.tui);
+    if let Some(ticks) = config.bench_ticks {
+        bench::run_bench(ticks);
+        return Ok(());
+    }
+    if let Some(minutes) = config.soak_minutes {
+        return soak::run_soak(&config, minutes);
+    }
+    if let Some(ticks) = config.verify_ticks {
+        return determinism::verify_determinism(&config, ticks);
+    }
+    if let Some(controllers) = config.compare {
+        return compare::run_comparison(&config, controllers);
+    }
+    if let Some(sweep) = &config.sweep {
+        return sweep::run_sweep(&config, sweep);
+    }
+    if let Some(episodes) = config.batch {
+        return batch::run_batch(&config, episodes);
+    }
+    if config.gym {
+        return gym::serve(&config);
+    }
+    if let Some(replay) = &config.export_dataset {
+        let dir = config
+            .output_dir
+            .as_deref()
+            .unwrap_or(dataset::DEFAULT_DATASET_DIR);
+        let frames = dataset::export_dataset(replay, dir, &observation::Encoding::default())?;
+        println!("Wrote {} frames of tensors to {}", frames, dir);
+        return Ok(());
+    }
+    if let Some(rates) = config.merge {
+        merge::run_merge(&config, rates);
+        return Ok(());
+    }
+    if config.tui {
+        let intersection = build_intersection(&config)?;
+        let stats = tui::run_tui(intersection, &config, &logs)?;
+        println!("{}", stats);
+        return Ok(());
+    }
+
     let sdl_context = sdl2::init()?;
     let video_subsystem = sdl_context.video()?;
-    let _image_context = sdl2::image::init(InitFlag::PNG | InitFlag::JPG)?;
-    let ttf_context = sdl2::ttf::init().map_err(|e| e.to_string())?;
+    #[cfg(feature = "image")]
+    let _image_context =
+        sdl2::image::init(sdl2::image::InitFlag::PNG | sdl2::image::InitFlag::JPG)?;
+    let fonts = text::Fonts::init()?;
 
-    if let Some(stats) = run_game(&sdl_context, &video_subsystem)? {
-        show_stats(&sdl_context, &video_subsystem, &ttf_context, &stats)?;
+    let window = video_subsystem
+        .window("SMART ROAD", WINDOW_WIDTH, WINDOW_HEIGHT)
+        .position_centered()
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let result = match config.renderer {
+        RendererKind::Sdl => {
+            let mut canvas = window.into_canvas();
+            if config.frame_rate.vsync {
+                canvas = canvas.present_vsync();
+            }
+            let canvas = canvas.build().map_err(|e| e.to_string())?;
+            let texture_creator = canvas.texture_creator();
+            let mut renderer = SdlRenderer::new(canvas, &texture_creator, &fonts)?;
+            run_front_end(&sdl_context, &mut renderer, &config, &logs)?
+        }
+        RendererKind::Wgpu => {
+            let mut renderer = open_wgpu_renderer(window, config.frame_rate.vsync)?;
+            run_front_end(&sdl_context, renderer.as_mut(), &config, &logs)?
+        }
+    };
+
+    if let Some(stats) = result {
+        if config.exit_when_done {
+            println!(
+                "{}",
+                stats.text.trim_end_matches(&tr!("stats.quit")).trim_end()
+            );
+            return Ok(());
+        }
+        #[cfg(feature = "ttf")]
+        show_stats(
+            &sdl_context,
+            &video_subsystem,
+            fonts.context(),
+            &stats,
+            &history_pages(&config),
+            config.frame_rate,
+        )?;
+        // No stats window without SDL2_ttf: the same pages go to the terminal
+        #[cfg(not(feature = "ttf"))]
+        for page in std::iter::once(stats.text).chain(history_pages(&config)) {
+            println!("{}\n", page);
+        }
     }
 
     Ok(())
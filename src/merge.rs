@@ -0,0 +1,462 @@
+use crate::config::Config;
+use crate::demand::next_gap;
+use crate::route::{Direction, Route};
+use crate::vehicle::Vehicle;
+use crate::velocities::Velocity;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use std::collections::HashSet;
+
+/// Centre line (y) of the eastbound main road
+const LANE_Y: f32 = 525.0;
+/// Centre line (x) of the northbound on-ramp, where it joins the main road
+const JOIN_X: f32 = 500.0;
+/// Stretch of the main road shared by main and ramp traffic, split into cells
+const ZONE_START: f32 = 400.0;
+const ZONE_END: f32 = 640.0;
+const CELL_PX: f32 = 20.0;
+/// Vehicles ask for the merge zone once they are this close to it
+const REQUEST_DISTANCE: f32 = 250.0;
+/// Seconds added to both ends of every booking, on top of the configured
+/// slot padding and weather and driver margins
+const BASE_MARGIN: f32 = 0.2;
+/// Half a vehicle's length along its heading
+const HALF_LENGTH: f32 = 35.0;
+/// Half a vehicle's width across its heading
+const HALF_WIDTH: f32 = 20.0;
+/// Bottom edge of the main road, where ramp vehicles start poking into the lane
+const LANE_EDGE: f32 = LANE_Y + HALF_WIDTH;
+/// A ramp vehicle whose front is this close to the lane counts as in it
+/// (a frame of travel plus a pixel)
+const POKE_MARGIN: f32 = 8.0;
+/// Ramp vehicles waiting for a booking stop with their front this far below the lane
+const RAMP_STOP_GAP: f32 = 15.0;
+/// Main road vehicles within this many pixels of the join point (centre to
+/// centre) keep ramp traffic out of the lane; it covers two vehicle lengths
+/// plus room for the main vehicle to notice the merge and stop
+const JOIN_CLEARANCE: f32 = 2.0 * HALF_LENGTH + 50.0;
+/// Simulated length of a run: five minutes at 60 Hz
+const MERGE_TICKS: u64 = 18_000;
+/// Seed used when `--seed` isn't given
+const DEFAULT_SEED: u64 = 1;
+
+#[derive(Clone, Copy)]
+struct TimeSlot {
+    start: f32,
+    end: f32,
+    vehicle_id: usize,
+}
+
+struct MergeVehicle {
+    vehicle: Vehicle,
+    from_ramp: bool,
+    granted: Option<Velocity>, // speed booked through the merge zone
+    spawned_at: f32,
+    distance: f32, // pixels driven so far
+}
+
+impl MergeVehicle {
+    /// On the main road: main traffic always, ramp traffic once it has turned
+    fn on_main_road(&self) -> bool {
+        !self.from_ramp || self.vehicle.has_turned
+    }
+
+    /// Position along the vehicle's current road, growing in the direction of travel
+    fn progress(&self) -> f32 {
+        let (x, y) = self.vehicle.get_visual_center();
+        if self.on_main_road() { x } else { -y }
+    }
+
+    fn past_zone(&self) -> bool {
+        self.on_main_road() && self.vehicle.get_visual_center().0 - HALF_LENGTH > ZONE_END
+    }
+
+    /// Pixels until the vehicle's front reaches the point it must stop at without a booking
+    fn distance_to_stop_line(&self) -> f32 {
+        let (x, y) = self.vehicle.get_visual_center();
+        if self.on_main_road() {
+            ZONE_START - (x + HALF_LENGTH)
+        } else {
+            (y - HALF_LENGTH) - (LANE_EDGE + RAMP_STOP_GAP)
+        }
+    }
+}
+
+/// A one-lane highway with an on-ramp. Main road and ramp vehicles book time
+/// slots on the cells of the merge zone just like crossing vehicles book the
+/// intersection grid, and may only drive into the zone with a booking.
+pub struct MergeRoad {
+    vehicles: Vec<MergeVehicle>,
+    cells: Vec<Vec<TimeSlot>>, // one list of bookings per zone cell
+    config: Config,
+    rng: StdRng,
+    collided: HashSet<(usize, usize)>,
+
+    pub main_passed: u32,
+    pub ramp_passed: u32,
+    pub main_wait: f32,
+    pub ramp_wait: f32,
+    pub total_velocities: f32,
+    pub max_velocity_recorded: f32,
+    pub min_velocity_recorded: f32,
+    pub collisions: u32,
+    pub pending_main: u32,
+    pub pending_ramp: u32,
+}
+
+impl MergeRoad {
+    pub fn new(config: &Config, seed: u64) -> Self {
+        let cell_count = ((ZONE_END - ZONE_START) / CELL_PX) as usize;
+        Self {
+            vehicles: Vec::new(),
+            cells: vec![Vec::new(); cell_count],
+            config: config.clone(),
+            rng: StdRng::seed_from_u64(seed),
+            collided: HashSet::new(),
+            main_passed: 0,
+            ramp_passed: 0,
+            main_wait: 0.0,
+            ramp_wait: 0.0,
+            total_velocities: 0.0,
+            max_velocity_recorded: 0.0,
+            min_velocity_recorded: f32::MAX,
+            collisions: 0,
+            pending_main: 0,
+            pending_ramp: 0,
+        }
+    }
+
+    /// Queue a vehicle on the main road or the ramp; it appears once its spawn area is clear
+    pub fn request_spawn(&mut self, from_ramp: bool) {
+        if from_ramp {
+            self.pending_ramp += 1;
+        } else {
+            self.pending_main += 1;
+        }
+    }
+
+    pub fn update(&mut self, current_time: f32) {
+        for slots in &mut self.cells {
+            slots.retain(|slot| slot.end >= current_time);
+        }
+        self.spawn_pending(current_time);
+
+        let following = self.following_speeds();
+        for (i, &following_speed) in following.iter().enumerate() {
+            let past_zone = self.vehicles[i].past_zone();
+            if self.vehicles[i].granted.is_none() && !past_zone {
+                self.request_zone(i, current_time);
+            }
+            // Hold the booked speed unless the vehicle ahead is slower; without
+            // a booking, stop at the line (at most one frame of travel away)
+            let speed = match self.vehicles[i].granted {
+                Some(granted) if granted < following_speed => granted,
+                Some(_) => following_speed,
+                None if !past_zone && self.vehicles[i].distance_to_stop_line() <= 10.0 => {
+                    Velocity::STOPPED
+                }
+                None => following_speed,
+            };
+
+            // Speed changes are immediate so bookings hold from a standstill
+            let merge_vehicle = &mut self.vehicles[i];
+            merge_vehicle.vehicle.current_speed = speed.min(merge_vehicle.vehicle.top_speed());
+            let before = merge_vehicle.vehicle.get_visual_center();
+            let had_turned = merge_vehicle.vehicle.has_turned;
+            merge_vehicle.vehicle.update();
+            if !had_turned && merge_vehicle.vehicle.has_turned {
+                // Settle exactly onto the main road's centre line
+                merge_vehicle.vehicle.position.1 =
+                    LANE_Y - merge_vehicle.vehicle.height as f32 / 2.0;
+            }
+            let after = merge_vehicle.vehicle.get_visual_center();
+            merge_vehicle.distance += (after.0 - before.0).abs() + (after.1 - before.1).abs();
+            if speed == Velocity::STOPPED {
+                merge_vehicle.vehicle.waiting_time += 1.0 / 60.0;
+            }
+            if merge_vehicle.granted.is_some() && merge_vehicle.past_zone() {
+                merge_vehicle.granted = None;
+                let id = merge_vehicle.vehicle.id;
+                for slots in &mut self.cells {
+                    slots.retain(|slot| slot.vehicle_id != id);
+                }
+            }
+        }
+
+        self.detect_collisions();
+        self.remove_finished(current_time);
+    }
+
+    /// Car-following on each road: slow down or stop behind the nearest vehicle
+    /// ahead. Across the join point, a ramp vehicle poking into the lane is ahead
+    /// of main traffic, and a main road vehicle covering the join is ahead of ramp
+    /// traffic that hasn't reached the lane yet, so a merge that runs late can't
+    /// be driven into and the two roads never wait on each other.
+    fn following_speeds(&self) -> Vec<Velocity> {
+        self.vehicles
+            .iter()
+            .map(|current| {
+                let leader = self
+                    .vehicles
+                    .iter()
+                    .filter(|other| other.vehicle.id != current.vehicle.id)
+                    .filter_map(|other| {
+                        if other.on_main_road() == current.on_main_road() {
+                            let gap = other.progress() - current.progress();
+                            (gap > 0.0).then_some((gap, other))
+                        } else if current.on_main_road() {
+                            let (x, _) = current.vehicle.get_visual_center();
+                            let (_, other_y) = other.vehicle.get_visual_center();
+                            let in_lane = other_y - HALF_LENGTH < LANE_EDGE + POKE_MARGIN;
+                            (in_lane && x < JOIN_X).then_some((JOIN_X - x, other))
+                        } else {
+                            let (_, y) = current.vehicle.get_visual_center();
+                            let (other_x, _) = other.vehicle.get_visual_center();
+                            let before_lane = y - HALF_LENGTH >= LANE_EDGE + POKE_MARGIN;
+                            let at_join = other_x > JOIN_X - JOIN_CLEARANCE
+                                && other_x < JOIN_X + 2.0 * HALF_LENGTH;
+                            (before_lane && at_join).then_some((y - LANE_Y, other))
+                        }
+                    })
+                    .min_by(|a, b| a.0.total_cmp(&b.0));
+                let Some((distance, _)) = leader else {
+                    return Velocity::FAST;
+                };
+                let required = current
+                    .vehicle
+                    .get_safe_following_distance(self.config.weather);
+                if distance < required * 0.7 {
+                    Velocity::STOPPED
+                } else if distance < required * 0.8 {
+                    Velocity::MEDIUM
+                } else {
+                    Velocity::FAST
+                }
+            })
+            .collect()
+    }
+
+    /// Try to book the merge zone cells for vehicle `i`, fastest speed first
+    fn request_zone(&mut self, i: usize, current_time: f32) {
+        let merge_vehicle = &self.vehicles[i];
+        if merge_vehicle.distance_to_stop_line() > REQUEST_DISTANCE {
+            return;
+        }
+        let margin = (BASE_MARGIN
+            + self.config.slot_padding
+            + self.config.weather.reservation_margin()
+            + merge_vehicle.vehicle.profile.reservation_padding())
+        .max(0.0);
+
+        let top_speed = merge_vehicle.vehicle.top_speed();
+        let speeds = std::iter::once(top_speed).chain(
+            Velocity::PRESETS
+                .into_iter()
+                .filter(|&preset| preset < top_speed),
+        );
+        for speed in speeds {
+            let windows = self.zone_windows(merge_vehicle, speed, current_time, margin);
+            let free = windows.iter().all(|&(cell, start, end)| {
+                self.cells[cell]
+                    .iter()
+                    .all(|slot| end <= slot.start || slot.end <= start)
+            });
+            if free {
+                let vehicle_id = merge_vehicle.vehicle.id;
+                for (cell, start, end) in windows {
+                    self.cells[cell].push(TimeSlot {
+                        start,
+                        end,
+                        vehicle_id,
+                    });
+                }
+                self.vehicles[i].granted = Some(speed);
+                return;
+            }
+        }
+    }
+
+    /// (cell, start, end) of every zone cell the vehicle would cover at `speed`
+    fn zone_windows(
+        &self,
+        merge_vehicle: &MergeVehicle,
+        speed: Velocity,
+        current_time: f32,
+        margin: f32,
+    ) -> Vec<(usize, f32, f32)> {
+        if speed == Velocity::STOPPED {
+            return Vec::new();
+        }
+        let pixels_per_second = speed.pixels_per_second();
+
+        // Ramp vehicles reach the main road at the join point after driving up the ramp
+        let (centre, arrival) = if merge_vehicle.on_main_road() {
+            (merge_vehicle.vehicle.get_visual_center().0, current_time)
+        } else {
+            let (_, y) = merge_vehicle.vehicle.get_visual_center();
+            (
+                JOIN_X,
+                current_time + (y - LANE_Y).max(0.0) / pixels_per_second,
+            )
+        };
+        let (front, rear) = (centre + HALF_LENGTH, centre - HALF_LENGTH);
+        // A ramp vehicle's body crosses the lane before it turns
+        let sweep = if merge_vehicle.on_main_road() {
+            0.0
+        } else {
+            (HALF_LENGTH + HALF_WIDTH) / pixels_per_second
+        };
+
+        (0..self.cells.len())
+            .filter_map(|cell| {
+                let c0 = ZONE_START + cell as f32 * CELL_PX;
+                let c1 = c0 + CELL_PX;
+                if c1 <= rear {
+                    return None;
+                }
+                let start = if c0 < front && c1 > rear {
+                    arrival - sweep
+                } else {
+                    arrival + (c0 - front).max(0.0) / pixels_per_second
+                };
+                let end = arrival + (c1 - rear) / pixels_per_second;
+                Some((cell, start - margin, end + margin))
+            })
+            .collect()
+    }
+
+    fn spawn_pending(&mut self, current_time: f32) {
+        let main_spawn = (0.0, LANE_Y - HALF_LENGTH);
+        let ramp_spawn = (JOIN_X - HALF_WIDTH, 980.0);
+        if self.pending_main > 0 && self.spawn_area_clear(main_spawn) {
+            self.pending_main -= 1;
+            self.create_vehicle(false, main_spawn, current_time);
+        }
+        if self.pending_ramp > 0 && self.spawn_area_clear(ramp_spawn) {
+            self.pending_ramp -= 1;
+            self.create_vehicle(true, ramp_spawn, current_time);
+        }
+    }
+
+    fn spawn_area_clear(&self, position: (f32, f32)) -> bool {
+        self.vehicles.iter().all(|other| {
+            let (x, y) = other.vehicle.position;
+            (x - position.0).abs() + (y - position.1).abs() > 150.0
+        })
+    }
+
+    fn create_vehicle(&mut self, from_ramp: bool, position: (f32, f32), current_time: f32) {
+        let profile = self.config.driver_mix.sample(&mut self.rng);
+        let vehicle = if from_ramp {
+            Vehicle::new(
+                Route::Right,
+                Direction::North,
+                position,
+                (JOIN_X, LANE_Y),
+                profile,
+            )
+        } else {
+            Vehicle::new(
+                Route::Straight,
+                Direction::East,
+                position,
+                (0.0, 0.0),
+                profile,
+            )
+        };
+        self.vehicles.push(MergeVehicle {
+            vehicle,
+            from_ramp,
+            granted: None,
+            spawned_at: current_time,
+            distance: 0.0,
+        });
+    }
+
+    fn detect_collisions(&mut self) {
+        for i in 0..self.vehicles.len() {
+            for j in i + 1..self.vehicles.len() {
+                let (a, b) = (&self.vehicles[i].vehicle, &self.vehicles[j].vehicle);
+                if a.overlaps(b) && self.collided.insert((a.id, b.id)) {
+                    self.collisions += 1;
+                }
+            }
+        }
+    }
+
+    fn remove_finished(&mut self, current_time: f32) {
+        let (finished, remaining): (Vec<_>, Vec<_>) = std::mem::take(&mut self.vehicles)
+            .into_iter()
+            .partition(|v| v.vehicle.is_outside_canvas());
+        self.vehicles = remaining;
+
+        for merge_vehicle in finished {
+            let velocity = merge_vehicle.distance / (current_time - merge_vehicle.spawned_at);
+            self.total_velocities += velocity;
+            self.max_velocity_recorded = self.max_velocity_recorded.max(velocity);
+            self.min_velocity_recorded = self.min_velocity_recorded.min(velocity);
+            if merge_vehicle.from_ramp {
+                self.ramp_passed += 1;
+                self.ramp_wait += merge_vehicle.vehicle.waiting_time;
+            } else {
+                self.main_passed += 1;
+                self.main_wait += merge_vehicle.vehicle.waiting_time;
+            }
+        }
+    }
+
+    pub fn get_final_stats(&self, simulated_time: f32) -> String {
+        let passed = self.main_passed + self.ramp_passed;
+        let average = |total: f32, count: u32| {
+            if count == 0 {
+                0.0
+            } else {
+                total / count as f32
+            }
+        };
+        format!(
+            "HIGHWAY MERGE STATS\n\nSimulated time: {:.0} s\nMain road vehicles passed: {}\nRamp vehicles merged: {}\nThroughput: {:.1} veh/min\nAverage Velocity: {:.1} px/s\nMax velocity recorded: {:.1} px/s\nMin velocity recorded: {:.1} px/s\nAverage wait (main road): {:.2} s\nAverage wait (ramp): {:.2} s\nWeather: {}\nCollisions: {}\nPending spawns: {}\nActive vehicles remaining: {}",
+            simulated_time,
+            self.main_passed,
+            self.ramp_passed,
+            passed as f32 / simulated_time * 60.0,
+            average(self.total_velocities, passed),
+            self.max_velocity_recorded,
+            if self.min_velocity_recorded == f32::MAX {
+                0.0
+            } else {
+                self.min_velocity_recorded
+            },
+            average(self.main_wait, self.main_passed),
+            average(self.ramp_wait, self.ramp_passed),
+            self.config.weather.name(),
+            self.collisions,
+            self.pending_main + self.pending_ramp,
+            self.vehicles.len(),
+        )
+    }
+}
+
+/// Run the merge scenario headlessly with Poisson arrivals on both roads and print its stats
+pub fn run_merge(config: &Config, [main_rate, ramp_rate]: [f32; 2]) {
+    let seed = config.seed.unwrap_or(DEFAULT_SEED);
+    let mut road = MergeRoad::new(config, seed);
+    let mut rng = StdRng::seed_from_u64(seed.wrapping_add(1));
+    let mut next_main = next_gap(main_rate, &mut rng);
+    let mut next_ramp = next_gap(ramp_rate, &mut rng);
+
+    for tick in 0..MERGE_TICKS {
+        let current_time = (tick + 1) as f32 / 60.0;
+        while next_main <= current_time {
+            road.request_spawn(false);
+            next_main += next_gap(main_rate, &mut rng);
+        }
+        while next_ramp <= current_time {
+            road.request_spawn(true);
+            next_ramp += next_gap(ramp_rate, &mut rng);
+        }
+        road.update(current_time);
+    }
+
+    println!("{}", road.get_final_stats(MERGE_TICKS as f32 / 60.0));
+}
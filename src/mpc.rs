@@ -0,0 +1,342 @@
+use crate::controller::{
+    CellOwners, Denial, DenialCause, IntersectionController, PermissionRequest, Timeline,
+};
+use crate::heatmap::Occupancy;
+use crate::layout::RoadLayout;
+use crate::reservation::{DEFAULT_FOOTPRINT_MARGIN, Footprint, ReservationController};
+use crate::route::{Direction, Route};
+use crate::velocities::Velocity;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Speed assumed gained per second inside the box, in pixels per second
+/// squared. Vehicles follow a plan in speed steps rather than exactly, so the
+/// time held for getting up to speed from a standstill assumes they pull away
+/// no harder than the most cautious driver.
+const BOX_ACCELERATION: f32 = 1000.0;
+/// Seconds between the entry times tried
+const TIME_STEP: f32 = 0.05;
+/// How far ahead a crossing is searched for, in seconds after the earliest
+/// possible entry
+const HORIZON: f32 = 4.0;
+/// Extra seconds held before and after each window, for the error of
+/// tracking a continuous profile with three speed levels
+const TRACKING_SLACK: f32 = 0.1;
+
+/// A booked crossing: when the vehicle reaches the box, the speed it
+/// crosses at, the fastest it can go and the seconds held either side
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct Trajectory {
+    entry: f32,
+    speed: Velocity,
+    top_speed: Velocity,
+    hold: f32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedPlans {
+    grid: serde_json::Value,
+    plans: Vec<(usize, Trajectory)>,
+}
+
+/// Speeds a vehicle can hold through the box, fastest first: its top speed,
+/// then each slower preset
+fn crossing_speeds(top_speed: Velocity) -> impl Iterator<Item = Velocity> {
+    std::iter::once(top_speed).chain(
+        Velocity::PRESETS
+            .into_iter()
+            .filter(move |&preset| preset < top_speed),
+    )
+}
+
+/// Model-predictive controller on the reservation grid. Instead of trying the
+/// three speeds from where the vehicle is now, it searches the time the
+/// vehicle reaches the box over a short horizon, from the earliest its
+/// acceleration allows, together with the speed it crosses at, and books the
+/// free slot that gets it out of the box soonest. On the way in the vehicle
+/// follows the plan with whichever speed level gets it to the line on time,
+/// re-planned every frame, so its average approach speed is continuous.
+pub struct MpcController {
+    grid: ReservationController,
+    plans: HashMap<usize, Trajectory>,
+    denial: Option<Denial>, // why the last request found no slot, until asked
+}
+
+impl MpcController {
+    pub fn new(layout: RoadLayout, path_padding: usize) -> Self {
+        Self {
+            grid: ReservationController::with_margins(
+                layout,
+                DEFAULT_FOOTPRINT_MARGIN,
+                path_padding,
+            ),
+            plans: HashMap::new(),
+            denial: None,
+        }
+    }
+
+    /// Window held on each cell of the footprint for a vehicle `distance`
+    /// away now entering at `entry`, crossing at `speed`. Cars enter the box
+    /// at whatever speed they approached at, so each window starts as if the
+    /// whole box were driven at `speed` and ends allowing for getting up to
+    /// `speed` from a standstill.
+    fn windows(
+        footprint: &Footprint,
+        distance: f32,
+        current_time: f32,
+        entry: f32,
+        speed: Velocity,
+        margin: f32,
+    ) -> Vec<((usize, usize), f32, f32)> {
+        let lag = speed.pixels_per_second() / (2.0 * BOX_ACCELERATION); // time lost getting up to speed
+        let hold = Self::hold(margin);
+        footprint
+            .windows(distance, current_time, entry, speed)
+            .map(|(cell, start, end)| (cell, start - hold, end + lag + hold))
+            .collect()
+    }
+
+    /// Seconds held before and after each window for a request's `margin`
+    fn hold(margin: f32) -> f32 {
+        margin.max(0.0) + TRACKING_SLACK
+    }
+
+    /// Book the crossing that gets out of the box soonest; without one, the
+    /// slots in the way of the one that would have
+    fn plan(
+        &mut self,
+        request: &PermissionRequest,
+        current_time: f32,
+    ) -> Result<Trajectory, Denial> {
+        if self.grid.path_closed_to(request) {
+            return Err(Denial::new(DenialCause::LaneClosed));
+        }
+        let footprint = self
+            .grid
+            .footprint(request.direction, request.route)
+            .ok_or(Denial::new(DenialCause::Rule))?;
+        let speed = if request.stopped {
+            Velocity::STOPPED
+        } else {
+            request.speed
+        };
+        let top_speed = request.top_speed;
+        let distance = request.distance_to_intersection.max(0.0);
+        let earliest =
+            current_time + speed.seconds_to_cover(distance, top_speed, request.acceleration);
+        let length = footprint.distance;
+
+        let mut candidates: Vec<(f32, Trajectory)> = (0..=(HORIZON / TIME_STEP) as usize)
+            .flat_map(|step| {
+                let entry = earliest + step as f32 * TIME_STEP;
+                crossing_speeds(top_speed).map(move |speed| {
+                    let exit = entry + length / speed.pixels_per_second();
+                    (
+                        exit,
+                        Trajectory {
+                            entry,
+                            speed,
+                            top_speed,
+                            hold: Self::hold(request.margin),
+                        },
+                    )
+                })
+            })
+            .collect();
+        candidates.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let windows = |trajectory: &Trajectory| {
+            Self::windows(
+                &footprint,
+                distance,
+                current_time,
+                trajectory.entry,
+                trajectory.speed,
+                request.margin,
+            )
+        };
+        let Some((_, trajectory)) = candidates
+            .iter()
+            .find(|(_, trajectory)| self.grid.can_reserve_windows(&windows(trajectory)))
+        else {
+            return Err(Denial {
+                cause: DenialCause::Conflict,
+                conflicts: self.grid.conflicts(&windows(&candidates[0].1)),
+            });
+        };
+
+        self.grid
+            .reserve_windows(request.vehicle_id, &windows(trajectory));
+        Ok(*trajectory)
+    }
+}
+
+impl IntersectionController for MpcController {
+    fn name(&self) -> &'static str {
+        "Model-predictive"
+    }
+
+    fn request_permission(
+        &mut self,
+        request: &PermissionRequest,
+        current_time: f32,
+    ) -> Option<Velocity> {
+        // A new request replaces whatever the vehicle held before
+        if self.plans.remove(&request.vehicle_id).is_some() {
+            self.grid.revoke(request.vehicle_id);
+        }
+        let trajectory = match self.plan(request, current_time) {
+            Ok(trajectory) => trajectory,
+            Err(denial) => {
+                self.denial = Some(denial);
+                return None;
+            }
+        };
+        self.denial = None;
+        self.plans.insert(request.vehicle_id, trajectory);
+        self.planned_speed(
+            request.vehicle_id,
+            request.distance_to_intersection,
+            current_time,
+        )
+    }
+
+    fn take_denial(&mut self) -> Option<Denial> {
+        self.denial.take()
+    }
+
+    /// On the way in, the fastest speed the vehicle can hold that doesn't
+    /// reach the line before the planned entry, or standing still if even
+    /// crawling would; in the box, the planned crossing speed
+    fn planned_speed(
+        &self,
+        vehicle_id: usize,
+        distance_to_intersection: f32,
+        current_time: f32,
+    ) -> Option<Velocity> {
+        let trajectory = self.plans.get(&vehicle_id)?;
+        let left = trajectory.entry - current_time;
+        if distance_to_intersection <= 0.0 || left <= 0.0 {
+            return Some(trajectory.speed);
+        }
+        let needed = distance_to_intersection / left;
+        Some(
+            crossing_speeds(trajectory.top_speed)
+                .find(|&speed| speed.pixels_per_second() <= needed)
+                .unwrap_or(Velocity::STOPPED),
+        )
+    }
+
+    fn paths_conflict(&self, a: (Direction, Route), b: (Direction, Route)) -> bool {
+        self.grid.paths_conflict(a, b)
+    }
+
+    fn notify_lane_closed(&mut self, direction: Direction, route: Route, closed: bool) {
+        self.grid.notify_lane_closed(direction, route, closed);
+    }
+
+    fn notify_progress(
+        &mut self,
+        vehicle_id: usize,
+        direction: Direction,
+        route: Route,
+        bounds: (f32, f32, f32, f32),
+        speed: Velocity,
+        current_time: f32,
+    ) {
+        self.grid
+            .notify_progress(vehicle_id, direction, route, bounds, speed, current_time);
+        // A vehicle held up on the way falls behind its plan; its windows
+        // and the entry it is steered to move back together
+        if let Some(trajectory) = self.plans.get_mut(&vehicle_id) {
+            trajectory.entry += self.grid.keep_on_schedule(
+                vehicle_id,
+                (direction, route),
+                bounds,
+                trajectory.speed,
+                trajectory.hold,
+                current_time,
+            );
+        }
+    }
+
+    fn take_withdrawn(&mut self) -> Vec<usize> {
+        let withdrawn = self.grid.take_withdrawn();
+        for vehicle_id in &withdrawn {
+            self.plans.remove(vehicle_id);
+        }
+        withdrawn
+    }
+
+    fn notify_exit(&mut self, vehicle_id: usize, current_time: f32) {
+        self.plans.remove(&vehicle_id);
+        self.grid.notify_exit(vehicle_id, current_time);
+    }
+
+    fn notify_blocked(
+        &mut self,
+        vehicle_id: usize,
+        bounds: (f32, f32, f32, f32),
+        current_time: f32,
+        until: f32,
+    ) {
+        self.grid
+            .notify_blocked(vehicle_id, bounds, current_time, until);
+    }
+
+    fn notify_unblocked(&mut self, vehicle_id: usize) {
+        self.grid.notify_unblocked(vehicle_id);
+    }
+
+    fn booked_over(&self, bounds: (f32, f32, f32, f32), from: f32, until: f32) -> Vec<usize> {
+        self.grid.booked_over(bounds, from, until)
+    }
+
+    fn revoke(&mut self, vehicle_id: usize) {
+        self.plans.remove(&vehicle_id);
+        self.grid.revoke(vehicle_id);
+    }
+
+    fn clear_bookings(&mut self) {
+        self.grid.clear_bookings();
+    }
+
+    fn reservation_window(&self, vehicle_id: usize) -> Option<(f32, f32)> {
+        self.grid.reservation_window(vehicle_id)
+    }
+
+    fn timeline(&self) -> Option<Timeline> {
+        self.grid.timeline()
+    }
+
+    fn cell_owners(&self, current_time: f32) -> Option<CellOwners> {
+        self.grid.cell_owners(current_time)
+    }
+
+    fn booked_slots(&self) -> Option<usize> {
+        self.grid.booked_slots()
+    }
+
+    fn occupancy(&self) -> Option<Occupancy> {
+        self.grid.occupancy()
+    }
+
+    fn tick(&mut self, current_time: f32) {
+        self.grid.tick(current_time);
+    }
+
+    fn save_state(&self) -> Option<serde_json::Value> {
+        serde_json::to_value(SavedPlans {
+            grid: self.grid.save_state()?,
+            plans: self.plans.iter().map(|(&id, &plan)| (id, plan)).collect(),
+        })
+        .ok()
+    }
+
+    fn restore_state(&mut self, state: serde_json::Value) -> Result<(), String> {
+        let saved: SavedPlans = serde_json::from_value(state).map_err(|e| e.to_string())?;
+        self.grid.restore_state(saved.grid)?;
+        self.plans = saved.plans.into_iter().collect();
+        Ok(())
+    }
+}
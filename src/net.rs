@@ -0,0 +1,317 @@
+use crate::camera::{Camera, vehicle_at};
+use crate::intersection::SmartIntersection;
+use crate::layout::RoadLayout;
+use crate::render::{FrameLimiter, FrameRate, FrameView, Renderer};
+use crate::replay::{FrameSnapshot, VehicleSnapshot};
+use crate::route::Direction;
+use crate::vehicle::Vehicle;
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+use sdl2::mouse::MouseButton;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, SyncSender, TryRecvError, TrySendError};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Frames between state updates sent to the client (20 per second)
+const STATE_INTERVAL: u32 = 3;
+
+/// Spawn requests taken from the client per tick; extra ones that arrived in
+/// the same tick are dropped
+const MAX_CLIENT_SPAWNS: usize = 2;
+
+/// State updates waiting for a slow client before newer ones are skipped
+const SEND_BACKLOG: usize = 4;
+
+/// Longest line (bytes) read from a client; a spawn request takes a few dozen
+const MAX_CLIENT_LINE: u64 = 1024;
+/// Longest line (bytes) read from the host, which sends every vehicle at once
+const MAX_STATE_LINE: u64 = 16 * 1024 * 1024;
+
+/// What a client asks the host to do
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    Spawn { direction: Option<Direction> }, // None = random side
+}
+
+/// Read newline-delimited JSON messages on a background thread. A peer
+/// sending a line longer than `max_line` bytes is disconnected.
+fn spawn_reader<T: DeserializeOwned + Send + 'static>(
+    stream: TcpStream,
+    max_line: u64,
+) -> Receiver<T> {
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut reader = BufReader::new(stream);
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            match (&mut reader)
+                .take(max_line + 1)
+                .read_until(b'\n', &mut line)
+            {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
+            }
+            if line.last() != Some(&b'\n') && line.len() as u64 > max_line {
+                warn!("Dropping peer: line longer than {} bytes", max_line);
+                let _ = reader.get_ref().shutdown(Shutdown::Both);
+                break;
+            }
+            match serde_json::from_slice(&line) {
+                Ok(message) => {
+                    if sender.send(message).is_err() {
+                        break;
+                    }
+                }
+                Err(e) => warn!("Ignoring bad network message: {}", e),
+            }
+        }
+    });
+    receiver
+}
+
+/// Write already serialized lines on a background thread, so a slow client
+/// never holds up the simulation. The thread ends when the sender is dropped
+/// or a write fails.
+fn spawn_writer(mut stream: TcpStream) -> SyncSender<Vec<u8>> {
+    let (sender, receiver) = mpsc::sync_channel::<Vec<u8>>(SEND_BACKLOG);
+    std::thread::spawn(move || {
+        for line in receiver {
+            if let Err(e) = stream.write_all(&line) {
+                warn!("Co-op client dropped: {}", e);
+                break;
+            }
+        }
+    });
+    sender
+}
+
+fn to_line<T: Serialize>(message: &T) -> serde_json::Result<Vec<u8>> {
+    let mut line = serde_json::to_vec(message)?;
+    line.push(b'\n');
+    Ok(line)
+}
+
+fn send_line<T: Serialize>(stream: &mut TcpStream, message: &T) -> std::io::Result<()> {
+    stream.write_all(&to_line(message)?)
+}
+
+struct Client {
+    lines: SyncSender<Vec<u8>>,
+    messages: Receiver<ClientMessage>,
+}
+
+/// Host side of co-op mode: accepts one client at a time, applies its spawn
+/// requests and streams the simulation state back to it
+pub struct NetHost {
+    listener: TcpListener,
+    client: Option<Client>,
+    frames: u32,
+}
+
+impl NetHost {
+    /// Listen on `bind` (loopback unless `--host-bind` says otherwise, since
+    /// clients are not authenticated)
+    pub fn listen(bind: &str, port: u16) -> Result<Self, String> {
+        let listener = TcpListener::bind((bind, port))
+            .map_err(|e| format!("Failed to listen on {}:{}: {}", bind, port, e))?;
+        listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+        info!("Waiting for a co-op client on {}:{}", bind, port);
+        Ok(Self {
+            listener,
+            client: None,
+            frames: 0,
+        })
+    }
+
+    /// Accept a waiting client and return the spawns it has asked for since the
+    /// last call, at most `MAX_CLIENT_SPAWNS` of them
+    pub fn poll_spawns(&mut self) -> Vec<Option<Direction>> {
+        if self.client.is_none()
+            && let Ok((stream, address)) = self.listener.accept()
+        {
+            match self.connect(stream) {
+                Ok(client) => {
+                    info!("Co-op client connected from {}", address);
+                    self.client = Some(client);
+                }
+                Err(e) => warn!("Co-op client {} rejected: {}", address, e),
+            }
+        }
+
+        let mut spawns = Vec::new();
+        let Some(client) = &self.client else {
+            return spawns;
+        };
+        loop {
+            match client.messages.try_recv() {
+                Ok(ClientMessage::Spawn { direction }) => {
+                    if spawns.len() < MAX_CLIENT_SPAWNS {
+                        spawns.push(direction);
+                    }
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    info!("Co-op client disconnected");
+                    self.client = None;
+                    break;
+                }
+            }
+        }
+        spawns
+    }
+
+    fn connect(&self, stream: TcpStream) -> std::io::Result<Client> {
+        // Accepted sockets can inherit the listener's non-blocking mode
+        stream.set_nonblocking(false)?;
+        stream.set_nodelay(true)?;
+        // The writer thread gives up on a client that stops reading
+        stream.set_write_timeout(Some(Duration::from_secs(1)))?;
+        Ok(Client {
+            messages: spawn_reader(stream.try_clone()?, MAX_CLIENT_LINE),
+            lines: spawn_writer(stream),
+        })
+    }
+
+    /// Send the current state to the client every few frames
+    pub fn send_state(&mut self, current_time: f32, intersection: &SmartIntersection) {
+        self.frames += 1;
+        let Some(client) = &mut self.client else {
+            return;
+        };
+        if !self.frames.is_multiple_of(STATE_INTERVAL) {
+            return;
+        }
+
+        let line = match to_line(&FrameSnapshot::new(current_time, intersection)) {
+            Ok(line) => line,
+            Err(e) => {
+                warn!("Failed to encode co-op state: {}", e);
+                return;
+            }
+        };
+        match client.lines.try_send(line) {
+            // A client still behind on earlier states skips this one
+            Ok(()) | Err(TrySendError::Full(_)) => {}
+            // The writer thread gave up after a failed write
+            Err(TrySendError::Disconnected(_)) => self.client = None,
+        }
+    }
+}
+
+/// Client side of co-op mode: draw the host's simulation and forward key
+/// presses as spawn requests. Returns when the window is closed or the host leaves.
+pub fn run_client(
+    sdl_context: &sdl2::Sdl,
+    renderer: &mut dyn Renderer,
+    address: &str,
+    window_width: u32,
+    frame_rate: FrameRate,
+) -> Result<(), String> {
+    let mut stream = TcpStream::connect(address)
+        .map_err(|e| format!("Failed to connect to {}: {}", address, e))?;
+    stream.set_nodelay(true).map_err(|e| e.to_string())?;
+    let states: Receiver<FrameSnapshot> = spawn_reader(
+        stream.try_clone().map_err(|e| e.to_string())?,
+        MAX_STATE_LINE,
+    );
+    info!("Connected to co-op host {}", address);
+
+    let mut vehicles: Vec<Vehicle> = Vec::new();
+    let mut layout = RoadLayout::default();
+    let mut camera = Camera::new(window_width);
+    let mut show_minimap = false;
+    let mut event_pump = sdl_context.event_pump()?;
+    let mut limiter = FrameLimiter::new(frame_rate);
+
+    'running: loop {
+        for event in event_pump.poll_iter() {
+            let request = match event {
+                Event::Quit { .. }
+                | Event::KeyDown {
+                    keycode: Some(Keycode::Escape),
+                    ..
+                } => break 'running,
+                Event::MouseButtonDown {
+                    mouse_btn: MouseButton::Left,
+                    x,
+                    y,
+                    ..
+                } => {
+                    let shown: Vec<&Vehicle> = vehicles.iter().collect();
+                    camera.follow(vehicle_at(&shown, camera.to_world(x, y)));
+                    None
+                }
+                Event::KeyDown {
+                    keycode: Some(key), ..
+                } => match key {
+                    Keycode::Up => Some(Some(Direction::North)),
+                    Keycode::Down => Some(Some(Direction::South)),
+                    Keycode::Right => Some(Some(Direction::East)),
+                    Keycode::Left => Some(Some(Direction::West)),
+                    Keycode::Space => Some(None),
+                    Keycode::Tab => {
+                        show_minimap = !show_minimap;
+                        None
+                    }
+                    _ => None,
+                },
+                _ => None,
+            };
+            if let Some(direction) = request {
+                send_line(&mut stream, &ClientMessage::Spawn { direction })
+                    .map_err(|e| format!("Lost connection to host: {}", e))?;
+            }
+        }
+
+        // Only the newest state matters
+        loop {
+            match states.try_recv() {
+                Ok(state) => {
+                    layout = state.layout;
+                    vehicles = state
+                        .vehicles
+                        .iter()
+                        .map(VehicleSnapshot::to_vehicle)
+                        .collect();
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    info!("Co-op host closed the connection");
+                    break 'running;
+                }
+            }
+        }
+
+        let shown: Vec<&Vehicle> = vehicles.iter().collect();
+        camera.update(&shown);
+        renderer.draw_frame(&FrameView {
+            vehicles: &shown,
+            camera: &camera,
+            show_minimap: show_minimap || camera.is_following(),
+            info_lines: None,
+            ghosts: &[],
+            log_lines: None,
+            layout,
+            timeline: None,
+            heatmap: None,
+            cell_owners: None,
+            hud_lines: None,
+            onboarding: false,
+            obstacles: &[],
+            roadworks: Vec::new(),
+            hazards: &[],
+            close_call_spots: &[],
+            entry_queues: Vec::new(),
+            vehicle_labels: false,
+            profile: None,
+        })?;
+        limiter.wait();
+    }
+    Ok(())
+}
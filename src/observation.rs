@@ -0,0 +1,147 @@
+use crate::route::{Direction, Route};
+use crate::vehicle::Vehicle;
+use crate::velocities::Velocity;
+use ndarray::{Array2, ArrayViewMut1};
+use serde::Deserialize;
+
+/// Numbers describing each vehicle
+pub const VEHICLE_FEATURES: usize = 13;
+/// Incoming lanes: every approach with every route, in `LANE_ORDER`
+pub const LANES: usize = 12;
+/// Road between the edge of the window and the stop line
+const APPROACH_LENGTH: f32 = 350.0;
+/// The intersection box the occupancy grid covers
+const BOX_MIN: f32 = 350.0;
+const BOX_SIZE: f32 = 300.0;
+
+const HEADINGS: [Direction; 4] = [
+    Direction::North,
+    Direction::East,
+    Direction::South,
+    Direction::West,
+];
+const ROUTES: [Route; 3] = [Route::Right, Route::Straight, Route::Left];
+
+/// Sizes of the encoded tensors; anything left out of a JSON object keeps
+/// its default
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct Encoding {
+    pub max_vehicles: usize, // vehicle rows, nearest the box first
+    pub lane_cells: usize,   // cells along each incoming lane
+    pub grid: usize,         // cells per side of the box; 0 leaves the grid out
+}
+
+impl Default for Encoding {
+    fn default() -> Self {
+        Self {
+            max_vehicles: 16,
+            lane_cells: 10,
+            grid: 10,
+        }
+    }
+}
+
+/// The road at one moment as fixed-size tensors
+#[derive(Debug, Clone)]
+pub struct Tensors {
+    pub vehicles: Array2<f32>, // max_vehicles × VEHICLE_FEATURES, zero rows for empty slots
+    pub lanes: Array2<f32>,    // LANES × lane_cells, 1 where a queued or approaching car is
+    pub grid: Array2<f32>,     // grid × grid over the box, 1 where any vehicle is
+    pub slots: Vec<usize>,     // id of the vehicle in each filled row of `vehicles`
+}
+
+/// Encode the vehicles on the road. Vehicles past the intersection are left
+/// out of the vehicle rows and lanes: nothing decided at the box concerns
+/// them any more.
+pub fn encode(vehicles: &[&Vehicle], encoding: &Encoding) -> Tensors {
+    let mut approaching: Vec<&Vehicle> = vehicles
+        .iter()
+        .copied()
+        .filter(|vehicle| !vehicle.is_past_intersection())
+        .collect();
+    approaching.sort_by(|a, b| {
+        a.distance_to_intersection()
+            .total_cmp(&b.distance_to_intersection())
+    });
+    approaching.truncate(encoding.max_vehicles);
+
+    let mut rows = Array2::zeros((encoding.max_vehicles, VEHICLE_FEATURES));
+    for (row, vehicle) in rows.rows_mut().into_iter().zip(&approaching) {
+        encode_vehicle(vehicle, row);
+    }
+
+    let mut lanes = Array2::zeros((LANES, encoding.lane_cells));
+    if encoding.lane_cells > 0 {
+        let cell = APPROACH_LENGTH / encoding.lane_cells as f32;
+        for vehicle in vehicles {
+            if vehicle.has_turned || vehicle.is_in_intersection() {
+                continue; // only cars still on their incoming lane
+            }
+            let distance = vehicle.distance_to_intersection();
+            if distance <= 0.0 || distance >= APPROACH_LENGTH {
+                continue;
+            }
+            lanes[[lane_index(vehicle), (distance / cell) as usize]] = 1.0;
+        }
+    }
+
+    let size = encoding.grid;
+    let mut grid = Array2::zeros((size, size));
+    if size > 0 {
+        let cell = BOX_SIZE / size as f32;
+        let index = |at: f32| ((at - BOX_MIN) / cell).clamp(0.0, (size - 1) as f32) as usize;
+        for vehicle in vehicles {
+            if !vehicle.is_in_intersection() {
+                continue;
+            }
+            let (x, y, w, h) = vehicle.get_visual_bounds();
+            for row in index(y)..=index(y + h) {
+                for col in index(x)..=index(x + w) {
+                    grid[[row, col]] = 1.0;
+                }
+            }
+        }
+    }
+
+    Tensors {
+        vehicles: rows,
+        lanes,
+        grid,
+        slots: approaching.iter().map(|vehicle| vehicle.id).collect(),
+    }
+}
+
+/// Row of `Tensors::lanes` for a car still on its incoming lane
+fn lane_index(vehicle: &Vehicle) -> usize {
+    let heading = HEADINGS
+        .iter()
+        .position(|&direction| direction == vehicle.direction)
+        .unwrap_or(0);
+    let route = ROUTES
+        .iter()
+        .position(|&route| route == vehicle.route)
+        .unwrap_or(0);
+    heading * ROUTES.len() + route
+}
+
+/// Present flag, heading (N, E, S, W) and route (right, straight, left) one
+/// hot, distance to the stop line, speed as a share of full speed, and
+/// whether it is waiting, allowed through and inside the box
+fn encode_vehicle(vehicle: &Vehicle, mut row: ArrayViewMut1<f32>) {
+    let flag = |on: bool| if on { 1.0 } else { 0.0 };
+    let in_box = vehicle.is_in_intersection();
+    let features = std::iter::once(1.0)
+        .chain(HEADINGS.map(|direction| flag(vehicle.direction == direction)))
+        .chain(ROUTES.map(|route| flag(vehicle.route == route)))
+        .chain([
+            (vehicle.distance_to_intersection() / APPROACH_LENGTH).min(1.0),
+            vehicle.current_speed.pixels_per_frame() / Velocity::FAST.pixels_per_frame(),
+            flag(vehicle.current_speed == Velocity::STOPPED && !in_box),
+            flag(vehicle.intersection_permission),
+            flag(in_box),
+        ]);
+    for (value, feature) in row.iter_mut().zip(features) {
+        *value = feature;
+    }
+}
@@ -0,0 +1,82 @@
+use crate::layout::RoadLayout;
+use crate::vehicle::allocate_id;
+use serde::{Deserialize, Serialize};
+
+/// What the user dropped on the road
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ObstacleKind {
+    StalledCar,
+    Debris,
+}
+
+impl ObstacleKind {
+    /// Length along the lane and width across it, px
+    fn size(self) -> (f32, f32) {
+        match self {
+            ObstacleKind::StalledCar => (70.0, 40.0),
+            ObstacleKind::Debris => (30.0, 30.0),
+        }
+    }
+
+    pub fn color(self) -> (u8, u8, u8) {
+        match self {
+            ObstacleKind::StalledCar => (255, 140, 0),
+            ObstacleKind::Debris => (120, 100, 80),
+        }
+    }
+}
+
+/// A static obstacle sitting on a lane until it is cleared. It takes an id
+/// from the vehicle sequence so the controller can hold cells under it like
+/// under a broken-down vehicle.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Obstacle {
+    pub id: usize,
+    pub kind: ObstacleKind,
+    pub area: (f32, f32, f32, f32), // world x, y, width, height
+}
+
+impl Obstacle {
+    /// An obstacle on the lane under the world point `(x, y)`, centred across
+    /// the lane and lying along it; None off the road or on a closed arm
+    pub fn place(kind: ObstacleKind, (x, y): (f32, f32), layout: RoadLayout) -> Option<Self> {
+        if let Some((cx, cy, cw, ch)) = layout.closed_area()
+            && x >= cx
+            && x < cx + cw
+            && y >= cy
+            && y < cy + ch
+        {
+            return None;
+        }
+
+        let on_vertical_road = (350.0..650.0).contains(&x);
+        let on_horizontal_road = (350.0..650.0).contains(&y);
+        let (length, width) = kind.size();
+        let area = match (on_vertical_road, on_horizontal_road) {
+            (false, false) => return None,
+            (true, false) => centred((lane_centre(x), y), (width, length)),
+            (false, true) => centred((x, lane_centre(y)), (length, width)),
+            // Inside the intersection box, on the nearest crossing of two lanes
+            (true, true) => centred((lane_centre(x), lane_centre(y)), (width, length)),
+        };
+        Some(Self {
+            id: allocate_id(),
+            kind,
+            area,
+        })
+    }
+
+    pub fn centre(&self) -> (f32, f32) {
+        let (x, y, w, h) = self.area;
+        (x + w / 2.0, y + h / 2.0)
+    }
+}
+
+/// Centre line of the 50 px lane containing `coordinate` on a 350..650 road
+fn lane_centre(coordinate: f32) -> f32 {
+    350.0 + ((coordinate - 350.0) / 50.0).floor() * 50.0 + 25.0
+}
+
+fn centred((x, y): (f32, f32), (w, h): (f32, f32)) -> (f32, f32, f32, f32) {
+    (x - w / 2.0, y - h / 2.0, w, h)
+}
@@ -0,0 +1,93 @@
+use crate::i18n::tr;
+use crate::layout::RoadLayout;
+use std::path::Path;
+use tracing::warn;
+
+/// Created once the overlay has been dismissed, so it only opens by itself on
+/// the first launch
+const SEEN_MARKER: &str = ".smart_road_onboarded";
+
+/// Locale keys of the key help listed by the overlay; empty ones are spacers
+const CONTROL_KEYS: [&str; 19] = [
+    "onboarding.welcome",
+    "onboarding.spawn",
+    "onboarding.spawn_route",
+    "onboarding.spawn_all",
+    "onboarding.random",
+    "onboarding.level",
+    "onboarding.follow",
+    "onboarding.obstacles",
+    "onboarding.panels",
+    "onboarding.charts",
+    "onboarding.replay",
+    "onboarding.pause",
+    "onboarding.weather",
+    "onboarding.quit",
+    "",
+    "onboarding.intersection",
+    "onboarding.approach",
+    "",
+    "onboarding.close",
+];
+
+/// An area of the road highlighted by the overlay
+pub struct Zone {
+    pub label: String,
+    pub area: (f32, f32, f32, f32), // world x, y, width, height
+    pub color: (u8, u8, u8),
+}
+
+/// The intersection box and the approaches of the open arms. Vehicles ask for
+/// permission within 150 px of the box.
+pub fn zones(layout: RoadLayout) -> Vec<Zone> {
+    let intersection = Zone {
+        label: tr!("zone.intersection"),
+        area: (350.0, 350.0, 300.0, 300.0),
+        color: (0, 191, 255),
+    };
+    let approaches = [
+        (350.0, 200.0, 300.0, 150.0),
+        (350.0, 650.0, 300.0, 150.0),
+        (200.0, 350.0, 150.0, 300.0),
+        (650.0, 350.0, 150.0, 300.0),
+    ]
+    .into_iter()
+    .filter(|&area| !overlaps(layout.closed_area(), area))
+    .map(|area| Zone {
+        label: tr!("zone.approach"),
+        area,
+        color: (255, 200, 0),
+    });
+    std::iter::once(intersection).chain(approaches).collect()
+}
+
+/// The overlay's key help in the chosen language
+pub fn control_lines() -> Vec<String> {
+    CONTROL_KEYS
+        .iter()
+        .map(|key| {
+            if key.is_empty() {
+                String::new()
+            } else {
+                tr!(key)
+            }
+        })
+        .collect()
+}
+
+fn overlaps(closed: Option<(f32, f32, f32, f32)>, (x, y, w, h): (f32, f32, f32, f32)) -> bool {
+    closed.is_some_and(|(cx, cy, cw, ch)| x < cx + cw && cx < x + w && y < cy + ch && cy < y + h)
+}
+
+/// Whether the overlay has never been dismissed in this directory
+pub fn first_launch() -> bool {
+    !Path::new(SEEN_MARKER).exists()
+}
+
+/// Remember that the overlay was seen; failing to is only logged, the
+/// overlay then just opens again next time
+pub fn mark_seen() {
+    if let Err(e) = std::fs::write(SEEN_MARKER, "") {
+        warn!("Could not write {}: {}", SEEN_MARKER, e);
+    }
+}
@@ -0,0 +1,94 @@
+use crate::intersection::PhaseTimings;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Drawn frames kept for the frame-time graph
+pub const PROFILE_FRAMES: usize = 120;
+
+/// Wall-clock time spent on each part of one drawn frame
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FrameSample {
+    pub following: Duration,
+    pub reservation: Duration,
+    pub release: Duration,
+    pub movement: Duration,
+    pub render: Duration,
+}
+
+impl FrameSample {
+    /// Locale key and time of each part, in the order the graph stacks them
+    pub fn parts(&self) -> [(&'static str, Duration); 5] {
+        [
+            ("profile.following", self.following),
+            ("profile.reservation", self.reservation),
+            ("profile.release", self.release),
+            ("profile.movement", self.movement),
+            ("profile.render", self.render),
+        ]
+    }
+
+    pub fn total(&self) -> Duration {
+        self.parts().iter().map(|(_, time)| *time).sum()
+    }
+}
+
+/// Per-frame phase times for the on-screen profiler (F3), taken from the
+/// intersection's running totals so the simulation needs no extra bookkeeping
+pub struct FrameProfiler {
+    frames: VecDeque<FrameSample>,
+    last: PhaseTimings, // totals when the previous frame was recorded
+}
+
+impl FrameProfiler {
+    /// Start profiling from the intersection's current totals
+    pub fn new(totals: PhaseTimings) -> Self {
+        Self {
+            frames: VecDeque::with_capacity(PROFILE_FRAMES),
+            last: totals,
+        }
+    }
+
+    /// Close a frame: the simulation time is whatever the totals grew by since
+    /// the last call, however many ticks ran. Totals that went backwards (a
+    /// controller switch or a loaded checkpoint) count from zero.
+    pub fn record(&mut self, totals: PhaseTimings, render: Duration) {
+        let since = |now: Duration, then: Duration| now.checked_sub(then).unwrap_or(now);
+        let sample = FrameSample {
+            following: since(totals.following, self.last.following),
+            reservation: since(totals.reservation, self.last.reservation),
+            release: since(totals.release, self.last.release),
+            movement: since(totals.movement, self.last.movement),
+            render,
+        };
+        self.last = totals;
+        if self.frames.len() == PROFILE_FRAMES {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(sample);
+    }
+
+    /// Recorded frames, oldest first
+    pub fn frames(&self) -> &VecDeque<FrameSample> {
+        &self.frames
+    }
+
+    /// Mean of every recorded frame
+    pub fn average(&self) -> FrameSample {
+        let count = self.frames.len().max(1) as u32;
+        let mut sum = FrameSample::default();
+        for frame in &self.frames {
+            sum.following += frame.following;
+            sum.reservation += frame.reservation;
+            sum.release += frame.release;
+            sum.movement += frame.movement;
+            sum.render += frame.render;
+        }
+        FrameSample {
+            following: sum.following / count,
+            reservation: sum.reservation / count,
+            release: sum.release / count,
+            movement: sum.movement / count,
+            render: sum.render / count,
+        }
+    }
+}
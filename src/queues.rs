@@ -0,0 +1,69 @@
+use crate::route::Direction;
+use crate::vehicle::Vehicle;
+use crate::velocities::Velocity;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// The approaches in the order the stats table lists them
+pub const APPROACHES: [Direction; 4] = [
+    Direction::North,
+    Direction::South,
+    Direction::East,
+    Direction::West,
+];
+
+/// Queue length on one approach over the run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ApproachQueue {
+    pub max: usize,
+    pub average: f32,
+}
+
+/// Vehicles queued on each approach, measured every tick
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QueueTracker {
+    max: HashMap<Direction, usize>,
+    vehicle_seconds: HashMap<Direction, f32>, // queue length integrated over time
+    elapsed: f32,
+    last_time: Option<f32>,
+}
+
+impl QueueTracker {
+    /// Count the vehicles standing still before the intersection on each
+    /// approach and add them to the running totals
+    pub fn update(&mut self, vehicles: &[&Vehicle], current_time: f32) {
+        let dt = self
+            .last_time
+            .map_or(1.0 / 60.0, |last| current_time - last);
+        self.last_time = Some(current_time);
+
+        let mut lengths: HashMap<Direction, usize> = HashMap::new();
+        for vehicle in vehicles {
+            if vehicle.current_speed == Velocity::STOPPED
+                && !vehicle.entered_intersection
+                && vehicle.crashed_at.is_none()
+            {
+                *lengths.entry(vehicle.approach_direction()).or_default() += 1;
+            }
+        }
+        for direction in APPROACHES {
+            let length = lengths.get(&direction).copied().unwrap_or(0);
+            let max = self.max.entry(direction).or_default();
+            *max = (*max).max(length);
+            *self.vehicle_seconds.entry(direction).or_default() += length as f32 * dt;
+        }
+        self.elapsed += dt;
+    }
+
+    /// Longest and time-averaged queue on one approach so far
+    pub fn approach(&self, direction: Direction) -> ApproachQueue {
+        ApproachQueue {
+            max: self.max.get(&direction).copied().unwrap_or(0),
+            average: if self.elapsed > 0.0 {
+                self.vehicle_seconds.get(&direction).copied().unwrap_or(0.0) / self.elapsed
+            } else {
+                0.0
+            },
+        }
+    }
+}
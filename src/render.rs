@@ -0,0 +1,1324 @@
+use crate::animation::{Animations, Pose, SheetLayout};
+use crate::assets::PANEL_FONT;
+#[cfg(feature = "image")]
+use crate::assets::{AssetWatcher, WatchedImage, load_car_texture, load_road_texture};
+use crate::camera::Camera;
+use crate::controller::{CellOwners, Timeline};
+use crate::hazard::Hazard;
+use crate::heatmap::{Occupancy, heat_color};
+use crate::i18n::tr;
+use crate::layout::RoadLayout;
+use crate::obstacle::Obstacle;
+use crate::onboarding::{control_lines, zones};
+use crate::profiler::{FrameProfiler, PROFILE_FRAMES};
+use crate::route::Direction;
+use crate::skid_marks::{SkidMark, SkidMarks};
+use crate::text::{Fonts, TextFont};
+use crate::vehicle::Vehicle;
+use sdl2::pixels::Color;
+use sdl2::pixels::PixelFormatEnum;
+use sdl2::rect::{Point, Rect};
+use sdl2::render::{BlendMode, Canvas, Texture, TextureCreator};
+use sdl2::surface::Surface;
+use sdl2::video::{Window, WindowContext};
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+#[cfg(feature = "image")]
+use tracing::info;
+use tracing::{trace_span, warn};
+
+/// The drawing backends selectable with `--renderer`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum RendererKind {
+    #[default]
+    Sdl,
+    Wgpu,
+}
+
+impl RendererKind {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.to_lowercase().as_str() {
+            "sdl" | "sdl2" => Ok(RendererKind::Sdl),
+            "wgpu" => Ok(RendererKind::Wgpu),
+            _ => Err(format!(
+                "Unknown renderer '{}' (expected sdl or wgpu)",
+                name
+            )),
+        }
+    }
+}
+
+/// How often frames are presented (`--no-vsync`, `--fps`)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameRate {
+    pub vsync: bool,
+    pub fps: Option<u32>, // None = uncapped
+}
+
+impl Default for FrameRate {
+    fn default() -> Self {
+        Self {
+            vsync: true,
+            fps: Some(60),
+        }
+    }
+}
+
+impl FrameRate {
+    /// Parse `--fps`: a positive number of frames per second or `uncapped`
+    pub fn parse_fps(text: &str) -> Result<Option<u32>, String> {
+        match text {
+            "uncapped" | "0" => Ok(None),
+            _ => text
+                .parse::<u32>()
+                .map(Some)
+                .map_err(|_| format!("Invalid --fps '{}' (expected a number or uncapped)", text)),
+        }
+    }
+}
+
+/// Sleeps out whatever is left of each frame at the target rate. It waits
+/// for a deadline rather than a fixed delay, so time already spent blocked
+/// on vsync counts towards the frame instead of adding to it.
+pub struct FrameLimiter {
+    frame: Option<Duration>,
+    next: Instant,
+}
+
+impl FrameLimiter {
+    pub fn new(rate: FrameRate) -> Self {
+        Self {
+            frame: rate
+                .fps
+                .map(|fps| Duration::from_secs_f64(1.0 / fps as f64)),
+            next: Instant::now(),
+        }
+    }
+
+    pub fn wait(&mut self) {
+        let Some(frame) = self.frame else {
+            return;
+        };
+        let now = Instant::now();
+        if self.next > now {
+            std::thread::sleep(self.next - now);
+        }
+        // A late frame starts the schedule afresh rather than rushing to catch up
+        self.next = self.next.max(now) + frame;
+    }
+}
+
+/// Everything shown in one frame
+pub struct FrameView<'a> {
+    pub vehicles: &'a [&'a Vehicle],
+    pub camera: &'a Camera,
+    pub show_minimap: bool,
+    pub info_lines: Option<Vec<String>>, // inspect panel for the followed vehicle
+    pub ghosts: &'a [&'a Vehicle],       // vehicles of a recorded run, drawn translucent
+    pub log_lines: Option<Vec<String>>,  // recent log output
+    pub layout: RoadLayout,              // closed arms are drawn over with grass
+    pub timeline: Option<TimelineView>,  // reservation debug chart
+    pub heatmap: Option<Occupancy>,      // how often each grid cell has been booked
+    pub cell_owners: Option<CellOwners>, // who holds each grid cell right now
+    pub hud_lines: Option<Vec<String>>,  // auto-spawn rate, challenge progress
+    pub onboarding: bool,                // first-launch help with the road zones marked
+    pub obstacles: &'a [Obstacle],       // stalled cars and debris dropped by the user
+    pub roadworks: Vec<(f32, f32, f32, f32)>, // closed stretches of lane
+    pub hazards: &'a [Hazard],           // jaywalkers crossing the road
+    pub close_call_spots: &'a [(f32, f32)], // where vehicles came too close
+    pub entry_queues: Vec<(Direction, Vec<String>)>, // who waits at each entry line, title first
+    pub vehicle_labels: bool,            // id, speed and permission above every vehicle
+    pub profile: Option<&'a FrameProfiler>, // recent frame times split by phase
+}
+
+/// Booked slots to chart, as seen at `now`
+pub struct TimelineView {
+    pub now: f32,
+    pub timeline: Timeline,
+    pub orphaned: Vec<usize>, // vehicles whose bookings nobody will use
+}
+
+/// Seconds of the past and of the future shown in the timeline
+const TIMELINE_PAST: f32 = 1.0;
+const TIMELINE_FUTURE: f32 = 4.0;
+/// Screen pixels per grid row in the timeline
+const TIMELINE_ROW_HEIGHT: u32 = 4;
+
+/// Screen pixels per frame in the profiler graph, and its height
+const PROFILE_BAR_WIDTH: u32 = 2;
+const PROFILE_GRAPH_HEIGHT: u32 = 80;
+/// Frame time at the top of the profiler graph: two frames at 60 Hz
+const PROFILE_SCALE: Duration = Duration::from_micros(33_333);
+/// Colours of the profiler phases, in the order `FrameSample::parts` lists them
+const PROFILE_COLORS: [Color; 5] = [
+    Color::RGB(70, 130, 230),  // following
+    Color::RGB(240, 150, 40),  // reservation
+    Color::RGB(60, 200, 90),   // cell release
+    Color::RGB(190, 90, 220),  // movement
+    Color::RGB(170, 170, 170), // rendering
+];
+
+/// Most vehicles listed in an entry queue panel; the rest are counted
+const QUEUE_PANEL_VEHICLES: usize = 8;
+/// Screen width of an entry queue panel
+const QUEUE_PANEL_WIDTH: u32 = 190;
+
+/// Opacity of ghost vehicles from a replay
+pub(crate) const GHOST_ALPHA: u8 = 90;
+
+/// Colour painted over the arms a layout has no road for
+pub(crate) const GRASS: Color = Color::RGB(60, 110, 60);
+
+/// Construction orange of lanes closed for roadworks
+pub(crate) const ROADWORKS: Color = Color::RGB(255, 120, 0);
+
+/// Flat colours of the road and vehicles when they are drawn as shapes
+pub(crate) const ASPHALT: Color = Color::RGB(70, 70, 70);
+pub(crate) const BOX: Color = Color::RGB(55, 55, 55);
+pub(crate) const LANE_MARK: Color = Color::RGB(230, 200, 60);
+pub(crate) const WRECK: Color = Color::RGB(255, 60, 60);
+
+/// A drawing backend. The game loop owns the simulation and input; a renderer
+/// only turns a `FrameView` into pixels, so backends can be swapped freely.
+pub trait Renderer {
+    fn draw_frame(&mut self, frame: &FrameView) -> Result<(), String>;
+}
+
+/// Default backend: SDL2 canvas with the road image, car sprites and TTF text.
+/// Without the images (missing, broken, or a build without the `image`
+/// feature) the road and vehicles are drawn as flat shapes instead, and
+/// without `ttf` the text panels are left out.
+pub struct SdlRenderer<'a> {
+    canvas: Canvas<Window>,
+    texture_creator: &'a TextureCreator<WindowContext>,
+    road: Option<Texture<'a>>,
+    sprites: Option<CarSprites<'a>>,
+    animations: Animations, // wheel and brake frames, and when to light the brakes
+    skid_marks: SkidMarks,
+    blank: Texture<'a>, // stretched into shapes: lights, skid marks, vehicles without sprites
+    panel_font: Option<TextFont<'a>>,
+    #[cfg(feature = "image")]
+    watcher: AssetWatcher, // reloads images edited while running
+}
+
+impl<'a> SdlRenderer<'a> {
+    pub fn new(
+        canvas: Canvas<Window>,
+        texture_creator: &'a TextureCreator<WindowContext>,
+        fonts: &'a Fonts,
+    ) -> Result<Self, String> {
+        Ok(Self {
+            canvas,
+            texture_creator,
+            road: load_road(texture_creator).unwrap_or_else(|e| {
+                warn!("Drawing the road from lines: {}", e);
+                None
+            }),
+            sprites: CarSprites::load(texture_creator).unwrap_or_else(|e| {
+                warn!("Drawing vehicles as rectangles: {}", e);
+                None
+            }),
+            animations: Animations::default(),
+            skid_marks: SkidMarks::default(),
+            blank: blank_texture(texture_creator)?,
+            panel_font: fonts.load(&PANEL_FONT, 16)?,
+            #[cfg(feature = "image")]
+            watcher: AssetWatcher::new(),
+        })
+    }
+
+    /// Swap in images edited on disk since the last check. A file caught
+    /// half-written fails to load and the old image stays until it changes again.
+    #[cfg(feature = "image")]
+    fn reload_changed_images(&mut self) {
+        for image in self.watcher.changed() {
+            let reloaded = match image {
+                WatchedImage::Road => {
+                    load_road_texture(self.texture_creator).map(|road| self.road = Some(road))
+                }
+                WatchedImage::Car(index) => match &mut self.sprites {
+                    Some(sprites) => sprites.reload(self.texture_creator, index),
+                    // The set failed to load earlier; the fixed file may complete it
+                    None => {
+                        CarSprites::load(self.texture_creator).map(|sprites| self.sprites = sprites)
+                    }
+                },
+            };
+            match reloaded {
+                Ok(()) => info!("Reloaded {:?}", image),
+                Err(e) => warn!("Failed to reload {:?}: {}", image, e),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "image")]
+fn load_road(
+    texture_creator: &TextureCreator<WindowContext>,
+) -> Result<Option<Texture<'_>>, String> {
+    load_road_texture(texture_creator).map(Some)
+}
+
+#[cfg(not(feature = "image"))]
+fn load_road(
+    _texture_creator: &TextureCreator<WindowContext>,
+) -> Result<Option<Texture<'_>>, String> {
+    Ok(None)
+}
+
+impl Renderer for SdlRenderer<'_> {
+    fn draw_frame(&mut self, frame: &FrameView) -> Result<(), String> {
+        let _span = trace_span!("render").entered();
+        let (window_width, window_height) = self.canvas.window().size();
+        #[cfg(feature = "image")]
+        self.reload_changed_images();
+
+        self.canvas.clear();
+        match &self.road {
+            Some(road) => self.canvas.copy(road, frame.camera.view(), None)?,
+            None => draw_plain_road(&mut self.canvas, frame.camera)?,
+        }
+        if let Some((x, y, w, h)) = frame.layout.closed_area() {
+            self.canvas.set_draw_color(GRASS);
+            self.canvas
+                .fill_rect(frame.camera.to_screen(x, y, w as u32, h as u32))?;
+        }
+        let now = Instant::now();
+        self.skid_marks.update(frame.vehicles, now);
+        draw_skid_marks(
+            &mut self.canvas,
+            &mut self.blank,
+            self.skid_marks.iter(),
+            frame.camera,
+        )?;
+        if let Some(occupancy) = &frame.heatmap {
+            draw_heatmap(&mut self.canvas, occupancy, frame.camera)?;
+        }
+        if let Some(owners) = &frame.cell_owners {
+            draw_cell_owners(&mut self.canvas, owners, frame.camera)?;
+        }
+        if frame.onboarding {
+            draw_zones(
+                &mut self.canvas,
+                self.texture_creator,
+                self.panel_font.as_ref(),
+                frame.layout,
+                frame.camera,
+            )?;
+        }
+        draw_roadworks(&mut self.canvas, &frame.roadworks, frame.camera)?;
+        draw_obstacles(&mut self.canvas, frame.obstacles, frame.camera)?;
+        draw_hazards(&mut self.canvas, frame.hazards, frame.camera)?;
+        draw_close_call_spots(&mut self.canvas, frame.close_call_spots, frame.camera)?;
+        self.animations.update(frame.vehicles, now);
+        match &mut self.sprites {
+            Some(sprites) => {
+                // Ghosts keep the first frame: their ids may clash with live vehicles
+                draw_vehicles(
+                    &mut self.canvas,
+                    sprites,
+                    frame.ghosts,
+                    None,
+                    frame.camera,
+                    GHOST_ALPHA,
+                )?;
+                draw_vehicles(
+                    &mut self.canvas,
+                    sprites,
+                    frame.vehicles,
+                    Some(&self.animations),
+                    frame.camera,
+                    255,
+                )?;
+            }
+            None => {
+                draw_vehicle_boxes(
+                    &mut self.canvas,
+                    &mut self.blank,
+                    frame.ghosts,
+                    None,
+                    frame.camera,
+                    GHOST_ALPHA,
+                )?;
+                draw_vehicle_boxes(
+                    &mut self.canvas,
+                    &mut self.blank,
+                    frame.vehicles,
+                    Some(&self.animations),
+                    frame.camera,
+                    255,
+                )?;
+            }
+        }
+        draw_brake_lights(
+            &mut self.canvas,
+            &mut self.blank,
+            frame.vehicles,
+            &self.animations,
+            frame.camera,
+        )?;
+        if frame.show_minimap {
+            draw_minimap(&mut self.canvas, frame.vehicles, frame.camera, window_width)?;
+        }
+        // Under the inspect panel, which shares the bottom-left corner
+        if let Some(timeline) = &frame.timeline {
+            let height = timeline.timeline.rows as u32 * TIMELINE_ROW_HEIGHT + 20;
+            draw_timeline(
+                &mut self.canvas,
+                timeline,
+                Rect::new(
+                    10,
+                    (window_height - height - 10) as i32,
+                    window_width - 20,
+                    height,
+                ),
+            )?;
+        }
+        if let Some(profiler) = frame.profile {
+            draw_profile(
+                &mut self.canvas,
+                self.texture_creator,
+                self.panel_font.as_ref(),
+                profiler,
+                window_width,
+            )?;
+        }
+        // Text panels need a font; builds without `ttf` go without
+        if let Some(font) = &self.panel_font {
+            if let Some(lines) = &frame.info_lines {
+                let height = panel_height(font, lines);
+                draw_text_panel(
+                    &mut self.canvas,
+                    self.texture_creator,
+                    font,
+                    lines,
+                    Rect::new(10, (window_height - height - 10) as i32, 320, height),
+                )?;
+            }
+            if let Some(lines) = &frame.hud_lines {
+                let height = panel_height(font, lines);
+                draw_text_panel(
+                    &mut self.canvas,
+                    self.texture_creator,
+                    font,
+                    lines,
+                    Rect::new(
+                        window_width as i32 - 330,
+                        (window_height - height - 10) as i32,
+                        320,
+                        height,
+                    ),
+                )?;
+            }
+            if let Some(lines) = &frame.log_lines {
+                // Leave room for the minimap in the top-right corner
+                let width = window_width - MINIMAP_SIZE - 3 * MINIMAP_MARGIN as u32;
+                draw_text_panel(
+                    &mut self.canvas,
+                    self.texture_creator,
+                    font,
+                    lines,
+                    Rect::new(10, 10, width, panel_height(font, lines)),
+                )?;
+            }
+            if let Some(owners) = &frame.cell_owners {
+                draw_cell_owner_ids(
+                    &mut self.canvas,
+                    self.texture_creator,
+                    font,
+                    owners,
+                    frame.camera,
+                )?;
+            }
+            if frame.vehicle_labels {
+                draw_vehicle_labels(
+                    &mut self.canvas,
+                    self.texture_creator,
+                    font,
+                    frame.vehicles,
+                    frame.camera,
+                )?;
+            }
+            draw_entry_queues(
+                &mut self.canvas,
+                self.texture_creator,
+                font,
+                &frame.entry_queues,
+                frame.camera,
+            )?;
+            if frame.onboarding {
+                // Over the grass in the top-left corner, clear of the zones
+                let lines = control_lines();
+                draw_text_panel(
+                    &mut self.canvas,
+                    self.texture_creator,
+                    font,
+                    &lines,
+                    Rect::new(10, 10, 330, panel_height(font, &lines)),
+                )?;
+            }
+        }
+        self.canvas.present();
+        Ok(())
+    }
+}
+
+/// Car textures loaded once and shared by every vehicle, indexed by `car_index`
+pub struct CarSprites<'a> {
+    textures: Vec<Texture<'a>>,
+    layouts: Vec<SheetLayout>, // how each texture splits into animation frames
+    draw_order: Vec<usize>,    // reused every frame to sort vehicles by texture
+}
+
+impl<'a> CarSprites<'a> {
+    #[cfg(feature = "image")]
+    pub fn load(
+        texture_creator: &'a TextureCreator<WindowContext>,
+    ) -> Result<Option<Self>, String> {
+        let mut textures = Vec::new();
+        let mut layouts = Vec::new();
+        for i in 1..=5 {
+            let mut texture = load_car_texture(texture_creator, i)?;
+            texture.set_blend_mode(BlendMode::Blend); // needed for translucent ghosts
+            let query = texture.query();
+            layouts.push(SheetLayout::new(query.width, query.height));
+            textures.push(texture);
+        }
+        Ok(Some(Self {
+            textures,
+            layouts,
+            draw_order: Vec::new(),
+        }))
+    }
+
+    /// Replace sprite `index` (1-5) with the file on disk, or the built-in car
+    #[cfg(feature = "image")]
+    fn reload(
+        &mut self,
+        texture_creator: &'a TextureCreator<WindowContext>,
+        index: u32,
+    ) -> Result<(), String> {
+        let mut texture = load_car_texture(texture_creator, index)?;
+        texture.set_blend_mode(BlendMode::Blend);
+        let slot = self.texture_slot(index);
+        let query = texture.query();
+        self.layouts[slot] = SheetLayout::new(query.width, query.height);
+        self.textures[slot] = texture;
+        Ok(())
+    }
+
+    /// No sprites without the `image` feature: vehicles are drawn as boxes
+    #[cfg(not(feature = "image"))]
+    pub fn load(
+        _texture_creator: &'a TextureCreator<WindowContext>,
+    ) -> Result<Option<Self>, String> {
+        Ok(None)
+    }
+
+    fn texture_slot(&self, car_index: u32) -> usize {
+        (car_index as usize).clamp(1, self.textures.len()) - 1
+    }
+}
+
+/// Draw every vehicle with its sprite, rotated to its heading, and with the
+/// frame `animations` picks when the sprite is a sheet.
+///
+/// Vehicles are drawn grouped by texture (and by crash tint within a texture)
+/// so the renderer switches textures and colour mods once per group rather
+/// than once per vehicle.
+fn draw_vehicles(
+    canvas: &mut Canvas<Window>,
+    sprites: &mut CarSprites,
+    vehicles: &[&Vehicle],
+    animations: Option<&Animations>,
+    camera: &Camera,
+    alpha: u8,
+) -> Result<(), String> {
+    let pose = |vehicle: &Vehicle| animations.map_or(Pose::default(), |a| a.pose(vehicle));
+    let red = |vehicle: &Vehicle| vehicle.crashed_at.is_some() || pose(vehicle).flashing;
+
+    let mut order = std::mem::take(&mut sprites.draw_order);
+    order.clear();
+    order.extend(0..vehicles.len());
+    // Stable, so vehicles sharing a texture keep their spawn order
+    order.sort_by_key(|&i| {
+        let vehicle = vehicles[i];
+        (sprites.texture_slot(vehicle.car_index), red(vehicle))
+    });
+
+    let mut current_tint = None;
+    for &i in &order {
+        let vehicle = vehicles[i];
+        let slot = sprites.texture_slot(vehicle.car_index);
+        let texture = &mut sprites.textures[slot];
+
+        // Tint wrecks red until they are cleared away, and vehicles in a close
+        // call while they flash; textures are shared, so the colour and
+        // transparency are reset whenever the group changes
+        let red = red(vehicle);
+        if current_tint != Some((slot, red)) {
+            if red {
+                texture.set_color_mod(255, 60, 60);
+            } else {
+                texture.set_color_mod(255, 255, 255);
+            }
+            texture.set_alpha_mod(alpha);
+            current_tint = Some((slot, red));
+        }
+
+        let dest_rect = camera.to_screen(
+            vehicle.position.0,
+            vehicle.position.1,
+            vehicle.width,
+            vehicle.height,
+        );
+
+        let source = sprites.layouts[slot].frame(pose(vehicle));
+
+        canvas.copy_ex(
+            &sprites.textures[slot],
+            source,
+            dest_rect,
+            vehicle.rotation,
+            None,
+            false,
+            false,
+        )?;
+    }
+
+    sprites.draw_order = order;
+    Ok(())
+}
+
+/// Colour of the brake lights
+const BRAKE_LIGHT: Color = Color::RGB(255, 20, 20);
+
+/// Two red lights on the rear bumper of every vehicle the controller or the
+/// car ahead is slowing down, turned with the car around its centre
+fn draw_brake_lights(
+    canvas: &mut Canvas<Window>,
+    blank: &mut Texture,
+    vehicles: &[&Vehicle],
+    animations: &Animations,
+    camera: &Camera,
+) -> Result<(), String> {
+    blank.set_alpha_mod(255);
+    blank.set_color_mod(BRAKE_LIGHT.r, BRAKE_LIGHT.g, BRAKE_LIGHT.b);
+    for vehicle in vehicles {
+        if vehicle.crashed_at.is_some() || !animations.pose(vehicle).braking {
+            continue;
+        }
+        let (x, y) = vehicle.position;
+        let body = camera.to_screen(x, y, vehicle.width, vehicle.height);
+        // Sprites face north, so the rear is the bottom edge before rotating
+        let rear = y + vehicle.height as f32 - 5.0;
+        for light_x in [x + 4.0, x + vehicle.width as f32 - 12.0] {
+            let light = camera.to_screen(light_x, rear, 8, 4);
+            draw_body_part(canvas, blank, body, light, vehicle.rotation)?;
+        }
+    }
+    Ok(())
+}
+
+/// Colour of the close-call markers
+const CLOSE_CALL_MARK: Color = Color::RGB(255, 150, 0);
+
+/// A small cross where each close call happened, left for the rest of the run
+fn draw_close_call_spots(
+    canvas: &mut Canvas<Window>,
+    spots: &[(f32, f32)],
+    camera: &Camera,
+) -> Result<(), String> {
+    canvas.set_draw_color(CLOSE_CALL_MARK);
+    for &(x, y) in spots {
+        let mark = camera.to_screen(x - 5.0, y - 5.0, 10, 10);
+        let (left, top, right, bottom) = (mark.left(), mark.top(), mark.right(), mark.bottom());
+        canvas.draw_line((left, top), (right, bottom))?;
+        canvas.draw_line((left, bottom), (right, top))?;
+    }
+    Ok(())
+}
+
+/// Colour of fresh skid marks
+const SKID: Color = Color::RGB(25, 25, 25);
+/// Length of each skid mark behind the wheel that left it
+const SKID_LENGTH: u32 = 30;
+
+/// Dark streaks trailing back from the rear wheels of each emergency stop,
+/// fading as they age
+fn draw_skid_marks<'m>(
+    canvas: &mut Canvas<Window>,
+    blank: &mut Texture,
+    marks: impl Iterator<Item = &'m SkidMark>,
+    camera: &Camera,
+) -> Result<(), String> {
+    let now = Instant::now();
+    blank.set_color_mod(SKID.r, SKID.g, SKID.b);
+    for mark in marks {
+        blank.set_alpha_mod((mark.strength(now).max(0.0) * 180.0) as u8);
+        let (x, y) = mark.position;
+        let (width, height) = mark.size;
+        let body = camera.to_screen(x, y, width, height);
+        let wheels = y + height as f32 - 15.0;
+        for wheel_x in [x + 6.0, x + width as f32 - 10.0] {
+            let streak = camera.to_screen(wheel_x, wheels, 4, SKID_LENGTH);
+            draw_body_part(canvas, blank, body, streak, mark.rotation)?;
+        }
+    }
+    Ok(())
+}
+
+/// Copy `blank` over `part` of a car body laid out facing north, turning it
+/// with the body around the body's centre
+fn draw_body_part(
+    canvas: &mut Canvas<Window>,
+    blank: &Texture,
+    body: Rect,
+    part: Rect,
+    rotation: f64,
+) -> Result<(), String> {
+    let pivot = Point::new(body.center().x() - part.x(), body.center().y() - part.y());
+    canvas.copy_ex(blank, None, part, rotation, pivot, false, false)
+}
+
+/// A white pixel with alpha blending, which is tinted and stretched into
+/// vehicle boxes, brake lights and skid marks; unlike fill_rect, copying a
+/// texture can rotate it
+fn blank_texture(texture_creator: &TextureCreator<WindowContext>) -> Result<Texture<'_>, String> {
+    let mut surface = Surface::new(1, 1, PixelFormatEnum::RGBA8888)?;
+    surface.fill_rect(None, Color::RGB(255, 255, 255))?;
+    let mut texture = texture_creator
+        .create_texture_from_surface(&surface)
+        .map_err(|e| e.to_string())?;
+    texture.set_blend_mode(BlendMode::Blend);
+    Ok(texture)
+}
+
+/// Vehicles as rectangles the size of their sprite, rotated to their heading,
+/// coloured by direction and red when wrecked, for when there are no sprites
+fn draw_vehicle_boxes(
+    canvas: &mut Canvas<Window>,
+    blank: &mut Texture,
+    vehicles: &[&Vehicle],
+    animations: Option<&Animations>,
+    camera: &Camera,
+    alpha: u8,
+) -> Result<(), String> {
+    blank.set_alpha_mod(alpha);
+    for vehicle in vehicles {
+        let flashing = animations.is_some_and(|animations| animations.pose(vehicle).flashing);
+        let color = if vehicle.crashed_at.is_some() || flashing {
+            WRECK
+        } else {
+            direction_color(vehicle.direction)
+        };
+        blank.set_color_mod(color.r, color.g, color.b);
+        let dest_rect = camera.to_screen(
+            vehicle.position.0,
+            vehicle.position.1,
+            vehicle.width,
+            vehicle.height,
+        );
+        canvas.copy_ex(blank, None, dest_rect, vehicle.rotation, None, false, false)?;
+    }
+    Ok(())
+}
+
+/// The road drawn from its geometry when there is no road image: grass, two
+/// crossing roads, the intersection box, dashed lane lines, stop lines and
+/// the centre lines
+fn draw_plain_road(canvas: &mut Canvas<Window>, camera: &Camera) -> Result<(), String> {
+    let size = WORLD_SIZE as u32;
+    canvas.set_draw_color(GRASS);
+    canvas.fill_rect(camera.to_screen(0.0, 0.0, size, size))?;
+    canvas.set_draw_color(ASPHALT);
+    canvas.fill_rect(camera.to_screen(350.0, 0.0, 300, size))?;
+    canvas.fill_rect(camera.to_screen(0.0, 350.0, size, 300))?;
+    canvas.set_draw_color(BOX);
+    canvas.fill_rect(camera.to_screen(350.0, 350.0, 300, 300))?;
+    canvas.set_draw_color(LANE_MARK);
+    canvas.fill_rect(camera.to_screen(498.0, 0.0, 4, 350))?;
+    canvas.fill_rect(camera.to_screen(498.0, 650.0, 4, 350))?;
+    canvas.fill_rect(camera.to_screen(0.0, 498.0, 350, 4))?;
+    canvas.fill_rect(camera.to_screen(650.0, 498.0, 350, 4))?;
+
+    // Dashes between lanes going the same way, and a stop line across each
+    // approach where it meets the box
+    canvas.set_draw_color(Color::RGB(220, 220, 220));
+    for divider in [400.0, 450.0, 550.0, 600.0] {
+        for start in (0..350).step_by(30).chain((650..1000).step_by(30)) {
+            let start = start as f32;
+            canvas.fill_rect(camera.to_screen(divider - 1.0, start, 2, 15))?;
+            canvas.fill_rect(camera.to_screen(start, divider - 1.0, 15, 2))?;
+        }
+    }
+    canvas.fill_rect(camera.to_screen(350.0, 344.0, 150, 4))?; // southbound
+    canvas.fill_rect(camera.to_screen(500.0, 652.0, 150, 4))?; // northbound
+    canvas.fill_rect(camera.to_screen(344.0, 500.0, 4, 150))?; // eastbound
+    canvas.fill_rect(camera.to_screen(652.0, 350.0, 4, 150)) // westbound
+}
+
+/// Side length of the minimap in screen pixels
+pub(crate) const MINIMAP_SIZE: u32 = 150;
+/// Gap between the minimap and the window corner
+pub(crate) const MINIMAP_MARGIN: i32 = 10;
+/// World width covered by the minimap
+pub(crate) const WORLD_SIZE: f32 = 1000.0;
+
+pub(crate) fn direction_color(direction: Direction) -> Color {
+    match direction {
+        Direction::North => Color::RGB(80, 160, 255),
+        Direction::South => Color::RGB(255, 90, 90),
+        Direction::East => Color::RGB(90, 220, 110),
+        Direction::West => Color::RGB(255, 210, 60),
+    }
+}
+
+/// Overview of the whole road in the top-right corner: every vehicle is a dot
+/// coloured by the direction it is heading. While the camera follows a vehicle
+/// the visible area is outlined and the followed vehicle is drawn larger.
+fn draw_minimap(
+    canvas: &mut Canvas<Window>,
+    vehicles: &[&Vehicle],
+    camera: &Camera,
+    window_width: u32,
+) -> Result<(), String> {
+    let left = window_width as i32 - MINIMAP_SIZE as i32 - MINIMAP_MARGIN;
+    let top = MINIMAP_MARGIN;
+    let scale = MINIMAP_SIZE as f32 / WORLD_SIZE;
+    let to_screen = |x: f32, y: f32| (left + (x * scale) as i32, top + (y * scale) as i32);
+
+    canvas.set_draw_color(Color::RGBA(20, 20, 20, 255));
+    canvas.fill_rect(Rect::new(left, top, MINIMAP_SIZE, MINIMAP_SIZE))?;
+
+    // Intersection box
+    let (ix, iy) = to_screen(350.0, 350.0);
+    let box_size = (300.0 * scale) as u32;
+    canvas.set_draw_color(Color::RGB(110, 110, 110));
+    canvas.draw_rect(Rect::new(ix, iy, box_size, box_size))?;
+
+    // One batch of dots per colour: the four headings, then wrecks
+    let mut dots: [Vec<Rect>; 5] = Default::default();
+    let mut followed = None;
+    for vehicle in vehicles {
+        let (vx, vy, vw, vh) = vehicle.get_visual_bounds();
+        let (x, y) = to_screen(vx + vw / 2.0, vy + vh / 2.0);
+        let batch = if vehicle.crashed_at.is_some() {
+            4
+        } else {
+            vehicle.direction as usize
+        };
+        if camera.following == Some(vehicle.id) {
+            followed = Some((batch, Rect::new(x - 4, y - 4, 8, 8)));
+        } else {
+            dots[batch].push(Rect::new(x - 2, y - 2, 4, 4));
+        }
+    }
+    let batch_color = |batch: usize| match batch {
+        0 => direction_color(Direction::North),
+        1 => direction_color(Direction::South),
+        2 => direction_color(Direction::East),
+        3 => direction_color(Direction::West),
+        _ => Color::RGB(255, 255, 255),
+    };
+    for (batch, rects) in dots.iter().enumerate() {
+        if !rects.is_empty() {
+            canvas.set_draw_color(batch_color(batch));
+            canvas.fill_rects(rects)?;
+        }
+    }
+    if let Some((batch, rect)) = followed {
+        canvas.set_draw_color(batch_color(batch));
+        canvas.fill_rect(rect)?;
+    }
+
+    if camera.is_following() {
+        let view = camera.view();
+        let (vx, vy) = to_screen(view.x() as f32, view.y() as f32);
+        let size = (view.width() as f32 * scale) as u32;
+        canvas.set_draw_color(Color::RGB(200, 200, 200));
+        canvas.draw_rect(Rect::new(vx, vy, size, size))?;
+    }
+
+    canvas.set_draw_color(Color::RGB(255, 255, 255));
+    canvas.draw_rect(Rect::new(left, top, MINIMAP_SIZE, MINIMAP_SIZE))
+}
+
+/// Translucent heat colours over the grid cells, under the vehicles
+fn draw_heatmap(
+    canvas: &mut Canvas<Window>,
+    occupancy: &Occupancy,
+    camera: &Camera,
+) -> Result<(), String> {
+    let max_share = occupancy.max_share();
+    let size = occupancy.cell_size.ceil() as u32;
+    canvas.set_blend_mode(BlendMode::Blend);
+    for row in 0..occupancy.rows {
+        for col in 0..occupancy.cols {
+            let share = occupancy.share(col, row);
+            if share == 0.0 {
+                continue;
+            }
+            let (r, g, b) = heat_color(share, max_share);
+            canvas.set_draw_color(Color::RGBA(r, g, b, 150));
+            canvas.fill_rect(camera.to_screen(
+                occupancy.left + col as f32 * occupancy.cell_size,
+                occupancy.top + row as f32 * occupancy.cell_size,
+                size,
+                size,
+            ))?;
+        }
+    }
+    canvas.set_blend_mode(BlendMode::None);
+    Ok(())
+}
+
+/// Each held grid cell tinted in its owner's timeline colour; cells held by
+/// more than one vehicle are red with a white border
+fn draw_cell_owners(
+    canvas: &mut Canvas<Window>,
+    owners: &CellOwners,
+    camera: &Camera,
+) -> Result<(), String> {
+    let size = owners.cell_size.ceil() as u32;
+    canvas.set_blend_mode(BlendMode::Blend);
+    for (idx, held_by) in owners.owners.iter().enumerate() {
+        let rect = camera.to_screen(
+            owners.left + (idx % owners.cols) as f32 * owners.cell_size,
+            owners.top + (idx / owners.cols) as f32 * owners.cell_size,
+            size,
+            size,
+        );
+        match held_by.as_slice() {
+            [] => continue,
+            [vehicle_id] => {
+                let color = vehicle_color(*vehicle_id);
+                canvas.set_draw_color(Color::RGBA(color.r, color.g, color.b, 140));
+                canvas.fill_rect(rect)?;
+            }
+            _ => {
+                canvas.set_draw_color(Color::RGBA(230, 30, 30, 200));
+                canvas.fill_rect(rect)?;
+                canvas.set_draw_color(Color::RGB(255, 255, 255));
+                canvas.draw_rect(rect)?;
+            }
+        }
+    }
+    canvas.set_blend_mode(BlendMode::None);
+    Ok(())
+}
+
+/// Cells are too small for a label each, so every owner's id goes once at
+/// the centre of the cells it holds
+fn draw_cell_owner_ids(
+    canvas: &mut Canvas<Window>,
+    texture_creator: &TextureCreator<WindowContext>,
+    font: &TextFont,
+    owners: &CellOwners,
+    camera: &Camera,
+) -> Result<(), String> {
+    // vehicle id -> (sum of cell columns, sum of cell rows, cells)
+    let mut centres: BTreeMap<usize, (f32, f32, f32)> = BTreeMap::new();
+    for (idx, held_by) in owners.owners.iter().enumerate() {
+        for vehicle_id in held_by {
+            let centre = centres.entry(*vehicle_id).or_default();
+            centre.0 += (idx % owners.cols) as f32 + 0.5;
+            centre.1 += (idx / owners.cols) as f32 + 0.5;
+            centre.2 += 1.0;
+        }
+    }
+    let line_height = font.line_height();
+    for (vehicle_id, (cols, rows, cells)) in centres {
+        let at = camera.to_screen(
+            owners.left + cols / cells * owners.cell_size,
+            owners.top + rows / cells * owners.cell_size,
+            0,
+            0,
+        );
+        font.draw(
+            canvas,
+            texture_creator,
+            &format!("#{vehicle_id}"),
+            vehicle_color(vehicle_id),
+            (at.x() - line_height / 2, at.y() - line_height / 2),
+        )?;
+    }
+    Ok(())
+}
+
+/// Colour of a vehicle's bars in the timeline; neighbouring ids differ clearly
+fn vehicle_color(vehicle_id: usize) -> Color {
+    const PALETTE: [Color; 8] = [
+        Color::RGB(80, 160, 255),
+        Color::RGB(255, 160, 60),
+        Color::RGB(90, 220, 110),
+        Color::RGB(220, 100, 220),
+        Color::RGB(255, 220, 70),
+        Color::RGB(70, 210, 210),
+        Color::RGB(240, 120, 140),
+        Color::RGB(170, 140, 255),
+    ];
+    PALETTE[vehicle_id % PALETTE.len()]
+}
+
+/// Booked slots as horizontal bars, one lane per grid row (top row first),
+/// from TIMELINE_PAST seconds ago to TIMELINE_FUTURE seconds ahead. Bars are
+/// coloured by vehicle; orphaned bookings are dark red, and a white line marks now.
+fn draw_timeline(
+    canvas: &mut Canvas<Window>,
+    view: &TimelineView,
+    panel: Rect,
+) -> Result<(), String> {
+    let inner = Rect::new(
+        panel.x() + 10,
+        panel.y() + 10,
+        panel.width() - 20,
+        panel.height() - 20,
+    );
+    let span = TIMELINE_PAST + TIMELINE_FUTURE;
+    let from = view.now - TIMELINE_PAST;
+    let to_x = |time: f32| {
+        inner.x() + ((time - from).clamp(0.0, span) / span * inner.width() as f32) as i32
+    };
+
+    canvas.set_blend_mode(BlendMode::Blend);
+    canvas.set_draw_color(Color::RGBA(0, 0, 0, 190));
+    canvas.fill_rect(panel)?;
+    canvas.set_draw_color(Color::RGB(0, 255, 255));
+    canvas.draw_rect(panel)?;
+    canvas.set_blend_mode(BlendMode::None);
+
+    // One tick per second
+    canvas.set_draw_color(Color::RGB(60, 60, 60));
+    for second in 0..=span as i32 {
+        let x = to_x(from.ceil() + second as f32);
+        canvas.draw_line((x, inner.y()), (x, inner.bottom()))?;
+    }
+
+    for slot in &view.timeline.slots {
+        if slot.end < from || slot.start > view.now + TIMELINE_FUTURE {
+            continue;
+        }
+        let (left, right) = (to_x(slot.start), to_x(slot.end));
+        let color = if view.orphaned.contains(&slot.vehicle_id) {
+            Color::RGB(150, 20, 20)
+        } else {
+            vehicle_color(slot.vehicle_id)
+        };
+        canvas.set_draw_color(color);
+        canvas.fill_rect(Rect::new(
+            left,
+            inner.y() + (slot.row as u32 * TIMELINE_ROW_HEIGHT) as i32,
+            (right - left).max(1) as u32,
+            TIMELINE_ROW_HEIGHT - 1,
+        ))?;
+    }
+
+    let now = to_x(view.now);
+    canvas.set_draw_color(Color::RGB(255, 255, 255));
+    canvas.draw_line((now, inner.y()), (now, inner.bottom()))
+}
+
+/// Frame-time graph under the minimap: one bar per recent frame, stacked by
+/// phase, with a line at the 60 Hz budget and, given a font, the average of
+/// each phase in its colour
+fn draw_profile(
+    canvas: &mut Canvas<Window>,
+    texture_creator: &TextureCreator<WindowContext>,
+    font: Option<&TextFont>,
+    profiler: &FrameProfiler,
+    window_width: u32,
+) -> Result<(), String> {
+    let average = profiler.average();
+    let legend = font.map(|font| {
+        let ms = |time: Duration| format!("{:.2}", time.as_secs_f64() * 1000.0);
+        let mut lines = vec![(
+            Color::RGB(255, 255, 255),
+            tr!(
+                "profile.title",
+                ms = ms(average.total()),
+                frames = profiler.frames().len()
+            ),
+        )];
+        for ((key, time), color) in average.parts().into_iter().zip(PROFILE_COLORS) {
+            lines.push((color, tr!(key, ms = ms(time))));
+        }
+        (font, lines)
+    });
+    let legend_height = legend.as_ref().map_or(0, |(font, lines)| {
+        font.line_height() as u32 * lines.len() as u32 + 10
+    });
+    let graph_width = PROFILE_BAR_WIDTH * PROFILE_FRAMES as u32;
+    let panel = Rect::new(
+        window_width as i32 - graph_width as i32 - 20 - MINIMAP_MARGIN,
+        MINIMAP_SIZE as i32 + 2 * MINIMAP_MARGIN,
+        graph_width + 20,
+        PROFILE_GRAPH_HEIGHT + 20 + legend_height,
+    );
+
+    canvas.set_blend_mode(BlendMode::Blend);
+    canvas.set_draw_color(Color::RGBA(0, 0, 0, 190));
+    canvas.fill_rect(panel)?;
+    canvas.set_draw_color(Color::RGB(0, 255, 255));
+    canvas.draw_rect(panel)?;
+    canvas.set_blend_mode(BlendMode::None);
+
+    let (left, bottom) = (panel.x() + 10, panel.y() + 10 + PROFILE_GRAPH_HEIGHT as i32);
+    let to_height = |time: Duration| {
+        (time.as_secs_f64() / PROFILE_SCALE.as_secs_f64() * PROFILE_GRAPH_HEIGHT as f64) as i32
+    };
+    for (index, frame) in profiler.frames().iter().enumerate() {
+        let x = left + index as i32 * PROFILE_BAR_WIDTH as i32;
+        let mut top = bottom;
+        for ((_, time), color) in frame.parts().into_iter().zip(PROFILE_COLORS) {
+            // Frames over the scale are cut off at the top
+            let height = to_height(time).min(top - (bottom - PROFILE_GRAPH_HEIGHT as i32));
+            if height <= 0 {
+                continue;
+            }
+            top -= height;
+            canvas.set_draw_color(color);
+            canvas.fill_rect(Rect::new(x, top, PROFILE_BAR_WIDTH, height as u32))?;
+        }
+    }
+    let budget = bottom - to_height(PROFILE_SCALE / 2);
+    canvas.set_draw_color(Color::RGB(255, 255, 255));
+    canvas.draw_line((left, budget), (left + graph_width as i32, budget))?;
+
+    if let Some((font, lines)) = legend {
+        let mut y = bottom + 10;
+        for (color, line) in lines {
+            font.draw(canvas, texture_creator, &line, color, (left, y))?;
+            y += font.line_height();
+        }
+    }
+    Ok(())
+}
+
+/// Height of a panel listing `lines`, padding included
+fn panel_height(font: &TextFont, lines: &[String]) -> u32 {
+    font.line_height() as u32 * lines.len() as u32 + 20
+}
+
+/// Permission dots of the vehicle labels: granted, requested but not
+/// granted, and not asked yet
+const LABEL_GRANTED: Color = Color::RGB(0, 220, 0);
+const LABEL_DENIED: Color = Color::RGB(230, 40, 40);
+const LABEL_NOT_REQUESTED: Color = Color::RGB(160, 160, 160);
+
+/// Each vehicle's id, as the log prints it, and speed just above its body,
+/// after a dot showing its permission state
+fn draw_vehicle_labels(
+    canvas: &mut Canvas<Window>,
+    texture_creator: &TextureCreator<WindowContext>,
+    font: &TextFont,
+    vehicles: &[&Vehicle],
+    camera: &Camera,
+) -> Result<(), String> {
+    let line_height = font.line_height();
+    for vehicle in vehicles {
+        let (x, y, w, h) = vehicle.get_visual_bounds();
+        let body = camera.to_screen(x, y, w as u32, h as u32);
+        let (left, top) = (body.x(), body.y() - line_height - 2);
+
+        canvas.set_draw_color(if vehicle.intersection_permission {
+            LABEL_GRANTED
+        } else if vehicle.requested_intersection {
+            LABEL_DENIED
+        } else {
+            LABEL_NOT_REQUESTED
+        });
+        canvas.fill_rect(Rect::new(left, top + (line_height - 8) / 2, 8, 8))?;
+
+        let label = format!(
+            "#{} {:.1}",
+            vehicle.id,
+            vehicle.current_speed.pixels_per_frame()
+        );
+        font.draw(
+            canvas,
+            texture_creator,
+            &label,
+            Color::RGB(255, 255, 255),
+            (left + 12, top),
+        )?;
+    }
+    Ok(())
+}
+
+/// Each approach's queue on the grass at the kerb side of its entry line,
+/// with a corner of the panel at the corner of the box
+fn draw_entry_queues(
+    canvas: &mut Canvas<Window>,
+    texture_creator: &TextureCreator<WindowContext>,
+    font: &TextFont,
+    queues: &[(Direction, Vec<String>)],
+    camera: &Camera,
+) -> Result<(), String> {
+    for (direction, lines) in queues {
+        let shown = QUEUE_PANEL_VEHICLES + 1; // the title too
+        let more = lines.len().saturating_sub(shown);
+        let mut lines: Vec<String> = lines.iter().take(shown).cloned().collect();
+        if more > 0 {
+            lines.push(tr!("queue.more", count = more));
+        }
+        let height = panel_height(font, &lines);
+        let corner = match direction {
+            Direction::South => camera.to_screen(340.0, 340.0, 0, 0),
+            Direction::West => camera.to_screen(660.0, 340.0, 0, 0),
+            Direction::North => camera.to_screen(660.0, 660.0, 0, 0),
+            Direction::East => camera.to_screen(340.0, 660.0, 0, 0),
+        };
+        let (x, y) = match direction {
+            Direction::South => (
+                corner.x() - QUEUE_PANEL_WIDTH as i32,
+                corner.y() - height as i32,
+            ),
+            Direction::West => (corner.x(), corner.y() - height as i32),
+            Direction::North => (corner.x(), corner.y()),
+            Direction::East => (corner.x() - QUEUE_PANEL_WIDTH as i32, corner.y()),
+        };
+        draw_text_panel(
+            canvas,
+            texture_creator,
+            font,
+            &lines,
+            Rect::new(x, y, QUEUE_PANEL_WIDTH, height),
+        )?;
+    }
+    Ok(())
+}
+
+/// Obstacles as filled blocks in their kind's colour with a dark outline
+fn draw_obstacles(
+    canvas: &mut Canvas<Window>,
+    obstacles: &[Obstacle],
+    camera: &Camera,
+) -> Result<(), String> {
+    for obstacle in obstacles {
+        let (x, y, w, h) = obstacle.area;
+        let rect = camera.to_screen(x, y, w as u32, h as u32);
+        let (r, g, b) = obstacle.kind.color();
+        canvas.set_draw_color(Color::RGB(r, g, b));
+        canvas.fill_rect(rect)?;
+        canvas.set_draw_color(Color::RGB(r / 3, g / 3, b / 3));
+        canvas.draw_rect(rect)?;
+    }
+    Ok(())
+}
+
+/// Jaywalkers as small blocks in their kind's colour with a dark outline
+fn draw_hazards(
+    canvas: &mut Canvas<Window>,
+    hazards: &[Hazard],
+    camera: &Camera,
+) -> Result<(), String> {
+    for hazard in hazards {
+        let (x, y, w, h) = hazard.area();
+        let rect = camera.to_screen(x, y, w as u32, h as u32);
+        let (r, g, b) = hazard.kind.color();
+        canvas.set_draw_color(Color::RGB(r, g, b));
+        canvas.fill_rect(rect)?;
+        canvas.set_draw_color(Color::RGB(r / 3, g / 3, b / 3));
+        canvas.draw_rect(rect)?;
+    }
+    Ok(())
+}
+
+/// Closed lanes in construction orange, striped across the lane every 20 px
+fn draw_roadworks(
+    canvas: &mut Canvas<Window>,
+    roadworks: &[(f32, f32, f32, f32)],
+    camera: &Camera,
+) -> Result<(), String> {
+    for &(x, y, w, h) in roadworks {
+        canvas.set_draw_color(ROADWORKS);
+        canvas.fill_rect(camera.to_screen(x, y, w as u32, h as u32))?;
+        canvas.set_draw_color(Color::RGB(255, 255, 255));
+        let along_y = h > w;
+        let length = w.max(h);
+        let mut offset = 10.0;
+        while offset < length {
+            let stripe = if along_y {
+                camera.to_screen(x, y + offset, w as u32, 4)
+            } else {
+                camera.to_screen(x + offset, y, 4, h as u32)
+            };
+            canvas.fill_rect(stripe)?;
+            offset += 20.0;
+        }
+    }
+    Ok(())
+}
+
+/// Tint and outline the road zones explained by the onboarding overlay, each
+/// labelled in its top-left corner when there is a font
+fn draw_zones(
+    canvas: &mut Canvas<Window>,
+    texture_creator: &TextureCreator<WindowContext>,
+    font: Option<&TextFont>,
+    layout: RoadLayout,
+    camera: &Camera,
+) -> Result<(), String> {
+    for zone in zones(layout) {
+        let (x, y, w, h) = zone.area;
+        let rect = camera.to_screen(x, y, w as u32, h as u32);
+        let (r, g, b) = zone.color;
+
+        canvas.set_blend_mode(BlendMode::Blend);
+        canvas.set_draw_color(Color::RGBA(r, g, b, 50));
+        canvas.fill_rect(rect)?;
+        canvas.set_blend_mode(BlendMode::None);
+        canvas.set_draw_color(Color::RGB(r, g, b));
+        canvas.draw_rect(rect)?;
+
+        if let Some(font) = font {
+            font.draw(
+                canvas,
+                texture_creator,
+                &zone.label,
+                Color::RGB(r, g, b),
+                (rect.x() + 4, rect.y() + 2),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Translucent panel listing `lines`; text running past the edge is cut off
+fn draw_text_panel(
+    canvas: &mut Canvas<Window>,
+    texture_creator: &TextureCreator<WindowContext>,
+    font: &TextFont,
+    lines: &[String],
+    panel: Rect,
+) -> Result<(), String> {
+    let line_height = font.line_height();
+
+    canvas.set_blend_mode(BlendMode::Blend);
+    canvas.set_draw_color(Color::RGBA(0, 0, 0, 190));
+    canvas.fill_rect(panel)?;
+    canvas.set_draw_color(Color::RGB(0, 255, 255));
+    canvas.draw_rect(panel)?;
+    canvas.set_blend_mode(BlendMode::None);
+
+    canvas.set_clip_rect(Rect::new(
+        panel.x(),
+        panel.y(),
+        panel.width() - 10,
+        panel.height(),
+    ));
+    let mut y = panel.y() + 10;
+    for line in lines {
+        font.draw(
+            canvas,
+            texture_creator,
+            line,
+            Color::RGB(255, 255, 255),
+            (panel.x() + 10, y),
+        )?;
+        y += line_height;
+    }
+    canvas.set_clip_rect(None);
+    Ok(())
+}
@@ -0,0 +1,352 @@
+use crate::render::{
+    ASPHALT, BOX, FrameView, GHOST_ALPHA, GRASS, LANE_MARK, MINIMAP_MARGIN, MINIMAP_SIZE,
+    ROADWORKS, Renderer, WORLD_SIZE, WRECK, direction_color,
+};
+use bytemuck::{Pod, Zeroable};
+use sdl2::pixels::Color;
+use sdl2::video::Window;
+
+/// Flat-coloured triangles: positions are already in clip space
+const SHADER: &str = r#"
+struct VertexOut {
+    @builtin(position) position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+};
+
+@vertex
+fn vs_main(@location(0) position: vec2<f32>, @location(1) color: vec4<f32>) -> VertexOut {
+    var out: VertexOut;
+    out.position = vec4<f32>(position, 0.0, 1.0);
+    out.color = color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOut) -> @location(0) vec4<f32> {
+    return in.color;
+}
+"#;
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Vertex {
+    position: [f32; 2],
+    color: [f32; 4],
+}
+
+/// Renderer drawing the road and vehicles as flat shapes with wgpu.
+///
+/// It needs no SDL2_image or SDL2_ttf assets: the road is drawn from its
+/// geometry and vehicles as boxes coloured by heading. The inspect panel is
+/// not drawn since there is no text rendering yet.
+pub struct WgpuRenderer {
+    // The surface points into the window, so it is declared (and dropped) first
+    surface: wgpu::Surface<'static>,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    config: wgpu::SurfaceConfiguration,
+    pipeline: wgpu::RenderPipeline,
+    vertex_buffer: wgpu::Buffer,
+    vertices: Vec<Vertex>,
+    window: Window,
+}
+
+impl WgpuRenderer {
+    pub fn new(window: Window, vsync: bool) -> Result<Self, String> {
+        let instance =
+            wgpu::Instance::new(wgpu::InstanceDescriptor::new_without_display_handle_from_env());
+        // SAFETY: the window is stored in the renderer and outlives the surface
+        let surface = unsafe {
+            let target =
+                wgpu::SurfaceTargetUnsafe::from_window(&window).map_err(|e| e.to_string())?;
+            instance.create_surface_unsafe(target)
+        }
+        .map_err(|e| e.to_string())?;
+
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            compatible_surface: Some(&surface),
+            ..Default::default()
+        }))
+        .map_err(|e| format!("No graphics adapter for wgpu: {}", e))?;
+        let (device, queue) =
+            pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor::default()))
+                .map_err(|e| e.to_string())?;
+
+        let (width, height) = window.size();
+        let mut config = surface
+            .get_default_config(&adapter, width, height)
+            .ok_or("Window surface is not supported by the graphics adapter")?;
+        config.present_mode = if vsync {
+            wgpu::PresentMode::AutoVsync
+        } else {
+            wgpu::PresentMode::AutoNoVsync
+        };
+        surface.configure(&device, &config);
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("quad shader"),
+            source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("quad layout"),
+            bind_group_layouts: &[],
+            immediate_size: 0,
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("quad pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                compilation_options: Default::default(),
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x4],
+                }],
+            },
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                compilation_options: Default::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: config.format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            multiview_mask: None,
+            cache: None,
+        });
+
+        let vertex_buffer = create_vertex_buffer(&device, 1024);
+
+        Ok(Self {
+            surface,
+            device,
+            queue,
+            config,
+            pipeline,
+            vertex_buffer,
+            vertices: Vec::new(),
+            window,
+        })
+    }
+
+    /// Fill the vertex list for one frame
+    fn build_vertices(&mut self, frame: &FrameView) {
+        let view = frame.camera.view();
+        let (view_x, view_y, view_size) = (view.x() as f32, view.y() as f32, view.width() as f32);
+        let world = |x: f32, y: f32| {
+            (
+                (x - view_x) / view_size * 2.0 - 1.0,
+                1.0 - (y - view_y) / view_size * 2.0,
+            )
+        };
+        let (window_width, window_height) = self.window.size();
+        let screen = |x: f32, y: f32| {
+            (
+                x / window_width as f32 * 2.0 - 1.0,
+                1.0 - y / window_height as f32 * 2.0,
+            )
+        };
+
+        let vertices = &mut self.vertices;
+        vertices.clear();
+
+        // Two crossing roads with the intersection box and centre lines
+        push_rect(
+            vertices,
+            world(350.0, 0.0),
+            world(650.0, WORLD_SIZE),
+            ASPHALT,
+        );
+        push_rect(
+            vertices,
+            world(0.0, 350.0),
+            world(WORLD_SIZE, 650.0),
+            ASPHALT,
+        );
+        push_rect(vertices, world(350.0, 350.0), world(650.0, 650.0), BOX);
+        for (from, to) in [
+            ((498.0, 0.0), (502.0, 350.0)),
+            ((498.0, 650.0), (502.0, WORLD_SIZE)),
+            ((0.0, 498.0), (350.0, 502.0)),
+            ((650.0, 498.0), (WORLD_SIZE, 502.0)),
+        ] {
+            push_rect(
+                vertices,
+                world(from.0, from.1),
+                world(to.0, to.1),
+                LANE_MARK,
+            );
+        }
+        if let Some((x, y, w, h)) = frame.layout.closed_area() {
+            push_rect(vertices, world(x, y), world(x + w, y + h), GRASS);
+        }
+
+        for &(x, y, w, h) in &frame.roadworks {
+            push_rect(vertices, world(x, y), world(x + w, y + h), ROADWORKS);
+        }
+
+        for obstacle in frame.obstacles {
+            let (x, y, w, h) = obstacle.area;
+            let (r, g, b) = obstacle.kind.color();
+            push_rect(
+                vertices,
+                world(x, y),
+                world(x + w, y + h),
+                Color::RGB(r, g, b),
+            );
+        }
+
+        for hazard in frame.hazards {
+            let (x, y, w, h) = hazard.area();
+            let (r, g, b) = hazard.kind.color();
+            push_rect(
+                vertices,
+                world(x, y),
+                world(x + w, y + h),
+                Color::RGB(r, g, b),
+            );
+        }
+
+        for ghost in frame.ghosts {
+            let (x, y, w, h) = ghost.get_visual_bounds();
+            let color = direction_color(ghost.direction);
+            let color = Color::RGBA(color.r, color.g, color.b, GHOST_ALPHA);
+            push_rect(vertices, world(x, y), world(x + w, y + h), color);
+        }
+
+        for vehicle in frame.vehicles {
+            let (x, y, w, h) = vehicle.get_visual_bounds();
+            let color = if vehicle.crashed_at.is_some() {
+                WRECK
+            } else {
+                direction_color(vehicle.direction)
+            };
+            push_rect(vertices, world(x, y), world(x + w, y + h), color);
+        }
+
+        if frame.show_minimap {
+            let left = (window_width as i32 - MINIMAP_SIZE as i32 - MINIMAP_MARGIN) as f32;
+            let top = MINIMAP_MARGIN as f32;
+            let size = MINIMAP_SIZE as f32;
+            let scale = size / WORLD_SIZE;
+            push_rect(
+                vertices,
+                screen(left, top),
+                screen(left + size, top + size),
+                Color::RGBA(20, 20, 20, 230),
+            );
+            for vehicle in frame.vehicles {
+                let (x, y, w, h) = vehicle.get_visual_bounds();
+                let (cx, cy) = (left + (x + w / 2.0) * scale, top + (y + h / 2.0) * scale);
+                let half = if frame.camera.following == Some(vehicle.id) {
+                    4.0
+                } else {
+                    2.0
+                };
+                push_rect(
+                    vertices,
+                    screen(cx - half, cy - half),
+                    screen(cx + half, cy + half),
+                    direction_color(vehicle.direction),
+                );
+            }
+        }
+    }
+}
+
+impl Renderer for WgpuRenderer {
+    fn draw_frame(&mut self, frame: &FrameView) -> Result<(), String> {
+        self.build_vertices(frame);
+
+        let needed = (self.vertices.len() * std::mem::size_of::<Vertex>()) as u64;
+        if needed > self.vertex_buffer.size() {
+            self.vertex_buffer = create_vertex_buffer(&self.device, self.vertices.len() * 2);
+        }
+        self.queue
+            .write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&self.vertices));
+
+        let surface_texture = match self.surface.get_current_texture() {
+            wgpu::CurrentSurfaceTexture::Success(texture)
+            | wgpu::CurrentSurfaceTexture::Suboptimal(texture) => texture,
+            wgpu::CurrentSurfaceTexture::Outdated | wgpu::CurrentSurfaceTexture::Lost => {
+                // Skip this frame and draw the next one on a fresh configuration
+                self.surface.configure(&self.device, &self.config);
+                return Ok(());
+            }
+            _ => return Ok(()),
+        };
+        let target = surface_texture
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("frame"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &target,
+                    depth_slice: None,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(to_wgpu_color(GRASS)),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+                multiview_mask: None,
+            });
+            pass.set_pipeline(&self.pipeline);
+            pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            pass.draw(0..self.vertices.len() as u32, 0..1);
+        }
+
+        self.queue.submit([encoder.finish()]);
+        surface_texture.present();
+        Ok(())
+    }
+}
+
+fn create_vertex_buffer(device: &wgpu::Device, vertices: usize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("quad vertices"),
+        size: (vertices * std::mem::size_of::<Vertex>()) as u64,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+/// Two triangles covering the rectangle between two clip-space corners
+fn push_rect(vertices: &mut Vec<Vertex>, (x0, y0): (f32, f32), (x1, y1): (f32, f32), color: Color) {
+    let color = [
+        color.r as f32 / 255.0,
+        color.g as f32 / 255.0,
+        color.b as f32 / 255.0,
+        color.a as f32 / 255.0,
+    ];
+    for (x, y) in [(x0, y0), (x1, y0), (x1, y1), (x0, y0), (x1, y1), (x0, y1)] {
+        vertices.push(Vertex {
+            position: [x, y],
+            color,
+        });
+    }
+}
+
+fn to_wgpu_color(color: Color) -> wgpu::Color {
+    wgpu::Color {
+        r: color.r as f64 / 255.0,
+        g: color.g as f64 / 255.0,
+        b: color.b as f64 / 255.0,
+        a: 1.0,
+    }
+}
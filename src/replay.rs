@@ -0,0 +1,414 @@
+use crate::driver::DriverProfile;
+use crate::events::{EventKind, SimEvent};
+use crate::intersection::SmartIntersection;
+use crate::layout::RoadLayout;
+use crate::route::{Direction, Route};
+use crate::vehicle::Vehicle;
+use crate::velocities::Velocity;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+/// Seconds of history kept before each close call
+const CLOSE_CALL_HISTORY: f32 = 3.0;
+
+/// Playback rate of close-call replays
+pub const SLOW_MOTION: f32 = 0.25;
+
+/// Frames (seconds at 60 Hz) kept for rewinding while paused
+const REWIND_FRAMES: usize = 600;
+
+/// Every vehicle on the road at one moment; a replay is one of these per frame
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FrameSnapshot {
+    pub time: f32,
+    pub vehicles_passed: u32,
+    #[serde(default)]
+    pub layout: RoadLayout,
+    pub vehicles: Vec<VehicleSnapshot>,
+}
+
+impl FrameSnapshot {
+    pub fn new(current_time: f32, intersection: &SmartIntersection) -> Self {
+        Self {
+            time: current_time,
+            vehicles_passed: intersection.total_vehicles_passed,
+            layout: intersection.layout,
+            vehicles: intersection
+                .vehicles()
+                .into_iter()
+                .map(VehicleSnapshot::new)
+                .collect(),
+        }
+    }
+}
+
+/// What is needed to draw a vehicle
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VehicleSnapshot {
+    pub id: usize,
+    pub car_index: u32,
+    pub direction: Direction,
+    pub route: Route,
+    pub position: (f32, f32),
+    pub width: u32,
+    pub height: u32,
+    pub rotation: f64,
+    pub crashed: bool,
+    #[serde(default)]
+    pub braking: bool,
+    #[serde(default)]
+    pub close_call_at: Option<f32>,
+    #[serde(default)]
+    pub speed: Option<Velocity>, // missing from replays recorded before speeds were kept
+    #[serde(default)]
+    pub permission: bool, // holds a crossing
+}
+
+impl VehicleSnapshot {
+    pub fn new(vehicle: &Vehicle) -> Self {
+        Self {
+            id: vehicle.id,
+            car_index: vehicle.car_index,
+            direction: vehicle.direction,
+            route: vehicle.route,
+            position: vehicle.position,
+            width: vehicle.width,
+            height: vehicle.height,
+            rotation: vehicle.rotation,
+            crashed: vehicle.crashed_at.is_some(),
+            braking: vehicle.braking,
+            close_call_at: vehicle.close_call_at,
+            speed: Some(vehicle.current_speed),
+            permission: vehicle.intersection_permission,
+        }
+    }
+
+    /// A stand-in vehicle carrying just what the renderers look at
+    pub fn to_vehicle(&self) -> Vehicle {
+        let mut vehicle = Vehicle::new(
+            self.route,
+            self.direction,
+            self.position,
+            (0.0, 0.0),
+            DriverProfile::Normal,
+        );
+        vehicle.id = self.id;
+        vehicle.car_index = self.car_index;
+        vehicle.width = self.width;
+        vehicle.height = self.height;
+        vehicle.rotation = self.rotation;
+        vehicle.crashed_at = self.crashed.then_some(0.0);
+        vehicle.braking = self.braking;
+        vehicle.close_call_at = self.close_call_at;
+        if let Some(speed) = self.speed {
+            vehicle.current_speed = speed;
+        }
+        vehicle.intersection_permission = self.permission;
+        vehicle
+    }
+}
+
+/// Reads a replay file written by `ReplayRecorder`
+pub fn read_frames(path: &str) -> Result<Vec<FrameSnapshot>, String> {
+    let file = File::open(path).map_err(|e| format!("{}: {}", path, e))?;
+    let mut frames = Vec::new();
+    for (number, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(|e| format!("{}: {}", path, e))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let frame: FrameSnapshot = serde_json::from_str(&line)
+            .map_err(|e| format!("{} line {}: {}", path, number + 1, e))?;
+        frames.push(frame);
+    }
+    Ok(frames)
+}
+
+/// Writes `frames` as a replay file, one `FrameSnapshot` JSON object per line
+fn write_frames(path: &Path, frames: &[FrameSnapshot]) -> Result<(), String> {
+    let file = File::create(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut writer = BufWriter::new(file);
+    for frame in frames {
+        serde_json::to_writer(&mut writer, frame).map_err(|e| e.to_string())?;
+        writer.write_all(b"\n").map_err(|e| e.to_string())?;
+    }
+    writer.flush().map_err(|e| e.to_string())
+}
+
+/// Writes a replay: one `FrameSnapshot` JSON object per line
+pub struct ReplayRecorder {
+    writer: BufWriter<File>,
+}
+
+impl ReplayRecorder {
+    pub fn create(path: &str) -> Result<Self, String> {
+        let file = File::create(path).map_err(|e| format!("{}: {}", path, e))?;
+        Ok(Self {
+            writer: BufWriter::new(file),
+        })
+    }
+
+    pub fn record(
+        &mut self,
+        current_time: f32,
+        intersection: &SmartIntersection,
+    ) -> Result<(), String> {
+        let frame = FrameSnapshot::new(current_time, intersection);
+        serde_json::to_writer(&mut self.writer, &frame).map_err(|e| e.to_string())?;
+        self.writer.write_all(b"\n").map_err(|e| e.to_string())
+    }
+
+    pub fn flush(&mut self) -> Result<(), String> {
+        self.writer.flush().map_err(|e| e.to_string())
+    }
+}
+
+/// A recorded run played back alongside the live one. It re-issues the
+/// recorded spawns so both runs see the same demand, and exposes the recorded
+/// vehicles of the current frame to be drawn as ghosts.
+pub struct GhostReplay {
+    frames: Vec<FrameSnapshot>,
+    cursor: Option<usize>, // last frame at or before the current time
+    seen: HashSet<usize>,
+    vehicles: Vec<Vehicle>,
+}
+
+impl GhostReplay {
+    pub fn load(path: &str) -> Result<Self, String> {
+        Ok(Self {
+            frames: read_frames(path)?,
+            cursor: None,
+            seen: HashSet::new(),
+            vehicles: Vec::new(),
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Move to `current_time` and return the vehicles that first appeared in
+    /// the recording since the last call, as (direction, route) to spawn
+    pub fn advance(&mut self, current_time: f32) -> Vec<(Direction, Route)> {
+        let mut spawns = Vec::new();
+        let mut next = self.cursor.map_or(0, |cursor| cursor + 1);
+        while next < self.frames.len() && self.frames[next].time <= current_time + 1e-4 {
+            for vehicle in &self.frames[next].vehicles {
+                if self.seen.insert(vehicle.id) {
+                    spawns.push((vehicle.direction, vehicle.route));
+                }
+            }
+            self.cursor = Some(next);
+            next += 1;
+        }
+
+        // Ghosts vanish once the live run outlasts the recording
+        let finished = self
+            .frames
+            .last()
+            .is_none_or(|last| current_time > last.time + 1.0 / 60.0);
+        self.vehicles = match self.cursor {
+            Some(cursor) if !finished => self.frames[cursor]
+                .vehicles
+                .iter()
+                .map(VehicleSnapshot::to_vehicle)
+                .collect(),
+            _ => Vec::new(),
+        };
+        spawns
+    }
+
+    /// Recorded vehicles at the current time
+    pub fn vehicles(&self) -> Vec<&Vehicle> {
+        self.vehicles.iter().collect()
+    }
+}
+
+/// The last few seconds of the road, kept so that every close call can be
+/// cut into a clip of the moments leading up to it
+#[derive(Default)]
+pub struct CloseCallBuffer {
+    frames: VecDeque<FrameSnapshot>,
+    latest: Option<CloseCallClip>,
+}
+
+/// The CLOSE_CALL_HISTORY seconds before a close call
+pub struct CloseCallClip {
+    pub time: f32,
+    pub vehicles: (usize, usize),
+    frames: Vec<FrameSnapshot>,
+}
+
+impl CloseCallBuffer {
+    /// Add the current frame and return the clip of a close call among `events`, if any
+    pub fn record(
+        &mut self,
+        current_time: f32,
+        intersection: &SmartIntersection,
+        events: &[SimEvent],
+    ) -> Option<&CloseCallClip> {
+        while self
+            .frames
+            .front()
+            .is_some_and(|frame| frame.time < current_time - CLOSE_CALL_HISTORY)
+        {
+            self.frames.pop_front();
+        }
+        self.frames
+            .push_back(FrameSnapshot::new(current_time, intersection));
+
+        let (vehicle_id, other_vehicle_id) = events.iter().find_map(|event| match event.kind {
+            EventKind::CloseCall { other_vehicle_id } => Some((event.vehicle_id, other_vehicle_id)),
+            _ => None,
+        })?;
+        self.latest = Some(CloseCallClip {
+            time: current_time,
+            vehicles: (vehicle_id, other_vehicle_id),
+            frames: self.frames.iter().cloned().collect(),
+        });
+        self.latest.as_ref()
+    }
+
+    /// The most recent close call
+    pub fn latest(&self) -> Option<&CloseCallClip> {
+        self.latest.as_ref()
+    }
+}
+
+impl CloseCallClip {
+    /// Save as `close_call_<time>s_<a>_<b>.jsonl` in `dir`, in the replay format
+    pub fn save(&self, dir: &str) -> Result<String, String> {
+        std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir, e))?;
+        let path = Path::new(dir).join(format!(
+            "close_call_{:.1}s_{}_{}.jsonl",
+            self.time, self.vehicles.0, self.vehicles.1
+        ));
+        write_frames(&path, &self.frames)?;
+        Ok(path.display().to_string())
+    }
+}
+
+/// A close-call clip played back at SLOW_MOTION while the simulation waits
+pub struct SlowMotionReplay {
+    pub vehicles: (usize, usize),
+    frames: Vec<FrameSnapshot>,
+    elapsed: f32, // clip seconds played so far
+    shown: Vec<Vehicle>,
+}
+
+impl SlowMotionReplay {
+    pub fn new(clip: &CloseCallClip) -> Self {
+        let mut replay = Self {
+            vehicles: clip.vehicles,
+            frames: clip.frames.clone(),
+            elapsed: 0.0,
+            shown: Vec::new(),
+        };
+        replay.advance(0.0);
+        replay
+    }
+
+    /// Play `real_seconds` of wall time; false once the clip is over
+    pub fn advance(&mut self, real_seconds: f32) -> bool {
+        self.elapsed += real_seconds * SLOW_MOTION;
+        let Some(start) = self.frames.first().map(|frame| frame.time) else {
+            return false;
+        };
+        let frame = self
+            .frames
+            .iter()
+            .take_while(|frame| frame.time - start <= self.elapsed)
+            .last();
+        if let Some(frame) = frame {
+            self.shown = frame
+                .vehicles
+                .iter()
+                .map(VehicleSnapshot::to_vehicle)
+                .collect();
+        }
+        self.frames
+            .last()
+            .is_some_and(|last| last.time - start >= self.elapsed)
+    }
+
+    /// Vehicles of the clip frame being shown
+    pub fn vehicles(&self) -> Vec<&Vehicle> {
+        self.shown.iter().collect()
+    }
+
+    /// Seconds before the close call of the frame being shown
+    pub fn time_to_close_call(&self) -> f32 {
+        let span = match (self.frames.first(), self.frames.last()) {
+            (Some(first), Some(last)) => last.time - first.time,
+            _ => 0.0,
+        };
+        (span - self.elapsed).max(0.0)
+    }
+}
+
+/// The last REWIND_FRAMES frames of the road, in a ring buffer, to step
+/// through while the simulation is paused
+#[derive(Default)]
+pub struct RewindBuffer {
+    frames: VecDeque<FrameSnapshot>,
+    cursor: Option<usize>, // frame being shown; None = the live road
+    shown: Vec<Vehicle>,
+}
+
+impl RewindBuffer {
+    pub fn record(&mut self, current_time: f32, intersection: &SmartIntersection) {
+        if self.frames.len() == REWIND_FRAMES {
+            self.frames.pop_front();
+        }
+        self.frames
+            .push_back(FrameSnapshot::new(current_time, intersection));
+    }
+
+    /// Move `frames` frames back (negative) or forward through the buffer;
+    /// stepping forward past the newest frame returns to the live road
+    pub fn step(&mut self, frames: isize) {
+        let Some(newest) = self.frames.len().checked_sub(1) else {
+            return;
+        };
+        let target = self.cursor.unwrap_or(newest) as isize + frames;
+        self.cursor = (target < newest as isize).then(|| target.max(0) as usize);
+        self.shown = match self.cursor {
+            Some(cursor) => self.frames[cursor]
+                .vehicles
+                .iter()
+                .map(VehicleSnapshot::to_vehicle)
+                .collect(),
+            None => Vec::new(),
+        };
+    }
+
+    /// Back to the live road, as when the simulation resumes
+    pub fn resume(&mut self) {
+        self.cursor = None;
+        self.shown.clear();
+    }
+
+    /// Vehicles of the frame being shown, or None on the live road
+    pub fn vehicles(&self) -> Option<Vec<&Vehicle>> {
+        self.cursor.map(|_| self.shown.iter().collect())
+    }
+
+    /// Seconds between the frame being shown and the newest one
+    pub fn seconds_back(&self) -> f32 {
+        match (self.cursor, self.frames.back()) {
+            (Some(cursor), Some(newest)) => newest.time - self.frames[cursor].time,
+            _ => 0.0,
+        }
+    }
+
+    /// Seconds of road kept
+    pub fn span(&self) -> f32 {
+        match (self.frames.front(), self.frames.back()) {
+            (Some(oldest), Some(newest)) => newest.time - oldest.time,
+            _ => 0.0,
+        }
+    }
+}
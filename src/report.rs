@@ -0,0 +1,418 @@
+use crate::assets::PANEL_FONT;
+use crate::controller::DenialCause;
+use crate::cost::{CostTerms, CostWeights};
+use crate::distribution::Summary;
+use crate::fuel::{ClassEmissions, VehicleClass};
+use crate::heatmap::{Occupancy, heat_color};
+use crate::history::RunHistory;
+use crate::intersection::SmartIntersection;
+use crate::queues::{APPROACHES, ApproachQueue};
+use crate::route::Direction;
+#[cfg(feature = "ttf")]
+use crate::stats::BarChart;
+use plotters::prelude::*;
+use plotters::style::{FontStyle, register_font};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Once;
+
+const CHART_SIZE: (u32, u32) = (800, 500);
+const HEATMAP_SIZE: (u32, u32) = (640, 640);
+/// Samples (seconds) averaged into each throughput point
+const THROUGHPUT_WINDOW: usize = 10;
+/// Width of each bar in the velocity histogram, px/s
+const VELOCITY_BUCKET: f32 = 20.0;
+
+static REGISTER_FONT: Once = Once::new();
+
+/// Machine-readable summary of a finished run. Read back leniently, so
+/// summaries stored before a field existed still load.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RunSummary {
+    pub duration: f32, // seconds the statistics cover, the warm-up left out
+    pub vehicles_passed: u32,
+    pub passed_by_approach: HashMap<Direction, u32>,
+    pub average_velocity: f32,
+    pub max_velocity: f32,
+    pub min_velocity: f32,
+    pub max_time_in_intersection: f32,
+    pub min_time_in_intersection: f32,
+    pub average_wait_time: f32,
+    pub average_spawn_delay: f32,
+    pub travel_time: Summary,
+    pub wait_time: Summary,
+    pub vehicles_never_stopped: usize,
+    pub average_stops: f32,
+    pub zero_stop_share: f32,
+    pub close_calls: u32,
+    pub close_calls_by_approach: HashMap<Direction, u32>,
+    pub collisions: u32,
+    pub vehicles_crashed: u32,
+    pub breakdowns: u32,
+    pub total_fuel_ml: f32,
+    pub average_fuel_ml: f32,
+    pub idle_fuel_share: f32,
+    pub co2_kg: f32,
+    pub nox_g: f32,
+    pub emissions_by_class: HashMap<VehicleClass, ClassEmissions>,
+    pub platoons: u32,
+    pub average_platoon_size: f32,
+    pub messages_lost: u32,
+    pub permissions_lost: u32,
+    pub duplicate_reservations: u32,
+    pub withdrawn_crossings: u32,
+    pub denials_by_cause: HashMap<DenialCause, u32>,
+    pub queue_by_approach: HashMap<Direction, ApproachQueue>,
+    pub controller: String,
+    pub layout: String,
+    pub weather: String,
+    pub active_vehicles_remaining: usize,
+    pub cost: CostTerms,
+    pub cost_weights: CostWeights,
+    pub score: f32,
+}
+
+impl RunSummary {
+    pub fn new(intersection: &SmartIntersection, duration: f32) -> Self {
+        let passed = intersection.total_vehicles_passed;
+        let or_zero = |value: f32| if value == f32::MAX { 0.0 } else { value };
+
+        Self {
+            duration: intersection.measured_time(duration),
+            vehicles_passed: passed,
+            passed_by_approach: by_approach(&intersection.passed_by_approach),
+            average_velocity: if passed > 0 {
+                intersection.total_velocities / passed as f32
+            } else {
+                0.0
+            },
+            max_velocity: intersection.max_velocity_recorded,
+            min_velocity: or_zero(intersection.min_velocity_recorded),
+            max_time_in_intersection: intersection.max_time_in_intersection,
+            min_time_in_intersection: or_zero(intersection.min_time_in_intersection),
+            average_wait_time: intersection.average_wait_time(),
+            average_spawn_delay: intersection.average_spawn_delay(),
+            travel_time: Summary::of(&intersection.travel_times),
+            wait_time: Summary::of(&intersection.wait_times),
+            vehicles_never_stopped: intersection.vehicles_never_stopped(),
+            average_stops: intersection.average_stops(),
+            zero_stop_share: intersection.zero_stop_share(),
+            close_calls: intersection.close_calls,
+            close_calls_by_approach: by_approach(&intersection.close_calls_by_approach),
+            collisions: intersection.collisions,
+            vehicles_crashed: intersection.vehicles_crashed,
+            breakdowns: intersection.breakdowns,
+            total_fuel_ml: intersection.total_fuel,
+            average_fuel_ml: intersection.average_fuel(),
+            idle_fuel_share: intersection.idle_share(),
+            co2_kg: intersection.total_co2() / 1000.0,
+            nox_g: intersection.total_nox(),
+            emissions_by_class: VehicleClass::ALL
+                .into_iter()
+                .map(|class| (class, intersection.class_emissions(class)))
+                .collect(),
+            platoons: intersection.platoons,
+            average_platoon_size: intersection.average_platoon_size(),
+            messages_lost: intersection.requests_lost + intersection.replies_lost,
+            permissions_lost: intersection.permissions_lost,
+            duplicate_reservations: intersection.duplicate_reservations,
+            withdrawn_crossings: intersection.withdrawn_crossings,
+            denials_by_cause: DenialCause::ALL
+                .into_iter()
+                .map(|cause| (cause, intersection.denial_count(cause)))
+                .collect(),
+            queue_by_approach: APPROACHES
+                .into_iter()
+                .map(|direction| (direction, intersection.queues.approach(direction)))
+                .collect(),
+            controller: intersection.controller.name().to_string(),
+            layout: intersection.layout.name().to_string(),
+            weather: intersection.weather.name().to_string(),
+            active_vehicles_remaining: intersection.vehicle_count(),
+            cost: intersection.cost.totals,
+            cost_weights: intersection.cost.weights,
+            score: 0.0,
+        }
+        .scored()
+    }
+
+    /// Fill in the score: the run's weighted cost per minute, negated so
+    /// higher is better. Per minute, so runs of different lengths compare
+    /// fairly.
+    fn scored(mut self) -> Self {
+        let minutes = (self.duration / 60.0).max(1.0 / 60.0);
+        self.score = -self.cost.weighted(&self.cost_weights) / minutes;
+        self
+    }
+}
+
+/// Every approach's count, zero where nothing was recorded
+fn by_approach(counts: &HashMap<Direction, u32>) -> HashMap<Direction, u32> {
+    APPROACHES
+        .into_iter()
+        .map(|direction| (direction, counts.get(&direction).copied().unwrap_or(0)))
+        .collect()
+}
+
+/// The stats screen's text, and the same run as `stats.json` would hold it
+pub struct FinalStats {
+    pub text: String,
+    #[cfg(feature = "ttf")]
+    pub json: String, // copied with Shift+C on the stats screen
+    #[cfg(feature = "ttf")]
+    pub charts: Vec<BarChart>,
+}
+
+/// Write `stats.json`, `history.json` and the PNG charts into `dir`; the
+/// occupancy heatmap only for controllers that book grid cells
+pub fn write_run_report(
+    dir: &str,
+    intersection: &SmartIntersection,
+    history: &RunHistory,
+    duration: f32,
+) -> Result<(), String> {
+    let dir = Path::new(dir);
+    std::fs::create_dir_all(dir)
+        .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+
+    let summary = RunSummary::new(intersection, duration);
+    write_json(&dir.join("stats.json"), &summary)?;
+    write_json(&dir.join("history.json"), &history.samples)?;
+
+    REGISTER_FONT.call_once(|| {
+        // Only fails on a corrupt font; charts then render without labels
+        let _ = register_font("sans-serif", FontStyle::Normal, PANEL_FONT.contents());
+    });
+
+    let throughput = history.throughput(THROUGHPUT_WINDOW);
+    line_chart(
+        &dir.join("throughput.png"),
+        "Throughput",
+        "vehicles / min",
+        &throughput,
+    )?;
+
+    let queue: Vec<(f32, f32)> = history
+        .samples
+        .iter()
+        .map(|s| (s.time, s.queued as f32))
+        .collect();
+    line_chart(
+        &dir.join("queue_length.png"),
+        "Queue length",
+        "vehicles waiting",
+        &queue,
+    )?;
+
+    velocity_histogram(
+        &dir.join("velocity_distribution.png"),
+        &intersection.exit_velocities,
+    )?;
+
+    density_heatmap(&dir.join("density_heatmap.png"), &history.density)?;
+
+    match intersection.controller.occupancy() {
+        Some(occupancy) => occupancy_heatmap(&dir.join("occupancy_heatmap.png"), &occupancy),
+        None => Ok(()),
+    }
+}
+
+fn write_json(path: &Path, value: &impl Serialize) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    std::fs::write(path, json).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+fn chart_error(path: &Path, error: impl std::fmt::Display) -> String {
+    format!("Failed to draw {}: {}", path.display(), error)
+}
+
+fn line_chart(
+    path: &Path,
+    title: &str,
+    y_label: &str,
+    points: &[(f32, f32)],
+) -> Result<(), String> {
+    let root = BitMapBackend::new(path, CHART_SIZE).into_drawing_area();
+    root.fill(&WHITE).map_err(|e| chart_error(path, e))?;
+
+    let max_time = points.last().map_or(1.0, |p| p.0).max(1.0);
+    let max_value = points.iter().map(|p| p.1).fold(1.0, f32::max) * 1.1;
+
+    let mut chart = ChartBuilder::on(&root)
+        .caption(title, ("sans-serif", 28))
+        .margin(15)
+        .x_label_area_size(40)
+        .y_label_area_size(60)
+        .build_cartesian_2d(0f32..max_time, 0f32..max_value)
+        .map_err(|e| chart_error(path, e))?;
+
+    chart
+        .configure_mesh()
+        .x_desc("time (s)")
+        .y_desc(y_label)
+        .draw()
+        .map_err(|e| chart_error(path, e))?;
+
+    chart
+        .draw_series(LineSeries::new(points.iter().copied(), &BLUE))
+        .map_err(|e| chart_error(path, e))?;
+
+    root.present().map_err(|e| chart_error(path, e))
+}
+
+/// Grid cells as squares coloured by how often they were booked, row 0 at the top
+fn occupancy_heatmap(path: &Path, occupancy: &Occupancy) -> Result<(), String> {
+    let root = BitMapBackend::new(path, HEATMAP_SIZE).into_drawing_area();
+    root.fill(&WHITE).map_err(|e| chart_error(path, e))?;
+
+    let max_share = occupancy.max_share();
+    let (cols, rows) = (occupancy.cols as u32, occupancy.rows as u32);
+    let mut chart = ChartBuilder::on(&root)
+        .caption(
+            format!(
+                "Reservation grid occupancy (busiest cell booked {:.0}% of the time)",
+                max_share * 100.0
+            ),
+            ("sans-serif", 20),
+        )
+        .margin(15)
+        .x_label_area_size(40)
+        .y_label_area_size(50)
+        .build_cartesian_2d(0u32..cols, 0u32..rows)
+        .map_err(|e| chart_error(path, e))?;
+
+    chart
+        .configure_mesh()
+        .disable_mesh()
+        .x_desc("column")
+        .y_desc("row")
+        .y_label_formatter(&|y| (rows - y).to_string())
+        .draw()
+        .map_err(|e| chart_error(path, e))?;
+
+    chart
+        .draw_series((0..rows).flat_map(|row| {
+            (0..cols).map(move |col| {
+                let share = occupancy.share(col as usize, row as usize);
+                let (r, g, b) = heat_color(share, max_share);
+                let y = rows - 1 - row; // plot y grows upwards
+                Rectangle::new([(col, y), (col + 1, y + 1)], RGBColor(r, g, b).filled())
+            })
+        }))
+        .map_err(|e| chart_error(path, e))?;
+
+    root.present().map_err(|e| chart_error(path, e))
+}
+
+/// The world as squares coloured by how often a vehicle stood on them, with
+/// the road edges outlined, so queues show up as hot strips on the approaches
+fn density_heatmap(path: &Path, density: &Occupancy) -> Result<(), String> {
+    let root = BitMapBackend::new(path, HEATMAP_SIZE).into_drawing_area();
+    root.fill(&WHITE).map_err(|e| chart_error(path, e))?;
+
+    let max_share = density.max_share();
+    let size = density.cols as f32 * density.cell_size;
+    let mut chart = ChartBuilder::on(&root)
+        .caption(
+            format!(
+                "Vehicle density (busiest square occupied {:.0}% of the time)",
+                max_share * 100.0
+            ),
+            ("sans-serif", 20),
+        )
+        .margin(15)
+        .x_label_area_size(40)
+        .y_label_area_size(50)
+        .build_cartesian_2d(0f32..size, 0f32..size)
+        .map_err(|e| chart_error(path, e))?;
+
+    chart
+        .configure_mesh()
+        .disable_mesh()
+        .x_desc("x (px)")
+        .y_desc("y (px)")
+        .x_label_formatter(&|x| format!("{:.0}", x))
+        .y_label_formatter(&|y| format!("{:.0}", size - y))
+        .draw()
+        .map_err(|e| chart_error(path, e))?;
+
+    // Plot y grows upwards, world y downwards
+    let cell = density.cell_size;
+    chart
+        .draw_series((0..density.rows).flat_map(|row| {
+            (0..density.cols).map(move |col| {
+                let (r, g, b) = heat_color(density.share(col, row), max_share);
+                let (x, y) = (col as f32 * cell, size - (row + 1) as f32 * cell);
+                Rectangle::new([(x, y), (x + cell, y + cell)], RGBColor(r, g, b).filled())
+            })
+        }))
+        .map_err(|e| chart_error(path, e))?;
+
+    for edge in [350.0, 650.0] {
+        chart
+            .draw_series([
+                PathElement::new(vec![(edge, 0.0), (edge, size)], WHITE.mix(0.4)),
+                PathElement::new(
+                    vec![(0.0, size - edge), (size, size - edge)],
+                    WHITE.mix(0.4),
+                ),
+            ])
+            .map_err(|e| chart_error(path, e))?;
+    }
+
+    root.present().map_err(|e| chart_error(path, e))
+}
+
+fn velocity_histogram(path: &Path, velocities: &[f32]) -> Result<(), String> {
+    let root = BitMapBackend::new(path, CHART_SIZE).into_drawing_area();
+    root.fill(&WHITE).map_err(|e| chart_error(path, e))?;
+
+    let buckets = velocities
+        .iter()
+        .map(|v| (v / VELOCITY_BUCKET) as u32)
+        .max()
+        .unwrap_or(0)
+        + 1;
+    let mut counts = vec![0u32; buckets as usize];
+    for v in velocities {
+        counts[(v / VELOCITY_BUCKET) as usize] += 1;
+    }
+    let max_count = counts.iter().copied().max().unwrap_or(0).max(1);
+
+    let mut chart = ChartBuilder::on(&root)
+        .caption("Velocity distribution", ("sans-serif", 28))
+        .margin(15)
+        .x_label_area_size(40)
+        .y_label_area_size(60)
+        .build_cartesian_2d(
+            (0u32..buckets).into_segmented(),
+            0u32..max_count + max_count / 10 + 1,
+        )
+        .map_err(|e| chart_error(path, e))?;
+
+    chart
+        .configure_mesh()
+        .x_desc("velocity (px/s)")
+        .y_desc("vehicles")
+        .x_label_formatter(&|bucket| match bucket {
+            SegmentValue::CenterOf(b) | SegmentValue::Exact(b) => {
+                format!("{:.0}", *b as f32 * VELOCITY_BUCKET)
+            }
+            SegmentValue::Last => String::new(),
+        })
+        .draw()
+        .map_err(|e| chart_error(path, e))?;
+
+    chart
+        .draw_series(
+            Histogram::vertical(&chart)
+                .style(BLUE.filled())
+                .margin(2)
+                .data(counts.iter().enumerate().map(|(i, &c)| (i as u32, c))),
+        )
+        .map_err(|e| chart_error(path, e))?;
+
+    root.present().map_err(|e| chart_error(path, e))
+}
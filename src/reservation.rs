@@ -0,0 +1,1559 @@
+use crate::construction::WORKS_LENGTH;
+use crate::controller::{
+    BookedSlot, CellOwners, Denial, DenialCause, IntersectionController, PermissionRequest,
+    SlotConflict, Timeline,
+};
+use crate::heatmap::Occupancy;
+use crate::layout::RoadLayout;
+use crate::route::{Direction, Route, exit_direction, get_spawn_position, get_turn_position};
+use crate::vehicle::{TURN_RADIUS, VEHICLE_LENGTH, VEHICLE_WIDTH};
+use crate::velocities::Velocity;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Intersection geometry
+const IX_MIN: f32 = 350.0;
+const IY_MIN: f32 = 350.0;
+const IX_MAX: f32 = 650.0;
+const IY_MAX: f32 = 650.0;
+
+/// Extra distance in pixels a vehicle's footprint covers ahead of and behind
+/// its rectangle, unless the controller is built with another margin
+pub const DEFAULT_FOOTPRINT_MARGIN: f32 = 10.0;
+/// Pixels driven between the points a path's footprint is traced at
+const TRACE_STEP: f32 = 1.0;
+/// Seconds a vehicle may fall behind its booking before its windows are
+/// moved, so that rounding in where it is measured to be doesn't move them
+/// every frame
+const LATE_TOLERANCE: f32 = 1.0 / 60.0;
+/// Pixels short of the box within which a vehicle at full speed can no
+/// longer brake to a stop before its footprint reaches it, counting the
+/// frame before the intersection hears its crossing was withdrawn
+const COMMIT_DISTANCE: f32 = 20.0;
+/// Most seconds a vehicle's windows may be moved earlier in all than they
+/// were granted for, when it turns out to be arriving sooner
+const MAX_SLOT_ADVANCE: f32 = 0.5;
+
+#[derive(Clone, Serialize, Deserialize)]
+struct TimeSlot {
+    start: f32,
+    end: f32,
+    vehicle_id: usize,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Cell {
+    slots: Vec<TimeSlot>,
+}
+
+/// A grid cell a vehicle's footprint passes over, with how far its centre
+/// has driven past the entry line when the footprint first reaches the cell
+/// and when it has left it again (negative before the centre is in the box)
+#[derive(Clone, Copy, Debug)]
+pub struct SweptCell {
+    pub cell: (usize, usize),
+    pub from: f32,
+    pub to: f32,
+}
+
+/// Cells a vehicle's rectangle sweeps on one route through the box
+#[derive(Clone, Debug)]
+pub struct Footprint {
+    pub cells: Vec<SweptCell>,
+    pub distance: f32, // centre's drive from the entry line until it leaves the box
+}
+
+impl Footprint {
+    /// Window on each cell for a vehicle `distance_to_intersection` short of
+    /// the entry line at `current_time` whose centre crosses it at `entry`
+    /// and drives on at `speed`. Cells the footprint already covers are held
+    /// from now.
+    pub fn windows(
+        &self,
+        distance_to_intersection: f32,
+        current_time: f32,
+        entry: f32,
+        speed: Velocity,
+    ) -> impl Iterator<Item = ((usize, usize), f32, f32)> + '_ {
+        let speed = speed.pixels_per_second();
+        self.cells.iter().map(move |swept| {
+            let start = if swept.from <= -distance_to_intersection {
+                current_time
+            } else {
+                entry + swept.from / speed
+            };
+            (swept.cell, start, entry + swept.to / speed)
+        })
+    }
+}
+
+/// Memoized footprint of one direction+route combination, with the centre
+/// positions it was traced from for telling how far along a vehicle is
+#[derive(Clone, Debug)]
+struct TracedPath {
+    footprint: Footprint,
+    centres: Vec<((f32, f32), f32)>,
+}
+
+type PathCache = HashMap<(Direction, Route), TracedPath>;
+
+/// A crossing granted by the controller itself: the speed its windows were
+/// booked for, which the vehicle has to keep to arrive when they start, the
+/// seconds of slack booked around them, how hard it speeds up, when its
+/// centre is booked to cross the entry line, how many seconds earlier
+/// than granted that has been moved and who asked for it: the vehicle
+/// itself, or the leader of the platoon it was booked with
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct Grant {
+    speed: Velocity,
+    margin: f32,
+    acceleration: f32,
+    entry: f32,
+    advanced: f32,
+    group: usize,
+}
+
+/// What a checkpoint keeps of the controller; the path cache is rebuilt
+#[derive(Serialize, Deserialize)]
+struct SavedBookings {
+    grid: Vec<Cell>,
+    closed_paths: Vec<(Direction, Route)>,
+    held_cells: HashMap<usize, Vec<(usize, usize)>>,
+    occupancy: Occupancy,
+    grants: HashMap<usize, Grant>,
+    entered: Vec<usize>,
+}
+
+/// The default controller: vehicles book time slots on a grid of cells
+/// covering the intersection and may only enter once their whole path is booked
+pub struct ReservationController {
+    zone_px: u32, // e.g., 30 => 10x10 grid
+    cols: usize,  // 300/zone_px
+    rows: usize,
+    grid: Vec<Cell>, // flattened rows*cols
+
+    // Memoized path calculations, traced with this many pixels of margin and
+    // widened by this many cells all round
+    path_cache: PathCache,
+    footprint_margin: f32,
+    path_padding: usize,
+    // Paths of lanes closed for roadworks, left out of booking until reopened.
+    // Their cache entries stay so vehicles already crossing still release cells.
+    closed_paths: HashSet<(Direction, Route)>,
+
+    // Cells blocked by stranded vehicles, released on repair
+    held_cells: HashMap<usize, Vec<(usize, usize)>>,
+
+    // Crossings this controller granted, and which vehicles' footprints have
+    // reached the box or are too close to stop short of it; those can no
+    // longer be turned back
+    grants: HashMap<usize, Grant>,
+    entered: HashSet<usize>,
+    // Vehicles that lost their crossing for falling behind it or being in
+    // the way of one that did, until the intersection asks
+    withdrawn: Vec<usize>,
+    // Why the last request was turned down, until the intersection asks
+    denial: Option<Denial>,
+
+    // Frames each cell spent booked, for the occupancy heatmap
+    occupancy: Occupancy,
+}
+
+impl ReservationController {
+    pub fn new() -> Self {
+        Self::for_layout(RoadLayout::Crossroads)
+    }
+
+    /// Controller whose path cache only holds the routes `layout` has roads for
+    pub fn for_layout(layout: RoadLayout) -> Self {
+        Self::with_margins(layout, DEFAULT_FOOTPRINT_MARGIN, 0)
+    }
+
+    /// Controller for `layout` whose footprints reach `margin` pixels ahead
+    /// of and behind each vehicle and take in `padding` more cells on every
+    /// side of the path
+    pub fn with_margins(layout: RoadLayout, margin: f32, padding: usize) -> Self {
+        let zone_px = 10;
+        let cols = 300 / zone_px;
+        let rows = cols;
+
+        let mut controller = Self {
+            zone_px: zone_px as u32,
+            cols,
+            rows,
+            grid: vec![Cell { slots: Vec::new() }; cols * rows],
+            path_cache: HashMap::new(),
+            footprint_margin: margin.max(0.0),
+            path_padding: padding,
+            closed_paths: HashSet::new(),
+            held_cells: HashMap::new(),
+            grants: HashMap::new(),
+            entered: HashSet::new(),
+            withdrawn: Vec::new(),
+            denial: None,
+            occupancy: Occupancy::new(cols, rows, (IX_MIN, IY_MIN), zone_px as f32),
+        };
+
+        // Pre-calculate all possible paths
+        controller.initialize_path_cache(layout);
+        controller
+    }
+
+    /// Pre-calculate all possible vehicle paths for memoization
+    fn initialize_path_cache(&mut self, layout: RoadLayout) {
+        for (direction, route) in layout.paths() {
+            let path = self.trace_path(direction, route);
+            self.path_cache.insert((direction, route), path);
+        }
+    }
+
+    /// Drive a vehicle's rectangle along `direction`/`route` through the box
+    /// a pixel at a time and note when it covers each cell. Vehicles turn on
+    /// the first frame their centre is in reach of the turn position, so up
+    /// to a frame's drive after the first point in reach depending on their
+    /// speed; the footprint covers every one of those turns, from the traced
+    /// point just before coming into reach. Each cell is then booked on its
+    /// neighbours within the path padding too, for as long as any of them is
+    /// covered.
+    fn trace_path(&self, direction: Direction, route: Route) -> TracedPath {
+        let spawn = get_spawn_position(direction, route);
+        let spawn_centre = (
+            spawn.0 + VEHICLE_WIDTH as f32 / 2.0,
+            spawn.1 + VEHICLE_LENGTH as f32 / 2.0,
+        );
+        // Distance from the spawn point to the entry line
+        let approach = match direction {
+            Direction::North => spawn_centre.1 - IY_MAX,
+            Direction::South => IY_MIN - spawn_centre.1,
+            Direction::East => IX_MIN - spawn_centre.0,
+            Direction::West => spawn_centre.0 - IX_MAX,
+        };
+        // Start just before the footprint reaches the box
+        let lead = VEHICLE_LENGTH as f32 / 2.0 + self.footprint_margin + TRACE_STEP;
+        let start = step(spawn_centre, direction, approach - lead);
+        let turn_position = get_turn_position(direction, route);
+        let latest_turn = if route == Route::Straight {
+            0
+        } else {
+            Velocity::FAST.pixels_per_frame().ceil() as usize + 1
+        };
+
+        let mut spans: Vec<Option<(f32, f32)>> = vec![None; self.cols * self.rows];
+        let mut centres = Vec::new();
+        let mut distance: f32 = 0.0;
+        for lateness in 0..=latest_turn {
+            let (mut centre, mut heading, mut driven) = (start, direction, -lead);
+            let mut in_reach_at = None;
+            let mut entered = false;
+            loop {
+                if heading == direction && route != Route::Straight {
+                    let ahead = step(centre, heading, TRACE_STEP);
+                    let reach = (ahead.0 - turn_position.0).hypot(ahead.1 - turn_position.1);
+                    if in_reach_at.is_none() && reach <= TURN_RADIUS {
+                        in_reach_at = Some(driven);
+                    }
+                    if in_reach_at.is_some_and(|at| driven >= at + lateness as f32) {
+                        heading = exit_direction(direction, route);
+                    }
+                }
+
+                let (x, y, w, h) = self.footprint_at(centre, heading);
+                let cells = self.cells_in_bounds(x, y, w, h);
+                if cells.is_empty() && entered {
+                    break;
+                }
+                entered |= !cells.is_empty();
+                for (col, row) in cells {
+                    let span = &mut spans[self.cell_index(col, row)];
+                    *span = Some(span.map_or((driven, driven), |(from, to)| {
+                        (from.min(driven), to.max(driven))
+                    }));
+                }
+                if lateness == 0 {
+                    centres.push((centre, driven));
+                }
+                if (IX_MIN..=IX_MAX).contains(&centre.0) && (IY_MIN..=IY_MAX).contains(&centre.1) {
+                    distance = distance.max(driven);
+                }
+
+                centre = step(centre, heading, TRACE_STEP);
+                driven += TRACE_STEP;
+            }
+        }
+
+        let spans = self.pad_spans(&spans);
+        let cells = spans
+            .into_iter()
+            .enumerate()
+            .filter_map(|(idx, span)| {
+                span.map(|(from, to)| SweptCell {
+                    cell: (idx % self.cols, idx / self.cols),
+                    from,
+                    to,
+                })
+            })
+            .collect();
+        TracedPath {
+            footprint: Footprint { cells, distance },
+            centres,
+        }
+    }
+
+    /// Spans widened to every cell within `path_padding` cells (diagonals
+    /// included) of a covered one, each the union of its neighbours' spans
+    fn pad_spans(&self, spans: &[Option<(f32, f32)>]) -> Vec<Option<(f32, f32)>> {
+        let padding = self.path_padding;
+        let mut padded = spans.to_vec();
+        if padding == 0 {
+            return padded;
+        }
+        for (idx, span) in spans.iter().enumerate() {
+            let Some((from, to)) = *span else { continue };
+            let (col, row) = (idx % self.cols, idx / self.cols);
+            for r in row.saturating_sub(padding)..=(row + padding).min(self.rows - 1) {
+                for c in col.saturating_sub(padding)..=(col + padding).min(self.cols - 1) {
+                    let cell = &mut padded[self.cell_index(c, r)];
+                    *cell = Some(cell.map_or((from, to), |(a, b)| (a.min(from), b.max(to))));
+                }
+            }
+        }
+        padded
+    }
+
+    /// A vehicle's rectangle around `centre` facing `heading`, stretched by
+    /// the footprint margin at both ends
+    fn footprint_at(&self, (x, y): (f32, f32), heading: Direction) -> (f32, f32, f32, f32) {
+        let along = VEHICLE_LENGTH as f32 / 2.0 + self.footprint_margin;
+        let across = VEHICLE_WIDTH as f32 / 2.0;
+        match heading {
+            Direction::North | Direction::South => {
+                (x - across, y - along, 2.0 * across, 2.0 * along)
+            }
+            Direction::East | Direction::West => (x - along, y - across, 2.0 * along, 2.0 * across),
+        }
+    }
+
+    /// Try intersection request with two-path system and adaptive speed
+    fn try_two_path_intersection_request(
+        &mut self,
+        request: &PermissionRequest,
+        current_time: f32,
+    ) -> Result<Velocity, Denial> {
+        self.try_platoon_request(request, &[], current_time)
+    }
+
+    /// Book the path for the requesting vehicle and every follower (id and
+    /// distance to the intersection) at one common speed, all or nothing.
+    /// Followers are checked against existing bookings but not against each
+    /// other: the intersection keeps platoon members apart itself. Turned
+    /// down, it says which slots were in the way at the slowest speed tried.
+    fn try_platoon_request(
+        &mut self,
+        request: &PermissionRequest,
+        followers: &[(usize, f32)],
+        current_time: f32,
+    ) -> Result<Velocity, Denial> {
+        // Vehicles only ask while they know of no crossing, so anything still
+        // booked for them is a grant whose reply was lost: this request
+        // replaces it rather than being turned down by it
+        for vehicle_id in std::iter::once(request.vehicle_id).chain(followers.iter().map(|f| f.0)) {
+            self.revoke(vehicle_id);
+        }
+        if self.path_closed_to(request) {
+            return Err(Denial::new(DenialCause::LaneClosed));
+        }
+        // Get cached footprint for this direction+route combination
+        let Some(path) = self.path_cache.get(&(request.direction, request.route)) else {
+            return Err(Denial::new(DenialCause::Rule));
+        };
+        let footprint = &path.footprint;
+
+        // Try different speeds until we get permission
+        // the vehicle's own speed first, then each slower preset; a vehicle
+        // stopped or crawling slower than any preset only pulls away at full speed
+        let speeds_to_try: Vec<Velocity> = if request.speed < Velocity::SLOW {
+            vec![request.top_speed]
+        } else {
+            std::iter::once(request.speed)
+                .chain(
+                    Velocity::PRESETS
+                        .into_iter()
+                        .filter(|&preset| preset < request.speed),
+                )
+                .collect()
+        };
+
+        let members: Vec<(usize, f32)> =
+            std::iter::once((request.vehicle_id, request.distance_to_intersection))
+                .chain(followers.iter().copied())
+                .collect();
+
+        let mut refused = Vec::new();
+        for attempt_speed in speeds_to_try {
+            // Followers can't be sure to keep the leader's pace through the
+            // queue, so theirs span arriving at that speed down to crawling in
+            let bookings: Vec<(usize, (usize, usize), f32, f32)> = members
+                .iter()
+                .enumerate()
+                .flat_map(|(position, &(vehicle_id, distance))| {
+                    let windows = Self::path_windows(
+                        footprint,
+                        distance,
+                        attempt_speed,
+                        current_time,
+                        request,
+                    );
+                    let slowest = if position == 0 {
+                        windows.clone()
+                    } else {
+                        Self::path_windows(
+                            footprint,
+                            distance,
+                            Velocity::SLOW,
+                            current_time,
+                            request,
+                        )
+                    };
+                    windows
+                        .into_iter()
+                        .zip(slowest)
+                        .map(move |((cell, start, _), (_, _, end))| (vehicle_id, cell, start, end))
+                })
+                .collect();
+
+            let windows: Vec<((usize, usize), f32, f32)> = bookings
+                .iter()
+                .map(|&(_, cell, start, end)| (cell, start, end))
+                .collect();
+            if !self.can_reserve_windows(&windows) {
+                refused = windows;
+                continue; // Try slower speed
+            }
+
+            for (vehicle_id, cell, start, end) in bookings {
+                self.reserve_cells_for_vehicle(vehicle_id, &[cell], start, end);
+            }
+            for &(vehicle_id, distance) in &members {
+                self.grants.insert(
+                    vehicle_id,
+                    Grant {
+                        speed: attempt_speed,
+                        margin: request.margin,
+                        acceleration: request.acceleration,
+                        entry: Self::entry_time(distance, attempt_speed, current_time, request),
+                        advanced: 0.0,
+                        group: request.vehicle_id,
+                    },
+                );
+            }
+            return Ok(attempt_speed);
+        }
+
+        Err(Denial {
+            cause: DenialCause::Conflict,
+            conflicts: self.conflicts(&refused),
+        })
+    }
+
+    /// Whether the requesting vehicle's lane is closed for roadworks. Vehicles
+    /// already inside the works when they started may still cross.
+    pub fn path_closed_to(&self, request: &PermissionRequest) -> bool {
+        self.closed_paths
+            .contains(&(request.direction, request.route))
+            && request.distance_to_intersection > WORKS_LENGTH
+    }
+
+    /// The cells a vehicle's footprint sweeps on `direction`/`route`
+    pub fn footprint(&self, direction: Direction, route: Route) -> Option<Footprint> {
+        Some(self.path_cache.get(&(direction, route))?.footprint.clone())
+    }
+
+    /// Time window (the request's margins included) needed on each cell of
+    /// `footprint` by a vehicle `distance_to_intersection` away driving at
+    /// `speed`. A vehicle slower than that first speeds up as fast as the
+    /// request says it can.
+    fn path_windows(
+        footprint: &Footprint,
+        distance_to_intersection: f32,
+        speed: Velocity,
+        current_time: f32,
+        request: &PermissionRequest,
+    ) -> Vec<((usize, usize), f32, f32)> {
+        let margin = request.margin;
+        let entry = Self::entry_time(distance_to_intersection, speed, current_time, request);
+        footprint
+            .windows(distance_to_intersection, current_time, entry, speed)
+            .map(|(cell, start, end)| (cell, start - margin, end + margin))
+            .collect()
+    }
+
+    /// When the centre of a vehicle `distance_to_intersection` away crosses
+    /// the entry line getting up to `speed` from the request's
+    fn entry_time(
+        distance_to_intersection: f32,
+        speed: Velocity,
+        current_time: f32,
+        request: &PermissionRequest,
+    ) -> f32 {
+        let current_speed = if request.stopped {
+            Velocity::STOPPED
+        } else {
+            request.speed
+        };
+        current_time
+            + current_speed.seconds_to_cover(distance_to_intersection, speed, request.acceleration)
+    }
+
+    /// Whether every cell is free for its window
+    pub fn can_reserve_windows(&self, windows: &[((usize, usize), f32, f32)]) -> bool {
+        windows
+            .iter()
+            .all(|&(cell, start, end)| self.can_reserve_cells(&[cell], start, end))
+    }
+
+    /// Other vehicles' slots overlapping each cell's window, one per cell
+    /// and vehicle. Cells outside the grid never conflict.
+    pub fn conflicts(&self, windows: &[((usize, usize), f32, f32)]) -> Vec<SlotConflict> {
+        let mut conflicts: Vec<SlotConflict> = Vec::new();
+        for &((col, row), start, end) in windows {
+            if col >= self.cols || row >= self.rows {
+                continue;
+            }
+            for slot in &self.grid[self.cell_index(col, row)].slots {
+                let seen = conflicts
+                    .iter()
+                    .any(|c| c.cell == (col, row) && c.vehicle_id == slot.vehicle_id);
+                if start < slot.end && slot.start < end && !seen {
+                    conflicts.push(SlotConflict {
+                        cell: (col, row),
+                        vehicle_id: slot.vehicle_id,
+                        start: slot.start,
+                        end: slot.end,
+                    });
+                }
+            }
+        }
+        conflicts
+    }
+
+    /// Book every cell for its window, without checking for conflicts
+    pub fn reserve_windows(&mut self, vehicle_id: usize, windows: &[((usize, usize), f32, f32)]) {
+        for &(cell, start, end) in windows {
+            self.reserve_cells_for_vehicle(vehicle_id, &[cell], start, end);
+        }
+    }
+
+    /// Check if cells can be reserved (without actually reserving them).
+    /// Cells outside the grid never conflict.
+    pub fn can_reserve_cells(
+        &self,
+        cells: &[(usize, usize)],
+        start_time: f32,
+        end_time: f32,
+    ) -> bool {
+        for &(col, row) in cells {
+            if col >= self.cols || row >= self.rows {
+                continue;
+            }
+            let idx = self.cell_index(col, row);
+            if self.conflict(&self.grid[idx], start_time, end_time) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Reserve cells for a vehicle. This doesn't check for conflicts: callers
+    /// ask `can_reserve_cells` first, except when holding cells under a
+    /// stranded vehicle, which has to block them whatever was booked.
+    pub fn reserve_cells_for_vehicle(
+        &mut self,
+        vehicle_id: usize,
+        cells: &[(usize, usize)],
+        start_time: f32,
+        end_time: f32,
+    ) {
+        for &(col, row) in cells {
+            if col >= self.cols || row >= self.rows {
+                continue;
+            }
+            let idx = self.cell_index(col, row);
+            self.grid[idx].slots.push(TimeSlot {
+                start: start_time,
+                end: end_time,
+                vehicle_id,
+            });
+        }
+    }
+
+    /// How far past the entry line the centre of a vehicle on `path` with
+    /// `bounds` has driven, going by the traced point nearest it. Vehicles
+    /// still on the approach before the first traced point or already on
+    /// their way out past the last are measured from it along their lane.
+    fn driven(path: &TracedPath, (vx, vy, vw, vh): (f32, f32, f32, f32)) -> Option<f32> {
+        let (cx, cy) = (vx + vw / 2.0, vy + vh / 2.0);
+        let from = |(x, y): (f32, f32)| (x - cx).hypot(y - cy);
+        let (index, &(centre, driven)) = path
+            .centres
+            .iter()
+            .enumerate()
+            .min_by(|(_, (a, _)), (_, (b, _))| from(*a).total_cmp(&from(*b)))?;
+        Some(if index == 0 {
+            driven - from(centre)
+        } else if index == path.centres.len() - 1 {
+            driven + from(centre)
+        } else {
+            driven
+        })
+    }
+
+    /// Cells of its footprint a vehicle on `path` has driven clear of
+    fn cells_driven_past(path: &TracedPath, driven: f32) -> Vec<(usize, usize)> {
+        path.footprint
+            .cells
+            .iter()
+            .filter(|swept| swept.to < driven)
+            .map(|swept| swept.cell)
+            .collect()
+    }
+
+    /// Keep a vehicle's remaining windows in step with where it actually is.
+    /// Once driving on at `speed` from its position would keep it on a cell
+    /// later than that cell's window less `slack` allows, every window it
+    /// still needs ends that much later, and those it hasn't reached yet
+    /// start that much later too, giving up the time it won't use. A vehicle
+    /// whose moved windows clash with other bookings loses its crossing
+    /// while it can still stop short of the box; once it can't, it keeps
+    /// them and whoever they clash with that still can loses theirs instead.
+    /// Returns how many seconds the windows were moved by.
+    pub fn keep_on_schedule(
+        &mut self,
+        vehicle_id: usize,
+        path: (Direction, Route),
+        bounds: (f32, f32, f32, f32),
+        speed: Velocity,
+        slack: f32,
+        current_time: f32,
+    ) -> f32 {
+        let pixels_per_second = speed.pixels_per_second();
+        let Some(traced) = self.path_cache.get(&path) else {
+            return 0.0;
+        };
+        let Some(driven) = Self::driven(traced, bounds) else {
+            return 0.0;
+        };
+        if pixels_per_second <= 0.0 || self.held_cells.contains_key(&vehicle_id) {
+            return 0.0;
+        }
+        let remaining: Vec<(SweptCell, f32, f32)> = traced
+            .footprint
+            .cells
+            .iter()
+            .filter(|swept| swept.to >= driven)
+            .filter_map(|swept| {
+                let (start, end) = self.window_on(swept.cell, vehicle_id)?;
+                Some((*swept, start, end))
+            })
+            .collect();
+        let late = remaining
+            .iter()
+            .map(|(swept, _, end)| {
+                current_time + (swept.to - driven) / pixels_per_second + slack - end
+            })
+            .fold(0.0, f32::max);
+        if late <= LATE_TOLERANCE {
+            return 0.0;
+        }
+
+        let windows: Vec<((usize, usize), f32, f32)> = remaining
+            .iter()
+            .map(|&(swept, start, end)| {
+                let start = if swept.from <= driven {
+                    start.min(current_time)
+                } else {
+                    start + late
+                };
+                (swept.cell, start, end + late)
+            })
+            .collect();
+        let cells: Vec<(usize, usize)> = windows.iter().map(|&(cell, _, _)| cell).collect();
+        self.release_specific_cells(&cells, vehicle_id);
+        // Platoon-mates were booked overlapping each other on purpose
+        let overlapping: Vec<usize> = windows
+            .iter()
+            .flat_map(|&((col, row), start, end)| {
+                self.grid[self.cell_index(col, row)]
+                    .slots
+                    .iter()
+                    .filter(move |slot| start < slot.end && slot.start < end)
+                    .map(|slot| slot.vehicle_id)
+            })
+            .filter(|&id| !self.same_booking(vehicle_id, id))
+            .collect();
+        if !self.entered.contains(&vehicle_id) && !overlapping.is_empty() {
+            self.withdraw(vehicle_id);
+            return 0.0;
+        }
+
+        let mut in_the_way: Vec<usize> = overlapping
+            .into_iter()
+            .filter(|id| !self.entered.contains(id) && !self.held_cells.contains_key(id))
+            .collect();
+        in_the_way.sort_unstable();
+        in_the_way.dedup();
+        for other in in_the_way {
+            self.withdraw(other);
+        }
+        self.reserve_windows(vehicle_id, &windows);
+        late
+    }
+
+    /// Move the windows of a vehicle still on its way to the box earlier
+    /// when, speeding up from `speed` as it does, it will reach the entry line
+    /// before it was booked to: by as much as it is early, up to
+    /// `MAX_SLOT_ADVANCE` in all since the grant, and only into time nobody
+    /// else holds. Later arrivals are left to `keep_on_schedule`.
+    fn advance_to_arrival(
+        &mut self,
+        vehicle_id: usize,
+        path: (Direction, Route),
+        driven: f32,
+        speed: Velocity,
+        current_time: f32,
+    ) {
+        let (Some(grant), Some(traced)) = (
+            self.grants.get(&vehicle_id).copied(),
+            self.path_cache.get(&path),
+        ) else {
+            return;
+        };
+        let arrival =
+            current_time + speed.seconds_to_cover(-driven, grant.speed, grant.acceleration);
+        let advance = (grant.entry - arrival).min(MAX_SLOT_ADVANCE - grant.advanced);
+        if advance <= LATE_TOLERANCE {
+            return;
+        }
+
+        let booked: Vec<((usize, usize), f32, f32)> = traced
+            .footprint
+            .cells
+            .iter()
+            .filter_map(|swept| {
+                let (start, end) = self.window_on(swept.cell, vehicle_id)?;
+                Some((swept.cell, start, end))
+            })
+            .collect();
+        let moved: Vec<((usize, usize), f32, f32)> = booked
+            .iter()
+            .map(|&(cell, start, end)| (cell, start - advance, end - advance))
+            .collect();
+        let cells: Vec<(usize, usize)> = booked.iter().map(|&(cell, _, _)| cell).collect();
+        self.release_specific_cells(&cells, vehicle_id);
+        if !self.can_reserve_windows(&moved) {
+            self.reserve_windows(vehicle_id, &booked);
+            return;
+        }
+        self.reserve_windows(vehicle_id, &moved);
+        if let Some(grant) = self.grants.get_mut(&vehicle_id) {
+            grant.entry -= advance;
+            grant.advanced += advance;
+        }
+    }
+
+    /// Earliest start and latest end of a vehicle's slots on one cell
+    fn window_on(&self, (col, row): (usize, usize), vehicle_id: usize) -> Option<(f32, f32)> {
+        self.grid[self.cell_index(col, row)]
+            .slots
+            .iter()
+            .filter(|slot| slot.vehicle_id == vehicle_id)
+            .fold(None, |window, slot| match window {
+                None => Some((slot.start, slot.end)),
+                Some((start, end)) => Some((slot.start.min(start), slot.end.max(end))),
+            })
+    }
+
+    /// Whether two vehicles' crossings were granted together, in one platoon
+    fn same_booking(&self, a: usize, b: usize) -> bool {
+        match (self.grants.get(&a), self.grants.get(&b)) {
+            (Some(a), Some(b)) => a.group == b.group,
+            _ => false,
+        }
+    }
+
+    /// Take a vehicle's crossing away, to be reported to the intersection
+    fn withdraw(&mut self, vehicle_id: usize) {
+        self.grants.remove(&vehicle_id);
+        self.entered.remove(&vehicle_id);
+        self.release_all_cells(vehicle_id);
+        self.withdrawn.push(vehicle_id);
+    }
+
+    /// Grid cells overlapped by a rectangle in canvas coordinates
+    fn cells_in_bounds(&self, x: f32, y: f32, w: f32, h: f32) -> Vec<(usize, usize)> {
+        if x + w <= IX_MIN || x >= IX_MAX || y + h <= IY_MIN || y >= IY_MAX {
+            return Vec::new();
+        }
+
+        let zone = self.zone_px as f32;
+        let first_col = ((x.max(IX_MIN) - IX_MIN) / zone) as usize;
+        let last_col = (((x + w).min(IX_MAX) - IX_MIN - 0.01) / zone) as usize;
+        let first_row = ((y.max(IY_MIN) - IY_MIN) / zone) as usize;
+        let last_row = (((y + h).min(IY_MAX) - IY_MIN - 0.01) / zone) as usize;
+
+        let mut cells = Vec::new();
+        for row in first_row..=last_row.min(self.rows - 1) {
+            for col in first_col..=last_col.min(self.cols - 1) {
+                cells.push((col, row));
+            }
+        }
+        cells
+    }
+
+    /// Drop the vehicle's slots on `cells`, keeping the rest of its booking
+    pub fn release_specific_cells(&mut self, cells: &[(usize, usize)], vehicle_id: usize) {
+        for &(col, row) in cells {
+            if col >= self.cols || row >= self.rows {
+                continue;
+            }
+            let idx = self.cell_index(col, row);
+            self.grid[idx]
+                .slots
+                .retain(|slot| slot.vehicle_id != vehicle_id);
+        }
+    }
+
+    pub fn release_all_cells(&mut self, vehicle_id: usize) {
+        for cell in &mut self.grid {
+            cell.slots.retain(|slot| slot.vehicle_id != vehicle_id);
+        }
+    }
+
+    /// Every slot on the grid: the cell (column, row), the vehicle holding
+    /// it and its time window
+    pub fn bookings(&self) -> impl Iterator<Item = ((usize, usize), usize, f32, f32)> + '_ {
+        self.grid.iter().enumerate().flat_map(move |(idx, cell)| {
+            let at = (idx % self.cols, idx / self.cols);
+            cell.slots
+                .iter()
+                .map(move |slot| (at, slot.vehicle_id, slot.start, slot.end))
+        })
+    }
+
+    fn conflict(&self, cell: &Cell, start: f32, end: f32) -> bool {
+        cell.slots
+            .iter()
+            .any(|slot| start < slot.end && slot.start < end)
+    }
+
+    fn cell_index(&self, col: usize, row: usize) -> usize {
+        row * self.cols + col
+    }
+}
+
+/// `point` moved `distance` pixels towards `heading`
+fn step((x, y): (f32, f32), heading: Direction, distance: f32) -> (f32, f32) {
+    match heading {
+        Direction::North => (x, y - distance),
+        Direction::South => (x, y + distance),
+        Direction::East => (x + distance, y),
+        Direction::West => (x - distance, y),
+    }
+}
+
+impl IntersectionController for ReservationController {
+    fn name(&self) -> &'static str {
+        "Reservation"
+    }
+
+    fn request_permission(
+        &mut self,
+        request: &PermissionRequest,
+        current_time: f32,
+    ) -> Option<Velocity> {
+        let result = self.try_two_path_intersection_request(request, current_time);
+        self.denial = result.as_ref().err().cloned();
+        result.ok()
+    }
+
+    fn take_denial(&mut self) -> Option<Denial> {
+        self.denial.take()
+    }
+
+    /// The speed the crossing was booked at, held all the way through
+    fn planned_speed(
+        &self,
+        vehicle_id: usize,
+        _distance_to_intersection: f32,
+        _current_time: f32,
+    ) -> Option<Velocity> {
+        self.grants.get(&vehicle_id).map(|grant| grant.speed)
+    }
+
+    fn request_platoon(
+        &mut self,
+        leader: &PermissionRequest,
+        followers: &[(usize, f32)],
+        current_time: f32,
+    ) -> Option<Velocity> {
+        let result = self.try_platoon_request(leader, followers, current_time);
+        self.denial = result.as_ref().err().cloned();
+        result.ok()
+    }
+
+    fn paths_conflict(&self, a: (Direction, Route), b: (Direction, Route)) -> bool {
+        let cells = |key| {
+            self.path_cache.get(&key).map(|path: &TracedPath| {
+                path.footprint
+                    .cells
+                    .iter()
+                    .map(|swept| swept.cell)
+                    .collect::<Vec<_>>()
+            })
+        };
+        match (cells(a), cells(b)) {
+            (Some(a), Some(b)) => a.iter().any(|cell| b.contains(cell)),
+            _ => true,
+        }
+    }
+
+    fn notify_lane_closed(&mut self, direction: Direction, route: Route, closed: bool) {
+        if closed {
+            self.closed_paths.insert((direction, route));
+        } else {
+            self.closed_paths.remove(&(direction, route));
+        }
+    }
+
+    /// Free the cells the vehicle has driven past, note when it can no
+    /// longer stop short of the box and keep its crossing in step with when
+    /// it will actually get there
+    fn notify_progress(
+        &mut self,
+        vehicle_id: usize,
+        direction: Direction,
+        route: Route,
+        bounds: (f32, f32, f32, f32),
+        speed: Velocity,
+        current_time: f32,
+    ) {
+        let Some(path) = self.path_cache.get(&(direction, route)) else {
+            return;
+        };
+        let Some(driven) = Self::driven(path, bounds) else {
+            return;
+        };
+        let cells_to_release = Self::cells_driven_past(path, driven);
+        let committed = path
+            .footprint
+            .cells
+            .iter()
+            .any(|swept| swept.from <= driven + COMMIT_DISTANCE);
+        if !cells_to_release.is_empty() {
+            self.release_specific_cells(&cells_to_release, vehicle_id);
+        }
+        if committed {
+            self.entered.insert(vehicle_id);
+        } else {
+            self.advance_to_arrival(vehicle_id, (direction, route), driven, speed, current_time);
+        }
+        if let Some(grant) = self.grants.get(&vehicle_id).copied() {
+            let late = self.keep_on_schedule(
+                vehicle_id,
+                (direction, route),
+                bounds,
+                grant.speed,
+                grant.margin.max(0.0),
+                current_time,
+            );
+            if let Some(grant) = self.grants.get_mut(&vehicle_id) {
+                grant.entry += late;
+            }
+        }
+    }
+
+    fn take_withdrawn(&mut self) -> Vec<usize> {
+        std::mem::take(&mut self.withdrawn)
+    }
+
+    /// A vehicle's slots on the cells of one row show up as a single window,
+    /// from the first of them starting to the last ending
+    fn timeline(&self) -> Option<Timeline> {
+        let mut slots: Vec<BookedSlot> = Vec::new();
+        for row in 0..self.rows {
+            let first = slots.len();
+            for col in 0..self.cols {
+                for slot in &self.grid[self.cell_index(col, row)].slots {
+                    match slots[first..]
+                        .iter_mut()
+                        .find(|booked| booked.vehicle_id == slot.vehicle_id)
+                    {
+                        Some(booked) => {
+                            booked.start = booked.start.min(slot.start);
+                            booked.end = booked.end.max(slot.end);
+                        }
+                        None => slots.push(BookedSlot {
+                            row,
+                            vehicle_id: slot.vehicle_id,
+                            start: slot.start,
+                            end: slot.end,
+                        }),
+                    }
+                }
+            }
+        }
+        Some(Timeline {
+            rows: self.rows,
+            slots,
+        })
+    }
+
+    fn cell_owners(&self, current_time: f32) -> Option<CellOwners> {
+        Some(CellOwners {
+            cols: self.cols,
+            left: self.occupancy.left,
+            top: self.occupancy.top,
+            cell_size: self.occupancy.cell_size,
+            owners: self
+                .grid
+                .iter()
+                .map(|cell| {
+                    cell.slots
+                        .iter()
+                        .filter(|slot| slot.start <= current_time && current_time < slot.end)
+                        .map(|slot| slot.vehicle_id)
+                        .collect()
+                })
+                .collect(),
+        })
+    }
+
+    fn booked_slots(&self) -> Option<usize> {
+        Some(self.grid.iter().map(|cell| cell.slots.len()).sum())
+    }
+
+    fn occupancy(&self) -> Option<Occupancy> {
+        Some(self.occupancy.clone())
+    }
+
+    fn tick(&mut self, current_time: f32) {
+        self.occupancy.frames += 1;
+        for (count, cell) in self.occupancy.counts.iter_mut().zip(&self.grid) {
+            if cell
+                .slots
+                .iter()
+                .any(|slot| slot.start <= current_time && current_time < slot.end)
+            {
+                *count += 1;
+            }
+        }
+    }
+
+    fn notify_exit(&mut self, vehicle_id: usize, _current_time: f32) {
+        self.held_cells.remove(&vehicle_id);
+        self.grants.remove(&vehicle_id);
+        self.entered.remove(&vehicle_id);
+        self.release_all_cells(vehicle_id);
+    }
+
+    /// Block the cells a stranded vehicle is sitting on until it is repaired
+    fn notify_blocked(
+        &mut self,
+        vehicle_id: usize,
+        (vx, vy, vw, vh): (f32, f32, f32, f32),
+        current_time: f32,
+        until: f32,
+    ) {
+        let cells = self.cells_in_bounds(vx, vy, vw, vh);
+        self.reserve_cells_for_vehicle(vehicle_id, &cells, current_time, until);
+        self.held_cells.insert(vehicle_id, cells);
+    }
+
+    fn reservation_window(&self, vehicle_id: usize) -> Option<(f32, f32)> {
+        self.bookings()
+            .filter(|&(_, id, _, _)| id == vehicle_id)
+            .fold(None, |window, (_, _, start, end)| match window {
+                None => Some((start, end)),
+                Some((first, last)) => Some((start.min(first), end.max(last))),
+            })
+    }
+
+    fn notify_unblocked(&mut self, vehicle_id: usize) {
+        if let Some(cells) = self.held_cells.remove(&vehicle_id) {
+            self.release_specific_cells(&cells, vehicle_id);
+        }
+    }
+
+    fn booked_over(&self, (x, y, w, h): (f32, f32, f32, f32), from: f32, until: f32) -> Vec<usize> {
+        let mut vehicles: Vec<usize> = self
+            .cells_in_bounds(x, y, w, h)
+            .into_iter()
+            .flat_map(|(col, row)| &self.grid[self.cell_index(col, row)].slots)
+            .filter(|slot| from < slot.end && slot.start < until)
+            .map(|slot| slot.vehicle_id)
+            .filter(|id| !self.held_cells.contains_key(id))
+            .collect();
+        vehicles.sort_unstable();
+        vehicles.dedup();
+        vehicles
+    }
+
+    fn revoke(&mut self, vehicle_id: usize) {
+        self.grants.remove(&vehicle_id);
+        self.entered.remove(&vehicle_id);
+        self.release_all_cells(vehicle_id);
+    }
+
+    fn clear_bookings(&mut self) {
+        // Cells held under stranded vehicles stay blocked
+        for cell in &mut self.grid {
+            cell.slots
+                .retain(|slot| self.held_cells.contains_key(&slot.vehicle_id));
+        }
+    }
+
+    fn save_state(&self) -> Option<serde_json::Value> {
+        serde_json::to_value(SavedBookings {
+            grid: self.grid.clone(),
+            closed_paths: self.closed_paths.iter().copied().collect(),
+            held_cells: self.held_cells.clone(),
+            occupancy: self.occupancy.clone(),
+            grants: self.grants.clone(),
+            entered: self.entered.iter().copied().collect(),
+        })
+        .ok()
+    }
+
+    fn restore_state(&mut self, state: serde_json::Value) -> Result<(), String> {
+        let saved: SavedBookings = serde_json::from_value(state).map_err(|e| e.to_string())?;
+        if saved.grid.len() != self.grid.len() {
+            return Err(format!(
+                "Checkpoint grid has {} cells, this controller {}",
+                saved.grid.len(),
+                self.grid.len()
+            ));
+        }
+        self.grid = saved.grid;
+        self.closed_paths = saved.closed_paths.into_iter().collect();
+        self.held_cells = saved.held_cells;
+        self.occupancy = saved.occupancy;
+        self.grants = saved.grants;
+        self.entered = saved.entered.into_iter().collect();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::driver::DriverProfile;
+    use crate::vehicle::Vehicle;
+    use proptest::prelude::*;
+
+    /// One operation on the grid, on a 30x30 grid with a margin of cells
+    /// outside it
+    #[derive(Debug, Clone)]
+    enum Op {
+        Reserve {
+            vehicle_id: usize,
+            cells: Vec<(usize, usize)>,
+            start: f32,
+            length: f32,
+        },
+        Release {
+            vehicle_id: usize,
+            cells: Vec<(usize, usize)>,
+        },
+        ReleaseAll {
+            vehicle_id: usize,
+        },
+    }
+
+    fn cells() -> impl Strategy<Value = Vec<(usize, usize)>> {
+        prop::collection::vec((0..32usize, 0..32usize), 1..12)
+    }
+
+    fn op() -> impl Strategy<Value = Op> {
+        prop_oneof![
+            4 => (0..6usize, cells(), 0.0..20.0f32, 0.1..5.0f32).prop_map(
+                |(vehicle_id, cells, start, length)| Op::Reserve {
+                    vehicle_id,
+                    cells,
+                    start,
+                    length,
+                }
+            ),
+            1 => (0..6usize, cells())
+                .prop_map(|(vehicle_id, cells)| Op::Release { vehicle_id, cells }),
+            1 => (0..6usize).prop_map(|vehicle_id| Op::ReleaseAll { vehicle_id }),
+        ]
+    }
+
+    /// Apply `op` the way the controller does: a reservation only goes
+    /// through if the whole set of cells is free
+    fn apply(controller: &mut ReservationController, op: &Op) {
+        match op {
+            Op::Reserve {
+                vehicle_id,
+                cells,
+                start,
+                length,
+            } => {
+                if controller.can_reserve_cells(cells, *start, start + length) {
+                    controller.reserve_cells_for_vehicle(
+                        *vehicle_id,
+                        cells,
+                        *start,
+                        start + length,
+                    );
+                }
+            }
+            Op::Release { vehicle_id, cells } => {
+                controller.release_specific_cells(cells, *vehicle_id)
+            }
+            Op::ReleaseAll { vehicle_id } => controller.release_all_cells(*vehicle_id),
+        }
+    }
+
+    /// Slots of different vehicles on the same cell whose windows overlap
+    fn overlaps(controller: &ReservationController) -> Vec<String> {
+        let slots: Vec<_> = controller.bookings().collect();
+        let mut found = Vec::new();
+        for (i, &(cell, a, a_start, a_end)) in slots.iter().enumerate() {
+            for &(other_cell, b, b_start, b_end) in &slots[i + 1..] {
+                if cell == other_cell && a != b && a_start < b_end && b_start < a_end {
+                    found.push(format!(
+                        "cell {:?}: vehicle {} {:.2}-{:.2} and vehicle {} {:.2}-{:.2}",
+                        cell, a, a_start, a_end, b, b_start, b_end
+                    ));
+                }
+            }
+        }
+        found
+    }
+
+    fn request() -> impl Strategy<Value = (Direction, Route, f32, Velocity, f32)> {
+        (
+            prop_oneof![
+                Just(Direction::North),
+                Just(Direction::South),
+                Just(Direction::East),
+                Just(Direction::West),
+            ],
+            prop_oneof![Just(Route::Left), Just(Route::Straight), Just(Route::Right)],
+            0.0..300.0f32,
+            prop_oneof![
+                Just(Velocity::STOPPED),
+                Just(Velocity::SLOW),
+                Just(Velocity::MEDIUM),
+                Just(Velocity::FAST),
+                (0.5..7.0f32).prop_map(Velocity::from_pixels_per_frame),
+            ],
+            0.0..0.5f32,
+        )
+    }
+
+    proptest! {
+        #[test]
+        fn no_two_vehicles_hold_a_cell_at_once(ops in prop::collection::vec(op(), 1..60)) {
+            let mut controller = ReservationController::new();
+            for op in &ops {
+                apply(&mut controller, op);
+                let found = overlaps(&controller);
+                prop_assert!(found.is_empty(), "after {:?}: {:?}", op, found);
+            }
+        }
+
+        #[test]
+        fn can_reserve_matches_the_grid(
+            ops in prop::collection::vec(op(), 0..40),
+            query in cells(),
+            start in 0.0..20.0f32,
+            length in 0.1..5.0f32,
+        ) {
+            let mut controller = ReservationController::new();
+            for op in &ops {
+                apply(&mut controller, op);
+            }
+            let end = start + length;
+            let taken = controller
+                .bookings()
+                .any(|(cell, _, slot_start, slot_end)| {
+                    query.contains(&cell) && start < slot_end && slot_start < end
+                });
+            prop_assert_eq!(controller.can_reserve_cells(&query, start, end), !taken);
+        }
+
+        #[test]
+        fn release_only_frees_that_vehicle(
+            ops in prop::collection::vec(op(), 0..40),
+            vehicle_id in 0..6usize,
+            released in cells(),
+        ) {
+            let mut controller = ReservationController::new();
+            for op in &ops {
+                apply(&mut controller, op);
+            }
+            let before: Vec<_> = controller.bookings().collect();
+
+            controller.release_specific_cells(&released, vehicle_id);
+            let kept: Vec<_> = before
+                .iter()
+                .copied()
+                .filter(|&(cell, id, _, _)| id != vehicle_id || !released.contains(&cell))
+                .collect();
+            prop_assert_eq!(controller.bookings().collect::<Vec<_>>(), kept);
+
+            controller.release_all_cells(vehicle_id);
+            let others: Vec<_> = before
+                .iter()
+                .copied()
+                .filter(|&(_, id, _, _)| id != vehicle_id)
+                .collect();
+            prop_assert_eq!(controller.bookings().collect::<Vec<_>>(), others);
+        }
+
+        #[test]
+        fn footprint_covers_every_cell_a_vehicle_drives_over(
+            (direction, route, _, _, _) in request(),
+            speed in 0.5..7.0f32,
+        ) {
+            let controller = ReservationController::new();
+            let footprint = controller.footprint(direction, route).unwrap();
+            let mut vehicle = Vehicle::new(
+                route,
+                direction,
+                get_spawn_position(direction, route),
+                get_turn_position(direction, route),
+                DriverProfile::Normal,
+            );
+            vehicle.current_speed = Velocity::from_pixels_per_frame(speed);
+            let mut driven = -vehicle.distance_to_intersection();
+            for _ in 0..(1500.0 / speed) as usize {
+                let (x, y, w, h) = vehicle.get_visual_bounds();
+                for cell in controller.cells_in_bounds(x, y, w, h) {
+                    let swept = footprint.cells.iter().find(|swept| swept.cell == cell);
+                    prop_assert!(
+                        swept.is_some_and(|swept| {
+                            swept.from - TRACE_STEP <= driven && driven <= swept.to + TRACE_STEP
+                        }),
+                        "cell {:?} at {:.1} px: {:?}",
+                        cell,
+                        driven,
+                        swept
+                    );
+                }
+                vehicle.update();
+                driven += speed;
+            }
+        }
+
+        #[test]
+        fn late_vehicles_only_overlap_vehicles_in_the_box(
+            requests in prop::collection::vec((request(), 0.0..1.0f32), 1..30),
+            progress in prop::collection::vec((0..30usize, 0.0..1.0f32, 0.0..3.0f32), 1..20),
+        ) {
+            let mut controller = ReservationController::new();
+            let mut current_time = 0.0;
+            let mut granted = Vec::new();
+            for (vehicle_id, ((direction, route, distance, speed, margin), wait)) in
+                requests.into_iter().enumerate()
+            {
+                current_time += wait;
+                let request = PermissionRequest {
+                    vehicle_id,
+                    direction,
+                    route,
+                    speed,
+                    top_speed: Velocity::FAST,
+                    acceleration: f32::INFINITY,
+                    distance_to_intersection: distance,
+                    stopped: speed == Velocity::STOPPED,
+                    margin,
+                };
+                if controller.request_permission(&request, current_time).is_some() {
+                    granted.push((vehicle_id, direction, route));
+                }
+            }
+            prop_assume!(!granted.is_empty());
+
+            // Vehicles report being somewhere along their path, up to a few
+            // seconds later than planned
+            for (pick, along, delay) in progress {
+                let (vehicle_id, direction, route) = granted[pick % granted.len()];
+                let centres = &controller.path_cache[&(direction, route)].centres;
+                let ((x, y), _) = centres[(along * (centres.len() - 1) as f32) as usize];
+                controller.notify_progress(
+                    vehicle_id,
+                    direction,
+                    route,
+                    (x - 1.0, y - 1.0, 2.0, 2.0),
+                    Velocity::FAST,
+                    current_time + delay,
+                );
+                let slots: Vec<_> = controller.bookings().collect();
+                for (i, &(cell, a, a_start, a_end)) in slots.iter().enumerate() {
+                    for &(other_cell, b, b_start, b_end) in &slots[i + 1..] {
+                        prop_assert!(
+                            cell != other_cell
+                                || a == b
+                                || a_end <= b_start
+                                || b_end <= a_start
+                                || (controller.entered.contains(&a)
+                                    && controller.entered.contains(&b)),
+                            "cell {:?}: vehicle {} {:.2}-{:.2} and vehicle {} {:.2}-{:.2}",
+                            cell, a, a_start, a_end, b, b_start, b_end
+                        );
+                    }
+                }
+            }
+        }
+
+        #[test]
+        fn granted_crossings_never_overlap(
+            requests in prop::collection::vec((request(), 0.0..1.0f32, any::<bool>()), 1..40),
+        ) {
+            let mut controller = ReservationController::new();
+            let mut current_time = 0.0;
+            let mut granted = Vec::new();
+            for (vehicle_id, ((direction, route, distance, speed, margin), wait, exit)) in
+                requests.into_iter().enumerate()
+            {
+                current_time += wait;
+                let request = PermissionRequest {
+                    vehicle_id,
+                    direction,
+                    route,
+                    speed,
+                    top_speed: Velocity::FAST,
+                    acceleration: f32::INFINITY,
+                    distance_to_intersection: distance,
+                    stopped: speed == Velocity::STOPPED,
+                    margin,
+                };
+                if controller.request_permission(&request, current_time).is_some() {
+                    granted.push(vehicle_id);
+                }
+                // Now and then the oldest vehicle still booked leaves
+                if exit && !granted.is_empty() {
+                    controller.notify_exit(granted.remove(0), current_time);
+                }
+                let found = overlaps(&controller);
+                prop_assert!(found.is_empty(), "after vehicle {}: {:?}", vehicle_id, found);
+            }
+        }
+
+        #[test]
+        fn asking_again_replaces_a_lost_grant(
+            (direction, route, distance, speed, margin) in request(),
+            wait in 0.0..0.5f32,
+        ) {
+            let mut controller = ReservationController::new();
+            let request = PermissionRequest {
+                vehicle_id: 0,
+                direction,
+                route,
+                speed,
+                top_speed: Velocity::FAST,
+                acceleration: f32::INFINITY,
+                distance_to_intersection: distance,
+                stopped: speed == Velocity::STOPPED,
+                margin,
+            };
+            prop_assert!(controller.request_permission(&request, 0.0).is_some());
+            let first: Vec<_> = controller.bookings().collect();
+
+            // The reply was lost and the vehicle, no nearer, asks again
+            prop_assert!(controller.request_permission(&request, wait).is_some());
+            let second: Vec<_> = controller.bookings().collect();
+            prop_assert_eq!(first.len(), second.len());
+            prop_assert!(second.iter().all(|&(_, id, _, _)| id == 0));
+
+            // Someone else asking for the same crossing still has to keep clear
+            let other = PermissionRequest {
+                vehicle_id: 1,
+                ..request
+            };
+            controller.request_permission(&other, wait);
+            let found = overlaps(&controller);
+            prop_assert!(found.is_empty(), "{:?}", found);
+        }
+
+        #[test]
+        fn a_late_leader_keeps_its_platoon(
+            (direction, route, distance, _, margin) in request(),
+            along in 0.0..0.5f32,
+            delay in 0.1..3.0f32,
+        ) {
+            let mut controller = ReservationController::new();
+            let leader = PermissionRequest {
+                vehicle_id: 0,
+                direction,
+                route,
+                speed: Velocity::FAST,
+                top_speed: Velocity::FAST,
+                acceleration: f32::INFINITY,
+                distance_to_intersection: distance,
+                stopped: false,
+                margin,
+            };
+            let followers = [(1, distance + 60.0), (2, distance + 120.0)];
+            prop_assert!(controller.request_platoon(&leader, &followers, 0.0).is_some());
+
+            // The leader reaches the box well after it was booked to
+            let centres = &controller.path_cache[&(direction, route)].centres;
+            let ((x, y), _) = centres[(along * (centres.len() - 1) as f32) as usize];
+            let entry = controller.grants[&0].entry;
+            controller.notify_progress(
+                0,
+                direction,
+                route,
+                (x - 1.0, y - 1.0, 2.0, 2.0),
+                Velocity::FAST,
+                entry + delay,
+            );
+            prop_assert_eq!(controller.take_withdrawn(), Vec::<usize>::new());
+            prop_assert!((0..3).all(|id| controller.grants.contains_key(&id)));
+        }
+
+        #[test]
+        fn denials_name_a_vehicle_holding_the_cell(
+            requests in prop::collection::vec((request(), 0.0..1.0f32), 1..40),
+        ) {
+            let mut controller = ReservationController::new();
+            let mut current_time = 0.0;
+            for (vehicle_id, ((direction, route, distance, speed, margin), wait)) in
+                requests.into_iter().enumerate()
+            {
+                current_time += wait;
+                let request = PermissionRequest {
+                    vehicle_id,
+                    direction,
+                    route,
+                    speed,
+                    top_speed: Velocity::FAST,
+                    acceleration: f32::INFINITY,
+                    distance_to_intersection: distance,
+                    stopped: speed == Velocity::STOPPED,
+                    margin,
+                };
+                let granted = controller.request_permission(&request, current_time).is_some();
+                let denial = controller.take_denial();
+                prop_assert_eq!(granted, denial.is_none());
+                let Some(denial) = denial else { continue };
+                prop_assert!(controller.take_denial().is_none());
+                for conflict in &denial.conflicts {
+                    prop_assert_eq!(denial.cause, DenialCause::Conflict);
+                    prop_assert!(conflict.vehicle_id != vehicle_id);
+                    prop_assert!(
+                        controller.bookings().any(|(cell, holder, _, _)| {
+                            cell == conflict.cell && holder == conflict.vehicle_id
+                        }),
+                        "vehicle {} blamed for {:?} without a booking",
+                        conflict.vehicle_id,
+                        conflict.cell
+                    );
+                }
+            }
+        }
+    }
+}
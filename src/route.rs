@@ -1,13 +1,14 @@
 use rand::Rng;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum Route {
     Right,
     Left,
     Straight,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum Direction {
     North, // Coming from south, going north
     South, // Coming from north, going south
@@ -15,9 +16,8 @@ pub enum Direction {
     West,  // Coming from east, going west
 }
 
-// Helper function to get random route
-pub fn get_random_route() -> Route {
-    let mut rng = rand::rng();
+/// Random route drawn from the given generator, for reproducible scenarios
+pub fn random_route(rng: &mut impl Rng) -> Route {
     match rng.random_range(0..3) {
         0 => Route::Right,
         1 => Route::Straight,
@@ -25,8 +25,7 @@ pub fn get_random_route() -> Route {
     }
 }
 
-pub fn get_random_direction() -> Direction {
-    let mut rng = rand::rng();
+pub fn get_random_direction(rng: &mut impl Rng) -> Direction {
     match rng.random_range(0..4) {
         0 => Direction::East,
         1 => Direction::North,
@@ -35,6 +34,17 @@ pub fn get_random_direction() -> Direction {
     }
 }
 
+/// Heading of a vehicle once it has left the intersection
+pub fn exit_direction(direction: Direction, route: Route) -> Direction {
+    match (route, direction) {
+        (Route::Straight, _) => direction,
+        (Route::Right, Direction::North) | (Route::Left, Direction::South) => Direction::East,
+        (Route::Right, Direction::South) | (Route::Left, Direction::North) => Direction::West,
+        (Route::Right, Direction::East) | (Route::Left, Direction::West) => Direction::South,
+        (Route::Right, Direction::West) | (Route::Left, Direction::East) => Direction::North,
+    }
+}
+
 // Helper function to get spawn position based on direction and route
 pub fn get_spawn_position(direction: Direction, route: Route) -> (f32, f32) {
     match direction {
@@ -73,6 +83,17 @@ pub fn get_spawn_position(direction: Direction, route: Route) -> (f32, f32) {
     }
 }
 
+/// Route asked for by the modifiers held with a spawn arrow: Shift turns
+/// left, Ctrl right and both go straight on; neither leaves it random
+pub fn route_for_modifiers(shift: bool, ctrl: bool) -> Option<Route> {
+    match (shift, ctrl) {
+        (true, false) => Some(Route::Left),
+        (false, true) => Some(Route::Right),
+        (true, true) => Some(Route::Straight),
+        (false, false) => None,
+    }
+}
+
 pub fn get_turn_position(direction: Direction, route: Route) -> (f32, f32) {
     match route {
         Route::Straight => (0.0, 0.0),
@@ -90,3 +111,17 @@ pub fn get_turn_position(direction: Direction, route: Route) -> (f32, f32) {
         },
     }
 }
+
+/// Area (x, y, width, height) a vehicle sweeps driving `length` px from the
+/// centre point `from` towards `direction`, one lane (50 px) wide
+pub fn lane_corridor(from: (f32, f32), direction: Direction, length: f32) -> (f32, f32, f32, f32) {
+    const LANE_WIDTH: f32 = 50.0;
+    let (x, y) = from;
+    let half = LANE_WIDTH / 2.0;
+    match direction {
+        Direction::North => (x - half, y - length, LANE_WIDTH, length),
+        Direction::South => (x - half, y, LANE_WIDTH, length),
+        Direction::East => (x, y - half, length, LANE_WIDTH),
+        Direction::West => (x - length, y - half, length, LANE_WIDTH),
+    }
+}
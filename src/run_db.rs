@@ -0,0 +1,322 @@
+use crate::config::Config;
+use crate::i18n::tr;
+use crate::report::RunSummary;
+use rusqlite::{Connection, OptionalExtension, params};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+
+/// Runs listed on each of the statistics screen's history pages
+const LISTED_RUNS: usize = 10;
+/// Shortest run (seconds) put on the leaderboard; shorter ones are too noisy
+const MIN_RANKED_DURATION: f32 = 60.0;
+
+/// A finished run as read back from the database
+#[derive(Debug, Clone)]
+pub struct StoredRun {
+    pub id: i64,
+    pub recorded_at: String, // UTC, "YYYY-MM-DD HH:MM"
+    pub config_hash: String,
+    pub controller: String,
+    pub vehicles_passed: u32,
+    pub average_wait: f32,
+    pub p95_wait: f32,
+    pub collisions: u32,
+    pub score: f32,
+}
+
+impl StoredRun {
+    /// Two lines for the statistics screen: when and how it ran, then its results
+    pub fn describe(&self) -> [String; 2] {
+        [
+            tr!(
+                "runs.setup",
+                id = self.id,
+                date = self.recorded_at,
+                controller = self.controller,
+                config = &self.config_hash[..8],
+            ),
+            tr!(
+                "runs.results",
+                passed = self.vehicles_passed,
+                average = format!("{:.1}", self.average_wait),
+                p95 = format!("{:.1}", self.p95_wait),
+                collisions = self.collisions,
+                score = format!("{:.0}", self.score),
+            ),
+        ]
+    }
+}
+
+/// SQLite file keeping the summary of every run, so controller changes can be
+/// tracked across sessions
+pub struct RunDatabase {
+    connection: Connection,
+}
+
+impl RunDatabase {
+    pub fn open(path: &str) -> Result<Self, String> {
+        let connection =
+            Connection::open(path).map_err(|e| format!("Failed to open {}: {}", path, e))?;
+        connection
+            .execute_batch(
+                "CREATE TABLE IF NOT EXISTS runs (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    recorded_at INTEGER NOT NULL,
+                    seed INTEGER,
+                    config_hash TEXT NOT NULL,
+                    controller TEXT NOT NULL,
+                    layout TEXT NOT NULL,
+                    weather TEXT NOT NULL,
+                    duration REAL NOT NULL,
+                    vehicles_passed INTEGER NOT NULL,
+                    average_wait REAL NOT NULL,
+                    p95_wait REAL NOT NULL,
+                    average_travel REAL NOT NULL,
+                    close_calls INTEGER NOT NULL,
+                    collisions INTEGER NOT NULL,
+                    fuel_per_vehicle REAL NOT NULL,
+                    summary TEXT NOT NULL,
+                    score REAL NOT NULL DEFAULT 0
+                )",
+            )
+            .map_err(|e| format!("Failed to prepare {}: {}", path, e))?;
+        // History files written before runs were scored
+        if connection.prepare("SELECT score FROM runs").is_err() {
+            connection
+                .execute_batch("ALTER TABLE runs ADD COLUMN score REAL NOT NULL DEFAULT 0")
+                .map_err(|e| format!("Failed to upgrade {}: {}", path, e))?;
+        }
+        Ok(Self { connection })
+    }
+
+    /// Store a finished run; the full summary is kept as JSON next to the
+    /// headline columns
+    pub fn record(&self, summary: &RunSummary, config: &Config) -> Result<i64, String> {
+        let recorded_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+        let json = serde_json::to_string(summary).map_err(|e| e.to_string())?;
+        self.connection
+            .execute(
+                "INSERT INTO runs (recorded_at, seed, config_hash, controller, layout, weather,
+                    duration, vehicles_passed, average_wait, p95_wait, average_travel,
+                    close_calls, collisions, fuel_per_vehicle, summary, score)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
+                params![
+                    recorded_at,
+                    // SQLite integers are signed; the bits round-trip through i64
+                    config.seed.map(|seed| seed as i64),
+                    config_hash(config),
+                    summary.controller,
+                    summary.layout,
+                    summary.weather,
+                    summary.duration,
+                    summary.vehicles_passed,
+                    summary.average_wait_time,
+                    summary.wait_time.p95,
+                    summary.travel_time.mean,
+                    summary.close_calls,
+                    summary.collisions,
+                    summary.average_fuel_ml,
+                    json,
+                    summary.score,
+                ],
+            )
+            .map_err(|e| format!("Failed to record run: {}", e))?;
+        Ok(self.connection.last_insert_rowid())
+    }
+
+    /// Full summary of the newest stored run, if any
+    pub fn latest_summary(&self) -> Result<Option<RunSummary>, String> {
+        let json: Option<String> = self
+            .connection
+            .query_row(
+                "SELECT summary FROM runs ORDER BY id DESC LIMIT 1",
+                [],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?;
+        json.map(|json| serde_json::from_str(&json).map_err(|e| e.to_string()))
+            .transpose()
+    }
+
+    /// The latest `limit` runs, newest first
+    pub fn recent(&self, limit: usize) -> Result<Vec<StoredRun>, String> {
+        self.query_runs("ORDER BY id DESC LIMIT ?2", limit)
+    }
+
+    /// The `limit` best-scoring runs long enough to be ranked
+    pub fn leaderboard(&self, limit: usize) -> Result<Vec<StoredRun>, String> {
+        self.query_runs(
+            "WHERE duration >= ?1 ORDER BY score DESC, id LIMIT ?2",
+            limit,
+        )
+    }
+
+    /// Leaderboard position of a stored run and the number of ranked runs, or
+    /// None when the run is too short to be ranked
+    pub fn rank(&self, id: i64) -> Result<Option<(u32, u32)>, String> {
+        self.connection
+            .query_row(
+                "SELECT
+                    (SELECT count(*) FROM runs WHERE duration >= ?1 AND score > run.score) + 1,
+                    (SELECT count(*) FROM runs WHERE duration >= ?1)
+                 FROM runs AS run WHERE id = ?2 AND duration >= ?1",
+                params![MIN_RANKED_DURATION, id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .map_err(|e| e.to_string())
+    }
+
+    /// Runs selected by `clause`, which may use the minimum ranked duration as
+    /// ?1 and `limit` as ?2
+    fn query_runs(&self, clause: &str, limit: usize) -> Result<Vec<StoredRun>, String> {
+        let mut statement = self
+            .connection
+            .prepare(&format!(
+                "SELECT id, strftime('%Y-%m-%d %H:%M', recorded_at, 'unixepoch'),
+                    config_hash, controller, vehicles_passed, average_wait, p95_wait,
+                    collisions, score
+                 FROM runs {}",
+                clause
+            ))
+            .map_err(|e| e.to_string())?;
+        let rows = statement
+            .query_map(params![MIN_RANKED_DURATION, limit as i64], |row| {
+                Ok(StoredRun {
+                    id: row.get(0)?,
+                    recorded_at: row.get(1)?,
+                    config_hash: row.get(2)?,
+                    controller: row.get(3)?,
+                    vehicles_passed: row.get(4)?,
+                    average_wait: row.get(5)?,
+                    p95_wait: row.get(6)?,
+                    collisions: row.get(7)?,
+                    score: row.get(8)?,
+                })
+            })
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+    }
+}
+
+/// Hex FNV-1a hash of the settings that shape a run, so runs with the same
+/// setup can be grouped. Output paths and front-end choices are left out; the
+/// seed is stored in its own column.
+pub fn config_hash(config: &Config) -> String {
+    let settings = format!(
+        "{:?}",
+        (
+            &config.controller,
+            &config.controller_script,
+            &config.layout,
+            &config.weather,
+            &config.driver_mix,
+            (&config.fleet_mix, config.speed_variance),
+            (config.breakdown_rate, config.breakdown_duration),
+            &config.arrival_rates,
+            (&config.sumo_routes, &config.spawn_csv),
+            (config.stress, config.platooning, config.packet_loss),
+            (
+                config.max_vehicles,
+                config.starvation_limit,
+                config.rerouting
+            ),
+            (
+                (&config.lane_closures, config.hazard_rate, config.warm_up),
+                (config.slot_padding, config.path_padding)
+            ),
+        )
+    );
+    let hash = settings
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+        });
+    format!("{:016x}", hash)
+}
+
+/// The run stored last in the configured history file, to show how a run
+/// that just finished compares; read before that run is recorded
+pub fn previous_run(config: &Config) -> Option<RunSummary> {
+    let path = config.history_db.as_ref()?;
+    RunDatabase::open(path)
+        .and_then(|db| db.latest_summary())
+        .unwrap_or_else(|e| {
+            warn!("Previous run unavailable: {}", e);
+            None
+        })
+}
+
+/// Store a finished run in the configured history file and return its score
+/// lines for the statistics screen, the score compared with `previous`; a
+/// failure to store is logged rather than ending the session
+pub fn record_run(
+    config: &Config,
+    summary: &RunSummary,
+    previous: Option<&RunSummary>,
+) -> Vec<String> {
+    let mut score = tr!("stats.score", score = format!("{:.1}", summary.score));
+    if let Some(previous) = previous {
+        let change = format!("{:+.1}", summary.score - previous.score);
+        score = format!("{}\t{}", score, tr!("delta.score", change = change));
+    }
+    let mut lines = vec![score];
+    let Some(path) = &config.history_db else {
+        return lines;
+    };
+    let stored = RunDatabase::open(path).and_then(|db| {
+        let id = db.record(summary, config)?;
+        Ok((id, db.rank(id)?))
+    });
+    match stored {
+        Ok((id, rank)) => {
+            info!("Run {} stored in {}", id, path);
+            lines.push(match rank {
+                Some((rank, ranked)) => tr!("stats.rank", rank = rank, total = ranked),
+                None => tr!("stats.unranked", seconds = MIN_RANKED_DURATION),
+            });
+        }
+        Err(e) => warn!("Run not stored: {}", e),
+    }
+    lines
+}
+
+/// Texts of the statistics screen's history pages: the latest runs and the
+/// leaderboard, or none without a history file
+pub fn history_pages(config: &Config) -> Vec<String> {
+    let Some(path) = &config.history_db else {
+        return Vec::new();
+    };
+    let runs = RunDatabase::open(path).and_then(|db| {
+        Ok([
+            (tr!("runs.recent"), db.recent(LISTED_RUNS)?),
+            (tr!("runs.leaderboard"), db.leaderboard(LISTED_RUNS)?),
+        ])
+    });
+    match runs {
+        Ok(pages) => pages
+            .into_iter()
+            .filter(|(_, runs)| !runs.is_empty())
+            .map(|(title, runs)| runs_page(&title, &runs))
+            .collect(),
+        Err(e) => {
+            warn!("Run history unavailable: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// A page listing `runs` two lines each
+fn runs_page(title: &str, runs: &[StoredRun]) -> String {
+    let mut text = format!("{}\n", title);
+    for run in runs {
+        let [setup, results] = run.describe();
+        text.push_str(&format!("\n{}\n{}", setup, results));
+    }
+    text.push_str(&format!("\n\n{}", tr!("stats.quit")));
+    text
+}
@@ -0,0 +1,51 @@
+use crate::i18n::tr;
+use crate::intersection::SmartIntersection;
+
+/// When a timed run (`--duration`) ends by itself
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RunLimit {
+    Seconds(f32),  // simulated seconds, warm-up included
+    Vehicles(u32), // vehicles through, as the statistics count them
+}
+
+impl RunLimit {
+    /// Parse `300` or `300s` for simulated seconds, or `500v` for vehicles passed
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let text = text.trim();
+        let invalid = || {
+            format!(
+                "Invalid duration '{}' (expected seconds such as 300 or 300s, or vehicles such as 500v)",
+                text
+            )
+        };
+        match text.strip_suffix('v') {
+            Some(count) => match count.parse::<u32>() {
+                Ok(count) if count > 0 => Ok(RunLimit::Vehicles(count)),
+                _ => Err(invalid()),
+            },
+            None => match text.strip_suffix('s').unwrap_or(text).parse::<f32>() {
+                Ok(seconds) if seconds > 0.0 && seconds.is_finite() => {
+                    Ok(RunLimit::Seconds(seconds))
+                }
+                _ => Err(invalid()),
+            },
+        }
+    }
+
+    pub fn reached(&self, intersection: &SmartIntersection, current_time: f32) -> bool {
+        match *self {
+            RunLimit::Seconds(seconds) => current_time >= seconds,
+            RunLimit::Vehicles(count) => intersection.total_vehicles_passed >= count,
+        }
+    }
+
+    /// Line for the statistics saying why the run stopped
+    pub fn describe(&self) -> String {
+        match *self {
+            RunLimit::Seconds(seconds) => {
+                tr!("limit.seconds", seconds = format!("{:.0}", seconds))
+            }
+            RunLimit::Vehicles(count) => tr!("limit.vehicles", count = count),
+        }
+    }
+}
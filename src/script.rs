@@ -0,0 +1,256 @@
+use crate::controller::{CellOwners, IntersectionController, PermissionRequest};
+use crate::route::{Direction, Route};
+use crate::velocities::Velocity;
+use rhai::{AST, Array, Dynamic, Engine, Map, Scope};
+use std::collections::HashMap;
+
+/// Operation budget per call so a runaway script can't freeze the simulation
+const MAX_OPERATIONS: u64 = 100_000;
+
+/// Grid handed to scripts: GRID_CELLS x GRID_CELLS cells of GRID_CELL_SIZE
+/// pixels covering the intersection box from (BOX_LEFT, BOX_TOP)
+const GRID_CELLS: usize = 10;
+const GRID_CELL_SIZE: f32 = 30.0;
+const BOX_LEFT: f32 = 350.0;
+const BOX_TOP: f32 = 350.0;
+
+/// A vehicle granted permission that has not yet left the intersection
+struct Grant {
+    request: PermissionRequest,
+    speed: Velocity,
+    granted_at: f32,
+    inside: bool,
+}
+
+/// Controller whose permission decision is made by a Rhai script.
+///
+/// The script must define `request_permission(vehicle, occupancy, time)` and
+/// return `"fast"`, `"medium"` or `"slow"` to grant the crossing at that speed
+/// (`true` means fast), or a number of px/frame; any other value denies it.
+/// Fast is the vehicle's own top speed. `occupancy.grid` is the intersection
+/// as rows of cells, each the id of a granted vehicle over it or `()`, and
+/// `occupancy.vehicles` lists the vehicles holding permission.
+pub struct ScriptController {
+    engine: Engine,
+    ast: AST,
+    grants: Vec<Grant>,
+    positions: HashMap<usize, (f32, f32, f32, f32)>, // bounds of granted vehicles last frame
+    last_error: Option<String>,
+}
+
+impl ScriptController {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+
+        let ast = engine
+            .compile_file(path.into())
+            .map_err(|e| format!("Failed to load controller script {}: {}", path, e))?;
+
+        let defines_entry_point = ast
+            .iter_functions()
+            .any(|f| f.name == "request_permission" && f.params.len() == 3);
+        if !defines_entry_point {
+            return Err(format!(
+                "Controller script {} must define request_permission(vehicle, occupancy, time)",
+                path
+            ));
+        }
+
+        Ok(Self {
+            engine,
+            ast,
+            grants: Vec::new(),
+            positions: HashMap::new(),
+            last_error: None,
+        })
+    }
+
+    /// Which granted vehicles' bodies cover each grid cell, as last reported
+    fn grid(&self) -> CellOwners {
+        let mut owners = vec![Vec::new(); GRID_CELLS * GRID_CELLS];
+        let cell = |coordinate: f32, origin: f32| {
+            ((coordinate - origin) / GRID_CELL_SIZE).clamp(0.0, GRID_CELLS as f32 - 1.0) as usize
+        };
+        let box_size = GRID_CELLS as f32 * GRID_CELL_SIZE;
+        for (&vehicle_id, &(x, y, w, h)) in &self.positions {
+            let outside = x + w <= BOX_LEFT
+                || x >= BOX_LEFT + box_size
+                || y + h <= BOX_TOP
+                || y >= BOX_TOP + box_size;
+            if outside {
+                continue;
+            }
+            for row in cell(y, BOX_TOP)..=cell(y + h - 0.01, BOX_TOP) {
+                for col in cell(x, BOX_LEFT)..=cell(x + w - 0.01, BOX_LEFT) {
+                    owners[row * GRID_CELLS + col].push(vehicle_id);
+                }
+            }
+        }
+        CellOwners {
+            cols: GRID_CELLS,
+            left: BOX_LEFT,
+            top: BOX_TOP,
+            cell_size: GRID_CELL_SIZE,
+            owners,
+        }
+    }
+
+    fn forget(&mut self, vehicle_id: usize) {
+        self.grants.retain(|g| g.request.vehicle_id != vehicle_id);
+        self.positions.remove(&vehicle_id);
+    }
+
+    /// Report a script error once instead of on every frame it repeats
+    fn report_error(&mut self, error: String) {
+        if self.last_error.as_ref() != Some(&error) {
+            tracing::warn!("Controller script error: {}", error);
+            self.last_error = Some(error);
+        }
+    }
+}
+
+fn vehicle_map(request: &PermissionRequest) -> Map {
+    let mut map = Map::new();
+    map.insert("id".into(), Dynamic::from(request.vehicle_id as i64));
+    map.insert(
+        "direction".into(),
+        Dynamic::from(format!("{:?}", request.direction)),
+    );
+    map.insert(
+        "route".into(),
+        Dynamic::from(format!("{:?}", request.route)),
+    );
+    map.insert("speed".into(), Dynamic::from(request.speed.name()));
+    map.insert(
+        "distance".into(),
+        Dynamic::from(request.distance_to_intersection as f64),
+    );
+    map.insert("margin".into(), Dynamic::from(request.margin as f64));
+    map.insert("stopped".into(), Dynamic::from(request.stopped));
+    map
+}
+
+fn parse_speed(result: &Dynamic, top_speed: Velocity) -> Option<Velocity> {
+    if let Some(granted) = result.clone().try_cast::<bool>() {
+        return granted.then_some(top_speed);
+    }
+    if let Ok(speed) = result.as_float() {
+        return (speed > 0.0).then(|| Velocity::from_pixels_per_frame(speed as f32).min(top_speed));
+    }
+    match result.clone().into_string().ok()?.to_lowercase().as_str() {
+        "fast" => Some(top_speed),
+        "medium" => Some(Velocity::MEDIUM.min(top_speed)),
+        "slow" => Some(Velocity::SLOW.min(top_speed)),
+        _ => None,
+    }
+}
+
+impl IntersectionController for ScriptController {
+    fn name(&self) -> &'static str {
+        "Script"
+    }
+
+    fn request_permission(
+        &mut self,
+        request: &PermissionRequest,
+        current_time: f32,
+    ) -> Option<Velocity> {
+        // Every vehicle holding permission, whether still approaching or already inside;
+        // grants end when the vehicle drives out of the intersection
+        let vehicles: Array = self
+            .grants
+            .iter()
+            .map(|grant| {
+                let mut map = vehicle_map(&grant.request);
+                map.insert("speed".into(), Dynamic::from(grant.speed.name()));
+                map.insert("granted_at".into(), Dynamic::from(grant.granted_at as f64));
+                map.insert("inside".into(), Dynamic::from(grant.inside));
+                Dynamic::from(map)
+            })
+            .collect();
+        // The first vehicle over each cell, or () where it is free
+        let grid: Array = self
+            .grid()
+            .owners
+            .chunks(GRID_CELLS)
+            .map(|row| {
+                let cells: Array = row
+                    .iter()
+                    .map(|owners| {
+                        owners
+                            .first()
+                            .map_or(Dynamic::UNIT, |&id| Dynamic::from(id as i64))
+                    })
+                    .collect();
+                Dynamic::from(cells)
+            })
+            .collect();
+        let mut occupancy = Map::new();
+        occupancy.insert("grid".into(), Dynamic::from(grid));
+        occupancy.insert("vehicles".into(), Dynamic::from(vehicles));
+
+        let result = self.engine.call_fn::<Dynamic>(
+            &mut Scope::new(),
+            &self.ast,
+            "request_permission",
+            (vehicle_map(request), occupancy, current_time as f64),
+        );
+
+        let speed = match result {
+            Ok(value) => parse_speed(&value, request.top_speed),
+            Err(e) => {
+                self.report_error(e.to_string());
+                None
+            }
+        }?;
+
+        self.grants.push(Grant {
+            request: *request,
+            speed,
+            granted_at: current_time,
+            inside: false,
+        });
+        Some(speed)
+    }
+
+    fn notify_enter(&mut self, vehicle_id: usize, _current_time: f32) {
+        if let Some(grant) = self
+            .grants
+            .iter_mut()
+            .find(|g| g.request.vehicle_id == vehicle_id)
+        {
+            grant.inside = true;
+        }
+    }
+
+    fn notify_leave(&mut self, vehicle_id: usize, _current_time: f32) {
+        self.forget(vehicle_id);
+    }
+
+    fn notify_exit(&mut self, vehicle_id: usize, _current_time: f32) {
+        self.forget(vehicle_id);
+    }
+
+    fn notify_progress(
+        &mut self,
+        vehicle_id: usize,
+        _direction: Direction,
+        _route: Route,
+        bounds: (f32, f32, f32, f32),
+        _speed: Velocity,
+        _current_time: f32,
+    ) {
+        if self
+            .grants
+            .iter()
+            .any(|g| g.request.vehicle_id == vehicle_id)
+        {
+            self.positions.insert(vehicle_id, bounds);
+        }
+    }
+
+    fn cell_owners(&self, _current_time: f32) -> Option<CellOwners> {
+        Some(self.grid())
+    }
+}
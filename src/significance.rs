@@ -0,0 +1,202 @@
+/// p-value below which a difference counts as real rather than chance
+pub const SIGNIFICANCE_LEVEL: f64 = 0.05;
+/// Largest number of non-zero differences the p-value is counted out exactly
+/// for; beyond it the normal approximation is close enough
+const EXACT_LIMIT: usize = 50;
+
+/// Result of a Wilcoxon signed-rank test on paired measurements
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SignedRankTest {
+    pub pairs: usize,   // pairs that differ; ties between the two are dropped
+    pub statistic: f64, // W+, the rank sum of the pairs where the second is larger
+    pub p_value: f64,   // two-sided
+}
+
+impl SignedRankTest {
+    pub fn significant(&self) -> bool {
+        self.p_value < SIGNIFICANCE_LEVEL
+    }
+
+    /// Whether the second measurement tends to be the larger one
+    pub fn second_larger(&self) -> bool {
+        let total = (self.pairs * (self.pairs + 1)) as f64 / 2.0;
+        self.statistic > total / 2.0
+    }
+}
+
+/// Wilcoxon signed-rank test of whether `second` differs from `first`, pair
+/// by pair. Equal pairs are dropped and tied differences share their average
+/// rank. The p-value is exact up to EXACT_LIMIT pairs, given the ties, and
+/// from the tie-corrected normal approximation beyond.
+pub fn wilcoxon_signed_rank(first: &[f32], second: &[f32]) -> SignedRankTest {
+    let mut differences: Vec<f64> = first
+        .iter()
+        .zip(second)
+        .map(|(&a, &b)| b as f64 - a as f64)
+        .filter(|&difference| difference != 0.0)
+        .collect();
+    differences.sort_by(|a, b| a.abs().total_cmp(&b.abs()));
+    let pairs = differences.len();
+    if pairs == 0 {
+        return SignedRankTest {
+            pairs,
+            statistic: 0.0,
+            p_value: 1.0,
+        };
+    }
+
+    // Ranks doubled so the averages of tied groups stay whole numbers
+    let mut doubled_ranks = vec![0usize; pairs];
+    let mut tie_correction = 0.0;
+    let mut start = 0;
+    while start < pairs {
+        let end = (start..pairs)
+            .find(|&i| differences[i].abs() != differences[start].abs())
+            .unwrap_or(pairs);
+        let shared = start + 1 + end; // twice the average of ranks start+1..=end
+        doubled_ranks[start..end].fill(shared);
+        let tied = (end - start) as f64;
+        tie_correction += tied.powi(3) - tied;
+        start = end;
+    }
+    let doubled_statistic: usize = differences
+        .iter()
+        .zip(&doubled_ranks)
+        .filter(|(difference, _)| **difference > 0.0)
+        .map(|(_, &rank)| rank)
+        .sum();
+    let statistic = doubled_statistic as f64 / 2.0;
+
+    let p_value = if pairs <= EXACT_LIMIT {
+        exact_p_value(&doubled_ranks, doubled_statistic)
+    } else {
+        normal_p_value(pairs, statistic, tie_correction)
+    };
+    SignedRankTest {
+        pairs,
+        statistic,
+        p_value: p_value.min(1.0),
+    }
+}
+
+/// Two-sided p-value of a doubled rank sum, counting every way of signing
+/// the ranks
+fn exact_p_value(doubled_ranks: &[usize], doubled_statistic: usize) -> f64 {
+    let total: usize = doubled_ranks.iter().sum();
+    // ways[s]: share of sign patterns whose positive ranks sum to s
+    let mut ways = vec![0.0f64; total + 1];
+    ways[0] = 1.0;
+    for &rank in doubled_ranks {
+        for sum in (rank..=total).rev() {
+            ways[sum] = (ways[sum] + ways[sum - rank]) / 2.0;
+        }
+        for share in &mut ways[..rank] {
+            *share /= 2.0;
+        }
+    }
+    let below: f64 = ways[..=doubled_statistic].iter().sum();
+    let above: f64 = ways[doubled_statistic..].iter().sum();
+    2.0 * below.min(above)
+}
+
+/// Two-sided p-value of a rank sum from the normal approximation, with the
+/// tie and continuity corrections
+fn normal_p_value(pairs: usize, statistic: f64, tie_correction: f64) -> f64 {
+    let n = pairs as f64;
+    let mean = n * (n + 1.0) / 4.0;
+    let variance = n * (n + 1.0) * (2.0 * n + 1.0) / 24.0 - tie_correction / 48.0;
+    // Continuity correction towards the mean
+    let distance = ((statistic - mean).abs() - 0.5).max(0.0);
+    if variance <= 0.0 {
+        1.0
+    } else {
+        erfc(distance / variance.sqrt() / std::f64::consts::SQRT_2)
+    }
+}
+
+/// Complementary error function, to within 1.2e-7 (Numerical Recipes'
+/// Chebyshev fit)
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let tail = t
+        * (-z * z - 1.265_512_23
+            + t * (1.000_023_68
+                + t * (0.374_091_96
+                    + t * (0.096_784_18
+                        + t * (-0.186_288_06
+                            + t * (0.278_868_07
+                                + t * (-1.135_203_98
+                                    + t * (1.488_515_87
+                                        + t * (-0.822_152_23 + t * 0.170_872_77)))))))))
+            .exp();
+    if x >= 0.0 { tail } else { 2.0 - tail }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "expected {}, got {}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn small_sample_matches_the_exact_table() {
+        // Differences 1, -2, 3, 4, 5, 6: W+ = 19 of 21, and only 3 of the 64
+        // sign patterns are as extreme on each side
+        let first = [0.0; 6];
+        let second = [1.0, -2.0, 3.0, 4.0, 5.0, 6.0];
+        let test = wilcoxon_signed_rank(&first, &second);
+        assert_eq!(test.pairs, 6);
+        assert_close(test.statistic, 19.0);
+        assert_close(test.p_value, 6.0 / 64.0);
+        assert!(test.second_larger());
+        assert!(!test.significant());
+    }
+
+    #[test]
+    fn tied_differences_share_their_rank() {
+        // Differences 1, 1, -2, 3 and a dropped 0: the two 1s share rank 1.5,
+        // so W+ = 1.5 + 1.5 + 4 = 7, which 5 of the 16 sign patterns reach
+        let first = [2.0, 3.0, 1.0, 0.0, 5.0];
+        let second = [3.0, 4.0, -1.0, 3.0, 5.0];
+        let test = wilcoxon_signed_rank(&first, &second);
+        assert_eq!(test.pairs, 4);
+        assert_close(test.statistic, 7.0);
+        assert_close(test.p_value, 10.0 / 16.0);
+    }
+
+    #[test]
+    fn identical_runs_are_not_significant() {
+        let runs = [4.0, 7.5, 1.0, 3.0];
+        let test = wilcoxon_signed_rank(&runs, &runs);
+        assert_eq!(test.pairs, 0);
+        assert_close(test.statistic, 0.0);
+        assert_close(test.p_value, 1.0);
+        assert!(!test.significant());
+    }
+
+    #[test]
+    fn exact_and_normal_agree_at_the_limit() {
+        let pairs = EXACT_LIMIT;
+        let doubled_ranks: Vec<usize> = (1..=pairs).map(|rank| 2 * rank).collect();
+        let total = pairs * (pairs + 1) / 2;
+        for statistic in (total / 8..=total / 2).step_by(25) {
+            let exact = exact_p_value(&doubled_ranks, 2 * statistic);
+            let normal = normal_p_value(pairs, statistic as f64, 0.0);
+            assert!(
+                (exact - normal).abs() < 0.005,
+                "W+ = {}: exact {} against normal {}",
+                statistic,
+                exact,
+                normal
+            );
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use crate::vehicle::Vehicle;
+use crate::velocities::Velocity;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+/// A stop this soon after last driving at full speed counts as an emergency stop
+const EMERGENCY_WINDOW: Duration = Duration::from_millis(500);
+/// How long a skid mark takes to fade away
+const SKID_FADE: Duration = Duration::from_secs(30);
+/// Marks kept at once; the oldest go first
+const MAX_MARKS: usize = 200;
+
+/// Where a car stopped hard, with the body as it was so the marks can be
+/// drawn behind its rear wheels
+pub struct SkidMark {
+    pub position: (f32, f32),
+    pub size: (u32, u32),
+    pub rotation: f64,
+    laid_at: Instant,
+}
+
+impl SkidMark {
+    /// 1 when fresh, falling to 0 as the mark fades
+    pub fn strength(&self, now: Instant) -> f32 {
+        1.0 - now.duration_since(self.laid_at).as_secs_f32() / SKID_FADE.as_secs_f32()
+    }
+}
+
+/// Skid marks left by vehicles going from Fast to Stopped in a short window,
+/// so spots where cars keep braking hard stand out over a run
+#[derive(Default)]
+pub struct SkidMarks {
+    marks: VecDeque<SkidMark>,
+    last_fast: HashMap<usize, Instant>, // when each vehicle was last seen at full speed
+}
+
+impl SkidMarks {
+    pub fn update(&mut self, vehicles: &[&Vehicle], now: Instant) {
+        self.last_fast
+            .retain(|id, _| vehicles.iter().any(|vehicle| vehicle.id == *id));
+        for vehicle in vehicles {
+            if vehicle.current_speed >= vehicle.top_speed() {
+                self.last_fast.insert(vehicle.id, now);
+            } else if vehicle.current_speed == Velocity::STOPPED
+                && let Some(fast_at) = self.last_fast.remove(&vehicle.id)
+                && now.duration_since(fast_at) <= EMERGENCY_WINDOW
+                && vehicle.crashed_at.is_none()
+            {
+                self.marks.push_back(SkidMark {
+                    position: vehicle.position,
+                    size: (vehicle.width, vehicle.height),
+                    rotation: vehicle.rotation,
+                    laid_at: now,
+                });
+            }
+        }
+        while self.marks.len() > MAX_MARKS
+            || self
+                .marks
+                .front()
+                .is_some_and(|mark| mark.strength(now) <= 0.0)
+        {
+            self.marks.pop_front();
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &SkidMark> {
+        self.marks.iter()
+    }
+}
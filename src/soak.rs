@@ -0,0 +1,154 @@
+use crate::bench::resident_memory_kb;
+use crate::compare::{DEFAULT_RATE, DEFAULT_SEED};
+use crate::config::Config;
+use crate::demand::PoissonArrivals;
+use crate::intersection::SmartIntersection;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use std::time::Instant;
+
+/// Simulated seconds between resource reports
+const REPORT_EVERY: f32 = 60.0;
+/// Longest the road is given to empty once spawning stops, in simulated seconds
+const DRAIN_LIMIT: f32 = 120.0;
+
+/// Run Poisson demand headlessly for `minutes` simulated minutes, printing the
+/// active vehicles, booked slots and memory every REPORT_EVERY seconds. Spawning
+/// then stops until the road is empty, when no slot should be left booked.
+/// Fails if any report found bookings outliving their vehicles or slots were
+/// left on the empty road.
+pub fn run_soak(config: &Config, minutes: f32) -> Result<(), String> {
+    let seed = config.seed.unwrap_or(DEFAULT_SEED);
+    let mut intersection = crate::build_intersection(config)?;
+    intersection.seed(seed);
+    let mut rng = StdRng::seed_from_u64(seed);
+    let rates = config.arrival_rates.unwrap_or([DEFAULT_RATE; 4]);
+    let mut arrivals = PoissonArrivals::new(rates, &mut rng);
+
+    println!(
+        "Soak test: {:.0} simulated minutes of {} on a {}",
+        minutes,
+        intersection.controller.name(),
+        intersection.layout.name()
+    );
+    println!(
+        "{:>8} {:>8} {:>7} {:>8} {:>7} {:>9} {:>10}",
+        "minute", "wall s", "active", "pending", "slots", "orphaned", "memory"
+    );
+
+    let started = Instant::now();
+    let report_ticks = (REPORT_EVERY * 60.0) as u64;
+    let ticks = (minutes * 3600.0) as u64;
+    let mut first_memory = None;
+    let mut leaky_reports = 0;
+    let mut reports = 0;
+    for tick in 0..ticks {
+        let current_time = (tick + 1) as f32 / 60.0;
+        for direction in arrivals.due(current_time, &mut rng) {
+            intersection.spawn_vehicle(Some(direction), None, current_time);
+        }
+        intersection.update(current_time);
+        intersection.drain_events();
+
+        if (tick + 1) % report_ticks == 0 {
+            let orphaned = orphaned_bookings(&intersection);
+            let memory = resident_memory_kb();
+            first_memory = first_memory.or(memory);
+            reports += 1;
+            if orphaned > 0 {
+                leaky_reports += 1;
+            }
+            println!(
+                "{:>8.0} {:>8.1} {:>7} {:>8} {:>7} {:>9} {:>10}",
+                current_time / 60.0,
+                started.elapsed().as_secs_f64(),
+                intersection.vehicle_count(),
+                intersection.pending_spawn_count(),
+                slots(&intersection),
+                orphaned,
+                mebibytes(memory)
+            );
+        }
+    }
+
+    // Let every vehicle leave and every jaywalker finish crossing; whatever
+    // is still booked then has leaked
+    let mut current_time = ticks as f32 / 60.0;
+    let drain_end = current_time + DRAIN_LIMIT;
+    intersection.hazard_rate = 0.0;
+    while (intersection.vehicle_count() > 0
+        || intersection.pending_spawn_count() > 0
+        || !intersection.hazards.is_empty())
+        && current_time < drain_end
+    {
+        current_time += 1.0 / 60.0;
+        intersection.update(current_time);
+        intersection.drain_events();
+    }
+    let left_on_road = intersection.vehicle_count();
+    let left_booked = intersection.controller.booked_slots().unwrap_or(0);
+
+    println!();
+    println!(
+        "Finished in {:.1} s; {} vehicles passed",
+        started.elapsed().as_secs_f64(),
+        intersection.total_vehicles_passed
+    );
+    let last_memory = resident_memory_kb();
+    if let (Some(first), Some(last)) = (first_memory, last_memory) {
+        println!(
+            "Memory: {} at the first report, {} at the end ({:+.1} MiB)",
+            mebibytes(Some(first)),
+            mebibytes(Some(last)),
+            (last as f64 - first as f64) / 1024.0
+        );
+    }
+    if left_on_road > 0 {
+        println!(
+            "{} vehicles were still on the road {:.0} s after spawning stopped",
+            left_on_road, DRAIN_LIMIT
+        );
+    }
+
+    let mut leaks = Vec::new();
+    if leaky_reports > 0 {
+        leaks.push(format!(
+            "{} of {} reports found bookings held by vehicles no longer on the road",
+            leaky_reports, reports
+        ));
+    }
+    if left_on_road == 0 && left_booked > 0 {
+        leaks.push(format!(
+            "{} slots were still booked once the road was empty",
+            left_booked
+        ));
+    }
+    if leaks.is_empty() {
+        println!("Leak check: no bookings outlived their vehicles");
+        Ok(())
+    } else {
+        Err(format!("Leak check failed: {}", leaks.join("; ")))
+    }
+}
+
+/// Vehicles holding bookings nobody will use
+fn orphaned_bookings(intersection: &SmartIntersection) -> usize {
+    intersection.controller.timeline().map_or(0, |timeline| {
+        intersection.orphaned_bookings(&timeline).len()
+    })
+}
+
+/// Booked slots, or "-" for controllers that book none
+fn slots(intersection: &SmartIntersection) -> String {
+    intersection
+        .controller
+        .booked_slots()
+        .map_or_else(|| "-".to_string(), |slots| slots.to_string())
+}
+
+fn mebibytes(kb: Option<u64>) -> String {
+    kb.map_or_else(
+        || "n/a".to_string(),
+        |kb| format!("{:.1} MiB", kb as f64 / 1024.0),
+    )
+}
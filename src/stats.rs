@@ -1,10 +1,21 @@
+use crate::assets::TITLE_FONT;
+#[cfg(feature = "image")]
+use crate::assets::load_car_texture;
+use crate::i18n::tr;
+use crate::queues::APPROACHES;
+use crate::render::{FrameLimiter, FrameRate, direction_color};
+use crate::report::{FinalStats, RunSummary};
+use crate::route::Direction;
+use crate::text::load_font;
 use rand::Rng;
 use sdl2::event::Event;
-use sdl2::image::LoadTexture;
-use sdl2::keyboard::Keycode;
+use sdl2::keyboard::{Keycode, Mod};
 use sdl2::pixels::Color;
 use sdl2::rect::Rect;
-use std::time::Duration;
+use sdl2::render::{Canvas, Texture, TextureCreator};
+use sdl2::ttf::{Font, Sdl2TtfContext};
+use sdl2::video::{Window, WindowContext};
+use std::collections::HashMap;
 
 struct AnimatedCar {
     x: f32,
@@ -46,8 +57,8 @@ impl AnimatedCar {
         // Check if car is in the stats box area (center of screen)
         let stats_left = 100.0;
         let stats_right = 850.0;
-        let stats_top = 150.0;
-        let stats_bottom = 800.0;
+        let stats_top = 50.0;
+        let stats_bottom = 900.0;
 
         self.x >= stats_left
             && self.x <= stats_right
@@ -56,11 +67,117 @@ impl AnimatedCar {
     }
 }
 
+/// Left edge of the bars, px
+const BAR_LEFT: i32 = 320;
+/// Length of the longest bar in a chart, px
+const BAR_MAX_WIDTH: u32 = 400;
+const BAR_HEIGHT: u32 = 26;
+/// Right edge of the column of changes since the previous run, px
+const CHANGE_RIGHT: i32 = 830;
+
+/// One count per approach, drawn as horizontal bars in the heading colours
+pub struct BarChart {
+    pub title: String,
+    pub bars: Vec<(Direction, u32)>,
+}
+
+/// The per-approach charts of a finished run
+pub fn direction_charts(summary: &RunSummary) -> Vec<BarChart> {
+    let bars = |counts: &HashMap<Direction, u32>| {
+        APPROACHES
+            .into_iter()
+            .map(|direction| (direction, counts.get(&direction).copied().unwrap_or(0)))
+            .collect()
+    };
+    vec![
+        BarChart {
+            title: tr!("charts.passed"),
+            bars: bars(&summary.passed_by_approach),
+        },
+        BarChart {
+            title: tr!("charts.close_calls"),
+            bars: bars(&summary.close_calls_by_approach),
+        },
+    ]
+}
+
+/// One screen of text, its body font sized so every line fits in the box
+struct Page<'a, 'ttf> {
+    lines: Vec<&'a str>,
+    font: Font<'ttf, 'static>,
+    max_label_width: u32,   // widest label in pixels, so every colon lines up
+    charts: &'a [BarChart], // drawn below the lines
+}
+
+impl<'a, 'ttf> Page<'a, 'ttf> {
+    fn new(ttf_context: &'ttf Sdl2TtfContext, text: &'a str) -> Result<Self, String> {
+        let lines: Vec<&str> = text.split('\n').collect();
+
+        // Shrink the body font when there are many lines so everything fits in the box
+        let font_size = ((720 / lines.len().max(1) as u16).saturating_sub(8) * 3 / 4).clamp(14, 28);
+        let font = load_font(ttf_context, &TITLE_FONT, font_size)?;
+
+        let quit_line = tr!("stats.quit");
+        let mut max_label_width = 0u32;
+        for line in &lines {
+            if *line == quit_line {
+                continue;
+            }
+            if let Some((label, _)) = line.split_once(':') {
+                let (width, _) = font.size_of(label.trim()).map_err(|e| e.to_string())?;
+                max_label_width = max_label_width.max(width);
+            }
+        }
+
+        Ok(Self {
+            lines,
+            font,
+            max_label_width,
+            charts: &[],
+        })
+    }
+
+    /// A page of bar charts under a title line
+    fn charts(
+        ttf_context: &'ttf Sdl2TtfContext,
+        title: &'a str,
+        charts: &'a [BarChart],
+    ) -> Result<Self, String> {
+        Ok(Self {
+            charts,
+            ..Self::new(ttf_context, title)?
+        })
+    }
+
+    /// The page as plain text for the clipboard, without the quit hint;
+    /// each bar becomes a `direction: count` line
+    fn plain_text(&self, quit_line: &str) -> String {
+        let mut lines: Vec<String> = self
+            .lines
+            .iter()
+            .filter(|line| **line != quit_line)
+            .map(|line| line.to_string())
+            .collect();
+        for chart in self.charts {
+            lines.extend([String::new(), chart.title.clone()]);
+            lines.extend(
+                chart
+                    .bars
+                    .iter()
+                    .map(|(direction, count)| format!("{:?}: {}", direction, count)),
+            );
+        }
+        lines.join("\n").trim().to_string()
+    }
+}
+
 pub fn show_stats(
     sdl_context: &sdl2::Sdl,
     video_subsystem: &sdl2::VideoSubsystem,
     ttf_context: &sdl2::ttf::Sdl2TtfContext,
-    stats_text: &str,
+    stats: &FinalStats,
+    extra_pages: &[String],
+    frame_rate: FrameRate,
 ) -> Result<(), String> {
     let window = video_subsystem
         .window("Statistics", 1000, 1000)
@@ -68,25 +185,34 @@ pub fn show_stats(
         .build()
         .map_err(|e| e.to_string())?;
 
-    let mut canvas = window
-        .into_canvas()
-        .present_vsync()
-        .build()
-        .map_err(|e| e.to_string())?;
+    let mut canvas = window.into_canvas();
+    if frame_rate.vsync {
+        canvas = canvas.present_vsync();
+    }
+    let mut canvas = canvas.build().map_err(|e| e.to_string())?;
+    let mut limiter = FrameLimiter::new(frame_rate);
 
     let texture_creator = canvas.texture_creator();
 
-    // Load a better font with larger size
-    let font = ttf_context.load_font("assets/fonts/Orbitron-VariableFont_wght.ttf", 28)?;
-    let title_font = ttf_context.load_font("assets/fonts/Orbitron-VariableFont_wght.ttf", 36)?;
-
-    // Load car textures
-    let mut car_textures = Vec::new();
-    for i in 1..=5 {
-        let path = format!("assets/Cars/car{}.png", i);
-        let texture = texture_creator.load_texture(&path)?;
-        car_textures.push(texture);
+    // The run's own stats first; Tab cycles through the rest
+    let charts_title = tr!("charts.title");
+    let mut pages = vec![Page::new(ttf_context, &stats.text)?];
+    if !stats.charts.is_empty() {
+        pages.push(Page::charts(ttf_context, &charts_title, &stats.charts)?);
     }
+    for text in extra_pages {
+        pages.push(Page::new(ttf_context, text)?);
+    }
+    let mut current_page = 0;
+    let title_font = load_font(ttf_context, &TITLE_FONT, 36)?;
+    let quit_line = tr!("stats.quit");
+    let mut clipboard_status = tr!("stats.copy_hint");
+
+    // Without the sprites the cars are drawn as squares
+    let car_textures = load_car_textures(&texture_creator).unwrap_or_else(|e| {
+        tracing::warn!("Drawing the stats screen cars as squares: {}", e);
+        Vec::new()
+    });
 
     let mut event_pump = sdl_context.event_pump()?;
     let mut animated_cars: Vec<AnimatedCar> = Vec::new();
@@ -96,28 +222,6 @@ pub fn show_stats(
         animated_cars.push(AnimatedCar::new());
     }
 
-    let lines: Vec<&str> = stats_text.split('\n').collect();
-
-    // First pass: calculate the maximum label WIDTH in pixels for alignment
-    let mut max_label_width = 0u32;
-    for line in &lines {
-        if line.contains(':') && !line.contains("Press esc") {
-            let parts: Vec<&str> = line.splitn(2, ':').collect();
-            if parts.len() == 2 {
-                let label = parts[0].trim();
-                let label_surface = font
-                    .render(label)
-                    .blended(Color::RGB(255, 255, 255))
-                    .map_err(|e| e.to_string())?;
-                let label_texture = texture_creator
-                    .create_texture_from_surface(&label_surface)
-                    .map_err(|e| e.to_string())?;
-                let label_query = label_texture.query();
-                max_label_width = max_label_width.max(label_query.width);
-            }
-        }
-    }
-
     'stats_running: loop {
         for event in event_pump.poll_iter() {
             match event {
@@ -128,6 +232,34 @@ pub fn show_stats(
                 } => {
                     break 'stats_running;
                 }
+                Event::KeyDown {
+                    keycode: Some(Keycode::Tab),
+                    ..
+                } => {
+                    current_page = (current_page + 1) % pages.len();
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::C),
+                    keymod,
+                    ..
+                } => {
+                    // Shift+C copies the JSON summary, C the page as shown
+                    let (text, copied) = if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) {
+                        (stats.json.clone(), tr!("stats.copied_json"))
+                    } else {
+                        (
+                            pages[current_page].plain_text(&quit_line),
+                            tr!("stats.copied"),
+                        )
+                    };
+                    clipboard_status = match video_subsystem.clipboard().set_clipboard_text(&text) {
+                        Ok(()) => copied,
+                        Err(e) => {
+                            tracing::warn!("Could not copy the stats: {}", e);
+                            tr!("stats.copy_failed")
+                        }
+                    };
+                }
                 _ => {}
             }
         }
@@ -154,23 +286,12 @@ pub fn show_stats(
         // Draw cars that should be behind stats
         for car in &animated_cars {
             if car.should_render_behind_stats() {
-                let car_texture = &car_textures[(car.car_type - 1) as usize];
-                let dest_rect = Rect::new(car.x as i32, car.y as i32, 40, 70);
-
-                canvas.copy_ex(
-                    car_texture,
-                    None,
-                    dest_rect,
-                    car.rotation,
-                    None,
-                    false,
-                    false,
-                )?;
+                draw_car(&mut canvas, &car_textures, car)?;
             }
         }
 
         // Draw stats box background
-        let stats_bg_rect = Rect::new(150, 200, 700, 600);
+        let stats_bg_rect = Rect::new(150, 100, 700, 800);
         canvas.set_draw_color(Color::RGBA(0, 0, 0, 200)); // Semi-transparent black
         canvas.fill_rect(stats_bg_rect)?;
 
@@ -179,11 +300,18 @@ pub fn show_stats(
         canvas.draw_rect(stats_bg_rect)?;
 
         // Draw inner border for depth
-        let inner_rect = Rect::new(155, 205, 690, 590);
+        let inner_rect = Rect::new(155, 105, 690, 790);
         canvas.draw_rect(inner_rect)?;
 
         // Draw stats text with enhanced styling and left-aligned padding
-        let mut y = 240;
+        let Page {
+            lines,
+            font,
+            max_label_width,
+            charts,
+        } = &pages[current_page];
+        let max_label_width = *max_label_width;
+        let mut y = 140;
 
         for (i, line) in lines.iter().enumerate() {
             if line.is_empty() {
@@ -215,15 +343,17 @@ pub fn show_stats(
             }
 
             // Special handling for the final instruction (press esc button to quit)
-            if line.contains("Press esc button to quit") {
-                // Split the line to make "esc" glow
-                let parts: Vec<&str> = line.split("esc").collect();
-
+            let quit_parts = (*line == quit_line)
+                .then(|| line.split_once("esc"))
+                .flatten()
+                .filter(|(before, after)| !before.is_empty() && !after.is_empty());
+            if let Some((before, after)) = quit_parts {
+                // The text around "esc", which glows
                 let mut current_x = 200; // Start position for centered text
 
                 // Render "Press " part
                 let surface1 = font
-                    .render(parts[0])
+                    .render(before)
                     .blended(Color::RGB(0, 255, 255)) // Cyan
                     .map_err(|e| e.to_string())?;
 
@@ -279,7 +409,7 @@ pub fn show_stats(
 
                 // Render " button to quit" part
                 let surface3 = font
-                    .render(" button to quit")
+                    .render(after)
                     .blended(Color::RGB(0, 255, 255)) // Cyan
                     .map_err(|e| e.to_string())?;
 
@@ -295,6 +425,12 @@ pub fn show_stats(
                 continue;
             }
 
+            // The change since the previous run follows a tab
+            let (line, change) = match line.split_once('\t') {
+                Some((line, change)) => (line, Some(change)),
+                None => (*line, None),
+            };
+
             // Handle lines with colons (stats data) with pixel-perfect alignment
             if line.contains(':') {
                 let parts: Vec<&str> = line.splitn(2, ':').collect();
@@ -358,6 +494,26 @@ pub fn show_stats(
                     );
                     canvas.copy(&value_texture, None, value_rect)?;
 
+                    // Change since the previous run, right-aligned in a column of its
+                    // own unless the value reaches into it
+                    if let Some(change) = change {
+                        let change_surface = font
+                            .render(change)
+                            .blended(Color::RGB(0, 255, 255))
+                            .map_err(|e| e.to_string())?;
+                        let change_texture = texture_creator
+                            .create_texture_from_surface(&change_surface)
+                            .map_err(|e| e.to_string())?;
+                        let change_query = change_texture.query();
+                        let change_rect = Rect::new(
+                            (CHANGE_RIGHT - change_query.width as i32).max(value_rect.right() + 12),
+                            y,
+                            change_query.width,
+                            change_query.height,
+                        );
+                        canvas.copy(&change_texture, None, change_rect)?;
+                    }
+
                     y += label_query.height as i32 + 8;
                     continue;
                 }
@@ -384,27 +540,166 @@ pub fn show_stats(
             y += query.height as i32 + 8;
         }
 
+        for chart in charts.iter() {
+            y = draw_bar_chart(&mut canvas, &texture_creator, font, chart, y + 20)?;
+        }
+
+        if pages.len() > 1 {
+            let hint = tr!(
+                "stats.next_page",
+                page = current_page + 1,
+                pages = pages.len()
+            );
+            draw_hint(&mut canvas, &texture_creator, font, &hint, 915)?;
+        }
+        draw_hint(&mut canvas, &texture_creator, font, &clipboard_status, 950)?;
+
         // Draw cars that should be in front of stats
         for car in &animated_cars {
             if !car.should_render_behind_stats() {
-                let car_texture = &car_textures[(car.car_type - 1) as usize];
-                let dest_rect = Rect::new(car.x as i32, car.y as i32, 40, 70);
-
-                canvas.copy_ex(
-                    car_texture,
-                    None,
-                    dest_rect,
-                    car.rotation,
-                    None,
-                    false,
-                    false,
-                )?;
+                draw_car(&mut canvas, &car_textures, car)?;
             }
         }
 
         canvas.present();
-        std::thread::sleep(Duration::from_millis(16)); // ~60 FPS
+        limiter.wait();
     }
 
     Ok(())
 }
+
+/// Draw `chart` from `y` down and return the y below it
+fn draw_bar_chart(
+    canvas: &mut Canvas<Window>,
+    texture_creator: &TextureCreator<WindowContext>,
+    font: &Font,
+    chart: &BarChart,
+    mut y: i32,
+) -> Result<i32, String> {
+    let title = font
+        .render(&chart.title)
+        .blended(Color::RGB(255, 255, 255))
+        .map_err(|e| e.to_string())?;
+    let title = texture_creator
+        .create_texture_from_surface(&title)
+        .map_err(|e| e.to_string())?;
+    let query = title.query();
+    canvas.copy(&title, None, Rect::new(200, y, query.width, query.height))?;
+    y += query.height as i32 + 12;
+
+    // Scaled to the largest count, so differences show even on a short run
+    let largest = chart
+        .bars
+        .iter()
+        .map(|&(_, count)| count)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    for &(direction, count) in &chart.bars {
+        let label = font
+            .render(&format!("{:?}", direction))
+            .blended(Color::RGB(255, 255, 255))
+            .map_err(|e| e.to_string())?;
+        let label = texture_creator
+            .create_texture_from_surface(&label)
+            .map_err(|e| e.to_string())?;
+        let query = label.query();
+        let label_y = y + (BAR_HEIGHT as i32 - query.height as i32) / 2;
+        canvas.copy(
+            &label,
+            None,
+            Rect::new(200, label_y, query.width, query.height),
+        )?;
+
+        let width = (BAR_MAX_WIDTH as u64 * count as u64 / largest as u64) as u32;
+        if width > 0 {
+            canvas.set_draw_color(direction_color(direction));
+            canvas.fill_rect(Rect::new(BAR_LEFT, y, width, BAR_HEIGHT))?;
+        }
+
+        let value = font
+            .render(&count.to_string())
+            .blended(Color::RGB(255, 255, 0))
+            .map_err(|e| e.to_string())?;
+        let value = texture_creator
+            .create_texture_from_surface(&value)
+            .map_err(|e| e.to_string())?;
+        let query = value.query();
+        let value_rect = Rect::new(
+            BAR_LEFT + width as i32 + 8,
+            label_y,
+            query.width,
+            query.height,
+        );
+        canvas.copy(&value, None, value_rect)?;
+        y += BAR_HEIGHT as i32 + 8;
+    }
+    Ok(y)
+}
+
+/// One cyan line centred across the window
+fn draw_hint(
+    canvas: &mut Canvas<Window>,
+    texture_creator: &TextureCreator<WindowContext>,
+    font: &Font,
+    text: &str,
+    y: i32,
+) -> Result<(), String> {
+    let surface = font
+        .render(text)
+        .blended(Color::RGB(0, 255, 255))
+        .map_err(|e| e.to_string())?;
+    let texture = texture_creator
+        .create_texture_from_surface(&surface)
+        .map_err(|e| e.to_string())?;
+    let query = texture.query();
+    let target_rect = Rect::new(
+        (1000 - query.width as i32) / 2,
+        y,
+        query.width,
+        query.height,
+    );
+    canvas.copy(&texture, None, target_rect)
+}
+
+/// A car with its sprite, or a grey square when there are no sprites
+fn draw_car(
+    canvas: &mut Canvas<Window>,
+    car_textures: &[Texture],
+    car: &AnimatedCar,
+) -> Result<(), String> {
+    let dest_rect = Rect::new(car.x as i32, car.y as i32, 40, 70);
+    match car_textures.get((car.car_type - 1) as usize) {
+        Some(car_texture) => canvas.copy_ex(
+            car_texture,
+            None,
+            dest_rect,
+            car.rotation,
+            None,
+            false,
+            false,
+        ),
+        None => {
+            canvas.set_draw_color(Color::RGB(90, 90, 90));
+            canvas.fill_rect(Rect::new(car.x as i32, car.y as i32, 40, 40))
+        }
+    }
+}
+
+/// The five car sprites driving past the stats
+#[cfg(feature = "image")]
+fn load_car_textures(
+    texture_creator: &TextureCreator<WindowContext>,
+) -> Result<Vec<Texture<'_>>, String> {
+    (1..=5)
+        .map(|i| load_car_texture(texture_creator, i))
+        .collect()
+}
+
+/// Without the `image` feature the cars behind the stats are plain squares
+#[cfg(not(feature = "image"))]
+fn load_car_textures(
+    _texture_creator: &TextureCreator<WindowContext>,
+) -> Result<Vec<Texture<'_>>, String> {
+    Ok(Vec::new())
+}
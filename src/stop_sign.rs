@@ -0,0 +1,120 @@
+use crate::controller::{IntersectionController, PermissionRequest};
+use crate::velocities::Velocity;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+/// Seconds without a request after which a waiting vehicle no longer holds up
+/// the ones behind it; longer than the longest backoff after a lost message
+const STALE_AFTER: f32 = 2.0;
+
+/// All-way stop: every vehicle comes to a full stop before the intersection,
+/// then vehicles cross one at a time in the order they arrived
+pub struct StopSignController {
+    arrivals: VecDeque<usize>, // vehicle ids waiting at the line, oldest first
+    last_asked: HashMap<usize, f32>, // when each waiting vehicle last asked
+    crossing: Option<usize>,   // vehicle currently allowed through
+}
+
+impl StopSignController {
+    pub fn new() -> Self {
+        Self {
+            arrivals: VecDeque::new(),
+            last_asked: HashMap::new(),
+            crossing: None,
+        }
+    }
+
+    fn forget(&mut self, vehicle_id: usize) {
+        self.arrivals.retain(|&id| id != vehicle_id);
+        self.last_asked.remove(&vehicle_id);
+        if self.crossing == Some(vehicle_id) {
+            self.crossing = None;
+        }
+    }
+
+    /// The earliest arrival still asking to cross. Vehicles that went quiet
+    /// (frozen, say) keep their place but don't block the queue meanwhile.
+    fn next_up(&self, current_time: f32) -> Option<usize> {
+        self.arrivals.iter().copied().find(|id| {
+            self.last_asked
+                .get(id)
+                .is_some_and(|&asked| current_time - asked <= STALE_AFTER)
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedQueue {
+    arrivals: VecDeque<usize>,
+    last_asked: HashMap<usize, f32>,
+    crossing: Option<usize>,
+}
+
+impl IntersectionController for StopSignController {
+    fn name(&self) -> &'static str {
+        "Stop sign"
+    }
+
+    fn request_permission(
+        &mut self,
+        request: &PermissionRequest,
+        current_time: f32,
+    ) -> Option<Velocity> {
+        // The first request marks the vehicle's arrival at the line
+        if !self.arrivals.contains(&request.vehicle_id) {
+            self.arrivals.push_back(request.vehicle_id);
+        }
+        self.last_asked.insert(request.vehicle_id, current_time);
+
+        // A rolling stop doesn't count
+        if !request.stopped {
+            return None;
+        }
+
+        if self.crossing.is_some() || self.next_up(current_time) != Some(request.vehicle_id) {
+            return None;
+        }
+
+        self.forget(request.vehicle_id);
+        self.crossing = Some(request.vehicle_id);
+        Some(Velocity::FAST)
+    }
+
+    /// A vehicle stranded in the queue gives up its place and queues again
+    /// once repaired. One stranded while crossing keeps the intersection.
+    fn notify_blocked(
+        &mut self,
+        vehicle_id: usize,
+        _bounds: (f32, f32, f32, f32),
+        _current_time: f32,
+        _until: f32,
+    ) {
+        self.arrivals.retain(|&id| id != vehicle_id);
+        self.last_asked.remove(&vehicle_id);
+    }
+
+    fn notify_leave(&mut self, vehicle_id: usize, _current_time: f32) {
+        self.forget(vehicle_id);
+    }
+
+    fn notify_exit(&mut self, vehicle_id: usize, _current_time: f32) {
+        self.forget(vehicle_id);
+    }
+
+    fn save_state(&self) -> Option<serde_json::Value> {
+        serde_json::to_value(SavedQueue {
+            arrivals: self.arrivals.clone(),
+            last_asked: self.last_asked.clone(),
+            crossing: self.crossing,
+        })
+        .ok()
+    }
+
+    fn restore_state(&mut self, state: serde_json::Value) -> Result<(), String> {
+        let saved: SavedQueue = serde_json::from_value(state).map_err(|e| e.to_string())?;
+        self.arrivals = saved.arrivals;
+        self.last_asked = saved.last_asked;
+        self.crossing = saved.crossing;
+        Ok(())
+    }
+}
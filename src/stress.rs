@@ -0,0 +1,130 @@
+use crate::i18n::tr;
+use crate::intersection::SmartIntersection;
+use serde::{Deserialize, Serialize};
+
+/// Length of each spawn-rate stage
+const STAGE_SECONDS: f32 = 20.0;
+/// Frames between spawns at the start of the ramp (one vehicle per second)
+const START_INTERVAL: f32 = 60.0;
+/// Shortest spawn interval the ramp will go down to
+const MIN_INTERVAL: f32 = 3.0;
+/// Each stage spawns this much faster than the previous one
+const RAMP_FACTOR: f32 = 0.8;
+/// Average wait (seconds) at which the intersection counts as saturated
+const WAIT_THRESHOLD: f32 = 5.0;
+
+/// Automatically increases spawn frequency until the intersection saturates
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StressRamp {
+    spawn_interval: f32, // frames between spawns
+    frames_since_spawn: f32,
+    stage: u32,
+    stage_start_time: f32,
+    stage_start_passed: u32,
+    stage_start_wait: f32,
+    max_throughput: f32, // vehicles per minute
+    max_throughput_spawn_rate: f32,
+    saturation_spawn_rate: Option<f32>,
+    finished: bool,
+}
+
+impl StressRamp {
+    pub fn new() -> Self {
+        Self {
+            spawn_interval: START_INTERVAL,
+            frames_since_spawn: 0.0,
+            stage: 1,
+            stage_start_time: 0.0,
+            stage_start_passed: 0,
+            stage_start_wait: 0.0,
+            max_throughput: 0.0,
+            max_throughput_spawn_rate: 0.0,
+            saturation_spawn_rate: None,
+            finished: false,
+        }
+    }
+
+    /// Vehicles per minute the ramp is currently trying to spawn
+    pub fn spawn_rate(&self) -> f32 {
+        60.0 * 60.0 / self.spawn_interval
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Advance one frame; returns true when a vehicle should be spawned
+    pub fn tick(&mut self, current_time: f32, intersection: &SmartIntersection) -> bool {
+        if self.finished {
+            return false;
+        }
+
+        if current_time - self.stage_start_time >= STAGE_SECONDS {
+            self.finish_stage(current_time, intersection);
+        }
+
+        self.frames_since_spawn += 1.0;
+        if self.frames_since_spawn >= self.spawn_interval {
+            self.frames_since_spawn -= self.spawn_interval;
+            return true;
+        }
+        false
+    }
+
+    fn finish_stage(&mut self, current_time: f32, intersection: &SmartIntersection) {
+        let passed = intersection.total_vehicles_passed - self.stage_start_passed;
+        let waited = intersection.total_wait_time - self.stage_start_wait;
+        let elapsed = current_time - self.stage_start_time;
+
+        let throughput = passed as f32 / elapsed * 60.0;
+        let average_wait = if passed > 0 {
+            waited / passed as f32
+        } else {
+            0.0
+        };
+
+        tracing::info!(
+            "Stress stage {}: spawn rate {:.1}/min, throughput {:.1}/min, average wait {:.2} s",
+            self.stage,
+            self.spawn_rate(),
+            throughput,
+            average_wait
+        );
+
+        if throughput > self.max_throughput {
+            self.max_throughput = throughput;
+            self.max_throughput_spawn_rate = self.spawn_rate();
+        }
+
+        if average_wait > WAIT_THRESHOLD {
+            self.saturation_spawn_rate = Some(self.spawn_rate());
+            self.finished = true;
+        } else if self.spawn_interval <= MIN_INTERVAL {
+            self.finished = true; // never saturated within the ramp
+        }
+
+        self.spawn_interval = (self.spawn_interval * RAMP_FACTOR).max(MIN_INTERVAL);
+        self.stage += 1;
+        self.stage_start_time = current_time;
+        self.stage_start_passed = intersection.total_vehicles_passed;
+        self.stage_start_wait = intersection.total_wait_time;
+    }
+
+    /// Lines appended to the final statistics
+    pub fn report(&self) -> Vec<String> {
+        vec![
+            tr!(
+                "stats.max_throughput",
+                rate = format!("{:.1}", self.max_throughput)
+            ),
+            tr!(
+                "stats.max_throughput_spawn_rate",
+                rate = format!("{:.1}", self.max_throughput_spawn_rate)
+            ),
+            match self.saturation_spawn_rate {
+                Some(rate) => tr!("stats.saturation", rate = format!("{:.1}", rate)),
+                None => tr!("stats.no_saturation"),
+            },
+        ]
+    }
+}
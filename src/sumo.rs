@@ -0,0 +1,197 @@
+use crate::demand::{ScheduledSpawn, SpawnSchedule};
+use crate::route::{Direction, Route};
+use std::collections::HashMap;
+
+/// Side of the intersection an edge is attached to
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Side {
+    North,
+    South,
+    East,
+    West,
+}
+
+/// Load vehicle demand from a SUMO `.rou.xml` file.
+///
+/// Edges are mapped onto approaches by name: an edge id starting with
+/// `north`/`south`/`east`/`west` (or a single `n`/`s`/`e`/`w` followed by a
+/// non-letter, e.g. `N_in`, `s2c`) belongs to that side of the intersection.
+/// The first edge of a route is the origin and the last edge the destination.
+pub fn load_sumo_routes(path: &str) -> Result<SpawnSchedule, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let spawns = parse_sumo_routes(&text)?;
+    if spawns.is_empty() {
+        return Err(format!(
+            "{}: no vehicles could be mapped onto the intersection",
+            path
+        ));
+    }
+    Ok(SpawnSchedule::new(spawns))
+}
+
+fn parse_sumo_routes(text: &str) -> Result<Vec<ScheduledSpawn>, String> {
+    let doc = roxmltree::Document::parse(text).map_err(|e| e.to_string())?;
+    let root = doc.root_element();
+
+    // Named routes can be referenced by vehicles and flows
+    let mut named_routes: HashMap<&str, &str> = HashMap::new();
+    for node in root.children().filter(|n| n.has_tag_name("route")) {
+        if let (Some(id), Some(edges)) = (node.attribute("id"), node.attribute("edges")) {
+            named_routes.insert(id, edges);
+        }
+    }
+
+    let mut spawns = Vec::new();
+    let mut skipped = 0;
+
+    for node in root.children().filter(|n| n.is_element()) {
+        let entries = match node.tag_name().name() {
+            "vehicle" | "trip" => {
+                let depart = node
+                    .attribute("depart")
+                    .ok_or("vehicle without depart time")?;
+                vec![parse_number(depart)?]
+            }
+            "flow" => flow_departures(&node)?,
+            _ => continue,
+        };
+
+        // Origin and destination either come from from/to (trips, flows)
+        // or from the first/last edge of the vehicle's route
+        let endpoints = match (node.attribute("from"), node.attribute("to")) {
+            (Some(from), Some(to)) => Some((from, to)),
+            _ => {
+                let edges = node
+                    .attribute("route")
+                    .and_then(|id| named_routes.get(id).copied())
+                    .or_else(|| {
+                        node.children()
+                            .find(|c| c.has_tag_name("route"))
+                            .and_then(|c| c.attribute("edges"))
+                    });
+                edges.and_then(|edges| {
+                    let mut parts = edges.split_whitespace();
+                    let first = parts.next()?;
+                    let last = parts.last().unwrap_or(first);
+                    Some((first, last))
+                })
+            }
+        };
+
+        let mapped = endpoints.and_then(|(from, to)| {
+            let origin = side_of_edge(from)?;
+            let destination = side_of_edge(to)?;
+            direction_and_route(origin, destination)
+        });
+
+        match mapped {
+            Some((direction, route)) => {
+                for time in entries {
+                    spawns.push(ScheduledSpawn {
+                        time,
+                        direction,
+                        route,
+                        class: None,
+                    });
+                }
+            }
+            None => skipped += entries.len(),
+        }
+    }
+
+    if skipped > 0 {
+        tracing::warn!("Skipped {} SUMO vehicles with unmappable routes", skipped);
+    }
+
+    Ok(spawns)
+}
+
+/// Expand a `<flow>` into individual departure times
+fn flow_departures(node: &roxmltree::Node) -> Result<Vec<f32>, String> {
+    let begin = parse_number(node.attribute("begin").unwrap_or("0"))?;
+    let end = parse_number(node.attribute("end").unwrap_or("3600"))?;
+
+    let period = if let Some(period) = node.attribute("period") {
+        parse_number(period)?
+    } else if let Some(per_hour) = node.attribute("vehsPerHour") {
+        3600.0 / parse_number(per_hour)?
+    } else if let Some(number) = node.attribute("number") {
+        (end - begin) / parse_number(number)?
+    } else {
+        return Err("flow needs one of period, vehsPerHour or number".to_string());
+    };
+
+    if period <= 0.0 || !period.is_finite() {
+        return Err("flow has a non-positive period".to_string());
+    }
+
+    let mut times = Vec::new();
+    let mut t = begin;
+    while t < end {
+        times.push(t);
+        t += period;
+    }
+    Ok(times)
+}
+
+fn parse_number(value: &str) -> Result<f32, String> {
+    value
+        .parse::<f32>()
+        .map_err(|_| format!("unsupported numeric value '{}'", value))
+}
+
+fn side_of_edge(edge: &str) -> Option<Side> {
+    let id = edge.trim_start_matches('-').to_lowercase();
+    for (name, side) in [
+        ("north", Side::North),
+        ("south", Side::South),
+        ("east", Side::East),
+        ("west", Side::West),
+    ] {
+        if id.starts_with(name) {
+            return Some(side);
+        }
+    }
+
+    let mut chars = id.chars();
+    let side = match chars.next()? {
+        'n' => Side::North,
+        's' => Side::South,
+        'e' => Side::East,
+        'w' => Side::West,
+        _ => return None,
+    };
+    match chars.next() {
+        Some(c) if c.is_alphabetic() => None,
+        _ => Some(side),
+    }
+}
+
+/// Map an origin side and destination side onto a travel direction and route
+fn direction_and_route(origin: Side, destination: Side) -> Option<(Direction, Route)> {
+    // Vehicles entering from the south side travel north, and so on
+    let direction = match origin {
+        Side::South => Direction::North,
+        Side::North => Direction::South,
+        Side::West => Direction::East,
+        Side::East => Direction::West,
+    };
+
+    let route = match (direction, destination) {
+        (Direction::North, Side::North)
+        | (Direction::South, Side::South)
+        | (Direction::East, Side::East)
+        | (Direction::West, Side::West) => Route::Straight,
+        (Direction::North, Side::East)
+        | (Direction::South, Side::West)
+        | (Direction::East, Side::South)
+        | (Direction::West, Side::North) => Route::Right,
+        (Direction::North, Side::West)
+        | (Direction::South, Side::East)
+        | (Direction::East, Side::North)
+        | (Direction::West, Side::South) => Route::Left,
+        _ => return None, // U-turns are not supported
+    };
+
+    Some((direction, route))
+}
@@ -0,0 +1,258 @@
+use crate::batch::{Episode, output_path, run_episodes, summarize};
+use crate::compare::DEFAULT_SEED;
+use crate::config::Config;
+use crate::weather::Weather;
+use std::fmt;
+
+/// Episodes per swept value when `--batch` isn't given
+pub const DEFAULT_SWEEP_EPISODES: usize = 10;
+/// Summary per swept value, inside `--output-dir` when given
+const SWEEP_CSV: &str = "sweep.csv";
+/// Every episode of the sweep, one row each
+const EPISODES_CSV: &str = "sweep_episodes.csv";
+
+/// Setting varied across a `--sweep`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SweepParameter {
+    Rate,          // Poisson arrivals per minute on every approach
+    PacketLoss,    // chance each reservation message is lost
+    BreakdownRate, // breakdowns per vehicle per second
+    HazardRate,    // jaywalkers per second
+    SlotPadding,   // extra seconds booked before and after every reservation
+    PathPadding,   // extra cells booked around each path
+    Weather,
+}
+
+impl SweepParameter {
+    fn parse(name: &str) -> Result<Self, String> {
+        match name.trim() {
+            "rate" => Ok(Self::Rate),
+            "packet-loss" => Ok(Self::PacketLoss),
+            "breakdown-rate" => Ok(Self::BreakdownRate),
+            "hazard-rate" => Ok(Self::HazardRate),
+            "slot-padding" => Ok(Self::SlotPadding),
+            "path-padding" => Ok(Self::PathPadding),
+            "weather" => Ok(Self::Weather),
+            _ => Err(format!(
+                "Unknown sweep parameter '{}' (expected rate, packet-loss, breakdown-rate, hazard-rate, slot-padding, path-padding or weather)",
+                name
+            )),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Rate => "rate",
+            Self::PacketLoss => "packet-loss",
+            Self::BreakdownRate => "breakdown-rate",
+            Self::HazardRate => "hazard-rate",
+            Self::SlotPadding => "slot-padding",
+            Self::PathPadding => "path-padding",
+            Self::Weather => "weather",
+        }
+    }
+}
+
+/// One point of a sweep
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SweepValue {
+    Number(f32),
+    Weather(Weather),
+}
+
+impl fmt::Display for SweepValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Number(value) => write!(f, "{}", value),
+            Self::Weather(weather) => write!(f, "{}", weather.name()),
+        }
+    }
+}
+
+/// A parameter and the values it takes, from `--sweep`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sweep {
+    pub parameter: SweepParameter,
+    pub values: Vec<SweepValue>,
+}
+
+impl Sweep {
+    /// Parse `name=from:to:step` or `name=a,b,c`, e.g. `rate=6:30:6`,
+    /// `packet-loss=0,0.05,0.1` or `weather=dry,rain,ice`
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let (name, spec) = text
+            .split_once('=')
+            .ok_or_else(|| format!("--sweep needs the form name=values, got '{}'", text))?;
+        let parameter = SweepParameter::parse(name)?;
+
+        let values = if parameter == SweepParameter::Weather {
+            spec.split(',')
+                .map(|name| Weather::parse(name.trim()).map(SweepValue::Weather))
+                .collect::<Result<Vec<_>, _>>()?
+        } else {
+            let number = |value: &str| {
+                value
+                    .trim()
+                    .parse::<f64>()
+                    .map_err(|_| format!("Invalid number '{}' in --sweep", value))
+            };
+            let numbers = match spec.split(':').collect::<Vec<_>>()[..] {
+                [from, to, step] => {
+                    let (from, to, step) = (number(from)?, number(to)?, number(step)?);
+                    if step <= 0.0 || to < from {
+                        return Err(format!(
+                            "--sweep range {} needs from <= to and a positive step",
+                            spec
+                        ));
+                    }
+                    // Counted in steps, in f64 so that 0:1:0.1 gives 0.9 rather than
+                    // 0.90000004 and rounding doesn't drop the last value
+                    let steps = ((to - from) / step + 1e-6).floor() as usize;
+                    (0..=steps)
+                        .map(|i| (from + i as f64 * step) as f32)
+                        .collect()
+                }
+                [_] => spec
+                    .split(',')
+                    .map(|value| number(value).map(|value| value as f32))
+                    .collect::<Result<Vec<_>, _>>()?,
+                _ => return Err(format!("Invalid --sweep range '{}'", spec)),
+            };
+            if let Some(bad) = numbers.iter().find(|&&value| value < 0.0) {
+                return Err(format!("--sweep values can't be negative, got {}", bad));
+            }
+            if parameter == SweepParameter::PacketLoss
+                && let Some(bad) = numbers.iter().find(|&&value| value >= 1.0)
+            {
+                return Err(format!("Packet loss must be below 1, got {}", bad));
+            }
+            if parameter == SweepParameter::PathPadding
+                && let Some(bad) = numbers.iter().find(|&&value| value.fract() != 0.0)
+            {
+                return Err(format!(
+                    "Path padding is a whole number of cells, got {}",
+                    bad
+                ));
+            }
+            numbers.into_iter().map(SweepValue::Number).collect()
+        };
+        Ok(Self { parameter, values })
+    }
+
+    /// The settings with the swept parameter set to `value`
+    fn apply(&self, config: &Config, value: SweepValue) -> Config {
+        let mut config = config.clone();
+        match (self.parameter, value) {
+            (SweepParameter::Rate, SweepValue::Number(rate)) => {
+                config.arrival_rates = Some([rate; 4])
+            }
+            (SweepParameter::PacketLoss, SweepValue::Number(chance)) => config.packet_loss = chance,
+            (SweepParameter::BreakdownRate, SweepValue::Number(rate)) => {
+                config.breakdown_rate = rate
+            }
+            (SweepParameter::HazardRate, SweepValue::Number(rate)) => config.hazard_rate = rate,
+            (SweepParameter::SlotPadding, SweepValue::Number(seconds)) => {
+                config.slot_padding = seconds
+            }
+            (SweepParameter::PathPadding, SweepValue::Number(cells)) => {
+                config.path_padding = cells as usize
+            }
+            (SweepParameter::Weather, SweepValue::Weather(weather)) => config.weather = weather,
+            _ => {}
+        }
+        config
+    }
+}
+
+/// Run the same seeds at every value of the swept parameter, print the mean
+/// and standard deviation of each measurement per value together with the
+/// share of episodes without a collision, and write the matrix to
+/// `sweep.csv` and every episode to `sweep_episodes.csv`
+pub fn run_sweep(config: &Config, sweep: &Sweep) -> Result<(), String> {
+    let episodes = config.batch.unwrap_or(DEFAULT_SWEEP_EPISODES);
+    let first = config.seed.unwrap_or(DEFAULT_SEED);
+    println!(
+        "Sweeping {} over {} values, {} episodes of {:.0} s each, seeds {} to {}",
+        sweep.parameter.name(),
+        sweep.values.len(),
+        episodes,
+        config.batch_ticks as f32 / 60.0,
+        first,
+        first + episodes.saturating_sub(1) as u64
+    );
+
+    let mut rows: Vec<(SweepValue, Vec<Episode>)> = Vec::new();
+    for &value in &sweep.values {
+        let results = run_episodes(
+            &sweep.apply(config, value),
+            episodes,
+            config.batch_ticks,
+            config.batch_threads,
+        )?;
+        rows.push((value, results));
+    }
+    let Some(labels) = rows.first().map(|(_, results)| summarize(results)) else {
+        return Ok(());
+    };
+
+    println!();
+    print!("{:<16}", sweep.parameter.name());
+    for (_, label, _) in &labels {
+        print!("{:>24}", label);
+    }
+    println!("{:>16}", "Collision-free");
+    for (value, results) in &rows {
+        print!("{:<16}", value.to_string());
+        for (_, _, spread) in summarize(results) {
+            print!(
+                "{:>24}",
+                format!("{:.2} ± {:.2}", spread.mean, spread.stddev)
+            );
+        }
+        println!("{:>15.0}%", collision_free(results) * 100.0);
+    }
+
+    let mut matrix = String::from(sweep.parameter.name());
+    for (name, _, _) in &labels {
+        matrix.push_str(&format!(",{}_mean,{}_stddev", name, name));
+    }
+    matrix.push_str(",collision_free\n");
+    let mut raw = format!("{},seed", sweep.parameter.name());
+    for (name, _, _) in &labels {
+        raw.push_str(&format!(",{}", name));
+    }
+    raw.push('\n');
+    for (value, results) in &rows {
+        matrix.push_str(&value.to_string());
+        for (_, _, spread) in summarize(results) {
+            matrix.push_str(&format!(",{},{}", spread.mean, spread.stddev));
+        }
+        matrix.push_str(&format!(",{}\n", collision_free(results)));
+        for episode in results {
+            raw.push_str(&format!("{},{}", value, episode.seed));
+            for metric in episode.metrics() {
+                raw.push_str(&format!(",{}", metric.value));
+            }
+            raw.push('\n');
+        }
+    }
+
+    let matrix_path = output_path(config, SWEEP_CSV)?;
+    std::fs::write(&matrix_path, matrix)
+        .map_err(|e| format!("{}: {}", matrix_path.display(), e))?;
+    let raw_path = output_path(config, EPISODES_CSV)?;
+    std::fs::write(&raw_path, raw).map_err(|e| format!("{}: {}", raw_path.display(), e))?;
+    println!();
+    println!(
+        "Wrote the matrix to {} and every episode to {}",
+        matrix_path.display(),
+        raw_path.display()
+    );
+    Ok(())
+}
+
+/// Share of episodes that ended without a collision
+fn collision_free(results: &[Episode]) -> f32 {
+    let clean = results.iter().filter(|e| e.collisions == 0).count();
+    clean as f32 / results.len().max(1) as f32
+}
@@ -0,0 +1,157 @@
+use crate::fuel::frame_fuel;
+use crate::route::{Direction, Route};
+use crate::vehicle::Vehicle;
+use crate::velocities::Velocity;
+use crate::weather::Weather;
+use bevy_ecs::prelude::*;
+use rayon::prelude::*;
+
+/// A vehicle's body as (x, y, width, height)
+pub type Bounds = (f32, f32, f32, f32);
+
+/// Road conditions the following system reads
+#[derive(Resource, Clone, Copy)]
+pub struct RoadConditions(pub Weather);
+
+/// Areas of the static obstacles on the road, which every vehicle stops for
+#[derive(Resource, Clone, Default)]
+pub struct Obstacles(pub Vec<Bounds>);
+
+/// Car-following result for this frame
+#[derive(Component, Clone, Copy)]
+pub struct Following {
+    pub speed: Velocity,        // fastest speed that keeps a safe gap to the leader
+    pub leader: Option<Entity>, // closest vehicle ahead in the same lane
+}
+
+impl Default for Following {
+    fn default() -> Self {
+        Self {
+            speed: Velocity::FAST,
+            leader: None,
+        }
+    }
+}
+
+/// What the reservation pass decided for this frame
+#[derive(Component, Clone, Copy, Default)]
+pub struct Plan {
+    pub speed: Option<Velocity>, // None: the vehicle doesn't move (wreck or stranded)
+    pub requested_intersection: bool,
+    pub intersection_permission: bool,
+    // Direction, route, bounds and speed to report to the controller as driven over
+    pub progress: Option<(Direction, Route, Bounds, Velocity)>,
+}
+
+/// State changes the last movement step produced
+#[derive(Component, Clone, Copy, Default)]
+pub struct Transitions {
+    pub turned: bool,
+    pub entered: bool,
+    pub left: bool,
+}
+
+/// Find each vehicle's leader and the speed it may drive behind it.
+/// Each vehicle only reads the others, so this runs in parallel.
+pub fn following_system(
+    conditions: Res<RoadConditions>,
+    obstacles: Res<Obstacles>,
+    vehicles: Query<(Entity, &Vehicle)>,
+    mut following: Query<&mut Following>,
+) {
+    let weather = conditions.0;
+    let snapshot: Vec<(Entity, &Vehicle)> = vehicles.iter().collect();
+
+    let results: Vec<(Entity, Following)> = snapshot
+        .par_iter()
+        .map(|&(entity, current_vehicle)| {
+            let mut target_speed = Velocity::FAST;
+            let mut closest_distance = f32::MAX;
+            let mut required_distance = 0.0;
+            let mut leader = None;
+
+            // Vehicles past the intersection only look out for obstacles
+            let past = current_vehicle.is_past_intersection();
+            for &(other_entity, other_vehicle) in &snapshot {
+                if past || other_entity == entity {
+                    continue;
+                }
+
+                if current_vehicle.is_ahead_of_me(other_vehicle) {
+                    let distance = current_vehicle.distance_to_vehicle(other_vehicle);
+                    if distance < closest_distance {
+                        closest_distance = distance;
+                        leader = Some(other_entity);
+                        required_distance = current_vehicle.get_safe_following_distance(weather);
+                    }
+                }
+            }
+
+            if let Some(distance) = current_vehicle.distance_to_obstacle(&obstacles.0)
+                && distance < closest_distance
+            {
+                closest_distance = distance;
+                leader = None;
+                required_distance = current_vehicle.get_safe_obstacle_distance(weather);
+            }
+
+            if closest_distance != f32::MAX && closest_distance < required_distance {
+                if closest_distance < required_distance * 0.7 {
+                    target_speed = Velocity::STOPPED
+                } else if closest_distance < required_distance * 0.8 {
+                    target_speed = Velocity::MEDIUM;
+                }
+            }
+
+            (
+                entity,
+                Following {
+                    speed: target_speed,
+                    leader,
+                },
+            )
+        })
+        .collect();
+
+    for (entity, result) in results {
+        if let Ok(mut current) = following.get_mut(entity) {
+            *current = result;
+        }
+    }
+}
+
+/// Apply each vehicle's plan, move it and record turns and intersection entry/exit
+pub fn movement_system(mut vehicles: Query<(&mut Vehicle, &Plan, &mut Transitions)>) {
+    for (mut vehicle, plan, mut transitions) in &mut vehicles {
+        *transitions = Transitions::default();
+        let Some(speed) = plan.speed else {
+            vehicle.braking = false; // stranded, nothing to brake for
+            continue;
+        };
+
+        let previous_speed = vehicle.current_speed;
+        vehicle.apply_speed(speed);
+        let fuel = frame_fuel(previous_speed, vehicle.current_speed);
+        vehicle.fuel_used += fuel.moving + fuel.idle;
+        vehicle.idle_fuel += fuel.idle;
+        vehicle.requested_intersection = plan.requested_intersection;
+        vehicle.intersection_permission = plan.intersection_permission;
+
+        let had_turned = vehicle.has_turned;
+        let had_entered = vehicle.entered_intersection;
+        let was_inside = vehicle.is_in_intersection();
+        vehicle.update();
+        let inside = vehicle.is_in_intersection();
+        vehicle.entered_intersection |= inside;
+        if vehicle.current_speed == Velocity::STOPPED && !vehicle.entered_intersection {
+            vehicle.waiting_time += 1.0 / 60.0;
+        }
+        if vehicle.current_speed == Velocity::STOPPED && previous_speed != Velocity::STOPPED {
+            vehicle.stops += 1;
+        }
+
+        transitions.turned = !had_turned && vehicle.has_turned;
+        transitions.entered = !had_entered && vehicle.entered_intersection;
+        transitions.left = was_inside && !inside;
+    }
+}
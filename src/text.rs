@@ -0,0 +1,118 @@
+use sdl2::pixels::Color;
+use sdl2::render::{Canvas, TextureCreator};
+use sdl2::video::{Window, WindowContext};
+
+#[cfg(feature = "ttf")]
+pub use with_ttf::*;
+#[cfg(not(feature = "ttf"))]
+pub use without_ttf::*;
+
+#[cfg(feature = "ttf")]
+mod with_ttf {
+    use super::*;
+    use crate::assets::Asset;
+    use sdl2::rect::Rect;
+    use sdl2::rwops::RWops;
+    use sdl2::ttf::{Font, Sdl2TtfContext};
+
+    /// `asset` at `size` points, from disk or the copy built into the binary
+    pub fn load_font<'ttf>(
+        context: &'ttf Sdl2TtfContext,
+        asset: &Asset,
+        size: u16,
+    ) -> Result<Font<'ttf, 'static>, String> {
+        if asset.on_disk() {
+            context.load_font(asset.path, size)
+        } else {
+            context.load_font_from_rwops(RWops::from_bytes(asset.bytes)?, size)
+        }
+    }
+
+    /// The font loader; fonts borrow it, so it lives as long as the windows
+    pub struct Fonts(Sdl2TtfContext);
+
+    impl Fonts {
+        pub fn init() -> Result<Self, String> {
+            sdl2::ttf::init().map(Fonts).map_err(|e| e.to_string())
+        }
+
+        pub fn load(&self, asset: &Asset, size: u16) -> Result<Option<TextFont<'_>>, String> {
+            load_font(&self.0, asset, size).map(|font| Some(TextFont(font)))
+        }
+
+        pub fn context(&self) -> &Sdl2TtfContext {
+            &self.0
+        }
+    }
+
+    pub struct TextFont<'a>(Font<'a, 'static>);
+
+    impl TextFont<'_> {
+        pub fn line_height(&self) -> i32 {
+            self.0.recommended_line_spacing().max(1)
+        }
+
+        /// Draw `text` with its top-left corner at `(x, y)`
+        pub fn draw(
+            &self,
+            canvas: &mut Canvas<Window>,
+            texture_creator: &TextureCreator<WindowContext>,
+            text: &str,
+            color: Color,
+            (x, y): (i32, i32),
+        ) -> Result<(), String> {
+            let surface = self
+                .0
+                .render(text)
+                .blended(color)
+                .map_err(|e| e.to_string())?;
+            let texture = texture_creator
+                .create_texture_from_surface(&surface)
+                .map_err(|e| e.to_string())?;
+            let query = texture.query();
+            canvas.copy(&texture, None, Rect::new(x, y, query.width, query.height))
+        }
+    }
+}
+
+#[cfg(not(feature = "ttf"))]
+mod without_ttf {
+    use super::*;
+    use crate::assets::Asset;
+    use std::convert::Infallible;
+    use std::marker::PhantomData;
+
+    /// Stands in for the font loader in a build without the `ttf` feature:
+    /// no font ever loads, and callers leave their text out
+    pub struct Fonts;
+
+    impl Fonts {
+        pub fn init() -> Result<Self, String> {
+            Ok(Fonts)
+        }
+
+        pub fn load(&self, _asset: &Asset, _size: u16) -> Result<Option<TextFont<'_>>, String> {
+            Ok(None)
+        }
+    }
+
+    /// Never constructed: without SDL2_ttf there are no fonts
+    pub struct TextFont<'a>(Infallible, PhantomData<&'a ()>);
+
+    impl TextFont<'_> {
+        pub fn line_height(&self) -> i32 {
+            match self.0 {}
+        }
+
+        pub fn draw(
+            &self,
+            _canvas: &mut Canvas<Window>,
+            _texture_creator: &TextureCreator<WindowContext>,
+            _text: &str,
+            _color: Color,
+            _at: (i32, i32),
+        ) -> Result<(), String> {
+            match self.0 {}
+        }
+    }
+}
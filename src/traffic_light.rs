@@ -0,0 +1,130 @@
+use crate::controller::{IntersectionController, PermissionRequest};
+use crate::layout::RoadLayout;
+use crate::route::{Direction, Route};
+use crate::velocities::Velocity;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Seconds each approach has a green light
+const GREEN: f32 = 8.0;
+/// Seconds of red in every direction after each green, so the last cars
+/// through are out of the box before the next approach starts
+const ALL_RED: f32 = 3.0;
+
+/// Fixed-time signals: each approach in turn gets a green light for all its
+/// lanes, separated by an all-red clearance. A vehicle is let through only if
+/// it can reach the stop line at full speed before its green runs out, and
+/// once the car ahead in its lane is out of the box: following distances
+/// alone don't keep cars apart through a turn.
+pub struct TrafficLightController {
+    approaches: Vec<Direction>, // green order; the arms that exist in the layout
+    phase: usize,               // index into `approaches` of the current or last green
+    phase_start: f32,           // when that green started
+    crossing: HashMap<(Direction, Route), usize>, // vehicle let through per lane, until it leaves the box
+}
+
+impl TrafficLightController {
+    pub fn new(layout: RoadLayout) -> Self {
+        Self {
+            approaches: [
+                Direction::North,
+                Direction::East,
+                Direction::South,
+                Direction::West,
+            ]
+            .into_iter()
+            .filter(|&direction| layout.has_approach(direction))
+            .collect(),
+            phase: 0,
+            phase_start: 0.0,
+            crossing: HashMap::new(),
+        }
+    }
+
+    fn forget(&mut self, vehicle_id: usize) {
+        self.crossing.retain(|_, &mut id| id != vehicle_id);
+    }
+
+    /// The approach with a green light and the seconds of green it has left,
+    /// or None during the all-red clearance
+    fn green(&self, current_time: f32) -> Option<(Direction, f32)> {
+        let left = GREEN - (current_time - self.phase_start);
+        (left > 0.0).then(|| (self.approaches[self.phase], left))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedPhase {
+    phase: usize,
+    phase_start: f32,
+    crossing: Vec<((Direction, Route), usize)>,
+}
+
+impl IntersectionController for TrafficLightController {
+    fn name(&self) -> &'static str {
+        "Traffic light"
+    }
+
+    fn request_permission(
+        &mut self,
+        request: &PermissionRequest,
+        current_time: f32,
+    ) -> Option<Velocity> {
+        let (direction, left) = self.green(current_time)?;
+        let seconds_to_line =
+            request.distance_to_intersection / request.top_speed.pixels_per_second();
+        let lane = (request.direction, request.route);
+        let lane_clear = self
+            .crossing
+            .get(&lane)
+            .is_none_or(|&id| id == request.vehicle_id);
+        if request.direction != direction || seconds_to_line >= left || !lane_clear {
+            return None;
+        }
+        self.crossing.insert(lane, request.vehicle_id);
+        Some(request.top_speed)
+    }
+
+    fn tick(&mut self, current_time: f32) {
+        if current_time - self.phase_start >= GREEN + ALL_RED {
+            self.phase = (self.phase + 1) % self.approaches.len();
+            self.phase_start = current_time;
+        }
+    }
+
+    fn notify_leave(&mut self, vehicle_id: usize, _current_time: f32) {
+        self.forget(vehicle_id);
+    }
+
+    fn notify_exit(&mut self, vehicle_id: usize, _current_time: f32) {
+        self.forget(vehicle_id);
+    }
+
+    fn revoke(&mut self, vehicle_id: usize) {
+        self.forget(vehicle_id);
+    }
+
+    fn save_state(&self) -> Option<serde_json::Value> {
+        serde_json::to_value(SavedPhase {
+            phase: self.phase,
+            phase_start: self.phase_start,
+            crossing: self
+                .crossing
+                .iter()
+                .map(|(&lane, &id)| (lane, id))
+                .collect(),
+        })
+        .ok()
+    }
+
+    fn restore_state(&mut self, state: serde_json::Value) -> Result<(), String> {
+        let saved: SavedPhase = serde_json::from_value(state).map_err(|e| e.to_string())?;
+        if saved.phase >= self.approaches.len() {
+            return Err(format!("No approach {} in this layout", saved.phase));
+        }
+        self.phase = saved.phase;
+        self.phase_start = saved.phase_start;
+        self.crossing = saved.crossing.into_iter().collect();
+        Ok(())
+    }
+}
@@ -0,0 +1,361 @@
+use crate::config::Config;
+use crate::csv_demand::load_csv_schedule;
+use crate::demand::{AutoSpawn, PoissonArrivals, SpawnSchedule};
+use crate::events::EventLog;
+use crate::history::RunHistory;
+use crate::i18n::tr;
+use crate::intersection::SmartIntersection;
+use crate::logging::LogBuffer;
+use crate::report::{RunSummary, write_run_report};
+use crate::route::{Direction, route_for_modifiers};
+use crate::run_db::{previous_run, record_run};
+use crate::sumo::load_sumo_routes;
+use crate::vehicle::Vehicle;
+use ratatui::DefaultTerminal;
+use ratatui::Frame;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Paragraph};
+use std::time::{Duration, Instant};
+
+/// Simulation step, the same 60 Hz as the SDL window
+const TICK: Duration = Duration::from_micros(16_667);
+/// Simulation ticks per redraw; terminals (especially over SSH) can't keep up with 60 FPS
+const TICKS_PER_DRAW: u32 = 4;
+/// World width and height in pixels
+const WORLD_SIZE: f32 = 1000.0;
+/// Log lines shown under the road
+const LOG_HEIGHT: u16 = 5;
+
+/// Run the simulation drawn as characters in the terminal and return the final stats
+pub fn run_tui(
+    mut intersection: SmartIntersection,
+    config: &Config,
+    logs: &LogBuffer,
+) -> Result<String, String> {
+    // Load everything that can fail before the terminal switches to raw mode
+    let mut schedule = SpawnSchedule::default();
+    if let Some(path) = &config.sumo_routes {
+        schedule = schedule.merged(load_sumo_routes(path)?);
+    }
+    if let Some(path) = &config.spawn_csv {
+        schedule = schedule.merged(load_csv_schedule(path)?);
+    }
+    let mut event_log = match &config.event_log {
+        Some(path) => Some(EventLog::create(path)?),
+        None => None,
+    };
+
+    let mut terminal = ratatui::init();
+    let result = run_loop(
+        &mut terminal,
+        &mut intersection,
+        &mut schedule,
+        &mut event_log,
+        config,
+        logs,
+    );
+    ratatui::restore();
+    let (current_time, history) = result?;
+
+    if let Some(log) = &mut event_log {
+        log.flush()?;
+    }
+    if let Some(dir) = &config.output_dir {
+        write_run_report(dir, &intersection, &history, current_time)?;
+        println!("Run report written to {}", dir);
+    }
+    let previous = previous_run(config);
+    let mut extra = record_run(
+        config,
+        &RunSummary::new(&intersection, current_time),
+        previous.as_ref(),
+    );
+    if let Some(limit) = config
+        .run_limit
+        .filter(|limit| limit.reached(&intersection, current_time))
+    {
+        extra.push(limit.describe());
+    }
+    let stats = intersection.get_final_stats(&extra, previous.as_ref());
+    Ok(stats
+        .trim_end_matches(&tr!("stats.quit"))
+        .trim_end()
+        .to_string())
+}
+
+/// Tick and draw until the user quits; returns the simulated time and history
+fn run_loop(
+    terminal: &mut DefaultTerminal,
+    intersection: &mut SmartIntersection,
+    schedule: &mut SpawnSchedule,
+    event_log: &mut Option<EventLog>,
+    config: &Config,
+    logs: &LogBuffer,
+) -> Result<(f32, RunHistory), String> {
+    let mut arrivals = config
+        .arrival_rates
+        .map(|rates| PoissonArrivals::new(rates, intersection.demand_rng()));
+    let mut history = RunHistory::default();
+    let mut current_time = 0.0f32;
+    let mut auto_spawn = AutoSpawn::default();
+    // The built-in controller in use; None while a script decides
+    let mut controller = config
+        .controller_script
+        .is_none()
+        .then_some(config.controller);
+    let mut tick = 0u32;
+    let mut next_tick = Instant::now();
+
+    loop {
+        // Handle input until the next tick is due
+        while event::poll(next_tick.saturating_duration_since(Instant::now()))
+            .map_err(|e| e.to_string())?
+        {
+            let Event::Key(key) = event::read().map_err(|e| e.to_string())? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match key.code {
+                KeyCode::Esc | KeyCode::Char('q') => return Ok((current_time, history)),
+                KeyCode::Up | KeyCode::Down | KeyCode::Right | KeyCode::Left => {
+                    let direction = match key.code {
+                        KeyCode::Up => Direction::North,
+                        KeyCode::Down => Direction::South,
+                        KeyCode::Right => Direction::East,
+                        _ => Direction::West,
+                    };
+                    let route = route_for_modifiers(
+                        key.modifiers.contains(KeyModifiers::SHIFT),
+                        key.modifiers.contains(KeyModifiers::CONTROL),
+                    );
+                    intersection.spawn_vehicle(Some(direction), route, current_time);
+                }
+                KeyCode::Char('a') => intersection.spawn_from_all_directions(current_time),
+                KeyCode::Char('r') => auto_spawn.enabled = !auto_spawn.enabled,
+                KeyCode::Char('s') => auto_spawn.enabled = false,
+                KeyCode::Char(digit @ '1'..='9') => {
+                    auto_spawn.set_level(digit.to_digit(10).unwrap_or(AutoSpawn::DEFAULT_LEVEL))
+                }
+                KeyCode::Char('x') => intersection.remove_broken_down_vehicles(current_time),
+                KeyCode::Char('w') => intersection.weather = intersection.weather.next(),
+                KeyCode::Char('k') => {
+                    controller = Some(intersection.cycle_controller(controller, current_time));
+                }
+                _ => {}
+            }
+        }
+        next_tick += TICK;
+        current_time += 1.0 / 60.0;
+
+        if !schedule.is_empty() {
+            for spawn in schedule.due(current_time) {
+                intersection.spawn_vehicle_of_class(
+                    Some(spawn.direction),
+                    Some(spawn.route),
+                    spawn.class,
+                    current_time,
+                );
+            }
+        }
+        if let Some(arrivals) = &mut arrivals {
+            for direction in arrivals.due(current_time, intersection.demand_rng()) {
+                intersection.spawn_vehicle(Some(direction), None, current_time);
+            }
+        }
+        if auto_spawn.tick() {
+            intersection.spawn_vehicle(None, None, current_time);
+        }
+
+        intersection.update(current_time);
+        history.record(current_time, intersection);
+        let events = intersection.drain_events();
+        if let Some(log) = event_log {
+            log.write(&events)?;
+        }
+        if let Some(limit) = &config.run_limit
+            && limit.reached(intersection, current_time)
+        {
+            return Ok((current_time, history));
+        }
+
+        tick += 1;
+        if tick.is_multiple_of(TICKS_PER_DRAW) {
+            terminal
+                .draw(|frame| draw(frame, intersection, logs, current_time, &auto_spawn))
+                .map_err(|e| e.to_string())?;
+        }
+    }
+}
+
+fn draw(
+    frame: &mut Frame,
+    intersection: &SmartIntersection,
+    logs: &LogBuffer,
+    current_time: f32,
+    spawning: &AutoSpawn,
+) {
+    let [main_area, log_area] =
+        Layout::vertical([Constraint::Min(0), Constraint::Length(LOG_HEIGHT + 2)])
+            .areas(frame.area());
+    let [road_area, side_area] =
+        Layout::horizontal([Constraint::Min(0), Constraint::Length(34)]).areas(main_area);
+
+    let road_block = Block::bordered().title(tr!("tui.road"));
+    let inner = road_block.inner(road_area);
+    frame.render_widget(road_block, road_area);
+    frame.render_widget(Paragraph::new(road_lines(intersection, inner)), inner);
+
+    frame.render_widget(
+        Paragraph::new(side_lines(intersection, current_time, spawning))
+            .block(Block::bordered().title(tr!("tui.stats"))),
+        side_area,
+    );
+
+    let log_lines: Vec<Line> = logs
+        .recent(LOG_HEIGHT as usize)
+        .into_iter()
+        .map(Line::from)
+        .collect();
+    frame.render_widget(
+        Paragraph::new(log_lines).block(Block::bordered().title(tr!("tui.log"))),
+        log_area,
+    );
+}
+
+/// The road as a grid of characters. Terminal cells are about twice as tall as
+/// they are wide, so the map uses two columns per row to stay square.
+fn road_lines(intersection: &SmartIntersection, area: Rect) -> Vec<Line<'static>> {
+    let rows = area.height.min(area.width / 2) as usize;
+    let cols = rows * 2;
+    if rows == 0 {
+        return Vec::new();
+    }
+
+    let road = Style::default().fg(Color::DarkGray);
+    let closed = intersection.layout.closed_area();
+    let mut grid: Vec<(char, Style)> = (0..rows * cols)
+        .map(|i| {
+            let x = ((i % cols) as f32 + 0.5) / cols as f32 * WORLD_SIZE;
+            let y = ((i / cols) as f32 + 0.5) / rows as f32 * WORLD_SIZE;
+            let on_vertical = (350.0..650.0).contains(&x);
+            let on_horizontal = (350.0..650.0).contains(&y);
+            let is_closed = closed.is_some_and(|(cx, cy, cw, ch)| {
+                (cx..cx + cw).contains(&x) && (cy..cy + ch).contains(&y)
+            });
+            if is_closed {
+                return (' ', Style::default());
+            }
+            match (on_vertical, on_horizontal) {
+                (true, true) => (':', road),
+                (true, false) | (false, true) => ('.', road),
+                (false, false) => (' ', Style::default()),
+            }
+        })
+        .collect();
+
+    for vehicle in intersection.vehicles() {
+        let (x, y, w, h) = vehicle.get_visual_bounds();
+        let col = ((x + w / 2.0) / WORLD_SIZE * cols as f32) as usize;
+        let row = ((y + h / 2.0) / WORLD_SIZE * rows as f32) as usize;
+        if col < cols && row < rows {
+            grid[row * cols + col] = vehicle_cell(vehicle);
+        }
+    }
+
+    grid.chunks(cols)
+        .map(|row| {
+            Line::from(
+                row.iter()
+                    .map(|&(c, style)| Span::styled(c.to_string(), style))
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .collect()
+}
+
+/// An arrow pointing where the vehicle is heading, coloured like the minimap
+fn vehicle_cell(vehicle: &Vehicle) -> (char, Style) {
+    if vehicle.crashed_at.is_some() {
+        return (
+            'X',
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        );
+    }
+    if vehicle.breakdown.is_some() {
+        return (
+            'B',
+            Style::default()
+                .fg(Color::Magenta)
+                .add_modifier(Modifier::BOLD),
+        );
+    }
+    let (arrow, color) = match vehicle.direction {
+        Direction::North => ('^', Color::Blue),
+        Direction::South => ('v', Color::LightRed),
+        Direction::East => ('>', Color::Green),
+        Direction::West => ('<', Color::Yellow),
+    };
+    (
+        arrow,
+        Style::default().fg(color).add_modifier(Modifier::BOLD),
+    )
+}
+
+fn side_lines(
+    intersection: &SmartIntersection,
+    current_time: f32,
+    spawning: &AutoSpawn,
+) -> Vec<Line<'static>> {
+    let warm_up_left = intersection.warm_up_left(current_time);
+    let time = if warm_up_left > 0.0 {
+        tr!(
+            "hud.warm_up",
+            seconds = format!("{:.0}", warm_up_left.ceil())
+        )
+    } else {
+        tr!("tui.time", seconds = format!("{:.1}", current_time))
+    };
+    vec![
+        Line::from(time),
+        Line::from(tr!("tui.controller", name = intersection.controller.name())),
+        Line::from(tr!("tui.weather", name = intersection.weather.name())),
+        Line::from(tr!(
+            "tui.passed",
+            count = intersection.total_vehicles_passed
+        )),
+        Line::from(tr!("tui.active", count = intersection.vehicle_count())),
+        Line::from(tr!("tui.queued", count = intersection.queued_vehicles())),
+        Line::from(tr!(
+            "tui.pending_spawns",
+            count = intersection.pending_spawn_count()
+        )),
+        Line::from(tr!("tui.close_calls", count = intersection.close_calls)),
+        Line::from(tr!("tui.collisions", count = intersection.collisions)),
+        Line::from(tr!(
+            "tui.random_spawning",
+            state = if spawning.enabled {
+                tr!(
+                    "tui.spawn_rate",
+                    rate = format!("{:.1}", spawning.rate()),
+                    level = spawning.level(),
+                )
+            } else {
+                tr!("tui.off")
+            }
+        )),
+        Line::from(""),
+        Line::from(tr!("tui.help_spawn")),
+        Line::from(tr!("tui.help_spawn_all")),
+        Line::from(tr!("tui.help_random")),
+        Line::from(tr!("tui.help_level")),
+        Line::from(tr!("tui.help_weather")),
+        Line::from(tr!("tui.help_controller")),
+        Line::from(tr!("tui.help_tow")),
+        Line::from(tr!("tui.help_quit")),
+    ]
+}
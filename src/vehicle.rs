@@ -1,17 +1,35 @@
+use crate::controller::Denial;
+use crate::driver::DriverProfile;
+use crate::fuel::VehicleClass;
 use crate::route::*;
 use crate::velocities::Velocity;
+use crate::weather::Weather;
+use bevy_ecs::component::Component;
 use rand::Rng;
-use sdl2::image::LoadTexture;
-use sdl2::render::{Texture, TextureCreator};
-use sdl2::video::WindowContext;
+use serde::{Deserialize, Serialize};
 use std::sync::atomic::{AtomicUsize, Ordering};
 
-pub struct Vehicle<'a> {
+/// Size of every vehicle across and along its heading, in pixels
+pub const VEHICLE_WIDTH: u32 = 40;
+pub const VEHICLE_LENGTH: u32 = 70;
+/// How near its turn position a vehicle's centre comes before it turns
+pub const TURN_RADIUS: f32 = 25.0;
+
+/// A vehicle stranded in place, with its repair time if it will recover
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Breakdown {
+    pub until: Option<f32>,
+}
+
+#[derive(Component, Clone, Serialize, Deserialize)]
+pub struct Vehicle {
     pub id: usize,
-    pub texture: Texture<'a>,
+    pub car_index: u32, // which car sprite (1-5) the renderer draws
     pub route: Route,
     pub direction: Direction,
     pub current_speed: Velocity,
+    #[serde(default)]
+    pub braking: bool, // slowed down this frame
     pub width: u32,
     pub height: u32,
     pub safety_distance: f32,
@@ -21,22 +39,55 @@ pub struct Vehicle<'a> {
     pub has_turned: bool,
     pub requested_intersection: bool,
     pub intersection_permission: bool,
+    pub entered_intersection: bool,
+    pub crashed_at: Option<f32>,
+    pub close_call_at: Option<f32>, // last time it came too close to another vehicle
+    pub breakdown: Option<Breakdown>,
+    pub frozen: bool,      // held in place with a debug key until released
+    pub waiting_time: f32, // seconds spent stopped before entering the intersection
+    pub stops: u32,        // times it came to a complete stop, anywhere on its journey
+    pub spawned_at: f32,
+    pub profile: DriverProfile,
+    pub platoon_ahead: Option<usize>, // platoon member directly ahead, kept in sight through turns
+
+    // Radio link to the controller
+    pub retry_at: Option<f32>, // no answer yet; don't ask again before this time
+    pub unanswered: u32,       // requests in a row that got no answer
+    pub unacknowledged_grant: bool, // the controller granted a crossing whose reply was lost
+    pub denial: Option<Denial>, // why its last request was turned down, until granted
+
+    pub class: VehicleClass,
+    pub fuel_used: f32,     // mL, idling included
+    pub idle_fuel: f32,     // mL burned standing still
+    pub cruise_factor: f32, // share of its class's top speed this driver cruises at
 }
 
 static NEXT_ID: AtomicUsize = AtomicUsize::new(1);
 
-impl<'a> Vehicle<'a> {
+/// A fresh id from the sequence vehicles are numbered by
+pub fn allocate_id() -> usize {
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// The id the next vehicle will get, to carry the sequence over a checkpoint
+pub fn next_id() -> usize {
+    NEXT_ID.load(Ordering::Relaxed)
+}
+
+pub fn set_next_id(id: usize) {
+    NEXT_ID.store(id, Ordering::Relaxed);
+}
+
+impl Vehicle {
     pub fn new(
-        texture_creator: &'a TextureCreator<WindowContext>,
         route: Route,
         direction: Direction,
         spawn_position: (f32, f32),
         turn_position: (f32, f32),
-    ) -> Result<Self, String> {
+        profile: DriverProfile,
+    ) -> Self {
         let mut rng = rand::rng();
         let car_index = rng.random_range(1..=5);
-        let path = format!("assets/Cars/car{}.png", car_index);
-        let texture = texture_creator.load_texture(&path)?;
         // Set rotation based on direction
         let rotation = match direction {
             Direction::North => 0.0,   // No rotation (assuming cars face north in image)
@@ -45,31 +96,73 @@ impl<'a> Vehicle<'a> {
             Direction::West => 270.0,  // Turn left 90 degrees (or -90.0)
         };
 
-        Ok(Self {
-            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
-            texture,
+        Self {
+            id: allocate_id(),
+            car_index,
             route,
             direction,
-            current_speed: Velocity::Fast,
-            width: 40,
-            height: 70,
-            safety_distance: 50.0,
+            current_speed: Velocity::FAST,
+            braking: false,
+            width: VEHICLE_WIDTH,
+            height: VEHICLE_LENGTH,
+            safety_distance: profile.safety_distance(),
             position: spawn_position,
             turn_position,
             rotation,
             has_turned: false,
             requested_intersection: false,
             intersection_permission: false,
-        })
+            entered_intersection: false,
+            crashed_at: None,
+            close_call_at: None,
+            breakdown: None,
+            frozen: false,
+            waiting_time: 0.0,
+            stops: 0,
+            spawned_at: 0.0,
+            profile,
+            platoon_ahead: None,
+            retry_at: None,
+            unanswered: 0,
+            unacknowledged_grant: false,
+            denial: None,
+            class: VehicleClass::default(),
+            fuel_used: 0.0,
+            idle_fuel: 0.0,
+            cruise_factor: 1.0,
+        }
     }
 
-    pub fn update(&mut self) {
-        let pixels_per_frame = match self.current_speed {
-            Velocity::Slow => 3.0,    // 3 pixel per frame
-            Velocity::Medium => 5.0,  // 5 pixels per frame
-            Velocity::Fast => 7.0,    // 7 pixels per frame
-            Velocity::Stopped => 0.0, // vehicle doesnt move
+    /// Fastest this vehicle goes: its driver's cruising speed, within what
+    /// its class can do
+    pub fn top_speed(&self) -> Velocity {
+        let top = self.class.max_speed().pixels_per_frame();
+        Velocity::from_pixels_per_frame(top * self.cruise_factor)
+    }
+
+    /// Speed gained per frame pulling away, in px/frame: the driver's pace,
+    /// within what the class can do
+    pub fn acceleration(&self) -> f32 {
+        self.profile.acceleration().min(self.class.acceleration())
+    }
+
+    /// Move towards the target speed, no faster than the vehicle can go:
+    /// braking as hard as its class allows, speeding up at the pace of the
+    /// driver's profile within what the class can do
+    pub fn apply_speed(&mut self, target: Velocity) {
+        let target = target.min(self.top_speed()).pixels_per_frame();
+        let current = self.current_speed.pixels_per_frame();
+        self.braking = target < current;
+        let next = if self.braking {
+            (current - self.class.deceleration()).max(target)
+        } else {
+            (current + self.acceleration()).min(target)
         };
+        self.current_speed = Velocity::from_pixels_per_frame(next);
+    }
+
+    pub fn update(&mut self) {
+        let pixels_per_frame = self.current_speed.pixels_per_frame();
 
         if !self.has_turned {
             let center = (
@@ -80,7 +173,7 @@ impl<'a> Vehicle<'a> {
             let dy = center.1 - self.turn_position.1;
             let distance = (dx * dx + dy * dy).sqrt();
 
-            if distance <= 25.0 {
+            if distance <= TURN_RADIUS {
                 self.execute_turn(); // change direction & rotation
                 self.has_turned = true;
             }
@@ -165,6 +258,23 @@ impl<'a> Vehicle<'a> {
             Route::Straight => {} // no turn
         }
     }
+
+    /// Heading the vehicle had on its way into the intersection
+    pub fn approach_direction(&self) -> Direction {
+        if !self.has_turned {
+            return self.direction;
+        }
+        [
+            Direction::North,
+            Direction::South,
+            Direction::East,
+            Direction::West,
+        ]
+        .into_iter()
+        .find(|&direction| exit_direction(direction, self.route) == self.direction)
+        .unwrap_or(self.direction)
+    }
+
     pub fn get_visual_center(&self) -> (f32, f32) {
         let (vx, vy, vw, vh) = self.get_visual_bounds();
         (vx + vw / 2.0, vy + vh / 2.0)
@@ -250,6 +360,14 @@ impl<'a> Vehicle<'a> {
         }
     }
 
+    /// Whether the two vehicle bodies physically overlap
+    pub fn overlaps(&self, other: &Vehicle) -> bool {
+        let (ax, ay, aw, ah) = self.get_visual_bounds();
+        let (bx, by, bw, bh) = other.get_visual_bounds();
+
+        ax < bx + bw && bx < ax + aw && ay < by + bh && by < ay + ah
+    }
+
     pub fn distance_to_vehicle(&self, other: &Vehicle) -> f32 {
         let my_center = self.get_visual_center();
         let other_center = other.get_visual_center();
@@ -285,8 +403,43 @@ impl<'a> Vehicle<'a> {
         }
     }
 
-    pub fn get_safe_following_distance(&self, _lead_vehicle: &Vehicle) -> f32 {
-        70.0 + self.safety_distance
+    /// Centre-to-centre distance kept to the vehicle ahead: the vehicle
+    /// length, then the standstill gap plus the time headway, both stretched
+    /// on slippery roads. The headway is taken at this vehicle's own speed,
+    /// so one running up on a stopped queue keeps its full braking gap and
+    /// only closes up to the standstill gap as it slows.
+    pub fn get_safe_following_distance(&self, weather: Weather) -> f32 {
+        self.following_distance_at(self.current_speed, weather)
+    }
+
+    /// Gap a vehicle keeps to a static obstacle, centre to centre like
+    /// `get_safe_following_distance`
+    pub fn get_safe_obstacle_distance(&self, weather: Weather) -> f32 {
+        self.following_distance_at(Velocity::STOPPED, weather)
+    }
+
+    fn following_distance_at(&self, speed: Velocity, weather: Weather) -> f32 {
+        let headway = speed.pixels_per_second() * self.profile.time_headway();
+        70.0 + (self.safety_distance + headway) * weather.braking_factor()
+    }
+
+    /// Distance (centre to centre along the heading) to the nearest obstacle
+    /// ahead in this vehicle's lane, if any
+    pub fn distance_to_obstacle(&self, obstacles: &[(f32, f32, f32, f32)]) -> Option<f32> {
+        let centre = self.get_visual_center();
+        let (lx, ly, lw, lh) = lane_corridor(centre, self.direction, 1000.0);
+        obstacles
+            .iter()
+            .filter(|&&(x, y, w, h)| x < lx + lw && lx < x + w && y < ly + lh && ly < y + h)
+            // Already on top of it (roadworks starting around the vehicle): drive off
+            .filter(|&&(x, y, w, h)| {
+                !(centre.0 >= x && centre.0 < x + w && centre.1 >= y && centre.1 < y + h)
+            })
+            .map(|&(x, y, w, h)| match self.direction {
+                Direction::North | Direction::South => (centre.1 - (y + h / 2.0)).abs(),
+                Direction::East | Direction::West => (centre.0 - (x + w / 2.0)).abs(),
+            })
+            .min_by(f32::total_cmp)
     }
 
     pub fn is_outside_canvas(&self) -> bool {
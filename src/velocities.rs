@@ -1,7 +1,70 @@
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub enum Velocity {
-    Slow,
-    Medium,
-    Fast,
-    Stopped,
+use serde::{Deserialize, Serialize};
+
+/// A speed in pixels per frame. Vehicles speed up and slow down smoothly
+/// within the limits of their class; controllers and car-following hand out
+/// target speeds, usually one of the named presets.
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct Velocity(f32);
+
+impl Velocity {
+    pub const STOPPED: Velocity = Velocity(0.0);
+    pub const SLOW: Velocity = Velocity(3.0);
+    pub const MEDIUM: Velocity = Velocity(5.0);
+    pub const FAST: Velocity = Velocity(7.0);
+    /// The named speeds, fastest first
+    pub const PRESETS: [Velocity; 3] = [Velocity::FAST, Velocity::MEDIUM, Velocity::SLOW];
+
+    /// Negative speeds count as standing still
+    pub fn from_pixels_per_frame(speed: f32) -> Self {
+        Velocity(speed.max(0.0))
+    }
+
+    /// Distance covered in one frame
+    pub fn pixels_per_frame(self) -> f32 {
+        self.0
+    }
+
+    pub fn pixels_per_second(self) -> f32 {
+        self.0 * 60.0
+    }
+
+    /// Seconds to cover `distance` pixels starting at this speed and
+    /// speeding up by `acceleration` px/frame every frame until reaching
+    /// `cruise`. Zero when `cruise` is standing still.
+    pub fn seconds_to_cover(self, distance: f32, cruise: Velocity, acceleration: f32) -> f32 {
+        let (from, to) = (self.pixels_per_second(), cruise.pixels_per_second());
+        if to <= 0.0 {
+            return 0.0;
+        }
+        let acceleration = acceleration * 3600.0; // px/s²
+        if from >= to || !acceleration.is_finite() || acceleration <= 0.0 {
+            return distance / to;
+        }
+        let accelerating = (to - from) / acceleration;
+        let covered = (from + to) / 2.0 * accelerating;
+        if distance <= covered {
+            // distance = from t + a t² / 2
+            ((from * from + 2.0 * acceleration * distance).sqrt() - from) / acceleration
+        } else {
+            accelerating + (distance - covered) / to
+        }
+    }
+
+    /// The slower of the two
+    pub fn min(self, other: Velocity) -> Velocity {
+        if other < self { other } else { self }
+    }
+
+    /// The nearest preset's name, for the UI and stats
+    pub fn name(self) -> &'static str {
+        if self == Velocity::STOPPED {
+            "stopped"
+        } else if self.0 < (Velocity::SLOW.0 + Velocity::MEDIUM.0) / 2.0 {
+            "slow"
+        } else if self.0 < (Velocity::MEDIUM.0 + Velocity::FAST.0) / 2.0 {
+            "medium"
+        } else {
+            "fast"
+        }
+    }
 }
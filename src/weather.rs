@@ -0,0 +1,59 @@
+use serde::{Deserialize, Serialize};
+
+/// Road conditions affecting how quickly vehicles can stop
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum Weather {
+    #[default]
+    Dry,
+    Rain,
+    Ice,
+}
+
+impl Weather {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.to_lowercase().as_str() {
+            "dry" => Ok(Weather::Dry),
+            "rain" => Ok(Weather::Rain),
+            "ice" => Ok(Weather::Ice),
+            _ => Err(format!(
+                "Unknown weather '{}' (expected dry, rain or ice)",
+                name
+            )),
+        }
+    }
+
+    /// Cycle to the next condition (used by the runtime hotkey)
+    pub fn next(self) -> Self {
+        match self {
+            Weather::Dry => Weather::Rain,
+            Weather::Rain => Weather::Ice,
+            Weather::Ice => Weather::Dry,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Weather::Dry => "Dry",
+            Weather::Rain => "Rain",
+            Weather::Ice => "Ice",
+        }
+    }
+
+    /// Multiplier applied to braking and safe following distances
+    pub fn braking_factor(self) -> f32 {
+        match self {
+            Weather::Dry => 1.0,
+            Weather::Rain => 1.5,
+            Weather::Ice => 2.5,
+        }
+    }
+
+    /// Seconds added before and after every reservation slot
+    pub fn reservation_margin(self) -> f32 {
+        match self {
+            Weather::Dry => 0.0,
+            Weather::Rain => 0.15,
+            Weather::Ice => 0.4,
+        }
+    }
+}